@@ -0,0 +1,7 @@
+-- Add down migration script here
+-- profit distribution entries table
+DROP TABLE profit_distribution_entries;
+-- profit distributions table
+DROP TABLE profit_distributions;
+-- funders ownership percentage
+ALTER TABLE funders DROP COLUMN percentage;
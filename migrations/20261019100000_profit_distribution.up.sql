@@ -0,0 +1,31 @@
+-- Add up migration script here
+-- funders ownership percentage
+ALTER TABLE funders
+ADD COLUMN percentage DOUBLE PRECISION NOT NULL DEFAULT 0 CONSTRAINT funder_percentage_must_be_between_0_and_100 CHECK (
+        percentage >= 0
+        AND percentage <= 100
+    );
+-- profit distributions table
+CREATE TABLE IF NOT EXISTS profit_distributions (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    total_incomes DOUBLE PRECISION NOT NULL,
+    total_expenses DOUBLE PRECISION NOT NULL,
+    net_profit DOUBLE PRECISION NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    admin_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    CONSTRAINT profit_distribution_period_must_be_valid CHECK (period_start < period_end),
+    CONSTRAINT profit_distribution_period_must_be_unique UNIQUE(company_id, period_start, period_end)
+);
+-- profit distribution entries table
+CREATE TABLE IF NOT EXISTS profit_distribution_entries (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    funder_name VARCHAR NOT NULL,
+    percentage DOUBLE PRECISION NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    statement VARCHAR NOT NULL,
+    funder_id UUID REFERENCES funders(id) ON DELETE SET NULL,
+    distribution_id UUID NOT NULL REFERENCES profit_distributions(id) ON DELETE CASCADE
+);
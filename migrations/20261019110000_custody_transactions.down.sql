@@ -0,0 +1,5 @@
+-- Add down migration script here
+-- custody transactions table
+DROP TABLE custody_transactions;
+-- custody transaction kinds
+DROP TYPE custody_kind;
@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- custody transaction kinds
+CREATE TYPE custody_kind AS ENUM ('top_up', 'expense', 'refund', 'adjustment');
+-- custody transactions table
+CREATE TABLE IF NOT EXISTS custody_transactions (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind custody_kind NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    note VARCHAR,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    expense_id UUID REFERENCES expenses(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS custody_transactions_user_time ON custody_transactions(user_id, time);
+-- opening balances
+INSERT INTO custody_transactions (kind, value, note, user_id)
+SELECT 'adjustment',
+    value,
+    'رصيد افتتاحي',
+    id
+FROM users
+WHERE value <> 0;
@@ -0,0 +1,13 @@
+-- Add down migration script here
+-- notifications table
+DROP TABLE notifications;
+-- expense review
+ALTER TABLE expenses DROP COLUMN status,
+    DROP COLUMN reviewer_id,
+    DROP COLUMN review_comment,
+    DROP COLUMN reviewed_at;
+-- expense statuses
+DROP TYPE expense_status;
+-- reviewers and reserved custody
+ALTER TABLE users DROP COLUMN is_reviewer,
+    DROP COLUMN reserved;
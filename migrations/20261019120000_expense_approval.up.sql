@@ -0,0 +1,21 @@
+-- Add up migration script here
+-- reviewers and reserved custody
+ALTER TABLE users
+ADD COLUMN is_reviewer BOOL NOT NULL DEFAULT FALSE,
+    ADD COLUMN reserved DOUBLE PRECISION NOT NULL DEFAULT 0;
+-- expense statuses
+CREATE TYPE expense_status AS ENUM ('pending', 'approved', 'rejected');
+-- expense review
+ALTER TABLE expenses
+ADD COLUMN status expense_status NOT NULL DEFAULT 'approved',
+    ADD COLUMN reviewer_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN review_comment VARCHAR,
+    ADD COLUMN reviewed_at TIMESTAMPTZ;
+-- notifications table
+CREATE TABLE IF NOT EXISTS notifications (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    title VARCHAR NOT NULL,
+    body VARCHAR NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE
+);
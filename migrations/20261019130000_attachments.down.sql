@@ -0,0 +1,3 @@
+-- Add down migration script here
+-- attachments table
+DROP TABLE attachments;
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- attachments table
+CREATE TABLE IF NOT EXISTS attachments (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expense_id UUID REFERENCES expenses(id) ON DELETE CASCADE,
+    income_id UUID REFERENCES incomes(id) ON DELETE CASCADE,
+    CONSTRAINT attachment_must_have_one_owner CHECK ((expense_id IS NULL) <> (income_id IS NULL)),
+    CONSTRAINT attachment_expense_name_must_be_unique UNIQUE(expense_id, name),
+    CONSTRAINT attachment_income_name_must_be_unique UNIQUE(income_id, name)
+);
@@ -0,0 +1,13 @@
+-- Add down migration script here
+-- generated expenses
+ALTER TABLE expenses DROP COLUMN schedule_id,
+    DROP COLUMN occurrence;
+-- generated incomes
+ALTER TABLE incomes DROP COLUMN schedule_id,
+    DROP COLUMN occurrence;
+-- recurring schedules table
+DROP TABLE recurring_schedules;
+-- schedule cadences
+DROP TYPE schedule_cadence;
+-- schedule kinds
+DROP TYPE schedule_kind;
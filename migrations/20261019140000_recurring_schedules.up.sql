@@ -0,0 +1,34 @@
+-- Add up migration script here
+-- schedule kinds
+CREATE TYPE schedule_kind AS ENUM ('income', 'expense');
+-- schedule cadences
+CREATE TYPE schedule_cadence AS ENUM ('monthly', 'quarterly', 'yearly');
+-- recurring schedules table
+CREATE TABLE IF NOT EXISTS recurring_schedules (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind schedule_kind NOT NULL,
+    cadence schedule_cadence NOT NULL,
+    value DOUBLE PRECISION NOT NULL CONSTRAINT schedule_value_must_be_positive CHECK (value > 0),
+    description VARCHAR NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE,
+    runs INTEGER NOT NULL DEFAULT 0,
+    next_run DATE,
+    active BOOL NOT NULL DEFAULT TRUE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    CONSTRAINT schedule_period_must_be_valid CHECK (
+        end_date IS NULL
+        OR start_date <= end_date
+    )
+);
+-- generated incomes
+ALTER TABLE incomes
+ADD COLUMN schedule_id UUID REFERENCES recurring_schedules(id) ON DELETE SET NULL,
+    ADD COLUMN occurrence DATE,
+    ADD CONSTRAINT income_occurrence_must_be_unique UNIQUE(schedule_id, occurrence);
+-- generated expenses
+ALTER TABLE expenses
+ADD COLUMN schedule_id UUID REFERENCES recurring_schedules(id) ON DELETE SET NULL,
+    ADD COLUMN occurrence DATE,
+    ADD CONSTRAINT expense_occurrence_must_be_unique UNIQUE(schedule_id, occurrence);
@@ -0,0 +1,15 @@
+-- Add down migration script here
+-- invoice summaries view
+DROP VIEW invoice_summaries;
+-- invoice payments table
+DROP TABLE invoice_payments;
+-- invoice lines table
+DROP TABLE invoice_lines;
+-- invoices table
+DROP TABLE invoices;
+-- invoice counters table
+DROP TABLE invoice_counters;
+-- invoice statuses
+DROP TYPE invoice_status;
+-- invoice states
+DROP TYPE invoice_state;
@@ -0,0 +1,90 @@
+-- Add up migration script here
+-- invoice states, paid and partially paid are derived from payments
+CREATE TYPE invoice_state AS ENUM ('draft', 'issued', 'void');
+-- invoice statuses
+CREATE TYPE invoice_status AS ENUM (
+    'draft',
+    'issued',
+    'partially_paid',
+    'paid',
+    'void'
+);
+-- invoice counters table, one gapless sequence per year
+CREATE TABLE IF NOT EXISTS invoice_counters (
+    year INTEGER NOT NULL PRIMARY KEY,
+    last_number INTEGER NOT NULL
+);
+-- invoices table
+CREATE TABLE IF NOT EXISTS invoices (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    year INTEGER,
+    number INTEGER,
+    state invoice_state NOT NULL DEFAULT 'draft',
+    issue_date DATE,
+    due_date DATE NOT NULL,
+    notes VARCHAR,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    admin_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    CONSTRAINT invoice_number_must_be_unique UNIQUE(year, number),
+    CONSTRAINT invoice_issued_must_have_number CHECK (
+        state = 'draft'
+        OR (
+            number IS NOT NULL
+            AND year IS NOT NULL
+            AND issue_date IS NOT NULL
+        )
+    )
+);
+-- invoice lines table
+CREATE TABLE IF NOT EXISTS invoice_lines (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    position INTEGER NOT NULL,
+    description VARCHAR NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL CONSTRAINT invoice_line_quantity_must_be_positive CHECK (quantity > 0),
+    unit_price DOUBLE PRECISION NOT NULL CONSTRAINT invoice_line_unit_price_must_not_be_negative CHECK (unit_price >= 0),
+    invoice_id UUID NOT NULL REFERENCES invoices(id) ON DELETE CASCADE
+);
+-- invoice payments table
+CREATE TABLE IF NOT EXISTS invoice_payments (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    value DOUBLE PRECISION NOT NULL CONSTRAINT invoice_payment_value_must_be_positive CHECK (value > 0),
+    note VARCHAR,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    admin_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    invoice_id UUID NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    income_id UUID NOT NULL REFERENCES incomes(id) ON DELETE CASCADE
+);
+-- invoice summaries view
+CREATE VIEW invoice_summaries AS
+SELECT invoices.*,
+    totals.total,
+    totals.paid,
+    (
+        CASE
+            WHEN invoices.state = 'draft' THEN 'draft'
+            WHEN invoices.state = 'void' THEN 'void'
+            WHEN totals.paid >= totals.total THEN 'paid'
+            WHEN totals.paid > 0 THEN 'partially_paid'
+            ELSE 'issued'
+        END
+    )::invoice_status AS status
+FROM invoices
+    CROSS JOIN LATERAL (
+        SELECT COALESCE(
+                (
+                    SELECT SUM(quantity * unit_price)
+                    FROM invoice_lines
+                    WHERE invoice_id = invoices.id
+                ),
+                0
+            ) AS total,
+            COALESCE(
+                (
+                    SELECT SUM(value)
+                    FROM invoice_payments
+                    WHERE invoice_id = invoices.id
+                ),
+                0
+            ) AS paid
+    ) AS totals;
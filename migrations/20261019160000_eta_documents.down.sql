@@ -0,0 +1,5 @@
+-- Add down migration script here
+-- e-invoice documents table
+DROP TABLE eta_documents;
+-- e-invoice statuses
+DROP TYPE eta_status;
@@ -0,0 +1,26 @@
+-- Add up migration script here
+-- e-invoice statuses, submitted documents wait for the tax authority validation
+CREATE TYPE eta_status AS ENUM (
+    'submitted',
+    'valid',
+    'invalid',
+    'rejected',
+    'cancelled'
+);
+-- e-invoice documents table, the last submission of every invoice
+CREATE TABLE IF NOT EXISTS eta_documents (
+    invoice_id UUID NOT NULL PRIMARY KEY REFERENCES invoices(id) ON DELETE CASCADE,
+    status eta_status NOT NULL DEFAULT 'submitted',
+    uuid VARCHAR CONSTRAINT eta_document_uuid_must_be_unique UNIQUE,
+    long_id VARCHAR,
+    submission_id VARCHAR,
+    hash VARCHAR NOT NULL,
+    document JSONB NOT NULL,
+    errors JSONB,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT eta_document_accepted_must_have_uuid CHECK (
+        status = 'rejected'
+        OR uuid IS NOT NULL
+    )
+);
@@ -0,0 +1,13 @@
+-- Add down migration script here
+-- expenses tax
+ALTER TABLE expenses DROP COLUMN net,
+    DROP COLUMN tax,
+    DROP COLUMN tax_rate,
+    DROP COLUMN tax_code;
+-- incomes tax
+ALTER TABLE incomes DROP COLUMN net,
+    DROP COLUMN tax,
+    DROP COLUMN tax_rate,
+    DROP COLUMN tax_code;
+-- tax codes
+DROP TYPE tax_code;
@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- tax codes, table tax rates depend on the item so they are stored per row
+CREATE TYPE tax_code AS ENUM ('standard', 'exempt', 'zero_rated', 'table');
+-- incomes tax, `value` stays the gross amount and `net` is derived from it
+ALTER TABLE incomes
+ADD COLUMN tax_code tax_code NOT NULL DEFAULT 'exempt',
+    ADD COLUMN tax_rate DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN tax DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN net DOUBLE PRECISION GENERATED ALWAYS AS (value - tax) STORED,
+    ADD CONSTRAINT income_tax_must_not_exceed_value CHECK (
+        tax >= 0
+        AND tax <= value
+    );
+-- expenses tax
+ALTER TABLE expenses
+ADD COLUMN tax_code tax_code NOT NULL DEFAULT 'exempt',
+    ADD COLUMN tax_rate DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN tax DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD COLUMN net DOUBLE PRECISION GENERATED ALWAYS AS (value - tax) STORED,
+    ADD CONSTRAINT expense_tax_must_not_exceed_value CHECK (
+        tax >= 0
+        AND tax <= value
+    );
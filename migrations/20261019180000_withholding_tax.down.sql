@@ -0,0 +1,6 @@
+-- Add down migration script here
+-- withholding tax
+ALTER TABLE expenses DROP COLUMN withholding,
+    DROP COLUMN withholding_rate,
+    DROP COLUMN supplier_registration,
+    DROP COLUMN supplier;
@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- withholding tax deducted from supplier payments, computed on the net amount
+ALTER TABLE expenses
+ADD COLUMN supplier VARCHAR,
+    ADD COLUMN supplier_registration VARCHAR,
+    ADD COLUMN withholding_rate DOUBLE PRECISION,
+    ADD COLUMN withholding DOUBLE PRECISION NOT NULL DEFAULT 0,
+    ADD CONSTRAINT expense_withholding_must_have_supplier CHECK (
+        withholding_rate IS NULL
+        OR supplier_registration IS NOT NULL
+    );
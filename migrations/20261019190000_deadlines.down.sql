@@ -0,0 +1,9 @@
+-- Add down migration script here
+-- deadline summaries view
+DROP VIEW deadline_summaries;
+-- deadlines table
+DROP TABLE deadlines;
+-- deadline statuses
+DROP TYPE deadline_status;
+-- deadline kinds
+DROP TYPE deadline_kind;
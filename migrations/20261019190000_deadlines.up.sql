@@ -0,0 +1,41 @@
+-- Add up migration script here
+-- deadline kinds, every kind but custom is derived from the company registrations
+CREATE TYPE deadline_kind AS ENUM (
+    'vat',
+    'withholding',
+    'income_tax',
+    'register_renewal',
+    'custom'
+);
+-- deadline statuses, derived from the due date and the fulfillment
+CREATE TYPE deadline_status AS ENUM ('upcoming', 'overdue', 'fulfilled');
+-- deadlines table, `period` is the first day of the period an obligation covers
+CREATE TABLE IF NOT EXISTS deadlines (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind deadline_kind NOT NULL DEFAULT 'custom',
+    title VARCHAR NOT NULL,
+    period DATE,
+    due_date DATE NOT NULL,
+    note VARCHAR,
+    fulfilled_at TIMESTAMPTZ,
+    fulfilled_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    admin_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    company_id UUID NOT NULL REFERENCES companies(id) ON DELETE CASCADE,
+    CONSTRAINT deadline_period_must_be_unique UNIQUE(company_id, kind, period),
+    CONSTRAINT deadline_derived_must_have_period CHECK (
+        kind = 'custom'
+        OR period IS NOT NULL
+    )
+);
+-- deadline summaries view
+CREATE VIEW deadline_summaries AS
+SELECT deadlines.*,
+    (
+        CASE
+            WHEN deadlines.fulfilled_at IS NOT NULL THEN 'fulfilled'
+            WHEN deadlines.due_date < CURRENT_DATE THEN 'overdue'
+            ELSE 'upcoming'
+        END
+    )::deadline_status AS status
+FROM deadlines;
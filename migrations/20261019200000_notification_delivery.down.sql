@@ -0,0 +1,23 @@
+-- Add down migration script here
+-- deadline reminders
+ALTER TABLE deadlines DROP COLUMN reminded_at;
+-- notifications inbox and email outbox
+DELETE FROM notifications
+WHERE user_id IS NULL;
+ALTER TABLE notifications DROP CONSTRAINT notification_must_have_recipient,
+    DROP COLUMN email_error,
+    DROP COLUMN email_attempts,
+    DROP COLUMN emailed_at,
+    DROP COLUMN email,
+    DROP COLUMN read_at,
+    DROP COLUMN kind,
+    DROP COLUMN company_id,
+    ALTER COLUMN user_id
+SET NOT NULL;
+-- notification kinds
+DROP TYPE notification_kind;
+-- user contact details
+ALTER TABLE users DROP COLUMN language,
+    DROP COLUMN email;
+-- notification languages
+DROP TYPE language;
@@ -0,0 +1,35 @@
+-- Add up migration script here
+-- notification languages
+CREATE TYPE language AS ENUM ('ar', 'en');
+-- user contact details
+ALTER TABLE users
+ADD COLUMN email VARCHAR CONSTRAINT user_email_must_be_unique UNIQUE,
+    ADD COLUMN language language NOT NULL DEFAULT 'ar';
+-- notification kinds
+CREATE TYPE notification_kind AS ENUM (
+    'general',
+    'deadline',
+    'pending_approval',
+    'expense_review',
+    'low_custody',
+    'new_document',
+    'schedule_stopped'
+);
+-- notifications inbox and email outbox, company notifications are only emailed
+-- until companies can sign in
+ALTER TABLE notifications
+ALTER COLUMN user_id DROP NOT NULL,
+    ADD COLUMN company_id UUID REFERENCES companies(id) ON DELETE CASCADE,
+    ADD COLUMN kind notification_kind NOT NULL DEFAULT 'general',
+    ADD COLUMN read_at TIMESTAMPTZ,
+    ADD COLUMN email VARCHAR,
+    ADD COLUMN emailed_at TIMESTAMPTZ,
+    ADD COLUMN email_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN email_error VARCHAR,
+    ADD CONSTRAINT notification_must_have_recipient CHECK (
+        user_id IS NOT NULL
+        OR company_id IS NOT NULL
+    );
+-- deadline reminders
+ALTER TABLE deadlines
+ADD COLUMN reminded_at TIMESTAMPTZ;
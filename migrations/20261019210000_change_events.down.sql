@@ -0,0 +1,8 @@
+-- Add down migration script here
+-- change triggers
+DROP TRIGGER funder_changed ON funders;
+DROP TRIGGER income_changed ON incomes;
+DROP TRIGGER expense_changed ON expenses;
+DROP TRIGGER company_changed ON companies;
+-- change publisher
+DROP FUNCTION notify_change;
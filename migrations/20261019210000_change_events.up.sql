@@ -0,0 +1,41 @@
+-- Add up migration script here
+-- publishes row changes on the `changes` channel for the live event stream,
+-- the arguments are the entity name and the column holding the company id
+CREATE FUNCTION notify_change() RETURNS TRIGGER AS $$
+DECLARE
+    data JSONB;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        data := to_jsonb(OLD);
+    ELSE
+        data := to_jsonb(NEW);
+    END IF;
+    PERFORM pg_notify(
+        'changes',
+        jsonb_build_object(
+            'entity', TG_ARGV[0],
+            'action', CASE TG_OP
+                WHEN 'INSERT' THEN 'created'
+                WHEN 'UPDATE' THEN 'updated'
+                ELSE 'deleted'
+            END,
+            'id', data->'id',
+            'companyId', data->TG_ARGV[1]
+        )::TEXT
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+-- change triggers
+CREATE TRIGGER company_changed
+AFTER INSERT OR UPDATE OR DELETE ON companies
+FOR EACH ROW EXECUTE FUNCTION notify_change('company', 'id');
+CREATE TRIGGER expense_changed
+AFTER INSERT OR UPDATE OR DELETE ON expenses
+FOR EACH ROW EXECUTE FUNCTION notify_change('expense', 'company_id');
+CREATE TRIGGER income_changed
+AFTER INSERT OR UPDATE OR DELETE ON incomes
+FOR EACH ROW EXECUTE FUNCTION notify_change('income', 'company_id');
+CREATE TRIGGER funder_changed
+AFTER INSERT OR UPDATE OR DELETE ON funders
+FOR EACH ROW EXECUTE FUNCTION notify_change('funder', 'company_id');
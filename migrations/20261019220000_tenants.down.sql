@@ -0,0 +1,79 @@
+-- Add down migration script here
+-- change publisher
+CREATE OR REPLACE FUNCTION notify_change() RETURNS TRIGGER AS $$
+DECLARE
+    data JSONB;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        data := to_jsonb(OLD);
+    ELSE
+        data := to_jsonb(NEW);
+    END IF;
+    PERFORM pg_notify(
+        'changes',
+        jsonb_build_object(
+            'entity', TG_ARGV[0],
+            'action', CASE TG_OP
+                WHEN 'INSERT' THEN 'created'
+                WHEN 'UPDATE' THEN 'updated'
+                ELSE 'deleted'
+            END,
+            'id', data->'id',
+            'companyId', data->TG_ARGV[1]
+        )::TEXT
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+-- global uniqueness, fails when several tenants share a value
+ALTER TABLE invoice_counters DROP CONSTRAINT invoice_counters_pkey,
+    ADD PRIMARY KEY (year);
+ALTER TABLE invoices DROP CONSTRAINT invoice_number_must_be_unique,
+    ADD CONSTRAINT invoice_number_must_be_unique UNIQUE(year, number);
+ALTER TABLE companies DROP CONSTRAINT company_must_be_unique,
+    ADD CONSTRAINT company_must_be_unique UNIQUE(owner, commercial_feature),
+    DROP CONSTRAINT company_username_must_be_unique,
+    ADD CONSTRAINT company_username_must_be_unique UNIQUE(username),
+    DROP CONSTRAINT company_email_must_be_unique,
+    ADD CONSTRAINT company_email_must_be_unique UNIQUE(email);
+ALTER TABLE users DROP CONSTRAINT user_name_must_be_unique,
+    ADD CONSTRAINT user_name_must_be_unique UNIQUE(name),
+    DROP CONSTRAINT user_email_must_be_unique,
+    ADD CONSTRAINT user_email_must_be_unique UNIQUE(email);
+-- views
+REVOKE SELECT ON invoice_summaries, deadline_summaries FROM accounting_tenant;
+ALTER VIEW deadline_summaries RESET (security_invoker);
+ALTER VIEW invoice_summaries RESET (security_invoker);
+-- tenant columns
+DO $$
+DECLARE
+    table_name TEXT;
+BEGIN
+    FOREACH table_name IN ARRAY ARRAY[
+        'users',
+        'companies',
+        'funders',
+        'incomes',
+        'expenses',
+        'profit_distributions',
+        'profit_distribution_entries',
+        'custody_transactions',
+        'notifications',
+        'attachments',
+        'recurring_schedules',
+        'invoice_counters',
+        'invoices',
+        'invoice_lines',
+        'invoice_payments',
+        'eta_documents',
+        'deadlines'
+    ] LOOP
+        EXECUTE format('REVOKE ALL ON %I FROM accounting_tenant', table_name);
+        EXECUTE format('DROP POLICY tenant_isolation ON %I', table_name);
+        EXECUTE format('ALTER TABLE %I DISABLE ROW LEVEL SECURITY', table_name);
+        EXECUTE format('ALTER TABLE %I DROP COLUMN tenant_id', table_name);
+    END LOOP;
+END $$;
+-- the role is kept, other databases of the cluster may use it
+DROP TABLE super_admins;
+DROP TABLE tenants;
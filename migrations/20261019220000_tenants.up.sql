@@ -0,0 +1,114 @@
+-- Add up migration script here
+-- tenants table, every accounting office hosted by the deployment
+CREATE TABLE IF NOT EXISTS tenants (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT tenant_name_must_be_unique UNIQUE(name)
+);
+-- the office of the data stored before tenants
+INSERT INTO tenants (name)
+VALUES ('default');
+-- super admins table, they manage the tenants and see none of their data
+CREATE TABLE IF NOT EXISTS super_admins (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR NOT NULL,
+    password VARCHAR NOT NULL,
+    CONSTRAINT super_admin_name_must_be_unique UNIQUE(name)
+);
+INSERT INTO super_admins (name, password)
+VALUES ('admin', 'admin');
+-- the role the tenant connections switch to, row level security does not
+-- apply to the owner of the tables
+DO $$ BEGIN
+    IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'accounting_tenant') THEN
+        CREATE ROLE accounting_tenant NOLOGIN;
+    END IF;
+END $$;
+GRANT accounting_tenant TO CURRENT_USER;
+-- tenant columns, the rows inserted by a tenant connection belong to its tenant
+DO $$
+DECLARE
+    table_name TEXT;
+BEGIN
+    FOREACH table_name IN ARRAY ARRAY[
+        'users',
+        'companies',
+        'funders',
+        'incomes',
+        'expenses',
+        'profit_distributions',
+        'profit_distribution_entries',
+        'custody_transactions',
+        'notifications',
+        'attachments',
+        'recurring_schedules',
+        'invoice_counters',
+        'invoices',
+        'invoice_lines',
+        'invoice_payments',
+        'eta_documents',
+        'deadlines'
+    ] LOOP
+        EXECUTE format('ALTER TABLE %I ADD COLUMN tenant_id UUID REFERENCES tenants(id) ON DELETE CASCADE', table_name);
+        EXECUTE format('UPDATE %I SET tenant_id = (SELECT id FROM tenants)', table_name);
+        EXECUTE format(
+            'ALTER TABLE %I ALTER COLUMN tenant_id SET NOT NULL, '
+            'ALTER COLUMN tenant_id SET DEFAULT current_setting(''app.tenant_id'')::UUID',
+            table_name
+        );
+        EXECUTE format('ALTER TABLE %I ENABLE ROW LEVEL SECURITY', table_name);
+        EXECUTE format(
+            'CREATE POLICY tenant_isolation ON %I '
+            'USING (tenant_id = NULLIF(current_setting(''app.tenant_id'', TRUE), '''')::UUID)',
+            table_name
+        );
+        EXECUTE format('GRANT SELECT, INSERT, UPDATE, DELETE ON %I TO accounting_tenant', table_name);
+    END LOOP;
+END $$;
+-- the views read the rows the querying tenant sees
+ALTER VIEW invoice_summaries SET (security_invoker = TRUE);
+ALTER VIEW deadline_summaries SET (security_invoker = TRUE);
+GRANT SELECT ON invoice_summaries, deadline_summaries TO accounting_tenant;
+-- uniqueness within a tenant
+ALTER TABLE users DROP CONSTRAINT user_name_must_be_unique,
+    ADD CONSTRAINT user_name_must_be_unique UNIQUE(tenant_id, name),
+    DROP CONSTRAINT user_email_must_be_unique,
+    ADD CONSTRAINT user_email_must_be_unique UNIQUE(tenant_id, email);
+ALTER TABLE companies DROP CONSTRAINT company_must_be_unique,
+    ADD CONSTRAINT company_must_be_unique UNIQUE(tenant_id, owner, commercial_feature),
+    DROP CONSTRAINT company_username_must_be_unique,
+    ADD CONSTRAINT company_username_must_be_unique UNIQUE(tenant_id, username),
+    DROP CONSTRAINT company_email_must_be_unique,
+    ADD CONSTRAINT company_email_must_be_unique UNIQUE(tenant_id, email);
+ALTER TABLE invoices DROP CONSTRAINT invoice_number_must_be_unique,
+    ADD CONSTRAINT invoice_number_must_be_unique UNIQUE(tenant_id, year, number);
+ALTER TABLE invoice_counters DROP CONSTRAINT invoice_counters_pkey,
+    ADD PRIMARY KEY (tenant_id, year);
+-- change publisher, the streams only relay the changes of their tenant
+CREATE OR REPLACE FUNCTION notify_change() RETURNS TRIGGER AS $$
+DECLARE
+    data JSONB;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        data := to_jsonb(OLD);
+    ELSE
+        data := to_jsonb(NEW);
+    END IF;
+    PERFORM pg_notify(
+        'changes',
+        jsonb_build_object(
+            'entity', TG_ARGV[0],
+            'action', CASE TG_OP
+                WHEN 'INSERT' THEN 'created'
+                WHEN 'UPDATE' THEN 'updated'
+                ELSE 'deleted'
+            END,
+            'id', data->'id',
+            'companyId', data->TG_ARGV[1],
+            'tenantId', data->'tenant_id'
+        )::TEXT
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,12 @@
+-- Add down migration script here
+-- two factor policy
+REVOKE ALL ON tenants FROM accounting_tenant;
+DROP POLICY tenant_isolation ON tenants;
+ALTER TABLE tenants DISABLE ROW LEVEL SECURITY;
+ALTER TABLE tenants DROP COLUMN admins_require_two_factor;
+-- recovery codes
+DROP TABLE recovery_codes;
+-- authenticators
+ALTER TABLE users DROP COLUMN totp_last_step,
+    DROP COLUMN totp_enabled,
+    DROP COLUMN totp_secret;
@@ -0,0 +1,25 @@
+-- Add up migration script here
+-- the authenticator of a user, the secret is pending until a code confirms it
+ALTER TABLE users
+ADD COLUMN totp_secret VARCHAR,
+    ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN totp_last_step BIGINT;
+-- single use codes for a lost authenticator, only their hashes are kept
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL DEFAULT current_setting('app.tenant_id')::UUID REFERENCES tenants(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR NOT NULL,
+    used_at TIMESTAMPTZ
+);
+ALTER TABLE recovery_codes ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON recovery_codes
+USING (tenant_id = NULLIF(current_setting('app.tenant_id', TRUE), '')::UUID);
+GRANT SELECT, INSERT, UPDATE, DELETE ON recovery_codes TO accounting_tenant;
+-- the admins of an office may be required to use an authenticator
+ALTER TABLE tenants
+ADD COLUMN admins_require_two_factor BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE tenants ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON tenants
+USING (id = NULLIF(current_setting('app.tenant_id', TRUE), '')::UUID);
+GRANT SELECT, UPDATE (admins_require_two_factor) ON tenants TO accounting_tenant;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE login_throttles;
+DROP TABLE login_attempts;
+DROP TYPE login_outcome;
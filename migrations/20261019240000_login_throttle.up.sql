@@ -0,0 +1,25 @@
+-- Add up migration script here
+-- the sign ins of the staff, the failures are the audit trail of the guessed
+-- passwords, `tenant_id` is NULL for an unknown office
+CREATE TYPE login_outcome AS ENUM ('succeeded', 'failed', 'locked');
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID REFERENCES tenants(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    ip VARCHAR,
+    outcome login_outcome NOT NULL,
+    time TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX login_attempts_time ON login_attempts (tenant_id, time);
+ALTER TABLE login_attempts ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON login_attempts
+USING (tenant_id = NULLIF(current_setting('app.tenant_id', TRUE), '')::UUID);
+GRANT SELECT ON login_attempts TO accounting_tenant;
+-- the consecutive failures of a user or an address, their next attempt waits
+-- until `locked_until`, `key` is `user:<id>` or `ip:<address>`
+CREATE TABLE IF NOT EXISTS login_throttles (
+    key VARCHAR NOT NULL PRIMARY KEY,
+    failures INT NOT NULL,
+    last_failure TIMESTAMPTZ NOT NULL,
+    locked_until TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,18 @@
+-- Add up migration script here
+-- the signed in devices of the users, a user token is only valid while its
+-- session exists
+CREATE TABLE IF NOT EXISTS sessions (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL DEFAULT current_setting('app.tenant_id')::UUID REFERENCES tenants(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    device_name VARCHAR,
+    ip VARCHAR,
+    user_agent VARCHAR,
+    time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX sessions_user ON sessions (user_id);
+ALTER TABLE sessions ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON sessions
+USING (tenant_id = NULLIF(current_setting('app.tenant_id', TRUE), '')::UUID);
+GRANT SELECT, INSERT, DELETE ON sessions TO accounting_tenant;
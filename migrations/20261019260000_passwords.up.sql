@@ -0,0 +1,25 @@
+-- Add up migration script here
+-- users signing in with a password given to them change it first
+ALTER TABLE users
+ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
+-- the seeded admin still using the published password
+UPDATE users
+SET must_change_password = TRUE
+WHERE name = 'admin'
+    AND password = 'admin';
+-- single use tokens an admin hands a user to choose a new password, only
+-- their hashes are kept
+CREATE TABLE IF NOT EXISTS password_resets (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL DEFAULT current_setting('app.tenant_id')::UUID REFERENCES tenants(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CONSTRAINT password_reset_token_must_be_unique UNIQUE(token_hash)
+);
+ALTER TABLE password_resets ENABLE ROW LEVEL SECURITY;
+CREATE POLICY tenant_isolation ON password_resets
+USING (tenant_id = NULLIF(current_setting('app.tenant_id', TRUE), '')::UUID);
+GRANT SELECT, INSERT, UPDATE, DELETE ON password_resets TO accounting_tenant;
@@ -0,0 +1,42 @@
+-- Add down migration script here
+CREATE OR REPLACE VIEW invoice_summaries WITH (security_invoker = TRUE) AS
+SELECT invoices.id,
+    invoices.year,
+    invoices.number,
+    invoices.state,
+    invoices.issue_date,
+    invoices.due_date,
+    invoices.notes,
+    invoices.time,
+    invoices.admin_id,
+    invoices.company_id,
+    totals.total,
+    totals.paid,
+    (
+        CASE
+            WHEN invoices.state = 'draft' THEN 'draft'
+            WHEN invoices.state = 'void' THEN 'void'
+            WHEN totals.paid >= totals.total THEN 'paid'
+            WHEN totals.paid > 0 THEN 'partially_paid'
+            ELSE 'issued'
+        END
+    )::invoice_status AS status
+FROM invoices
+    CROSS JOIN LATERAL (
+        SELECT COALESCE(
+                (
+                    SELECT SUM(quantity * unit_price)
+                    FROM invoice_lines
+                    WHERE invoice_id = invoices.id
+                ),
+                0
+            ) AS total,
+            COALESCE(
+                (
+                    SELECT SUM(value)
+                    FROM invoice_payments
+                    WHERE invoice_id = invoices.id
+                ),
+                0
+            ) AS paid
+    ) AS totals;
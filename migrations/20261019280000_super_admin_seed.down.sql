@@ -0,0 +1,2 @@
+-- Add down migration script here
+-- the seeded credential is not restored
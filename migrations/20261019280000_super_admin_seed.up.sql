@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- the super admin seeded with a known password, the first super admin is
+-- created from the configuration instead
+DELETE FROM super_admins
+WHERE name = 'admin'
+    AND password = 'admin';
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE companies DROP COLUMN IF EXISTS portal_session;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- the portal tokens carry the session of the company, a new password or user
+-- name replaces it and signs the portal out
+ALTER TABLE companies
+ADD COLUMN portal_session UUID NOT NULL DEFAULT gen_random_uuid();
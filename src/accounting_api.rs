@@ -1,24 +1,145 @@
-use std::{borrow::Cow, path::Path};
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
 
-use rocket::{async_trait, fs::TempFile};
+use chrono::NaiveDate;
+use rocket::{async_trait, fs::TempFile, futures::stream::BoxStream, serde::json::Value};
 use sqlx::types::Uuid;
 
-use crate::local_storage::models::*;
+use crate::{file_system::MemoryFile, i18n::Message, local_storage::models::*};
 
-use thiserror::Error;
-
-#[derive(Debug, Error)]
+#[derive(Debug)]
 pub enum Error {
-    #[error("لم يتم العثور علي هدف")]
     ObjectNotFound,
-    #[error("لا يوجد قيمة كافية: \"{0} > {1}\"")]
     NotEnoughUserValue(f64, f64),
-    #[error("قيمة غير صحيحة:  >= 0")]
     InvalidValue,
-    #[error("حدث خطأ في قاعدة البيانات:\n {0}")]
+    InvalidTaxRate,
+    InvalidWithholding,
+    DeadlineAlreadyFulfilled,
+    DerivedDeadline,
+    InvalidTaxPeriod,
+    ExpenseNotPending,
+    /// a reviewer approving an expense they spent
+    OwnExpenseReview,
+    InvalidInvoiceState,
+    EmptyInvoice,
+    PaymentExceedsBalance(f64, f64),
+    InvalidFundersPercentage(f64),
+    UnreadableSheet(Cow<'static, str>),
+    EmptySheet,
+    /// a mapped field the imported rows do not have
+    UnknownField(String),
+    MissingColumn(String),
+    EtaNotConfigured,
+    EtaAlreadySubmitted,
+    MissingTaxRegistration,
+    Eta(Cow<'static, str>),
+    InvalidCredentials,
+    /// the seconds before the user or the address may try to login again
+    LoginLocked(i64),
+    InvalidTwoFactorCode,
+    /// the minimum length of the password policy
+    PasswordTooShort(usize),
+    /// the password is in the bundled breach list
+    BreachedPassword,
+    /// the old password given to change it
+    WrongPassword,
+    /// an unknown, used or expired reset token
+    InvalidResetToken,
+    /// enabling without a pending authenticator or setting up over an enabled one
+    InvalidTwoFactorState,
+    /// the admins of the office must keep their authenticator
+    TwoFactorEnforced,
+    /// a unique constraint, `field` is the API field holding the taken value
+    Duplicate {
+        field: Option<&'static str>,
+        message: Message,
+    },
+    /// a check constraint or a reference to a missing row
+    Constraint {
+        field: Option<&'static str>,
+        message: Message,
+    },
     Other(Cow<'static, str>),
 }
 
+/// logged in the default language, responses localize `Error::message`
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message().localize(Language::default()))
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    pub fn message(&self) -> Message {
+        match self {
+            Self::ObjectNotFound => "error.notFound".into(),
+            Self::NotEnoughUserValue(value, available) => Message::new("error.notEnoughValue")
+                .arg(value)
+                .arg(available),
+            Self::InvalidValue => "error.invalidValue".into(),
+            Self::InvalidTaxRate => "error.invalidTaxRate".into(),
+            Self::InvalidWithholding => "error.invalidWithholding".into(),
+            Self::DeadlineAlreadyFulfilled => "error.deadlineAlreadyFulfilled".into(),
+            Self::DerivedDeadline => "error.derivedDeadline".into(),
+            Self::InvalidTaxPeriod => "error.invalidTaxPeriod".into(),
+            Self::ExpenseNotPending => "error.expenseNotPending".into(),
+            Self::OwnExpenseReview => "error.ownExpenseReview".into(),
+            Self::InvalidInvoiceState => "error.invalidInvoiceState".into(),
+            Self::EmptyInvoice => "error.emptyInvoice".into(),
+            Self::PaymentExceedsBalance(value, balance) => {
+                Message::new("error.paymentExceedsBalance")
+                    .arg(value)
+                    .arg(balance)
+            }
+            Self::InvalidFundersPercentage(total) => {
+                Message::new("error.invalidFundersPercentage").arg(total)
+            }
+            Self::UnreadableSheet(reason) => Message::new("error.unreadableSheet").arg(reason),
+            Self::EmptySheet => "error.emptySheet".into(),
+            Self::UnknownField(field) => Message::new("error.unknownField").arg(field),
+            Self::MissingColumn(column) => Message::new("error.missingColumn").arg(column),
+            Self::EtaNotConfigured => "error.etaNotConfigured".into(),
+            Self::EtaAlreadySubmitted => "error.etaAlreadySubmitted".into(),
+            Self::MissingTaxRegistration => "error.missingTaxRegistration".into(),
+            Self::Eta(reason) => Message::new("error.eta").arg(reason),
+            Self::InvalidCredentials => "error.invalidCredentials".into(),
+            Self::LoginLocked(wait) => Message::new("error.loginLocked").arg(wait),
+            Self::InvalidTwoFactorCode => "error.invalidTwoFactorCode".into(),
+            Self::PasswordTooShort(length) => Message::new("error.passwordTooShort").arg(length),
+            Self::BreachedPassword => "error.breachedPassword".into(),
+            Self::WrongPassword => "error.wrongPassword".into(),
+            Self::InvalidResetToken => "error.invalidResetToken".into(),
+            Self::InvalidTwoFactorState => "error.invalidTwoFactorState".into(),
+            Self::TwoFactorEnforced => "error.twoFactorEnforced".into(),
+            Self::Duplicate { message, .. } | Self::Constraint { message, .. } => message.clone(),
+            Self::Other(reason) => Message::new("error.other").arg(reason),
+        }
+    }
+
+    /// the request field the error is about, when there is one
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Self::NotEnoughUserValue(..) | Self::InvalidValue | Self::PaymentExceedsBalance(..) => {
+                Some("value")
+            }
+            Self::InvalidTaxRate => Some("taxRate"),
+            Self::InvalidWithholding => Some("withholdingRate"),
+            Self::InvalidFundersPercentage(_) => Some("percentage"),
+            Self::InvalidTwoFactorCode => Some("code"),
+            Self::PasswordTooShort(_) | Self::BreachedPassword => Some("password"),
+            Self::WrongPassword => Some("oldPassword"),
+            Self::InvalidResetToken => Some("token"),
+            Self::Duplicate { field, .. } | Self::Constraint { field, .. } => *field,
+            _ => None,
+        }
+    }
+}
+
 #[async_trait]
 pub trait AcountingApi {
     type Company;
@@ -27,14 +148,79 @@ pub trait AcountingApi {
     type Income;
     type Document;
     type Funder;
+    type ProfitDistribution;
+    type CustodyTransaction;
+    type Notification;
+    type Attachment;
+    type Schedule;
+    type Invoice;
+    type ReceivablesAging;
+    type CompanyStatementEntry;
+    type ImportError;
+    type EtaDocument;
+    type VatReturn;
+    type VatReturnEntry;
+    type WithholdingStatement;
+    type WithholdingEntry;
+    type Deadline;
+    type PendingEmail;
     type Error;
 
     async fn create_company(&self, c: &CreateCompany) -> Result<Self::Company, Error>;
 
     async fn update_company(&self, id: Uuid, c: &UpdateCompany) -> Result<Self::Company, Error>;
 
+    async fn get_company(&self, id: Uuid) -> Result<Self::Company, Error>;
+
+    /// the company of the portal credentials
+    async fn login_company(&self, c: &LoginCompany) -> Result<Self::Company, Error>;
+
     async fn search_company(&self, s: &str) -> Result<Vec<Self::Company>, Error>;
 
+    /// same as `search_company` row by row, for exports
+    fn stream_companies(&self, s: String) -> BoxStream<'_, Result<Self::Company, Error>>;
+
+    async fn get_company_statement(
+        &self,
+        company_id: Uuid,
+    ) -> Result<Vec<Self::CompanyStatementEntry>, Error>;
+
+    /// creates the companies in one transaction that is committed only when
+    /// `commit` is set and every row was inserted, returns the failed rows
+    async fn import_companies(
+        &self,
+        rows: &[(usize, CreateCompany)],
+        commit: bool,
+    ) -> Result<Vec<Self::ImportError>, Error>;
+
+    /// output and input tax of the month starting at `period`
+    async fn get_vat_return(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+    ) -> Result<Self::VatReturn, Error>;
+
+    /// the taxed rows of the month starting at `period`, for the tax portal
+    fn stream_vat_entries(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+        side: VatSide,
+    ) -> BoxStream<'_, Result<Self::VatReturnEntry, Error>>;
+
+    /// the withholding tax deducted in the quarter starting at `period`
+    async fn get_withholding_statement(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+    ) -> Result<Self::WithholdingStatement, Error>;
+
+    fn stream_withholding_entries(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+    ) -> BoxStream<'_, Result<Self::WithholdingEntry, Error>>;
+
     async fn pay_company(&self, c: &Self::Company, v: f64) -> Result<Self::Company, Error>;
 
     async fn delete_company(&self, id: Uuid) -> Result<(), Error>;
@@ -45,36 +231,174 @@ pub trait AcountingApi {
         f: &CreateFunder,
     ) -> Result<Self::Funder, Error>;
     async fn get_funders(&self, company_id: Uuid) -> Result<Vec<Self::Funder>, Error>;
+    async fn update_funder(&self, id: Uuid, f: &UpdateFunder) -> Result<Self::Funder, Error>;
     async fn delete_funder(&self, id: Uuid) -> Result<(), Error>;
 
+    async fn create_distribution(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        d: &CreateDistribution,
+    ) -> Result<Self::ProfitDistribution, Error>;
+    async fn get_distributions(
+        &self,
+        company_id: Uuid,
+    ) -> Result<Vec<Self::ProfitDistribution>, Error>;
+    async fn get_distribution_statement(
+        &self,
+        company_id: Uuid,
+        distribution_id: Uuid,
+        funder_id: Uuid,
+    ) -> Result<PathBuf, Error>;
+
     async fn register_user(&self, u: &RegisterUser) -> Result<Self::User, Error>;
 
     async fn update_user(&self, id: Uuid, u: &UpdateUser) -> Result<Self::User, Error>;
 
     async fn get_users(&self) -> Result<Vec<Self::User>, Error>;
 
-    async fn pay_user(&self, id: Uuid, v: f64) -> Result<Self::User, Error>;
+    async fn pay_user(
+        &self,
+        actor_id: Uuid,
+        id: Uuid,
+        t: &CreateCustodyTransaction,
+    ) -> Result<Self::User, Error>;
+
+    async fn adjust_user(
+        &self,
+        actor_id: Uuid,
+        id: Uuid,
+        t: &CreateCustodyTransaction,
+    ) -> Result<Self::User, Error>;
+
+    async fn get_user_statement(&self, id: Uuid) -> Result<Vec<Self::CustodyTransaction>, Error>;
 
     async fn get_user(&self, id: Uuid) -> Result<Self::User, Error>;
 
     async fn login_user(&self, u: &LoginUser) -> Result<Self::User, Error>;
 
+    /// the latest attempts first
+    async fn get_login_attempts(
+        &self,
+        outcome: Option<LoginOutcome>,
+    ) -> Result<Vec<LoginAttempt>, Error>;
+
+    async fn get_two_factor(&self, user_id: Uuid) -> Result<TwoFactorStatus, Error>;
+
+    /// replaces the pending authenticator of the user, an enabled one must be
+    /// disabled first
+    async fn setup_two_factor(&self, user_id: Uuid) -> Result<TwoFactorSetup, Error>;
+
+    /// enables the pending authenticator when `code` is one of its codes,
+    /// returns new recovery codes
+    async fn enable_two_factor(&self, user_id: Uuid, code: &str) -> Result<Vec<String>, Error>;
+
+    /// checks an authenticator code or uses up a recovery code
+    async fn verify_two_factor(&self, user_id: Uuid, code: &str) -> Result<(), Error>;
+
+    /// replaces the recovery codes of the user, `code` is an authenticator code
+    async fn regenerate_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<Vec<String>, Error>;
+
+    async fn disable_two_factor(&self, user_id: Uuid) -> Result<(), Error>;
+
+    async fn get_two_factor_policy(&self) -> Result<TwoFactorPolicy, Error>;
+
+    async fn update_two_factor_policy(&self, p: &TwoFactorPolicy)
+        -> Result<TwoFactorPolicy, Error>;
+
     async fn delete_user(&self, id: Uuid) -> Result<(), Error>;
 
+    /// the sessions of the user are closed, the other devices sign in again
+    async fn change_password(&self, user_id: Uuid, c: &ChangePassword) -> Result<(), Error>;
+
+    async fn create_password_reset(&self, user_id: Uuid) -> Result<PasswordReset, Error>;
+
+    /// uses up the reset token, the sessions of its user are closed
+    async fn reset_password(&self, r: &ResetPassword) -> Result<(), Error>;
+
+    async fn create_session(&self, user_id: Uuid, s: &CreateSession) -> Result<Session, Error>;
+
+    /// the open sessions of the user, `current` is the session of the request
+    async fn get_sessions(&self, user_id: Uuid, current: Uuid) -> Result<Vec<Session>, Error>;
+
+    /// signs one device of the user out
+    async fn delete_session(&self, user_id: Uuid, id: Uuid) -> Result<(), Error>;
+
+    /// signs every device of the user out
+    async fn delete_sessions(&self, user_id: Uuid) -> Result<(), Error>;
+
     async fn get_expenses(
         &self,
         user_id: Option<Uuid>,
         company_id: Option<Uuid>,
+        status: Option<ExpenseStatus>,
     ) -> Result<Vec<Self::Expense>, Error>;
 
+    fn stream_expenses(
+        &self,
+        user_id: Option<Uuid>,
+        company_id: Option<Uuid>,
+        status: Option<ExpenseStatus>,
+    ) -> BoxStream<'_, Result<Self::Expense, Error>>;
+
+    async fn get_expense(&self, id: Uuid) -> Result<Self::Expense, Error>;
+
     async fn create_expense(
         &self,
         user_id: Uuid,
         company_id: Uuid,
         expense: &CreateExpense,
+        files: &mut [TempFile<'_>],
     ) -> Result<Self::Expense, Error>;
 
-    async fn delete_expense(&self, id: Uuid) -> Result<(), Error>;
+    async fn approve_expense(
+        &self,
+        reviewer_id: Uuid,
+        id: Uuid,
+        review: &ReviewExpense,
+    ) -> Result<Self::Expense, Error>;
+
+    async fn reject_expense(
+        &self,
+        reviewer_id: Uuid,
+        id: Uuid,
+        review: &ReviewExpense,
+    ) -> Result<Self::Expense, Error>;
+
+    /// records approved expenses without touching the custody, same
+    /// transaction rules as `import_companies`
+    async fn import_expenses(
+        &self,
+        admin_id: Uuid,
+        rows: &[(usize, ImportExpense)],
+        commit: bool,
+    ) -> Result<Vec<Self::ImportError>, Error>;
+
+    async fn delete_expense(&self, actor_id: Uuid, id: Uuid) -> Result<(), Error>;
+
+    async fn get_notifications(
+        &self,
+        user_id: Uuid,
+        unread_only: bool,
+    ) -> Result<Vec<Self::Notification>, Error>;
+    async fn count_unread_notifications(&self, user_id: Uuid) -> Result<i64, Error>;
+    async fn mark_notification_read(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        read: bool,
+    ) -> Result<Self::Notification, Error>;
+    /// returns how many notifications were marked
+    async fn mark_all_notifications_read(&self, user_id: Uuid) -> Result<u64, Error>;
+    /// the oldest unsent emails that failed less than `max_attempts` times
+    async fn get_pending_emails(&self, max_attempts: i32)
+        -> Result<Vec<Self::PendingEmail>, Error>;
+    /// records a delivery attempt, `error` is `None` when the email was sent
+    async fn mark_email_sent(&self, id: Uuid, error: Option<&str>) -> Result<(), Error>;
 
     async fn get_incomes(
         &self,
@@ -82,21 +406,163 @@ pub trait AcountingApi {
         company_id: Option<Uuid>,
     ) -> Result<Vec<Self::Income>, Error>;
 
+    fn stream_incomes(
+        &self,
+        admin_id: Option<Uuid>,
+        company_id: Option<Uuid>,
+    ) -> BoxStream<'_, Result<Self::Income, Error>>;
+
+    async fn get_income(&self, id: Uuid) -> Result<Self::Income, Error>;
+
     async fn create_income(
         &self,
         admin_id: Uuid,
         company_id: Uuid,
         income: &CreateIncome,
+        files: &mut [TempFile<'_>],
     ) -> Result<Self::Income, Error>;
 
+    async fn import_incomes(
+        &self,
+        admin_id: Uuid,
+        rows: &[(usize, ImportIncome)],
+        commit: bool,
+    ) -> Result<Vec<Self::ImportError>, Error>;
+
     async fn delete_income(&self, id: Uuid) -> Result<(), Error>;
 
+    /// all the files are saved or none
+    async fn create_expense_attachments(
+        &self,
+        expense_id: Uuid,
+        files: &mut [TempFile<'_>],
+    ) -> Result<Vec<Self::Attachment>, Error>;
+
+    async fn create_income_attachments(
+        &self,
+        income_id: Uuid,
+        files: &mut [TempFile<'_>],
+    ) -> Result<Vec<Self::Attachment>, Error>;
+
+    async fn get_attachment(&self, owner_id: Uuid, id: Uuid) -> Result<PathBuf, Error>;
+
+    async fn delete_attachment(&self, owner_id: Uuid, id: Uuid) -> Result<(), Error>;
+
     async fn create_document(
         &self,
         company_id: Uuid,
         file: &mut TempFile<'_>,
     ) -> Result<Self::Document, Error>;
 
+    /// saves a generated file into the company documents folder
+    async fn save_company_document(
+        &self,
+        company_id: Uuid,
+        file: MemoryFile,
+    ) -> Result<Self::Document, Error>;
+
     async fn get_documents(&self, company_id: Uuid) -> Result<Vec<Self::Document>, Error>;
-    async fn delete_document(&self, path: impl AsRef<Path> + Send) -> Result<(), Error>;
+    /// `name` is one of the documents of the company, never a path
+    async fn delete_document(&self, company_id: Uuid, name: &str) -> Result<(), Error>;
+
+    async fn create_schedule(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        schedule: &CreateSchedule,
+    ) -> Result<Self::Schedule, Error>;
+    async fn get_schedule(&self, id: Uuid) -> Result<Self::Schedule, Error>;
+    async fn get_schedules(&self, company_id: Option<Uuid>) -> Result<Vec<Self::Schedule>, Error>;
+    async fn update_schedule(
+        &self,
+        id: Uuid,
+        schedule: &UpdateSchedule,
+    ) -> Result<Self::Schedule, Error>;
+    async fn delete_schedule(&self, id: Uuid) -> Result<(), Error>;
+
+    /// generates the incomes and expenses of every schedule due until `today`,
+    /// returns how many rows were generated
+    async fn run_schedules(&self, today: NaiveDate) -> Result<u64, Error>;
+
+    async fn create_invoice(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        invoice: &CreateInvoice,
+    ) -> Result<Self::Invoice, Error>;
+    async fn get_invoice(&self, id: Uuid) -> Result<Self::Invoice, Error>;
+    async fn get_invoices(
+        &self,
+        company_id: Option<Uuid>,
+        status: Option<InvoiceStatus>,
+    ) -> Result<Vec<Self::Invoice>, Error>;
+    async fn update_invoice(
+        &self,
+        id: Uuid,
+        invoice: &UpdateInvoice,
+    ) -> Result<Self::Invoice, Error>;
+    async fn delete_invoice(&self, id: Uuid) -> Result<(), Error>;
+    /// assigns the next gapless number of the issue year
+    async fn issue_invoice(&self, id: Uuid, issue_date: NaiveDate) -> Result<Self::Invoice, Error>;
+    async fn void_invoice(&self, id: Uuid) -> Result<Self::Invoice, Error>;
+    /// records a payment and the matching income row
+    async fn create_invoice_payment(
+        &self,
+        admin_id: Uuid,
+        id: Uuid,
+        payment: &CreateInvoicePayment,
+    ) -> Result<Self::Invoice, Error>;
+    async fn get_receivables_aging(
+        &self,
+        today: NaiveDate,
+        company_id: Option<Uuid>,
+    ) -> Result<Vec<Self::ReceivablesAging>, Error>;
+
+    async fn get_eta_document(&self, invoice_id: Uuid) -> Result<Self::EtaDocument, Error>;
+
+    /// documents still waiting for the tax authority validation
+    async fn get_submitted_eta_documents(&self) -> Result<Vec<Self::EtaDocument>, Error>;
+
+    /// records a submission, an invoice can only be submitted again after its
+    /// last document was rejected or found invalid
+    async fn save_eta_document(
+        &self,
+        invoice_id: Uuid,
+        d: &CreateEtaDocument,
+    ) -> Result<Self::EtaDocument, Error>;
+
+    async fn update_eta_status(
+        &self,
+        invoice_id: Uuid,
+        status: EtaStatus,
+        errors: Option<&Value>,
+    ) -> Result<Self::EtaDocument, Error>;
+
+    async fn create_deadline(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        d: &CreateDeadline,
+    ) -> Result<Self::Deadline, Error>;
+    async fn get_deadline(&self, id: Uuid) -> Result<Self::Deadline, Error>;
+    /// ordered by due date, `status` defaults to the unfulfilled ones
+    async fn get_deadlines(
+        &self,
+        company_id: Option<Uuid>,
+        status: Option<DeadlineStatus>,
+    ) -> Result<Vec<Self::Deadline>, Error>;
+    async fn fulfill_deadline(
+        &self,
+        actor_id: Uuid,
+        id: Uuid,
+        d: &FulfillDeadline,
+    ) -> Result<Self::Deadline, Error>;
+    /// only custom deadlines can be deleted, derived ones come back on the next sync
+    async fn delete_deadline(&self, id: Uuid) -> Result<(), Error>;
+    /// records the obligations of every company falling due between `from` and
+    /// `until`, returns how many were added
+    async fn sync_deadlines(&self, from: NaiveDate, until: NaiveDate) -> Result<u64, Error>;
+    /// notifies the admins and the companies once about the unfulfilled
+    /// deadlines due until `until`, returns how many were reminded
+    async fn remind_deadlines(&self, until: NaiveDate) -> Result<u64, Error>;
 }
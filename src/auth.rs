@@ -1,6 +1,9 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, env, sync::OnceLock};
+
+use chrono::Utc;
 
 use rocket::{
+    fairing::AdHoc,
     http::Status,
     request::FromRequest,
     request::Outcome,
@@ -8,34 +11,176 @@ use rocket::{
     Request,
 };
 
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    okapi::openapi3::{Object, SecurityRequirement, SecurityScheme, SecuritySchemeData},
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use schemars::JsonSchema;
+
+use jsonwebtoken::{
+    decode, encode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
 use sqlx::types::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::local_storage::Tenants;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 struct Claims {
     sub: Uuid,
+    /// the office of the user, `None` for the super admins
+    #[serde(default)]
+    tenant: Option<Uuid>,
     is_admin: bool,
+    #[serde(default)]
+    is_reviewer: bool,
+    #[serde(default)]
+    is_super_admin: bool,
+    /// a business owner signed in to the portal, `sub` is their company
+    #[serde(default)]
+    is_company: bool,
+    /// a user who gave their password and still has to give an authenticator
+    /// code, or to set one up
+    #[serde(default)]
+    pre_auth: bool,
+    /// a user who must change their password before signing in
+    #[serde(default)]
+    change_password: bool,
+    /// the server side session of a user token, the token is revoked with it,
+    /// or the portal session of a company token
+    #[serde(default)]
+    session: Option<Uuid>,
     exp: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Claims {
+    /// a signed in user of an office, not a super admin nor a company
+    fn is_user(&self) -> bool {
+        self.tenant.is_some() && !self.is_company && !self.pre_auth && !self.change_password
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct ApiToken<'r>(pub Cow<'r, str>);
 
-const SECRET: &str = "hello world secret";
+/// the key the tokens are signed with, set by the stage
+static SECRET: OnceLock<String> = OnceLock::new();
+/// the seconds a pre-auth or a change password token is valid for
+const PRE_AUTH_SECS: i64 = 5 * 60;
+/// the seconds a super admin token is valid for, they have no sessions
+const SUPER_ADMIN_SECS: i64 = 8 * 60 * 60;
+/// the seconds a portal token is valid for, the portal guard checks the
+/// company can still sign in with its session on every request
+const PORTAL_SECS: i64 = 12 * 60 * 60;
+
+fn secret() -> &'static [u8] {
+    SECRET.get().expect("auth stage attached").as_bytes()
+}
+
+/// reads the key signing the tokens from `JWT_SECRET`
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("auth stage", |rocket| async {
+        let secret = env::var("JWT_SECRET").expect("`JWT_SECRET` must be set");
+        assert!(!secret.is_empty(), "`JWT_SECRET` must not be empty");
+        // a second ignition in the same process keeps the first key
+        SECRET.get_or_init(|| secret);
+        rocket
+    })
+}
 
 impl<'r> ApiToken<'r> {
-    pub fn generate(id: Uuid, is_admin: bool) -> Self {
-        let claims = Claims {
+    pub fn generate(
+        id: Uuid,
+        tenant: Uuid,
+        session: Uuid,
+        is_admin: bool,
+        is_reviewer: bool,
+    ) -> Self {
+        Self::sign(Claims {
             sub: id,
+            tenant: Some(tenant),
             is_admin,
+            is_reviewer,
+            is_super_admin: false,
+            is_company: false,
+            pre_auth: false,
+            change_password: false,
+            session: Some(session),
             exp: usize::MAX,
-        };
+        })
+    }
+
+    pub fn super_admin(id: Uuid) -> Self {
+        Self::sign(Claims {
+            sub: id,
+            tenant: None,
+            is_admin: false,
+            is_reviewer: false,
+            is_super_admin: true,
+            is_company: false,
+            pre_auth: false,
+            change_password: false,
+            session: None,
+            exp: (Utc::now().timestamp() + SUPER_ADMIN_SECS) as usize,
+        })
+    }
+
+    pub fn company(id: Uuid, tenant: Uuid, session: Uuid) -> Self {
+        Self::sign(Claims {
+            sub: id,
+            tenant: Some(tenant),
+            is_admin: false,
+            is_reviewer: false,
+            is_super_admin: false,
+            is_company: true,
+            pre_auth: false,
+            change_password: false,
+            session: Some(session),
+            exp: (Utc::now().timestamp() + PORTAL_SECS) as usize,
+        })
+    }
+
+    /// the token of the second step of the login, only good for the two
+    /// factor routes
+    pub fn pre_auth(id: Uuid, tenant: Uuid) -> Self {
+        Self::sign(Claims {
+            sub: id,
+            tenant: Some(tenant),
+            is_admin: false,
+            is_reviewer: false,
+            is_super_admin: false,
+            is_company: false,
+            pre_auth: true,
+            change_password: false,
+            session: None,
+            exp: (Utc::now().timestamp() + PRE_AUTH_SECS) as usize,
+        })
+    }
+
+    /// the token of a user who must change their password, only good for
+    /// changing it
+    pub fn change_password(id: Uuid, tenant: Uuid) -> Self {
+        Self::sign(Claims {
+            sub: id,
+            tenant: Some(tenant),
+            is_admin: false,
+            is_reviewer: false,
+            is_super_admin: false,
+            is_company: false,
+            pre_auth: false,
+            change_password: true,
+            session: None,
+            exp: (Utc::now().timestamp() + PRE_AUTH_SECS) as usize,
+        })
+    }
+
+    fn sign(claims: Claims) -> Self {
         let token = encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(SECRET.as_ref()),
+            &EncodingKey::from_secret(secret()),
         )
         .expect("valid encoded token");
         let api_token = Self(token.into());
@@ -43,48 +188,198 @@ impl<'r> ApiToken<'r> {
         api_token
     }
 
-    fn validate(&self) -> Option<(Uuid, bool)> {
+    /// the user the token was generated for, when it is valid
+    pub fn user_id(&self) -> Option<Uuid> {
+        self.validate().ok().map(|claims| claims.sub)
+    }
+
+    /// the office of the user the token was generated for, when it is valid
+    pub fn tenant_id(&self) -> Option<Uuid> {
+        self.validate().ok().and_then(|claims| claims.tenant)
+    }
+
+    fn validate(&self) -> Result<Claims, ApiTokenError> {
         let token_data = decode::<Claims>(
             &self.0,
-            &DecodingKey::from_secret(SECRET.as_ref()),
+            &DecodingKey::from_secret(secret()),
             &Validation::new(Algorithm::HS256),
         )
-        .ok()?;
+        .map_err(|error| match error.kind() {
+            ErrorKind::ExpiredSignature => ApiTokenError::Expired,
+            _ => ApiTokenError::Invalid,
+        })?;
 
-        Some((token_data.claims.sub, token_data.claims.is_admin))
+        Ok(token_data.claims)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ApiTokenError {
     Missing,
+    Expired,
     Invalid,
+    /// the session of the token was signed out
+    Revoked,
+    /// a valid token without the role the route needs
+    Forbidden,
+}
+
+impl ApiTokenError {
+    pub fn status(self) -> Status {
+        match self {
+            Self::Missing | Self::Expired | Self::Invalid | Self::Revoked => Status::Unauthorized,
+            Self::Forbidden => Status::Forbidden,
+        }
+    }
+}
+
+/// why the guards rejected the request, the catchers answer with it
+struct AuthFailure(Option<ApiTokenError>);
+
+pub fn failure(request: &Request<'_>) -> Option<ApiTokenError> {
+    request.local_cache(|| AuthFailure(None)).0
+}
+
+pub(crate) fn fail(request: &Request<'_>, error: ApiTokenError) -> ApiTokenError {
+    request.local_cache(|| AuthFailure(Some(error)));
+    error
 }
 
-pub struct UGuard(pub Uuid);
+/// the claims of the request token, the failure is kept for the catchers
+fn claims(request: &Request<'_>) -> Result<Claims, ApiTokenError> {
+    let auth = request
+        .headers()
+        .get_one("Authorization")
+        .ok_or_else(|| fail(request, ApiTokenError::Missing))?;
+    let api_token = ApiToken(auth.into());
+    rocket::debug!("[token] validating: {api_token:?}");
+    api_token.validate().map_err(|error| fail(request, error))
+}
+
+/// the claims of the request token whose session, for a user token, is
+/// still open and whose password, for a change password token, is still to
+/// be changed, checked once per request
+struct SessionClaims(Result<Claims, ApiTokenError>);
+
+async fn session_claims(request: &Request<'_>) -> Result<Claims, ApiTokenError> {
+    request
+        .local_cache_async(async { SessionClaims(check_session(request).await) })
+        .await
+        .0
+        .clone()
+}
+
+async fn check_session(request: &Request<'_>) -> Result<Claims, ApiTokenError> {
+    let claims = claims(request)?;
+    let tenants = request
+        .rocket()
+        .state::<Tenants>()
+        .expect("database stage attached");
+    let open = match claims {
+        Claims {
+            tenant: Some(tenant),
+            change_password: true,
+            ..
+        } => tenants.must_change_password(tenant, claims.sub).await,
+        Claims {
+            tenant: Some(tenant),
+            session,
+            ..
+        } if claims.is_user() => {
+            // the tokens generated before the sessions
+            let session = session.ok_or_else(|| fail(request, ApiTokenError::Revoked))?;
+            tenants
+                .touch_session(tenant, claims.sub, session, request.client_ip())
+                .await
+        }
+        _ => return Ok(claims),
+    };
+    match open {
+        Ok(true) => Ok(claims),
+        Ok(false) => Err(fail(request, ApiTokenError::Revoked)),
+        Err(error) => {
+            rocket::error!("[sessions] {error}");
+            Err(fail(request, ApiTokenError::Invalid))
+        }
+    }
+}
+
+/// the office of the user of the request token, the super admins have none
+/// and the companies only reach their portal
+pub(crate) async fn tenant(request: &Request<'_>) -> Result<Uuid, ApiTokenError> {
+    match session_claims(request).await? {
+        claims @ Claims {
+            tenant: Some(tenant),
+            ..
+        } if claims.is_user() => Ok(tenant),
+        _ => Err(fail(request, ApiTokenError::Forbidden)),
+    }
+}
+
+/// the office, the company and the portal session of a portal token
+pub(crate) fn company(request: &Request<'_>) -> Result<(Uuid, Uuid, Uuid), ApiTokenError> {
+    match claims(request)? {
+        Claims {
+            sub,
+            tenant: Some(tenant),
+            is_company: true,
+            session,
+            ..
+        } => {
+            // the tokens generated before the portal sessions
+            let session = session.ok_or_else(|| fail(request, ApiTokenError::Revoked))?;
+            Ok((tenant, sub, session))
+        }
+        _ => Err(fail(request, ApiTokenError::Forbidden)),
+    }
+}
+
+/// forwards to the lower ranked routes, answered with 403 when none matches
+fn forbid<G>(request: &Request<'_>) -> Outcome<G, ApiTokenError> {
+    fail(request, ApiTokenError::Forbidden);
+    Outcome::Forward(())
+}
+
+/// the token sent as is in `Authorization`, `role` is who it must belong to
+pub(crate) fn security(role: &str, description: &str) -> RequestHeaderInput {
+    let scheme = SecurityScheme {
+        description: Some(description.to_owned()),
+        data: SecuritySchemeData::ApiKey {
+            name: "Authorization".to_owned(),
+            location: "header".to_owned(),
+        },
+        extensions: Object::default(),
+    };
+    let mut requirement = SecurityRequirement::new();
+    requirement.insert(role.to_owned(), vec![]);
+    RequestHeaderInput::Security(role.to_owned(), scheme, requirement)
+}
+
+/// any signed in user and their session
+pub struct UGuard(pub Uuid, pub Uuid);
 pub struct AGuard(pub Uuid);
+/// admin or reviewer
+pub struct RGuard(pub Uuid);
+/// manages the tenants, not a user of any of them
+pub struct SGuard(pub Uuid);
+/// a user in the middle of the two step login, the user and their office
+pub struct PGuard(pub Uuid, pub Uuid);
+/// a signed in user or one in the middle of the two step login, setting up
+/// their authenticator, the user and their office
+pub struct TGuard(pub Uuid, pub Uuid);
+/// a signed in user or one who must change their password first, the user
+/// and their office
+pub struct CGuard(pub Uuid, pub Uuid);
 
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for AGuard {
     type Error = ApiTokenError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        match request.headers().get_one("Authorization") {
-            Some(auth) => {
-                let api_token = ApiToken(auth.into());
-                rocket::debug!("[token] validating: {api_token:?}");
-                match api_token.validate() {
-                    Some(t) => {
-                        if t.1 {
-                            Outcome::Success(AGuard(t.0))
-                        } else {
-                            Outcome::Forward(())
-                        }
-                    }
-                    None => Outcome::Failure((Status::Unauthorized, ApiTokenError::Invalid)),
-                }
-            }
-            None => Outcome::Failure((Status::BadRequest, ApiTokenError::Missing)),
+        match session_claims(request).await {
+            Ok(t) if t.is_admin && t.is_user() => Outcome::Success(AGuard(t.sub)),
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
         }
     }
 }
@@ -94,16 +389,176 @@ impl<'r> FromRequest<'r> for UGuard {
     type Error = ApiTokenError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        match request.headers().get_one("Authorization") {
-            Some(auth) => {
-                let api_token = ApiToken(auth.into());
-                rocket::debug!("[token] validating: {api_token:?}");
-                match api_token.validate() {
-                    Some(t) => Outcome::Success(UGuard(t.0)),
-                    None => Outcome::Failure((Status::Unauthorized, ApiTokenError::Invalid)),
-                }
+        match session_claims(request).await {
+            Ok(
+                t @ Claims {
+                    session: Some(session),
+                    ..
+                },
+            ) if t.is_user() => Outcome::Success(UGuard(t.sub, session)),
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RGuard {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match session_claims(request).await {
+            Ok(t) if (t.is_admin || t.is_reviewer) && t.is_user() => {
+                Outcome::Success(RGuard(t.sub))
             }
-            None => Outcome::Failure((Status::BadRequest, ApiTokenError::Missing)),
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SGuard {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match claims(request) {
+            Ok(t) if t.is_super_admin => Outcome::Success(SGuard(t.sub)),
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
         }
     }
 }
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for PGuard {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match claims(request) {
+            Ok(Claims {
+                sub,
+                tenant: Some(tenant),
+                is_company: false,
+                pre_auth: true,
+                ..
+            }) => Outcome::Success(PGuard(sub, tenant)),
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TGuard {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match session_claims(request).await {
+            Ok(
+                t @ Claims {
+                    tenant: Some(tenant),
+                    ..
+                },
+            ) if t.is_user() || (t.pre_auth && !t.is_company) => {
+                Outcome::Success(TGuard(t.sub, tenant))
+            }
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CGuard {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match session_claims(request).await {
+            Ok(
+                t @ Claims {
+                    tenant: Some(tenant),
+                    ..
+                },
+            ) if t.is_user() || t.change_password => Outcome::Success(CGuard(t.sub, tenant)),
+            Ok(_) => forbid(request),
+            Err(e) => Outcome::Failure((e.status(), e)),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for AGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security("admin", "the token of an admin"))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for UGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security("user", "the token of any signed in user"))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for RGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security("reviewer", "the token of an admin or a reviewer"))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for PGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security("preAuth", "the token of the first login step"))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for TGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security(
+            "twoFactorSetup",
+            "the token of the first login step or of any signed in user",
+        ))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for CGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security(
+            "changePassword",
+            "the token of a user who must change their password or of any signed in user",
+        ))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for SGuard {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(security("superAdmin", "the token of a super admin"))
+    }
+}
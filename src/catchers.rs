@@ -0,0 +1,52 @@
+use rocket::{
+    catcher::BoxFuture, fairing::AdHoc, http::Status, response::Responder, Catcher, Request,
+};
+
+use crate::{
+    auth::{self, ApiTokenError},
+    types::{error::ErrorCode, response::ResponseEnum},
+};
+
+/// the statuses answered with the JSON envelope instead of an HTML page
+const STATUSES: [u16; 7] = [400, 401, 403, 404, 413, 422, 500];
+
+fn error(status: Status, request: &Request<'_>) -> ResponseEnum<()> {
+    let (code, message) = match (status.code, auth::failure(request)) {
+        // a 404 after a role guard forwarded is a route the user may not use
+        (401 | 403 | 404, Some(failure)) => (
+            ErrorCode::from(failure),
+            match failure {
+                ApiTokenError::Missing => "auth.missing",
+                ApiTokenError::Expired => "auth.expired",
+                ApiTokenError::Invalid => "auth.invalid",
+                ApiTokenError::Revoked => "auth.revoked",
+                ApiTokenError::Forbidden => "auth.forbidden",
+            },
+        ),
+        (400, _) => (ErrorCode::BadRequest, "request.badRequest"),
+        (401, None) => (ErrorCode::Unauthorized, "auth.invalid"),
+        (403, None) => (ErrorCode::Forbidden, "auth.forbidden"),
+        (404, None) => (ErrorCode::NotFound, "request.notFound"),
+        (413, _) => (ErrorCode::PayloadTooLarge, "request.tooLarge"),
+        (422, _) => (ErrorCode::MalformedBody, "request.malformed"),
+        _ => (ErrorCode::Internal, "request.internal"),
+    };
+    ResponseEnum::error(code, message.into(), vec![])
+}
+
+/// not a `#[catch]` function, those keep the status they caught
+fn handle<'r>(status: Status, request: &'r Request<'_>) -> BoxFuture<'r> {
+    Box::pin(async move { error(status, request).respond_to(request) })
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("catchers stage", |rocket| async {
+        rocket.register(
+            "/",
+            STATUSES
+                .into_iter()
+                .map(|code| Catcher::new(code, handle))
+                .collect::<Vec<_>>(),
+        )
+    })
+}
@@ -0,0 +1,169 @@
+use std::sync::Mutex;
+
+use rocket::{fairing::AdHoc, get, routes, serde::json::Json, Build, Rocket, Route, State};
+use rocket_okapi::{
+    okapi::{
+        merge::merge_specs,
+        openapi3::{Info, OpenApi, PathItem, RefOr, Response},
+    },
+    swagger_ui::{make_swagger_ui, SwaggerUIConfig},
+};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject},
+    JsonSchema,
+};
+
+/// the media type rocket_okapi gives every `Form` body, the forms of this API
+/// all upload files
+const FORM_MEDIA_TYPE: &str = "application/octet-stream";
+
+/// the specification of the mounted routes, filled by the route stages
+pub struct ApiDocs(Mutex<OpenApi>);
+
+impl ApiDocs {
+    fn add(&self, base: &str, mut spec: OpenApi) {
+        for item in spec.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.patch,
+                &mut item.delete,
+            ];
+            for operation in operations.into_iter().flatten() {
+                if let Some(RefOr::Object(body)) = &mut operation.request_body {
+                    if let Some(form) = body.content.remove(FORM_MEDIA_TYPE) {
+                        body.content.insert("multipart/form-data".to_owned(), form);
+                    }
+                }
+                operation.responses.responses.values_mut().for_each(dedup);
+            }
+        }
+        let mut docs = self.0.lock().expect("api docs lock");
+        for (path, item) in &spec.paths {
+            let path = format!("{}{path}", base.trim_end_matches('/'));
+            if let Some(documented) = docs.paths.get_mut(&path) {
+                accept_bodies(documented, item);
+            }
+        }
+        if let Err(error) = merge_specs(&mut docs, &base, &spec) {
+            rocket::error!("[docs] {base}: {error}");
+        }
+    }
+}
+
+/// a `ResponseResult` documents both of its sides, the same envelope twice
+fn dedup(response: &mut RefOr<Response>) {
+    let RefOr::Object(response) = response else {
+        return;
+    };
+    let mut descriptions: Vec<_> = response.description.lines().collect();
+    descriptions.dedup();
+    response.description = descriptions.join("\n");
+    for media_type in response.content.values_mut() {
+        let any_of = media_type
+            .schema
+            .as_mut()
+            .and_then(|schema| schema.subschemas.as_mut())
+            .and_then(|subschemas| subschemas.any_of.as_mut());
+        if let Some(any_of) = any_of {
+            any_of.dedup();
+            if let [schema] = any_of.as_slice() {
+                media_type.schema = Some(schema.clone().into_object());
+            }
+        }
+    }
+}
+
+/// the routes told apart only by their `format` share one operation, it
+/// accepts the bodies of all of them
+fn accept_bodies(documented: &mut PathItem, item: &PathItem) {
+    let operations = [
+        (&mut documented.post, &item.post),
+        (&mut documented.put, &item.put),
+        (&mut documented.patch, &item.patch),
+    ];
+    for (documented, operation) in operations {
+        let bodies = documented
+            .as_mut()
+            .zip(operation.as_ref())
+            .map(|(documented, operation)| (&mut documented.request_body, &operation.request_body));
+        if let Some((Some(RefOr::Object(documented)), Some(RefOr::Object(body)))) = bodies {
+            for (media_type, content) in &body.content {
+                documented
+                    .content
+                    .entry(media_type.clone())
+                    .or_insert_with(|| content.clone());
+            }
+        }
+    }
+}
+
+/// mounts `routes` at `base` and adds them to `/api/openapi.json`
+pub fn mount(
+    rocket: Rocket<Build>,
+    base: &'static str,
+    (routes, spec): (Vec<Route>, OpenApi),
+) -> Rocket<Build> {
+    match rocket.state::<ApiDocs>() {
+        Some(docs) => docs.add(base, spec),
+        None => rocket::warn!("[docs] the docs stage is not attached, {base} is not documented"),
+    }
+    rocket.mount(base, routes)
+}
+
+#[get("/openapi.json")]
+pub fn get_openapi(docs: &State<ApiDocs>) -> Json<OpenApi> {
+    let mut spec = docs.0.lock().expect("api docs lock").clone();
+    spec.info = Info {
+        title: "Accounting API".to_owned(),
+        description: Some(
+            "Every JSON response is wrapped in `{status, message, data}`, failed ones add a \
+             `code` and the field `details`. The messages follow `Accept-Language`."
+                .to_owned(),
+        ),
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        ..Info::default()
+    };
+    Json(spec)
+}
+
+/// serves the specification and an explorer at `/api/docs`, attach before the
+/// routes so they find `ApiDocs`
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("docs stage", |rocket| async {
+        rocket
+            .manage(ApiDocs(Mutex::new(OpenApi::new())))
+            .mount("/api", routes![get_openapi])
+            .mount(
+                "/api/docs",
+                make_swagger_ui(&SwaggerUIConfig {
+                    url: "../openapi.json".to_owned(),
+                    ..SwaggerUIConfig::default()
+                }),
+            )
+    })
+}
+
+/// the schema of an uploaded file
+pub struct Binary;
+
+impl JsonSchema for Binary {
+    fn schema_name() -> String {
+        "Binary".to_owned()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some("binary".to_owned()),
+            ..SchemaObject::default()
+        }
+        .into()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+}
@@ -0,0 +1,110 @@
+use rocket::serde::json::Value;
+
+/// the canonical form the tax authority hashes and signs: every property name
+/// is upper cased and quoted followed by its value, every array item is
+/// prefixed with the name of its array and every value is quoted
+pub fn serialize(document: &Value) -> String {
+    let mut canonical = String::new();
+    write(&mut canonical, document);
+    canonical
+}
+
+fn quote(canonical: &mut String, text: &str) {
+    canonical.push('"');
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            canonical.push('\\');
+        }
+        canonical.push(c);
+    }
+    canonical.push('"');
+}
+
+fn write(canonical: &mut String, value: &Value) {
+    match value {
+        Value::Object(properties) => {
+            for (name, value) in properties {
+                let name = name.to_uppercase();
+                quote(canonical, &name);
+                match value {
+                    Value::Array(items) => {
+                        for item in items {
+                            quote(canonical, &name);
+                            write(canonical, item);
+                        }
+                    }
+                    value => write(canonical, value),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| write(canonical, item)),
+        Value::String(text) => quote(canonical, text),
+        Value::Number(number) => quote(canonical, &number.to_string()),
+        Value::Bool(boolean) => quote(canonical, &boolean.to_string()),
+        Value::Null => quote(canonical, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashing;
+
+    /// a document shaped like the sample of the SDK, its names are not in
+    /// alphabetical order so their order must be kept
+    const DOCUMENT: &str = r#"{
+        "issuer": {
+            "type": "B",
+            "id": "113317713",
+            "name": "Issuer Company",
+            "address": { "country": "EG", "branchID": "0" }
+        },
+        "documentType": "I",
+        "dateTimeIssued": "2020-10-27T23:59:59Z",
+        "invoiceLines": [
+            {
+                "description": "Computer1",
+                "quantity": 2,
+                "unitValue": { "currencySold": "EGP", "amountEGP": 57.5 },
+                "taxableItems": [
+                    { "taxType": "T1", "amount": 16.1, "rate": 14 },
+                    { "taxType": "T4", "amount": 5.75, "rate": 5 }
+                ]
+            },
+            {
+                "description": "Printer \"A4\"",
+                "quantity": 1,
+                "unitValue": { "currencySold": "EGP", "amountEGP": 10 },
+                "taxableItems": [],
+                "discount": { "rate": 0, "amount": 0 }
+            }
+        ],
+        "totalAmount": 135.85,
+        "signatures": null
+    }"#;
+
+    const CANONICAL: &str = concat!(
+        r#""ISSUER""TYPE""B""ID""113317713""NAME""Issuer Company""#,
+        r#""ADDRESS""COUNTRY""EG""BRANCHID""0""#,
+        r#""DOCUMENTTYPE""I""DATETIMEISSUED""2020-10-27T23:59:59Z""#,
+        r#""INVOICELINES""INVOICELINES""DESCRIPTION""Computer1""QUANTITY""2""#,
+        r#""UNITVALUE""CURRENCYSOLD""EGP""AMOUNTEGP""57.5""#,
+        r#""TAXABLEITEMS""TAXABLEITEMS""TAXTYPE""T1""AMOUNT""16.1""RATE""14""#,
+        r#""TAXABLEITEMS""TAXTYPE""T4""AMOUNT""5.75""RATE""5""#,
+        r#""INVOICELINES""DESCRIPTION""Printer \"A4\"""QUANTITY""1""#,
+        r#""UNITVALUE""CURRENCYSOLD""EGP""AMOUNTEGP""10""#,
+        r#""TAXABLEITEMS""DISCOUNT""RATE""0""AMOUNT""0""#,
+        r#""TOTALAMOUNT""135.85""SIGNATURES""""#,
+    );
+
+    #[test]
+    fn documents_are_serialized_in_their_order() {
+        let document: Value = rocket::serde::json::from_str(DOCUMENT).expect("a json document");
+        let canonical = serialize(&document);
+        assert_eq!(canonical, CANONICAL);
+        assert_eq!(
+            hashing::sha256_hex(canonical.as_bytes()),
+            "bc71937884e199aab1bdbc4eda25c64737bcdd1515c68c656f47e97a7008b801"
+        );
+    }
+}
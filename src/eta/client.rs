@@ -0,0 +1,239 @@
+use std::time::{Duration, Instant};
+
+use rocket::{
+    async_trait,
+    serde::{
+        json::{self, Value},
+        Deserialize,
+    },
+    tokio::sync::Mutex,
+};
+
+use crate::{accounting_api, local_storage::models::EtaStatus};
+
+impl From<reqwest::Error> for accounting_api::Error {
+    fn from(error: reqwest::Error) -> Self {
+        rocket::error!("[Eta] {error:#?}");
+        Self::Eta(error.to_string().into())
+    }
+}
+
+#[derive(Debug)]
+pub enum Submission {
+    Accepted {
+        submission_id: String,
+        uuid: String,
+        long_id: String,
+    },
+    Rejected {
+        submission_id: Option<String>,
+        errors: Value,
+    },
+}
+
+/// the tax authority invoicing api
+#[async_trait]
+pub trait EtaClient: Send + Sync {
+    async fn submit(&self, document: &Value) -> Result<Submission, accounting_api::Error>;
+
+    /// the validation status of an accepted document with the validation
+    /// results when it is invalid
+    async fn status(&self, uuid: &str)
+        -> Result<(EtaStatus, Option<Value>), accounting_api::Error>;
+
+    async fn cancel(&self, uuid: &str, reason: &str) -> Result<(), accounting_api::Error>;
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Token {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct AcceptedDocument {
+    uuid: String,
+    long_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct RejectedDocument {
+    error: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct SubmissionResponse {
+    submission_id: Option<String>,
+    #[serde(default)]
+    accepted_documents: Vec<AcceptedDocument>,
+    #[serde(default)]
+    rejected_documents: Vec<RejectedDocument>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct DocumentResponse {
+    status: String,
+    validation_results: Option<Value>,
+}
+
+/// the invoicing api over http, `api_url` and `identity_url` can point to a
+/// mock server
+#[derive(Debug)]
+pub struct HttpEtaClient {
+    pub http: reqwest::Client,
+    pub api_url: String,
+    pub identity_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl HttpEtaClient {
+    pub fn new(
+        http: reqwest::Client,
+        api_url: String,
+        identity_url: String,
+        client_id: String,
+        client_secret: String,
+    ) -> Self {
+        Self {
+            http,
+            api_url: api_url.trim_end_matches('/').to_owned(),
+            identity_url: identity_url.trim_end_matches('/').to_owned(),
+            client_id,
+            client_secret,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// a cached client credentials token, renewed a minute before it expires
+    async fn token(&self) -> Result<String, accounting_api::Error> {
+        let mut cached = self.token.lock().await;
+        if let Some((token, expires)) = cached.as_ref() {
+            if Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        let token = self
+            .http
+            .post(format!("{}/connect/token", self.identity_url))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("scope", "InvoicingAPI"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Token>()
+            .await?;
+        let expires = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+        *cached = Some((token.access_token.clone(), expires));
+        Ok(token.access_token)
+    }
+
+    /// the body of a failed request as the error, the api explains the
+    /// failure there
+    async fn checked(
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, accounting_api::Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        rocket::error!("[Eta] {status}: {body}");
+        Err(accounting_api::Error::Eta(
+            format!("{status}: {body}").into(),
+        ))
+    }
+}
+
+#[async_trait]
+impl EtaClient for HttpEtaClient {
+    async fn submit(&self, document: &Value) -> Result<Submission, accounting_api::Error> {
+        let response = self
+            .http
+            .post(format!("{}/api/v1/documentsubmissions", self.api_url))
+            .bearer_auth(self.token().await?)
+            .json(&json::json!({ "documents": [document] }))
+            .send()
+            .await?;
+        let mut response = Self::checked(response)
+            .await?
+            .json::<SubmissionResponse>()
+            .await?;
+
+        if let Some(accepted) = response.accepted_documents.pop() {
+            return Ok(Submission::Accepted {
+                submission_id: response.submission_id.unwrap_or_default(),
+                uuid: accepted.uuid,
+                long_id: accepted.long_id,
+            });
+        }
+        Ok(Submission::Rejected {
+            submission_id: response.submission_id,
+            errors: response
+                .rejected_documents
+                .pop()
+                .map(|rejected| rejected.error)
+                .unwrap_or(Value::Null),
+        })
+    }
+
+    async fn status(
+        &self,
+        uuid: &str,
+    ) -> Result<(EtaStatus, Option<Value>), accounting_api::Error> {
+        let response = self
+            .http
+            .get(format!("{}/api/v1/documents/{uuid}/raw", self.api_url))
+            .bearer_auth(self.token().await?)
+            .send()
+            .await?;
+        let document = Self::checked(response)
+            .await?
+            .json::<DocumentResponse>()
+            .await?;
+
+        let status = match document.status.to_lowercase().as_str() {
+            "submitted" => EtaStatus::Submitted,
+            "valid" => EtaStatus::Valid,
+            "invalid" => EtaStatus::Invalid,
+            "rejected" => EtaStatus::Rejected,
+            "cancelled" => EtaStatus::Cancelled,
+            status => {
+                return Err(accounting_api::Error::Eta(
+                    format!("حالة غير معروفة \"{status}\"").into(),
+                ))
+            }
+        };
+        let errors = match status {
+            EtaStatus::Invalid => document.validation_results,
+            _ => None,
+        };
+        Ok((status, errors))
+    }
+
+    async fn cancel(&self, uuid: &str, reason: &str) -> Result<(), accounting_api::Error> {
+        let response = self
+            .http
+            .put(format!(
+                "{}/api/v1.0/documents/state/{uuid}/state",
+                self.api_url
+            ))
+            .bearer_auth(self.token().await?)
+            .json(&json::json!({ "status": "cancelled", "reason": reason }))
+            .send()
+            .await?;
+        Self::checked(response).await?;
+        Ok(())
+    }
+}
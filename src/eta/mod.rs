@@ -0,0 +1,486 @@
+pub mod canonical;
+pub mod client;
+pub mod signer;
+
+use std::{env, sync::Arc, time::Duration};
+
+use rocket::{
+    fairing::AdHoc,
+    serde::{
+        json::{self, Value},
+        Serialize,
+    },
+    tokio::{self, select, time},
+};
+use sqlx::types::Uuid;
+
+use crate::{
+    accounting_api::{self, AcountingApi},
+    hashing,
+    local_storage::{
+        models::{Company, CreateEtaDocument, EtaDocument, EtaStatus, Invoice, InvoiceStatus},
+        LocalStorageAccountingApi, Tenants,
+    },
+};
+
+use self::{
+    client::{EtaClient, HttpEtaClient, Submission},
+    signer::{HttpSigner, Signer, Unsigned},
+};
+
+const DEFAULT_API_URL: &str = "https://api.invoicing.eta.gov.eg";
+const DEFAULT_IDENTITY_URL: &str = "https://id.eta.gov.eg";
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5 * 60;
+
+/// the office issuing the invoices as registered with the tax authority
+#[derive(Debug)]
+pub struct Issuer {
+    /// the 9 digit tax registration number
+    pub id: String,
+    pub name: String,
+    pub branch_id: String,
+    pub governate: String,
+    pub region_city: String,
+    pub street: String,
+    pub building_number: String,
+    pub activity_code: String,
+    /// `GS1` or `EGS`
+    pub item_type: String,
+    pub item_code: String,
+    pub unit_type: String,
+}
+
+pub struct EtaSettings {
+    pub issuer: Issuer,
+    pub client: Box<dyn EtaClient>,
+    pub signer: Box<dyn Signer>,
+}
+
+/// e-invoicing with the egyptian tax authority, disabled until
+/// `ETA_ISSUER_ID` is set
+pub struct Eta {
+    settings: Option<Arc<EtaSettings>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct Address<'a> {
+    #[serde(rename = "branchID", skip_serializing_if = "Option::is_none")]
+    branch_id: Option<&'a str>,
+    country: &'a str,
+    governate: &'a str,
+    region_city: &'a str,
+    street: &'a str,
+    building_number: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct Party<'a> {
+    address: Address<'a>,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    id: &'a str,
+    name: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct UnitValue {
+    currency_sold: &'static str,
+    #[serde(rename = "amountEGP")]
+    amount_egp: f64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct Discount {
+    rate: f64,
+    amount: f64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct Line<'a> {
+    description: &'a str,
+    item_type: &'a str,
+    item_code: &'a str,
+    unit_type: &'a str,
+    quantity: f64,
+    internal_code: String,
+    sales_total: f64,
+    total: f64,
+    value_difference: f64,
+    total_taxable_fees: f64,
+    net_total: f64,
+    items_discount: f64,
+    unit_value: UnitValue,
+    discount: Discount,
+    /// no taxes are recorded on invoice lines yet
+    taxable_items: Vec<Value>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct Document<'a> {
+    issuer: Party<'a>,
+    receiver: Party<'a>,
+    document_type: &'a str,
+    document_type_version: &'a str,
+    date_time_issued: String,
+    taxpayer_activity_code: &'a str,
+    #[serde(rename = "internalID")]
+    internal_id: &'a str,
+    invoice_lines: Vec<Line<'a>>,
+    total_discount_amount: f64,
+    total_sales_amount: f64,
+    net_amount: f64,
+    tax_totals: Vec<Value>,
+    total_amount: f64,
+    extra_discount_amount: f64,
+    total_items_discount_amount: f64,
+}
+
+/// the api accepts at most five decimals
+fn round(value: f64) -> f64 {
+    (value * 100_000.0).round() / 100_000.0
+}
+
+impl EtaSettings {
+    /// the unsigned e-invoice of an issued invoice, the company is the
+    /// receiver and must have a tax registration number
+    pub fn document(
+        &self,
+        invoice: &Invoice,
+        company: &Company,
+    ) -> Result<Value, accounting_api::Error> {
+        let (internal_id, issue_date) = match (&invoice.number, invoice.issue_date) {
+            (Some(number), Some(issue_date)) => (number, issue_date),
+            _ => return Err(accounting_api::Error::InvalidInvoiceState),
+        };
+        let receiver_id = company
+            .register_number
+            .as_deref()
+            .ok_or(accounting_api::Error::MissingTaxRegistration)?;
+        // companies only carry a free text location
+        let location = company.activity_location.as_deref().unwrap_or("-");
+
+        let issuer = &self.issuer;
+        let lines = invoice
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(position, line)| {
+                let total = round(line.quantity * line.unit_price);
+                Line {
+                    description: &line.description,
+                    item_type: &issuer.item_type,
+                    item_code: &issuer.item_code,
+                    unit_type: &issuer.unit_type,
+                    quantity: round(line.quantity),
+                    internal_code: (position + 1).to_string(),
+                    sales_total: total,
+                    total,
+                    value_difference: 0.0,
+                    total_taxable_fees: 0.0,
+                    net_total: total,
+                    items_discount: 0.0,
+                    unit_value: UnitValue {
+                        currency_sold: "EGP",
+                        amount_egp: round(line.unit_price),
+                    },
+                    discount: Discount {
+                        rate: 0.0,
+                        amount: 0.0,
+                    },
+                    taxable_items: vec![],
+                }
+            })
+            .collect::<Vec<_>>();
+        let total = round(lines.iter().map(|line| line.net_total).sum());
+
+        let document = Document {
+            issuer: Party {
+                address: Address {
+                    branch_id: Some(&issuer.branch_id),
+                    country: "EG",
+                    governate: &issuer.governate,
+                    region_city: &issuer.region_city,
+                    street: &issuer.street,
+                    building_number: &issuer.building_number,
+                },
+                kind: "B",
+                id: &issuer.id,
+                name: &issuer.name,
+            },
+            receiver: Party {
+                address: Address {
+                    branch_id: None,
+                    country: "EG",
+                    governate: location,
+                    region_city: location,
+                    street: location,
+                    building_number: "0",
+                },
+                kind: "B",
+                id: receiver_id,
+                name: &company.commercial_feature,
+            },
+            document_type: "I",
+            document_type_version: self.signer.document_version(),
+            date_time_issued: format!("{}T00:00:00Z", issue_date.format("%Y-%m-%d")),
+            taxpayer_activity_code: &issuer.activity_code,
+            internal_id,
+            invoice_lines: lines,
+            total_discount_amount: 0.0,
+            total_sales_amount: total,
+            net_amount: total,
+            tax_totals: vec![],
+            total_amount: total,
+            extra_discount_amount: 0.0,
+            total_items_discount_amount: 0.0,
+        };
+        Ok(json::to_value(document).expect("serializable document"))
+    }
+}
+
+impl Eta {
+    pub fn settings(&self) -> Result<&EtaSettings, accounting_api::Error> {
+        self.settings
+            .as_deref()
+            .ok_or(accounting_api::Error::EtaNotConfigured)
+    }
+
+    /// the document of an invoice as it was last submitted, or as it would be
+    /// submitted now without the signature
+    pub async fn document(
+        &self,
+        storage: &LocalStorageAccountingApi,
+        invoice_id: Uuid,
+    ) -> Result<Value, accounting_api::Error> {
+        match storage.get_eta_document(invoice_id).await {
+            Ok(document) => return Ok(document.document.0),
+            Err(accounting_api::Error::ObjectNotFound) => {}
+            Err(error) => return Err(error),
+        }
+        let invoice = storage.get_invoice(invoice_id).await?;
+        let company = storage.get_company(invoice.company_id).await?;
+        self.settings()?.document(&invoice, &company)
+    }
+
+    /// signs and submits an issued invoice, rejected and invalid documents can
+    /// be submitted again
+    pub async fn submit(
+        &self,
+        storage: &LocalStorageAccountingApi,
+        invoice_id: Uuid,
+    ) -> Result<EtaDocument, accounting_api::Error> {
+        let settings = self.settings()?;
+        let invoice = storage.get_invoice(invoice_id).await?;
+        if matches!(invoice.status, InvoiceStatus::Draft | InvoiceStatus::Void) {
+            return Err(accounting_api::Error::InvalidInvoiceState);
+        }
+        match storage.get_eta_document(invoice_id).await {
+            Ok(previous)
+                if !matches!(previous.status, EtaStatus::Rejected | EtaStatus::Invalid) =>
+            {
+                return Err(accounting_api::Error::EtaAlreadySubmitted)
+            }
+            Ok(_) | Err(accounting_api::Error::ObjectNotFound) => {}
+            Err(error) => return Err(error),
+        }
+        let company = storage.get_company(invoice.company_id).await?;
+
+        let mut document = settings.document(&invoice, &company)?;
+        let canonical = canonical::serialize(&document);
+        let hash = hashing::sha256_hex(canonical.as_bytes());
+        let signatures = settings.signer.sign(&canonical).await?;
+        if !signatures.is_empty() {
+            document["signatures"] = json::to_value(signatures).expect("serializable signatures");
+        }
+
+        let submission = match settings.client.submit(&document).await? {
+            Submission::Accepted {
+                submission_id,
+                uuid,
+                long_id,
+            } => CreateEtaDocument {
+                status: EtaStatus::Submitted,
+                uuid: Some(uuid),
+                long_id: Some(long_id),
+                submission_id: Some(submission_id),
+                hash,
+                document,
+                errors: None,
+            },
+            Submission::Rejected {
+                submission_id,
+                errors,
+            } => CreateEtaDocument {
+                status: EtaStatus::Rejected,
+                uuid: None,
+                long_id: None,
+                submission_id,
+                hash,
+                document,
+                errors: Some(errors),
+            },
+        };
+        storage.save_eta_document(invoice_id, &submission).await
+    }
+
+    /// asks the tax authority for the validation status of a document
+    pub async fn refresh(
+        &self,
+        storage: &LocalStorageAccountingApi,
+        invoice_id: Uuid,
+    ) -> Result<EtaDocument, accounting_api::Error> {
+        let settings = self.settings()?;
+        let document = storage.get_eta_document(invoice_id).await?;
+        let uuid = match &document.uuid {
+            Some(uuid) => uuid,
+            None => return Ok(document),
+        };
+        let (status, errors) = settings.client.status(uuid).await?;
+        if status == document.status {
+            return Ok(document);
+        }
+        storage
+            .update_eta_status(invoice_id, status, errors.as_ref())
+            .await
+    }
+
+    /// cancels a valid document, the invoice can be voided afterwards
+    pub async fn cancel(
+        &self,
+        storage: &LocalStorageAccountingApi,
+        invoice_id: Uuid,
+        reason: &str,
+    ) -> Result<EtaDocument, accounting_api::Error> {
+        let settings = self.settings()?;
+        let document = storage.get_eta_document(invoice_id).await?;
+        let uuid = match (&document.uuid, document.status) {
+            (Some(uuid), EtaStatus::Valid) => uuid,
+            _ => return Err(accounting_api::Error::InvalidInvoiceState),
+        };
+        settings.client.cancel(uuid, reason).await?;
+        storage
+            .update_eta_status(invoice_id, EtaStatus::Cancelled, None)
+            .await
+    }
+}
+
+fn var(name: &str) -> String {
+    env::var(name).unwrap_or_else(|_| panic!("`{name}` must be set"))
+}
+
+fn settings() -> Option<EtaSettings> {
+    let id = env::var("ETA_ISSUER_ID").ok()?;
+    let http = reqwest::Client::new();
+    let signer: Box<dyn Signer> = match env::var("ETA_SIGNER_URL") {
+        Ok(url) => Box::new(HttpSigner {
+            http: http.clone(),
+            url,
+        }),
+        Err(_) => {
+            rocket::warn!("`ETA_SIGNER_URL` is not set, e-invoices are submitted unsigned");
+            Box::new(Unsigned)
+        }
+    };
+    let client = HttpEtaClient::new(
+        http,
+        env::var("ETA_API_URL").unwrap_or_else(|_| DEFAULT_API_URL.into()),
+        env::var("ETA_IDENTITY_URL").unwrap_or_else(|_| DEFAULT_IDENTITY_URL.into()),
+        var("ETA_CLIENT_ID"),
+        var("ETA_CLIENT_SECRET"),
+    );
+
+    Some(EtaSettings {
+        issuer: Issuer {
+            id,
+            name: var("ETA_ISSUER_NAME"),
+            branch_id: env::var("ETA_ISSUER_BRANCH_ID").unwrap_or_else(|_| "0".into()),
+            governate: var("ETA_ISSUER_GOVERNATE"),
+            region_city: var("ETA_ISSUER_REGION_CITY"),
+            street: var("ETA_ISSUER_STREET"),
+            building_number: var("ETA_ISSUER_BUILDING_NUMBER"),
+            activity_code: var("ETA_ACTIVITY_CODE"),
+            item_type: env::var("ETA_ITEM_TYPE").unwrap_or_else(|_| "EGS".into()),
+            item_code: var("ETA_ITEM_CODE"),
+            unit_type: env::var("ETA_UNIT_TYPE").unwrap_or_else(|_| "EA".into()),
+        },
+        client: Box::new(client),
+        signer,
+    })
+}
+
+/// manages the e-invoicing settings and polls the status of submitted
+/// documents in the background
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("eta stage", |rocket| async {
+        rocket
+            .manage(Eta {
+                settings: settings().map(Arc::new),
+            })
+            .attach(AdHoc::on_liftoff("eta poller", |rocket| {
+                Box::pin(async move {
+                    let eta = match &rocket.state::<Eta>().expect("eta stage attached").settings {
+                        Some(settings) => Eta {
+                            settings: Some(settings.clone()),
+                        },
+                        None => return,
+                    };
+                    let tenants = rocket
+                        .state::<Tenants>()
+                        .expect("database stage attached")
+                        .clone();
+                    let interval = env::var("ETA_POLL_INTERVAL_SECS")
+                        .ok()
+                        .map(|v| {
+                            v.parse()
+                                .expect("`ETA_POLL_INTERVAL_SECS` must be a number")
+                        })
+                        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+                    let mut shutdown = rocket.shutdown();
+
+                    tokio::spawn(async move {
+                        let mut interval = time::interval(Duration::from_secs(interval));
+                        loop {
+                            select! {
+                                _ = interval.tick() => match tenants.get_tenants().await {
+                                    Ok(offices) => {
+                                        for tenant in offices {
+                                            match tenants.storage(tenant.id).await {
+                                                Ok(storage) => poll(&eta, &storage).await,
+                                                Err(error) => rocket::error!("[eta] {error}"),
+                                            }
+                                        }
+                                    }
+                                    Err(error) => rocket::error!("[eta] {error}"),
+                                },
+                                _ = &mut shutdown => break,
+                            }
+                        }
+                    });
+                })
+            }))
+    })
+}
+
+async fn poll(eta: &Eta, storage: &LocalStorageAccountingApi) {
+    let documents = match storage.get_submitted_eta_documents().await {
+        Ok(documents) => documents,
+        Err(error) => return rocket::error!("[eta] {error}"),
+    };
+    for document in documents {
+        match eta.refresh(storage, document.invoice_id).await {
+            Ok(document) if document.status != EtaStatus::Submitted => rocket::info!(
+                "[eta] invoice {} is {:?}",
+                document.invoice_id,
+                document.status
+            ),
+            Ok(_) => {}
+            Err(error) => rocket::error!("[eta] {error}"),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use rocket::{async_trait, serde::Serialize};
+
+use crate::accounting_api;
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Signature {
+    /// `I` for the issuer signature
+    pub signature_type: &'static str,
+    /// base64 CAdES-BES signature of the canonical document
+    pub value: String,
+}
+
+/// signs the canonical form of a document, usually with the hardware token
+/// the tax authority issued to the taxpayer
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// unsigned documents are only accepted with version `0.9`
+    fn document_version(&self) -> &'static str {
+        "1.0"
+    }
+
+    async fn sign(&self, canonical: &str) -> Result<Vec<Signature>, accounting_api::Error>;
+}
+
+/// no signature, the tax authority accepts these on its preproduction system
+#[derive(Debug)]
+pub struct Unsigned;
+
+#[async_trait]
+impl Signer for Unsigned {
+    fn document_version(&self) -> &'static str {
+        "0.9"
+    }
+
+    async fn sign(&self, _canonical: &str) -> Result<Vec<Signature>, accounting_api::Error> {
+        Ok(vec![])
+    }
+}
+
+/// a signing service next to the token, it receives the canonical document as
+/// text and answers with the base64 signature
+#[derive(Debug)]
+pub struct HttpSigner {
+    pub http: reqwest::Client,
+    pub url: String,
+}
+
+#[async_trait]
+impl Signer for HttpSigner {
+    async fn sign(&self, canonical: &str) -> Result<Vec<Signature>, accounting_api::Error> {
+        let response = self
+            .http
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(canonical.to_owned())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(vec![Signature {
+            signature_type: "I",
+            value: response.text().await?.trim().to_owned(),
+        }])
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use rocket::{
+    fairing::AdHoc,
+    serde::{json, Deserialize, Serialize},
+    tokio::{self, select, sync::broadcast, time},
+};
+use sqlx::{postgres::PgListener, types::Uuid};
+
+use crate::local_storage::Tenants;
+
+/// the postgres channel the change triggers publish on
+pub const CHANNEL: &str = "changes";
+/// changes kept for slow subscribers before they lag behind
+const CAPACITY: usize = 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum Entity {
+    Company,
+    Expense,
+    Income,
+    Funder,
+    Document,
+}
+
+impl Entity {
+    /// the event name in the stream
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Company => "company",
+            Self::Expense => "expense",
+            Self::Income => "income",
+            Self::Funder => "funder",
+            Self::Document => "document",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum Action {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// a change of a row or a company document, the clients fetch the row again
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Change {
+    pub entity: Entity,
+    pub action: Action,
+    /// `None` for documents, they are identified by their path
+    pub id: Option<Uuid>,
+    /// `None` for expenses and incomes without a company
+    pub company_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// set by the database, the streams only relay the changes of their tenant
+    #[serde(default, skip_serializing)]
+    pub tenant_id: Option<Uuid>,
+}
+
+/// fans the changes published by the database out to the event streams
+pub struct Events(broadcast::Sender<Change>);
+
+impl Events {
+    pub fn subscribe(&self) -> broadcast::Receiver<Change> {
+        self.0.subscribe()
+    }
+}
+
+async fn listen(tenants: &Tenants, events: &broadcast::Sender<Change>) {
+    let mut listener = match PgListener::connect_with(&tenants.db).await {
+        Ok(listener) => listener,
+        Err(error) => return rocket::error!("[events] {error}"),
+    };
+    if let Err(error) = listener.listen(CHANNEL).await {
+        return rocket::error!("[events] {error}");
+    }
+    loop {
+        // the listener reconnects by itself, changes made meanwhile are lost
+        match listener.recv().await {
+            Ok(notification) => match json::from_str::<Change>(notification.payload()) {
+                Ok(change) => {
+                    // fails when nobody is subscribed
+                    let _ = events.send(change);
+                }
+                Err(error) => rocket::error!("[events] {error}: {}", notification.payload()),
+            },
+            Err(error) => {
+                rocket::error!("[events] {error}");
+                time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// relays the database changes to the `/api/events` subscribers
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("events stage", |rocket| async {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        rocket
+            .manage(Events(sender))
+            .attach(AdHoc::on_liftoff("events listener", |rocket| {
+                Box::pin(async move {
+                    let events = rocket
+                        .state::<Events>()
+                        .expect("events stage attached")
+                        .0
+                        .clone();
+                    let tenants = rocket
+                        .state::<Tenants>()
+                        .expect("database stage attached")
+                        .clone();
+                    let mut shutdown = rocket.shutdown();
+
+                    tokio::spawn(async move {
+                        select! {
+                            _ = listen(&tenants, &events) => {}
+                            _ = &mut shutdown => {}
+                        }
+                    });
+                })
+            }))
+    })
+}
@@ -0,0 +1,460 @@
+use std::{
+    env, io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use chrono::{DateTime, Utc};
+use rocket::{
+    futures::{stream::BoxStream, StreamExt},
+    http::ContentType,
+    response::stream::ByteStream,
+    tokio::{fs, task},
+};
+use rust_xlsxwriter::{Format, FormatAlign, Workbook, XlsxError};
+
+use crate::{
+    accounting_api,
+    local_storage::models::{
+        Company, Expense, ExpenseStatus, Income, VatReturnEntry, WithholdingEntry,
+    },
+    types::response::ExportFile,
+};
+
+impl From<XlsxError> for accounting_api::Error {
+    fn from(error: XlsxError) -> Self {
+        rocket::error!("[Export] {error:#?}");
+        Self::Other(error.to_string().into())
+    }
+}
+
+#[derive(Debug)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Time(DateTime<Utc>),
+    Empty,
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Option<String>> for Cell {
+    fn from(text: Option<String>) -> Self {
+        text.map(Self::Text).unwrap_or(Self::Empty)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(number: f64) -> Self {
+        Self::Number(number)
+    }
+}
+
+impl From<Option<f64>> for Cell {
+    fn from(number: Option<f64>) -> Self {
+        number.map(Self::Number).unwrap_or(Self::Empty)
+    }
+}
+
+impl From<DateTime<Utc>> for Cell {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self::Time(time)
+    }
+}
+
+impl From<Option<DateTime<Utc>>> for Cell {
+    fn from(time: Option<DateTime<Utc>>) -> Self {
+        time.map(Self::Time).unwrap_or(Self::Empty)
+    }
+}
+
+/// a listing row that can be written to a spreadsheet
+pub trait ExportRow {
+    const NAME: &'static str;
+    /// column titles with their widths in characters
+    const COLUMNS: &'static [(&'static str, f64)];
+
+    fn cells(self) -> Vec<Cell>;
+}
+
+impl ExportRow for Expense {
+    const NAME: &'static str = "expenses";
+    const COLUMNS: &'static [(&'static str, f64)] = &[
+        ("التاريخ", 20.0),
+        ("الشركة", 30.0),
+        ("الموظف", 20.0),
+        ("البيان", 45.0),
+        ("القيمة", 15.0),
+        ("الصافي", 15.0),
+        ("الضريبة", 15.0),
+        ("نوع الضريبة", 22.0),
+        ("نسبة الضريبة", 12.0),
+        ("المورد", 25.0),
+        ("رقم تسجيل المورد", 15.0),
+        ("نسبة الخصم", 12.0),
+        ("الخصم", 15.0),
+        ("الحالة", 15.0),
+        ("المراجع", 20.0),
+        ("تعليق المراجعة", 30.0),
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        let status = match self.status {
+            ExpenseStatus::Pending => "في انتظار المراجعة",
+            ExpenseStatus::Approved => "معتمد",
+            ExpenseStatus::Rejected => "مرفوض",
+        };
+        vec![
+            self.time.into(),
+            self.company.into(),
+            self.user.into(),
+            self.description.into(),
+            self.value.into(),
+            self.net.into(),
+            self.tax.into(),
+            self.tax_code.label().to_owned().into(),
+            self.tax_rate.into(),
+            self.supplier.into(),
+            self.supplier_registration.into(),
+            self.withholding_rate.into(),
+            self.withholding.into(),
+            status.to_owned().into(),
+            self.reviewer.into(),
+            self.review_comment.into(),
+        ]
+    }
+}
+
+impl ExportRow for Income {
+    const NAME: &'static str = "incomes";
+    const COLUMNS: &'static [(&'static str, f64)] = &[
+        ("التاريخ", 20.0),
+        ("الشركة", 30.0),
+        ("المسؤول", 20.0),
+        ("البيان", 45.0),
+        ("القيمة", 15.0),
+        ("الصافي", 15.0),
+        ("الضريبة", 15.0),
+        ("نوع الضريبة", 22.0),
+        ("نسبة الضريبة", 12.0),
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        vec![
+            self.time.into(),
+            self.company.into(),
+            self.admin.into(),
+            self.description.into(),
+            self.value.into(),
+            self.net.into(),
+            self.tax.into(),
+            self.tax_code.label().to_owned().into(),
+            self.tax_rate.into(),
+        ]
+    }
+}
+
+/// the columns of the tax portal sales and purchases statements
+impl ExportRow for VatReturnEntry {
+    const NAME: &'static str = "vat-return";
+    const COLUMNS: &'static [(&'static str, f64)] = &[
+        ("رقم المستند", 38.0),
+        ("تاريخ المستند", 20.0),
+        ("البيان", 45.0),
+        ("نوع الضريبة", 22.0),
+        ("فئة الضريبة", 12.0),
+        ("القيمة الصافية", 15.0),
+        ("قيمة الضريبة", 15.0),
+        ("الاجمالي", 15.0),
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        vec![
+            self.id.to_string().into(),
+            self.time.into(),
+            self.description.into(),
+            self.tax_code.label().to_owned().into(),
+            self.tax_rate.into(),
+            self.net.into(),
+            self.tax.into(),
+            self.value.into(),
+        ]
+    }
+}
+
+impl ExportRow for Company {
+    const NAME: &'static str = "companies";
+    const COLUMNS: &'static [(&'static str, f64)] = &[
+        ("المالك", 25.0),
+        ("الاسم التجاري", 30.0),
+        ("تعمل", 8.0),
+        ("الكيان القانوني", 20.0),
+        ("رقم الملف", 15.0),
+        ("رقم التسجيل", 15.0),
+        ("رقم السجل", 15.0),
+        ("تاريخ البدء", 20.0),
+        ("تاريخ التوقف", 20.0),
+        ("مأمورية الضرائب العامة", 25.0),
+        ("مأمورية القيمة المضافة", 25.0),
+        ("طبيعة النشاط", 25.0),
+        ("مقر النشاط", 30.0),
+        ("البريد الالكتروني", 30.0),
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        let is_working = if self.is_working { "نعم" } else { "لا" };
+        vec![
+            self.owner.into(),
+            self.commercial_feature.into(),
+            is_working.to_owned().into(),
+            self.legal_entity.into(),
+            self.file_number.into(),
+            self.register_number.into(),
+            self.record_number.into(),
+            self.start_date.into(),
+            self.stop_date.into(),
+            self.general_tax_mission.into(),
+            self.value_tax_mission.into(),
+            self.activity_nature.into(),
+            self.activity_location.into(),
+            self.email.into(),
+        ]
+    }
+}
+
+/// the columns of the withholding tax statement (form 41)
+impl ExportRow for WithholdingEntry {
+    const NAME: &'static str = "form-41";
+    const COLUMNS: &'static [(&'static str, f64)] = &[
+        ("رقم التسجيل الضريبي", 18.0),
+        ("اسم المتعامل", 30.0),
+        ("تاريخ التعامل", 20.0),
+        ("طبيعة التعامل", 45.0),
+        ("القيمة الاجمالية للتعامل", 18.0),
+        ("نسبة الخصم", 12.0),
+        ("القيمة المخصومة", 15.0),
+    ];
+
+    fn cells(self) -> Vec<Cell> {
+        vec![
+            self.supplier_registration.into(),
+            self.supplier.into(),
+            self.time.into(),
+            self.description.into(),
+            self.net.into(),
+            self.withholding_rate.into(),
+            self.withholding.into(),
+        ]
+    }
+}
+
+/// prefixes the text a spreadsheet would run as a formula with a quote, the
+/// descriptions and names are typed by the users
+fn plain_text(text: String) -> String {
+    if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{text}")
+    } else {
+        text
+    }
+}
+
+fn csv_line(fields: impl IntoIterator<Item = String>) -> Vec<u8> {
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(vec![]);
+    writer
+        .write_record(fields)
+        .expect("writing to memory does not fail");
+    writer
+        .into_inner()
+        .expect("writing to memory does not fail")
+}
+
+/// the titles line prefixed with a byte order mark so excel reads it as UTF-8
+pub fn csv_header<T: ExportRow>() -> Vec<u8> {
+    let mut header = "\u{feff}".as_bytes().to_vec();
+    header.extend(csv_line(
+        T::COLUMNS.iter().map(|(title, _)| title.to_string()),
+    ));
+    header
+}
+
+pub fn csv_row(row: impl ExportRow) -> Vec<u8> {
+    csv_line(row.cells().into_iter().map(|cell| match cell {
+        Cell::Text(text) => plain_text(text),
+        Cell::Number(number) => format!("{number:.2}"),
+        Cell::Time(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        Cell::Empty => String::new(),
+    }))
+}
+
+/// a right to left workbook written row by row in constant memory mode, rows
+/// are flushed to temporary files as they are written
+pub struct XlsxExport {
+    workbook: Workbook,
+    number: Format,
+    time: Format,
+    row: u32,
+}
+
+impl XlsxExport {
+    pub fn new<T: ExportRow>() -> Result<Self, XlsxError> {
+        let mut workbook = Workbook::new();
+        let header = Format::new().set_bold().set_align(FormatAlign::Center);
+        let worksheet = workbook.add_worksheet_with_constant_memory();
+        worksheet.set_name(T::NAME)?.set_right_to_left(true);
+        for (column, (title, width)) in T::COLUMNS.iter().enumerate() {
+            worksheet.set_column_width(column as u16, *width)?;
+            worksheet.write_string_with_format(0, column as u16, *title, &header)?;
+        }
+        worksheet.set_freeze_panes(1, 0)?;
+
+        Ok(Self {
+            workbook,
+            number: Format::new().set_num_format("#,##0.00"),
+            time: Format::new().set_num_format("yyyy-mm-dd hh:mm"),
+            row: 0,
+        })
+    }
+
+    pub fn push(&mut self, row: impl ExportRow) -> Result<(), XlsxError> {
+        self.row += 1;
+        let worksheet = self.workbook.worksheet_from_index(0)?;
+        for (column, cell) in row.cells().into_iter().enumerate() {
+            let column = column as u16;
+            match cell {
+                Cell::Text(text) => {
+                    worksheet.write_string(self.row, column, plain_text(text))?;
+                }
+                Cell::Number(number) => {
+                    worksheet.write_number_with_format(self.row, column, number, &self.number)?;
+                }
+                Cell::Time(time) => {
+                    worksheet.write_datetime_with_format(
+                        self.row,
+                        column,
+                        time.naive_utc(),
+                        &self.time,
+                    )?;
+                }
+                Cell::Empty => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the workbook to a temporary file and returns it opened, the
+    /// file is unlinked right away so it is gone once the response is sent
+    pub async fn finish(mut self) -> io::Result<fs::File> {
+        static EXPORTS: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "export-{}-{}.xlsx",
+            std::process::id(),
+            EXPORTS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let save_path = path.clone();
+        task::spawn_blocking(move || self.workbook.save(save_path))
+            .await
+            .map_err(io::Error::other)?
+            .map_err(io::Error::other)?;
+        let file = fs::File::open(&path).await?;
+        fs::remove_file(&path).await?;
+        Ok(file)
+    }
+}
+
+/// a CSV download, boxed so the routes returning it can name its type
+pub type CsvFile<'a> = ExportFile<ByteStream<BoxStream<'a, Vec<u8>>>>;
+
+/// streams `rows` as a CSV download, a failing row ends the file early since
+/// the response status is already sent
+pub fn csv<'a, T: ExportRow + Send + 'a>(
+    mut rows: BoxStream<'a, Result<T, accounting_api::Error>>,
+) -> CsvFile<'a> {
+    ExportFile {
+        name: format!("{}.csv", T::NAME),
+        content_type: ContentType::CSV,
+        body: ByteStream::from(
+            ByteStream! {
+                yield csv_header::<T>();
+                while let Some(row) = rows.next().await {
+                    match row {
+                        Ok(row) => yield csv_row(row),
+                        Err(error) => {
+                            rocket::error!("[Export] {error}");
+                            break;
+                        }
+                    }
+                }
+            }
+            .0
+            .boxed(),
+        ),
+    }
+}
+
+pub async fn xlsx<T: ExportRow>(
+    mut rows: BoxStream<'_, Result<T, accounting_api::Error>>,
+) -> Result<ExportFile<fs::File>, accounting_api::Error> {
+    let mut export = XlsxExport::new::<T>()?;
+    while let Some(row) = rows.next().await {
+        export.push(row?)?;
+    }
+    Ok(ExportFile {
+        name: format!("{}.xlsx", T::NAME),
+        content_type: ContentType::new(
+            "application",
+            "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ),
+        body: export.finish().await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row(Vec<Cell>);
+
+    impl ExportRow for Row {
+        const NAME: &'static str = "rows";
+        const COLUMNS: &'static [(&'static str, f64)] = &[("text", 10.0), ("number", 10.0)];
+
+        fn cells(self) -> Vec<Cell> {
+            self.0
+        }
+    }
+
+    fn csv(cells: Vec<Cell>) -> String {
+        String::from_utf8(csv_row(Row(cells))).expect("a utf-8 line")
+    }
+
+    #[test]
+    fn formulas_are_written_as_text() {
+        for formula in ["=1+1", "+1", "-1", "@SUM(A1)", "\tcmd", "\rcmd"] {
+            assert_eq!(plain_text(formula.into()), format!("'{formula}"));
+        }
+        assert_eq!(plain_text("a=1".into()), "a=1");
+        assert_eq!(
+            csv(vec![
+                Cell::Text("=HYPERLINK(\"x\")".into()),
+                Cell::Number(-5.0)
+            ]),
+            "\"'=HYPERLINK(\"\"x\"\")\",-5.00\r\n"
+        );
+    }
+
+    #[test]
+    fn csv_lines_keep_commas_and_empty_cells() {
+        assert_eq!(
+            csv(vec![Cell::Text("a, b".into()), Cell::Empty]),
+            "\"a, b\",\r\n"
+        );
+        assert!(String::from_utf8(csv_header::<Row>())
+            .expect("a utf-8 line")
+            .starts_with("\u{feff}text,number"));
+    }
+}
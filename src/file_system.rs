@@ -2,7 +2,7 @@ use std::{
     borrow::Cow,
     ffi::OsStr,
     io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use chrono::{DateTime, Utc};
@@ -25,9 +25,21 @@ impl FileSystem {
         }
     }
 
+    /// joins `path` to the root, refusing the paths that could escape it,
+    /// `..`, an absolute path or a prefix
+    fn within_root(&self, path: &Path) -> io::Result<PathBuf> {
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("path escapes the root `{:?}`", path),
+            ));
+        }
+        Ok(self.root.join(path))
+    }
+
     pub async fn rename(&mut self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
-        let from = self.root.join(from);
-        let to = self.root.join(to);
+        let from = self.within_root(from.as_ref())?;
+        let to = self.within_root(to.as_ref())?;
         rocket::trace!("[rename] renaming\n\tfrom: {:?}\n\tto: {:?}", from, to,);
         fs::rename(from, to).await?;
         Ok(())
@@ -38,7 +50,7 @@ impl FileSystem {
         path: impl AsRef<Path>,
         file: impl FileSystemFile,
     ) -> io::Result<()> {
-        let path = self.root.join(path.as_ref());
+        let path = self.within_root(path.as_ref())?;
         rocket::trace!("[save] saving {:?}", path);
         match path.parent() {
             Some(parent) => {
@@ -61,22 +73,29 @@ impl FileSystem {
     }
 
     pub async fn delete(&mut self, path: impl AsRef<Path> + Send) -> io::Result<()> {
-        let path = self.root.join(path.as_ref());
+        let path = self.within_root(path.as_ref())?;
         rocket::trace!("[delete] deleting {:?}", path);
         fs::remove_file(path).await?;
         Ok(())
     }
 
+    pub async fn delete_dir(&mut self, path: impl AsRef<Path> + Send) -> io::Result<()> {
+        let path = self.within_root(path.as_ref())?;
+        rocket::trace!("[delete_dir] deleting {:?}", path);
+        if path.exists() {
+            fs::remove_dir_all(path).await?;
+        }
+        Ok(())
+    }
+
     pub async fn get(&self, path: impl AsRef<Path>) -> Vec<Cow<'static, Path>> {
         let mut files = Vec::new();
         let path = self.root.join(path);
         rocket::info!("FileSystem::get({path:?})");
-        for entry in path.read_dir().into_iter().flatten() {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                rocket::info!("\t Found {path:?}");
-                files.push(path.into());
-            }
+        for entry in path.read_dir().into_iter().flatten().flatten() {
+            let path = entry.path();
+            rocket::info!("\t Found {path:?}");
+            files.push(path.into());
         }
         files
     }
@@ -109,6 +128,7 @@ impl FileSystemFile for &mut TempFile<'_> {
 }
 
 #[async_trait]
+#[allow(clippy::needless_lifetimes)]
 impl FileSystemFile for &Path {
     async fn save_to(self, path: impl AsRef<Path> + Send) -> io::Result<()> {
         fs::rename(self, path).await
@@ -130,3 +150,64 @@ impl FileSystemFile for &Path {
             .map(|ct| ct.into())
     }
 }
+
+#[derive(Debug)]
+pub struct MemoryFile {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+impl MemoryFile {
+    pub fn new(name: impl Into<String>, content: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.into(),
+            content: content.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl FileSystemFile for MemoryFile {
+    async fn save_to(self, path: impl AsRef<Path> + Send) -> io::Result<()> {
+        fs::write(path, &self.content).await
+    }
+
+    fn name_with_ext(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn name_without_ext(&self) -> Option<&str> {
+        Path::new(&self.name).file_stem().and_then(|f| f.to_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn paths_stay_within_the_root() {
+        let root = std::env::temp_dir().join("accounting_file_system_test");
+        let mut fs = FileSystem::new(&root).await;
+        for path in ["../outside", "/etc/passwd", "companies/../../outside"] {
+            let file = MemoryFile::new("outside", "content");
+            let error = fs.save(path, file).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{path}");
+            let error = fs.delete(path).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{path}");
+            let error = fs.delete_dir(path).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{path}");
+            let error = fs.rename("inside", path).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{path}");
+        }
+
+        fs.save("companies/inside", MemoryFile::new("inside", "content"))
+            .await
+            .unwrap();
+        fs.rename("companies/inside", "companies/moved")
+            .await
+            .unwrap();
+        fs.delete("companies/moved").await.unwrap();
+        fs.delete_dir("companies").await.unwrap();
+    }
+}
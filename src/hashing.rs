@@ -0,0 +1,10 @@
+use sha2::{Digest, Sha256};
+
+/// lower case hex sha256 of `bytes`, the stored form of the tokens and the
+/// hash of the documents sent to the tax authority
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
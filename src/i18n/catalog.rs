@@ -0,0 +1,668 @@
+use crate::local_storage::models::Language;
+
+/// the text of a message id in a language
+pub fn text(id: &str, language: Language) -> Option<&'static str> {
+    CATALOG
+        .iter()
+        .find(|(key, _, _)| *key == id)
+        .map(|(_, ar, en)| match language {
+            Language::Ar => *ar,
+            Language::En => *en,
+        })
+}
+
+/// message id, arabic and english
+const CATALOG: &[(&str, &str, &str)] = &[
+    // users
+    (
+        "user.loggedIn",
+        "تم تسجيل الدخول بنجاح",
+        "Signed in successfully",
+    ),
+    (
+        "user.registered",
+        "تم تسجيل مستخدم جديد بنجاح",
+        "User registered successfully",
+    ),
+    ("user.found", "تم ايجاد مستخدمين", "Users found"),
+    (
+        "user.paid",
+        "تم اضافة القيمة للعهدة",
+        "Value added to the custody",
+    ),
+    ("user.adjusted", "تم تسوية العهدة", "Custody adjusted"),
+    (
+        "user.statement",
+        "تم ايجاد كشف حساب العهدة",
+        "Custody statement found",
+    ),
+    (
+        "user.statementForbidden",
+        "غير مسموح بعرض كشف حساب مستخدم اخر",
+        "You may not view the statement of another user",
+    ),
+    ("user.deleted", "تم حذف المستخدم", "User deleted"),
+    (
+        "user.passwordChangeRequired",
+        "يجب تغيير كلمة المرور قبل الدخول",
+        "Change your password to sign in",
+    ),
+    (
+        "user.passwordChanged",
+        "تم تغيير كلمة المرور وتسجيل خروج الاجهزة الاخرى",
+        "Password changed, the other devices were signed out",
+    ),
+    (
+        "user.passwordResetCreated",
+        "تم انشاء رمز اعادة تعيين كلمة المرور",
+        "Password reset token created",
+    ),
+    (
+        "user.passwordReset",
+        "تم تعيين كلمة المرور، سجل الدخول بها",
+        "Password set, sign in with it",
+    ),
+    (
+        "user.sessions",
+        "تم ايجاد الاجهزة المسجل الدخول منها",
+        "Signed in devices found",
+    ),
+    (
+        "user.sessionDeleted",
+        "تم تسجيل خروج الجهاز",
+        "The device was signed out",
+    ),
+    (
+        "user.sessionsDeleted",
+        "تم تسجيل خروج كل الاجهزة",
+        "Every device was signed out",
+    ),
+    (
+        "user.loggedOut",
+        "تم تسجيل خروج المستخدم من كل الاجهزة",
+        "The user was signed out of every device",
+    ),
+    (
+        "user.loginAttempts",
+        "تم ايجاد محاولات تسجيل الدخول",
+        "Login attempts found",
+    ),
+    (
+        "user.unlocked",
+        "تم السماح للمستخدم بتسجيل الدخول مرة اخرى",
+        "The user may login again",
+    ),
+    (
+        "user.twoFactorRequired",
+        "ادخل رمز تطبيق المصادقة",
+        "Enter the code of your authenticator app",
+    ),
+    (
+        "user.twoFactorSetupRequired",
+        "يجب اعداد تطبيق المصادقة قبل الدخول",
+        "Set up an authenticator app to sign in",
+    ),
+    (
+        "user.twoFactor",
+        "تم ايجاد حالة التحقق بخطوتين",
+        "Two factor status found",
+    ),
+    (
+        "user.twoFactorSetUp",
+        "امسح الرمز ثم اكد باحد رموز التطبيق",
+        "Scan the code then confirm with one of its codes",
+    ),
+    (
+        "user.twoFactorEnabled",
+        "تم تفعيل التحقق بخطوتين، احفظ رموز الاسترداد",
+        "Two factor enabled, keep the recovery codes safe",
+    ),
+    (
+        "user.recoveryCodes",
+        "تم انشاء رموز استرداد جديدة",
+        "New recovery codes generated",
+    ),
+    (
+        "user.twoFactorDisabled",
+        "تم ايقاف التحقق بخطوتين",
+        "Two factor disabled",
+    ),
+    (
+        "user.twoFactorReset",
+        "تم الغاء تطبيق المصادقة للمستخدم",
+        "Authenticator removed from the user",
+    ),
+    (
+        "user.twoFactorPolicy",
+        "تم ايجاد سياسة التحقق بخطوتين",
+        "Two factor policy found",
+    ),
+    (
+        "user.twoFactorPolicyUpdated",
+        "تم حفظ سياسة التحقق بخطوتين",
+        "Two factor policy saved",
+    ),
+    // companies
+    ("company.created", "تم انشاء شركة جديدة", "Company created"),
+    ("company.found", "تم العثور علي شركات", "Companies found"),
+    (
+        "company.saved",
+        "تم خفظ الشركة بنجاح",
+        "Company saved successfully",
+    ),
+    ("company.deleted", "تم حذف الشركة", "Company deleted"),
+    (
+        "company.statement",
+        "تم ايجاد كشف حساب الشركة",
+        "Company statement found",
+    ),
+    (
+        "company.vatReturn",
+        "تم اعداد اقرار القيمة المضافة",
+        "VAT return prepared",
+    ),
+    (
+        "company.withholdingStatement",
+        "تم اعداد كشف الخصم والاضافة",
+        "Withholding statement prepared",
+    ),
+    // expenses and incomes
+    ("expense.found", "تم ايجاد رؤؤوس اموال", "Expenses found"),
+    ("expense.created", "تم اضافة مصروفات", "Expense added"),
+    (
+        "expense.approved",
+        "تمت الموافقة علي المصروف",
+        "Expense approved",
+    ),
+    ("expense.rejected", "تم رفض المصروف", "Expense rejected"),
+    ("expense.deleted", "تم مسح مصروفات", "Expense deleted"),
+    ("income.found", "تم ايجاد رؤؤوس اموال", "Incomes found"),
+    ("income.created", "تم اضافة واردات", "Income added"),
+    ("income.deleted", "تم مسح واردات", "Income deleted"),
+    (
+        "attachment.created",
+        "تم اضافة المرفقات بنجاح",
+        "Attachments added successfully",
+    ),
+    ("attachment.deleted", "تم مسح المرفق", "Attachment deleted"),
+    // documents
+    (
+        "document.created",
+        "تم انشاء مستند بنجاح",
+        "Document created successfully",
+    ),
+    (
+        "document.found",
+        "تم ايجاد مستندات بنجاح",
+        "Documents found",
+    ),
+    ("document.deleted", "تم مسح المستند", "Document deleted"),
+    // funders
+    (
+        "funder.created",
+        "تم اضافة ممول ببنجاح",
+        "Funder added successfully",
+    ),
+    (
+        "funder.updated",
+        "تم تعديل الممول بنجاح",
+        "Funder updated successfully",
+    ),
+    (
+        "funder.deleted",
+        "تم مسح الممول بنجاح",
+        "Funder deleted successfully",
+    ),
+    (
+        "distribution.created",
+        "تم توزيع الارباح علي الممولين بنجاح",
+        "Profits distributed to the funders successfully",
+    ),
+    (
+        "distribution.found",
+        "تم ايجاد توزيعات ارباح",
+        "Profit distributions found",
+    ),
+    // schedules
+    (
+        "schedule.created",
+        "تم انشاء جدول متكرر بنجاح",
+        "Recurring schedule created",
+    ),
+    (
+        "schedule.found",
+        "تم ايجاد جداول متكررة",
+        "Recurring schedules found",
+    ),
+    (
+        "schedule.updated",
+        "تم تعديل الجدول المتكرر",
+        "Recurring schedule updated",
+    ),
+    (
+        "schedule.deleted",
+        "تم مسح الجدول المتكرر",
+        "Recurring schedule deleted",
+    ),
+    (
+        "schedule.ran",
+        "تم تشغيل الجداول المتكررة",
+        "Recurring schedules ran",
+    ),
+    // invoices
+    (
+        "invoice.created",
+        "تم انشاء فاتورة جديدة",
+        "Invoice created",
+    ),
+    ("invoice.found", "تم ايجاد فواتير", "Invoices found"),
+    ("invoice.foundOne", "تم ايجاد الفاتورة", "Invoice found"),
+    ("invoice.updated", "تم تعديل الفاتورة", "Invoice updated"),
+    ("invoice.issued", "تم اصدار الفاتورة", "Invoice issued"),
+    ("invoice.voided", "تم الغاء الفاتورة", "Invoice voided"),
+    ("invoice.paid", "تم تسجيل السداد", "Payment recorded"),
+    ("invoice.deleted", "تم مسح الفاتورة", "Invoice deleted"),
+    (
+        "invoice.aging",
+        "تم ايجاد اعمار المديونيات",
+        "Receivables aging found",
+    ),
+    (
+        "eta.found",
+        "تم ايجاد الفاتورة الالكترونية",
+        "E-invoice found",
+    ),
+    (
+        "eta.submitted",
+        "تم ارسال الفاتورة الي منظومة الفاتورة الالكترونية",
+        "Invoice submitted to the e-invoicing system",
+    ),
+    (
+        "eta.refreshed",
+        "تم تحديث حالة الفاتورة الالكترونية",
+        "E-invoice status updated",
+    ),
+    (
+        "eta.cancelled",
+        "تم الغاء الفاتورة الالكترونية",
+        "E-invoice cancelled",
+    ),
+    // deadlines
+    ("deadline.created", "تم اضافة موعد", "Deadline added"),
+    ("deadline.found", "تم ايجاد مواعيد", "Deadlines found"),
+    (
+        "deadline.fulfilled",
+        "تم استيفاء الموعد",
+        "Deadline fulfilled",
+    ),
+    ("deadline.deleted", "تم مسح الموعد", "Deadline deleted"),
+    (
+        "deadline.synced",
+        "تم تحديث مواعيد الشركات",
+        "Company deadlines updated",
+    ),
+    // portal
+    (
+        "portal.loggedIn",
+        "تم تسجيل دخول الشركة بنجاح",
+        "Company signed in successfully",
+    ),
+    // tenants
+    (
+        "tenant.loggedIn",
+        "تم تسجيل دخول مدير المكاتب بنجاح",
+        "Signed in as the offices manager",
+    ),
+    (
+        "tenant.created",
+        "تم انشاء مكتب جديد ومديره بنجاح",
+        "Office and its admin created successfully",
+    ),
+    ("tenant.found", "تم ايجاد مكاتب", "Offices found"),
+    // notifications
+    (
+        "notification.found",
+        "تم ايجاد اشعارات",
+        "Notifications found",
+    ),
+    (
+        "notification.counted",
+        "تم حساب الاشعارات غير المقروءة",
+        "Unread notifications counted",
+    ),
+    (
+        "notification.read",
+        "تم قراءة الاشعار",
+        "Notification marked as read",
+    ),
+    (
+        "notification.unread",
+        "تم تحديد الاشعار كغير مقروء",
+        "Notification marked as unread",
+    ),
+    (
+        "notification.readAll",
+        "تم قراءة كل الاشعارات",
+        "All notifications marked as read",
+    ),
+    // imports
+    (
+        "import.failed",
+        "يوجد {0} خطأ في الملف ولم يتم استيراد اي صف",
+        "The file has {0} errors, no row was imported",
+    ),
+    ("import.imported", "تم استيراد {0} صف", "{0} rows imported"),
+    (
+        "import.valid",
+        "الملف صالح للاستيراد",
+        "The file can be imported",
+    ),
+    // requests the routes could not handle
+    ("auth.missing", "يجب تسجيل الدخول", "You need to sign in"),
+    ("auth.expired", "انتهت صلاحية الجلسة", "The session expired"),
+    (
+        "auth.invalid",
+        "رمز الدخول غير صالح",
+        "The token is invalid",
+    ),
+    (
+        "auth.revoked",
+        "تم تسجيل الخروج من هذا الجهاز، سجل الدخول مرة اخرى",
+        "This device was signed out, sign in again",
+    ),
+    (
+        "auth.forbidden",
+        "غير مسموح لك بهذه العملية",
+        "You are not allowed to do this",
+    ),
+    ("request.badRequest", "طلب غير صالح", "Bad request"),
+    (
+        "request.notFound",
+        "الصفحة المطلوبة غير موجودة",
+        "The requested resource does not exist",
+    ),
+    (
+        "request.tooLarge",
+        "حجم الطلب اكبر من المسموح",
+        "The request is too large",
+    ),
+    (
+        "request.malformed",
+        "بيانات الطلب غير مكتملة او غير صحيحة",
+        "The request body is incomplete or invalid",
+    ),
+    (
+        "request.internal",
+        "حدث خطأ في السيرفر",
+        "Internal server error",
+    ),
+    // errors
+    ("error.notFound", "لم يتم العثور علي هدف", "Not found"),
+    (
+        "error.notEnoughValue",
+        "لا يوجد قيمة كافية: \"{0} > {1}\"",
+        "Not enough value: \"{0} > {1}\"",
+    ),
+    (
+        "error.invalidValue",
+        "قيمة غير صحيحة:  >= 0",
+        "Invalid value: must be >= 0",
+    ),
+    (
+        "error.invalidTaxRate",
+        "نسبة ضريبة الجدول مطلوبة ويجب ان تكون بين 0 و 100",
+        "The table tax rate is required and must be between 0 and 100",
+    ),
+    (
+        "error.invalidWithholding",
+        "نسبة الخصم يجب ان تكون بين 0 و 100 مع رقم تسجيل ضريبي للمورد من 9 ارقام",
+        "The withholding rate must be between 0 and 100 with a 9 digit supplier tax registration",
+    ),
+    (
+        "error.deadlineAlreadyFulfilled",
+        "تم استيفاء الموعد بالفعل",
+        "The deadline is already fulfilled",
+    ),
+    (
+        "error.derivedDeadline",
+        "لا يمكن مسح المواعيد المحسوبة من تسجيلات الشركة",
+        "Deadlines derived from the company registrations cannot be deleted",
+    ),
+    (
+        "error.invalidTaxPeriod",
+        "فترة ضريبية غير صحيحة",
+        "Invalid tax period",
+    ),
+    (
+        "error.expenseNotPending",
+        "المصروف ليس في انتظار المراجعة",
+        "The expense is not pending review",
+    ),
+    (
+        "error.ownExpenseReview",
+        "لا يمكن للمراجع اعتماد مصروف قام به",
+        "Reviewers cannot approve their own expenses",
+    ),
+    (
+        "error.invalidInvoiceState",
+        "لا يمكن تنفيذ العملية علي الفاتورة في حالتها الحالية",
+        "The invoice does not allow this operation in its current state",
+    ),
+    (
+        "error.emptyInvoice",
+        "لا يمكن اصدار فاتورة بدون بنود",
+        "An invoice without lines cannot be issued",
+    ),
+    (
+        "error.paymentExceedsBalance",
+        "قيمة السداد اكبر من المتبقي علي الفاتورة: \"{0} > {1}\"",
+        "The payment exceeds the invoice balance: \"{0} > {1}\"",
+    ),
+    (
+        "error.invalidFundersPercentage",
+        "مجموع نسب الممولين لا يساوي 100: \"{0}\"",
+        "The funder percentages do not add up to 100: \"{0}\"",
+    ),
+    (
+        "error.unreadableSheet",
+        "تعذر قراءة الملف: {0}",
+        "The file could not be read: {0}",
+    ),
+    (
+        "error.emptySheet",
+        "تعذر قراءة الملف: الملف فارغ",
+        "The file could not be read: it is empty",
+    ),
+    (
+        "error.unknownField",
+        "تعذر قراءة الملف: حقل غير معروف \"{0}\"",
+        "The file could not be read: unknown field \"{0}\"",
+    ),
+    (
+        "error.missingColumn",
+        "العمود \"{0}\" غير موجود في الملف",
+        "The column \"{0}\" is missing from the file",
+    ),
+    (
+        "error.etaNotConfigured",
+        "لم يتم ضبط اعدادات الفاتورة الالكترونية",
+        "E-invoicing is not configured",
+    ),
+    (
+        "error.etaAlreadySubmitted",
+        "تم ارسال الفاتورة الي منظومة الفاتورة الالكترونية بالفعل",
+        "The invoice is already submitted to the e-invoicing system",
+    ),
+    (
+        "error.missingTaxRegistration",
+        "الشركة ليس لها رقم تسجيل ضريبي",
+        "The company has no tax registration number",
+    ),
+    (
+        "error.eta",
+        "خطأ في منظومة الفاتورة الالكترونية: {0}",
+        "E-invoicing system error: {0}",
+    ),
+    (
+        "error.invalidCredentials",
+        "اسم المستخدم او كلمة المرور غير صحيحة",
+        "Wrong user name or password",
+    ),
+    (
+        "error.loginLocked",
+        "محاولات فاشلة كثيرة، حاول مرة اخرى بعد {0} ثانية",
+        "Too many failed attempts, try again in {0} seconds",
+    ),
+    (
+        "error.passwordTooShort",
+        "يجب ان تكون كلمة المرور {0} حروف على الاقل",
+        "The password must have at least {0} characters",
+    ),
+    (
+        "error.breachedPassword",
+        "كلمة المرور شائعة ومسربة، اختر غيرها",
+        "This password is common and leaked, choose another one",
+    ),
+    (
+        "error.wrongPassword",
+        "كلمة المرور الحالية غير صحيحة",
+        "Wrong current password",
+    ),
+    (
+        "error.invalidResetToken",
+        "رمز اعادة التعيين غير صحيح او منتهي",
+        "Unknown, used or expired reset token",
+    ),
+    (
+        "error.invalidTwoFactorCode",
+        "رمز التحقق غير صحيح او مستخدم من قبل",
+        "Wrong or already used code",
+    ),
+    (
+        "error.invalidTwoFactorState",
+        "التحقق بخطوتين غير معد لهذا المستخدم",
+        "Two factor is not set up that way for this user",
+    ),
+    (
+        "error.twoFactorEnforced",
+        "التحقق بخطوتين اجباري للمديرين",
+        "Two factor is required for admins",
+    ),
+    (
+        "error.other",
+        "حدث خطأ في قاعدة البيانات:\n {0}",
+        "A database error occurred:\n {0}",
+    ),
+    // database constraints
+    (
+        "constraint.unique",
+        "القيمة مستخدمة بالفعل",
+        "The value is already taken",
+    ),
+    (
+        "constraint.foreignKey",
+        "العنصر المرتبط غير موجود او مازال مستخدم",
+        "The related item does not exist or is still in use",
+    ),
+    ("constraint.check", "قيمة غير صحيحة", "Invalid value"),
+    (
+        "constraint.user_name_must_be_unique",
+        "اسم المستخدم مستخدم بالفعل",
+        "The user name is taken",
+    ),
+    (
+        "constraint.user_email_must_be_unique",
+        "البريد الالكتروني مستخدم لمستخدم اخر",
+        "The email is used by another user",
+    ),
+    (
+        "constraint.company_must_be_unique",
+        "توجد شركة بنفس الاسم التجاري لنفس المالك",
+        "The owner already has a company with this commercial name",
+    ),
+    (
+        "constraint.company_file_number_must_be_digits",
+        "رقم الملف يجب ان يتكون من ارقام فقط",
+        "The file number must only contain digits",
+    ),
+    (
+        "constraint.company_register_number_must_be_9_digits",
+        "رقم التسجيل الضريبي يجب ان يتكون من 9 ارقام",
+        "The tax registration number must be 9 digits",
+    ),
+    (
+        "constraint.company_record_number_must_be_digits",
+        "رقم السجل التجاري يجب ان يتكون من ارقام فقط",
+        "The commercial record number must only contain digits",
+    ),
+    (
+        "constraint.company_username_must_be_unique",
+        "اسم المستخدم مستخدم لشركة اخرى",
+        "The user name is used by another company",
+    ),
+    (
+        "constraint.company_email_must_be_unique",
+        "البريد الالكتروني مستخدم لشركة اخرى",
+        "The email is used by another company",
+    ),
+    (
+        "constraint.funder_percentage_must_be_between_0_and_100",
+        "نسبة الممول يجب ان تكون بين 0 و 100",
+        "The funder percentage must be between 0 and 100",
+    ),
+    (
+        "constraint.profit_distribution_period_must_be_valid",
+        "نهاية الفترة يجب ان تكون بعد بدايتها",
+        "The period must end after it starts",
+    ),
+    (
+        "constraint.profit_distribution_period_must_be_unique",
+        "تم توزيع الارباح عن هذه الفترة بالفعل",
+        "The profits of this period are already distributed",
+    ),
+    (
+        "constraint.attachment_must_have_unique_name",
+        "يوجد مرفق بنفس الاسم",
+        "An attachment with the same name exists",
+    ),
+    (
+        "constraint.schedule_value_must_be_positive",
+        "القيمة يجب ان تكون اكبر من صفر",
+        "The value must be greater than zero",
+    ),
+    (
+        "constraint.schedule_period_must_be_valid",
+        "تاريخ النهاية يجب ان يكون بعد تاريخ البداية",
+        "The end date must be after the start date",
+    ),
+    (
+        "constraint.invoice_line_quantity_must_be_positive",
+        "الكمية يجب ان تكون اكبر من صفر",
+        "The quantity must be greater than zero",
+    ),
+    (
+        "constraint.invoice_line_unit_price_must_not_be_negative",
+        "سعر الوحدة لا يمكن ان يكون سالب",
+        "The unit price cannot be negative",
+    ),
+    (
+        "constraint.invoice_payment_value_must_be_positive",
+        "قيمة السداد يجب ان تكون اكبر من صفر",
+        "The payment must be greater than zero",
+    ),
+    (
+        "constraint.tax_must_not_exceed_value",
+        "الضريبة لا يمكن ان تتجاوز القيمة",
+        "The tax cannot exceed the value",
+    ),
+    (
+        "constraint.expense_withholding_must_have_supplier",
+        "الخصم من المنبع يتطلب رقم تسجيل ضريبي للمورد",
+        "Withholding requires a supplier tax registration number",
+    ),
+    (
+        "constraint.tenant_name_must_be_unique",
+        "اسم المكتب مستخدم بالفعل",
+        "The office name is taken",
+    ),
+];
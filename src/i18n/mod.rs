@@ -0,0 +1,201 @@
+mod catalog;
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Formatter},
+};
+
+use rocket::{
+    fairing::AdHoc,
+    serde::{Deserialize, Deserializer, Serialize, Serializer},
+    Request,
+};
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+
+use crate::{
+    accounting_api::AcountingApi,
+    auth::ApiToken,
+    local_storage::{models::Language, Tenants},
+};
+
+/// a message of the API, localized when the response is sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// looked up in the catalog, `{0}`, `{1}`.. are replaced by the arguments
+    Id(Cow<'static, str>, Vec<String>),
+    /// shown as is
+    Text(Cow<'static, str>),
+}
+
+impl Message {
+    pub fn new(id: impl Into<Cow<'static, str>>) -> Self {
+        Self::Id(id.into(), vec![])
+    }
+
+    pub fn arg(self, arg: impl Display) -> Self {
+        match self {
+            Self::Id(id, mut args) => {
+                args.push(arg.to_string());
+                Self::Id(id, args)
+            }
+            text => text,
+        }
+    }
+
+    /// unknown ids are shown as they are
+    pub fn localize(&self, language: Language) -> Cow<'static, str> {
+        match self {
+            Self::Id(id, args) => match catalog::text(id, language) {
+                Some(text) if args.is_empty() => text.into(),
+                Some(text) => args
+                    .iter()
+                    .enumerate()
+                    .fold(text.to_owned(), |text, (i, arg)| {
+                        text.replace(&format!("{{{i}}}"), arg)
+                    })
+                    .into(),
+                None => id.clone(),
+            },
+            Self::Text(text) => text.clone(),
+        }
+    }
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Self::Text("".into())
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(id: &'static str) -> Self {
+        Self::new(id)
+    }
+}
+
+/// outside of a response the message is shown in the default language
+impl Display for Message {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.localize(Language::default()))
+    }
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.localize(Language::default()))
+    }
+}
+
+/// a plain string once localized
+impl JsonSchema for Message {
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|text| Self::Text(text.into()))
+    }
+}
+
+/// the language of the current request
+struct RequestLanguage(Language);
+
+/// the language the response to `request` is written in
+pub fn language(request: &Request<'_>) -> Language {
+    request
+        .local_cache(|| RequestLanguage(Language::default()))
+        .0
+}
+
+/// the most preferred supported language of an `Accept-Language` header
+pub fn accepted_language(header: &str) -> Option<Language> {
+    let mut ranges = header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let tag = parts.next()?.split('-').next()?.to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .map(|q| q.parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            let language = match tag.as_str() {
+                "ar" => Language::Ar,
+                "en" => Language::En,
+                _ => return None,
+            };
+            Some((language, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect::<Vec<_>>();
+    // stable, the header order breaks the ties
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges.first().map(|(language, _)| *language)
+}
+
+/// picks the language of each request from `Accept-Language`, then from the
+/// preference of the signed in user, Arabic otherwise
+pub fn stage() -> AdHoc {
+    AdHoc::on_request("language", |request, _| {
+        Box::pin(async move {
+            let header = request
+                .headers()
+                .get_one("Accept-Language")
+                .and_then(accepted_language);
+            let language = match header {
+                Some(language) => language,
+                None => preference(request).await.unwrap_or_default(),
+            };
+            request.local_cache(|| RequestLanguage(language));
+        })
+    })
+}
+
+async fn preference(request: &Request<'_>) -> Option<Language> {
+    let token = ApiToken(request.headers().get_one("Authorization")?.into());
+    let (user_id, tenant_id) = (token.user_id()?, token.tenant_id()?);
+    let tenants = request.rocket().state::<Tenants>()?;
+    tenants
+        .storage(tenant_id)
+        .await
+        .ok()?
+        .get_user(user_id)
+        .await
+        .ok()
+        .map(|user| user.language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_most_preferred_supported_language_is_picked() {
+        assert_eq!(
+            accepted_language("en-US,en;q=0.9,ar;q=0.8"),
+            Some(Language::En)
+        );
+        assert_eq!(
+            accepted_language("fr-FR, ar;q=0.5, en;q=0.7"),
+            Some(Language::En)
+        );
+        assert_eq!(
+            accepted_language("AR-eg;q=0.9, en;q=0.9"),
+            Some(Language::Ar)
+        );
+        // an unreadable quality is not accepted
+        assert_eq!(accepted_language("en;q=high, ar;q=0.1"), Some(Language::Ar));
+        assert_eq!(accepted_language("en;q=0, fr"), None);
+        assert_eq!(accepted_language(""), None);
+    }
+}
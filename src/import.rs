@@ -0,0 +1,573 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Cursor},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use calamine::{Data, Reader};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use rocket::{fs::TempFile, tokio::fs, FromForm};
+use schemars::JsonSchema;
+
+use crate::{
+    accounting_api,
+    docs::Binary,
+    i18n::Message,
+    local_storage::models::{
+        CreateCompany, ImportError, ImportExpense, ImportIncome, ImportReport, TaxCode,
+    },
+    types::response::{ResponseEnum, ResponseResult},
+};
+
+impl From<calamine::Error> for accounting_api::Error {
+    fn from(error: calamine::Error) -> Self {
+        rocket::error!("[Import] {error:#?}");
+        Self::UnreadableSheet(error.to_string().into())
+    }
+}
+
+impl From<csv::Error> for accounting_api::Error {
+    fn from(error: csv::Error) -> Self {
+        rocket::error!("[Import] {error:#?}");
+        Self::UnreadableSheet(error.to_string().into())
+    }
+}
+
+/// an uploaded sheet, `mapping` gives the column title of a field when the
+/// sheet does not use the titles of the exports
+#[derive(FromForm, Debug, JsonSchema)]
+pub struct ImportForm<'r> {
+    #[schemars(with = "Binary")]
+    pub file: TempFile<'r>,
+    pub mapping: HashMap<String, String>,
+}
+
+/// a row of a sheet being validated, the errors of every cell are collected
+/// instead of stopping at the first one
+pub struct Row<'s> {
+    line: usize,
+    cells: &'s [String],
+    columns: &'s HashMap<&'static str, usize>,
+    errors: Vec<ImportError>,
+}
+
+impl Row<'_> {
+    fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(ImportError {
+            row: self.line,
+            column: Some(field.to_owned()),
+            message: message.into(),
+        });
+    }
+
+    fn text(&self, field: &str) -> Option<String> {
+        self.columns
+            .get(field)
+            .and_then(|&column| self.cells.get(column))
+            .map(|cell| cell.trim())
+            .filter(|cell| !cell.is_empty())
+            .map(str::to_owned)
+    }
+
+    fn required(&mut self, field: &str) -> String {
+        self.text(field).unwrap_or_else(|| {
+            self.error(field, "القيمة مطلوبة");
+            String::new()
+        })
+    }
+
+    /// digits only, `length` is the exact count when given
+    fn digits(&mut self, field: &str, length: Option<usize>) -> Option<String> {
+        let text = self.text(field)?;
+        let valid = text.chars().all(|c| c.is_ascii_digit())
+            && length.map(|length| text.len() == length).unwrap_or(true);
+        if valid {
+            return Some(text);
+        }
+        match length {
+            Some(length) => self.error(field, format!("يجب ان يتكون من {length} ارقام")),
+            None => self.error(field, "يجب ان يحتوي علي ارقام فقط"),
+        }
+        None
+    }
+
+    fn value(&mut self, field: &str) -> f64 {
+        let text = self.required(field);
+        match text.replace(',', "").parse::<f64>() {
+            Ok(value) if value > 0.0 => value,
+            _ if text.is_empty() => 0.0,
+            _ => {
+                self.error(field, "قيمة غير صحيحة، يجب ان تكون اكبر من 0");
+                0.0
+            }
+        }
+    }
+
+    /// a percentage, the `%` sign is optional
+    fn rate(&mut self, field: &str) -> Option<f64> {
+        let text = self.text(field)?;
+        match text.trim_end_matches('%').trim().parse::<f64>() {
+            Ok(rate) if rate >= 0.0 => Some(rate),
+            _ => {
+                self.error(field, "نسبة غير صحيحة");
+                None
+            }
+        }
+    }
+
+    /// exempt when the cell is empty
+    fn tax_code(&mut self, field: &str) -> TaxCode {
+        let text = match self.text(field) {
+            Some(text) => text,
+            None => return TaxCode::default(),
+        };
+        TaxCode::parse(&text).unwrap_or_else(|| {
+            self.error(field, "نوع ضريبة غير معروف");
+            TaxCode::default()
+        })
+    }
+
+    fn boolean(&mut self, field: &str, default: bool) -> bool {
+        match self.text(field).as_deref() {
+            None => default,
+            Some("نعم" | "true" | "TRUE" | "yes" | "1") => true,
+            Some("لا" | "false" | "FALSE" | "no" | "0") => false,
+            Some(_) => {
+                self.error(field, "يجب ان تكون القيمة نعم او لا");
+                default
+            }
+        }
+    }
+
+    fn time(&mut self, field: &str) -> Option<DateTime<Utc>> {
+        let text = self.text(field)?;
+        let time = DateTime::parse_from_rfc3339(&text)
+            .map(|time| time.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+                    .iter()
+                    .find_map(|format| NaiveDateTime::parse_from_str(&text, format).ok())
+                    .or_else(|| {
+                        ["%Y-%m-%d", "%d/%m/%Y"]
+                            .iter()
+                            .find_map(|format| NaiveDate::parse_from_str(&text, format).ok())
+                            .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    })
+                    .map(|time| Utc.from_utc_datetime(&time))
+            });
+        if time.is_none() {
+            self.error(field, "تاريخ غير صحيح، يجب ان يكون بالصيغة yyyy-mm-dd");
+        }
+        time
+    }
+}
+
+/// a record that can be read from a row of an imported sheet
+pub trait ImportRow: Sized {
+    /// field names with the column titles used by the exports
+    const FIELDS: &'static [(&'static str, &'static str)];
+    /// fields the sheet must have a column for
+    const REQUIRED: &'static [&'static str];
+
+    fn parse(row: &mut Row<'_>) -> Self;
+}
+
+impl ImportRow for CreateCompany {
+    const FIELDS: &'static [(&'static str, &'static str)] = &[
+        ("owner", "المالك"),
+        ("commercialFeature", "الاسم التجاري"),
+        ("isWorking", "تعمل"),
+        ("legalEntity", "الكيان القانوني"),
+        ("fileNumber", "رقم الملف"),
+        ("registerNumber", "رقم التسجيل"),
+        ("recordNumber", "رقم السجل"),
+        ("startDate", "تاريخ البدء"),
+        ("stopDate", "تاريخ التوقف"),
+        ("generalTaxMission", "مأمورية الضرائب العامة"),
+        ("valueTaxMission", "مأمورية القيمة المضافة"),
+        ("activityNature", "طبيعة النشاط"),
+        ("activityLocation", "مقر النشاط"),
+        ("email", "البريد الالكتروني"),
+    ];
+    const REQUIRED: &'static [&'static str] = &["owner", "commercialFeature"];
+
+    fn parse(row: &mut Row<'_>) -> Self {
+        Self {
+            owner: row.required("owner"),
+            commercial_feature: row.required("commercialFeature"),
+            is_working: row.boolean("isWorking", true),
+            legal_entity: row.text("legalEntity"),
+            file_number: row.digits("fileNumber", None),
+            register_number: row.digits("registerNumber", Some(9)),
+            start_date: row.time("startDate"),
+            stop_date: row.time("stopDate"),
+            general_tax_mission: row.text("generalTaxMission"),
+            value_tax_mission: row.text("valueTaxMission"),
+            activity_nature: row.text("activityNature"),
+            activity_location: row.text("activityLocation"),
+            record_number: row.digits("recordNumber", None),
+            username: None,
+            password: None,
+            email: row.text("email"),
+        }
+    }
+}
+
+impl ImportRow for ImportExpense {
+    const FIELDS: &'static [(&'static str, &'static str)] = &[
+        ("time", "التاريخ"),
+        ("company", "الشركة"),
+        ("owner", "المالك"),
+        ("user", "الموظف"),
+        ("description", "البيان"),
+        ("value", "القيمة"),
+        ("taxCode", "نوع الضريبة"),
+        ("taxRate", "نسبة الضريبة"),
+        ("supplier", "المورد"),
+        ("supplierRegistration", "رقم تسجيل المورد"),
+        ("withholdingRate", "نسبة الخصم"),
+    ];
+    const REQUIRED: &'static [&'static str] = &["company", "description", "value"];
+
+    fn parse(row: &mut Row<'_>) -> Self {
+        Self {
+            user: row.text("user"),
+            company: row.required("company"),
+            owner: row.text("owner"),
+            value: row.value("value"),
+            description: row.required("description"),
+            time: row.time("time"),
+            tax_code: row.tax_code("taxCode"),
+            tax_rate: row.rate("taxRate"),
+            supplier: row.text("supplier"),
+            supplier_registration: row.digits("supplierRegistration", Some(9)),
+            withholding_rate: row.rate("withholdingRate"),
+        }
+    }
+}
+
+impl ImportRow for ImportIncome {
+    const FIELDS: &'static [(&'static str, &'static str)] = &[
+        ("time", "التاريخ"),
+        ("company", "الشركة"),
+        ("owner", "المالك"),
+        ("admin", "المسؤول"),
+        ("description", "البيان"),
+        ("value", "القيمة"),
+        ("taxCode", "نوع الضريبة"),
+        ("taxRate", "نسبة الضريبة"),
+    ];
+    const REQUIRED: &'static [&'static str] = &["company", "description", "value"];
+
+    fn parse(row: &mut Row<'_>) -> Self {
+        Self {
+            admin: row.text("admin"),
+            company: row.required("company"),
+            owner: row.text("owner"),
+            value: row.value("value"),
+            description: row.required("description"),
+            time: row.time("time"),
+            tax_code: row.tax_code("taxCode"),
+            tax_rate: row.rate("taxRate"),
+        }
+    }
+}
+
+/// the valid rows of a sheet with their line numbers and the rejected ones
+pub struct Sheet<T> {
+    pub rows: Vec<(usize, T)>,
+    total: usize,
+    errors: Vec<ImportError>,
+    /// the title of the column every field was read from
+    titles: HashMap<&'static str, String>,
+}
+
+impl<T> Sheet<T> {
+    /// a sheet with rejected rows is never committed
+    pub fn commit(&self, dry_run: bool) -> bool {
+        !dry_run && self.errors.is_empty()
+    }
+
+    /// the result of an import, `errors` are the rows rejected by the storage
+    pub fn report(self, dry_run: bool, errors: Vec<ImportError>) -> ResponseResult<ImportReport> {
+        let committed = self.commit(dry_run) && errors.is_empty();
+        let titles = self.titles;
+        let mut errors = self
+            .errors
+            .into_iter()
+            .chain(errors)
+            .map(|error| ImportError {
+                column: error
+                    .column
+                    .map(|field| titles.get(field.as_str()).cloned().unwrap_or(field)),
+                ..error
+            })
+            .collect::<Vec<_>>();
+        errors.sort_by_key(|error| error.row);
+
+        let report = ImportReport {
+            rows: self.total,
+            dry_run,
+            committed,
+            errors,
+        };
+        if !report.errors.is_empty() {
+            let message = Message::new("import.failed").arg(report.errors.len());
+            Err(ResponseEnum::unprocessable(report, message))
+        } else if committed {
+            let message = Message::new("import.imported").arg(report.rows);
+            Ok(ResponseEnum::created(report, message))
+        } else {
+            Ok(ResponseEnum::ok(report, "import.valid".into()))
+        }
+    }
+}
+
+/// small uploads are kept in memory by rocket and are copied out to be read
+async fn content(file: &mut TempFile<'_>) -> io::Result<Vec<u8>> {
+    static IMPORTS: AtomicUsize = AtomicUsize::new(0);
+    if let Some(path) = file.path() {
+        return fs::read(path).await;
+    }
+    let path = env::temp_dir().join(format!(
+        "import-{}-{}",
+        std::process::id(),
+        IMPORTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    file.copy_to(&path).await?;
+    let content = fs::read(&path).await;
+    fs::remove_file(&path).await?;
+    content
+}
+
+fn cell(data: &Data) -> String {
+    match data {
+        Data::Float(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+            format!("{number:.0}")
+        }
+        // excel counts days from the end of 1899
+        Data::DateTime(time) => NaiveDate::from_ymd_opt(1899, 12, 30)
+            .and_then(|epoch| epoch.and_hms_opt(0, 0, 0))
+            .and_then(|epoch| {
+                epoch
+                    .checked_add_signed(Duration::seconds((time.as_f64() * 86400.0).round() as i64))
+            })
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default(),
+        Data::Error(_) | Data::Empty => String::new(),
+        data => data.to_string(),
+    }
+}
+
+/// the lines of the first worksheet with their numbers
+fn xlsx(content: Vec<u8>) -> Result<Vec<(usize, Vec<String>)>, calamine::Error> {
+    let mut workbook = calamine::open_workbook_auto_from_rs(Cursor::new(content))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or(calamine::Error::Msg("الملف لا يحتوي علي صفحات"))??;
+    let first = range.start().map(|(row, _)| row as usize).unwrap_or(0);
+    Ok(range
+        .rows()
+        .enumerate()
+        .map(|(index, row)| (first + index + 1, row.iter().map(cell).collect()))
+        .collect())
+}
+
+fn csv(content: &[u8]) -> Result<Vec<(usize, Vec<String>)>, csv::Error> {
+    let content = content
+        .strip_prefix("\u{feff}".as_bytes())
+        .unwrap_or(content);
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content)
+        .records()
+        .enumerate()
+        .map(|(index, record)| Ok((index + 1, record?.iter().map(str::to_owned).collect())))
+        .collect()
+}
+
+/// reads an uploaded CSV or XLSX sheet, the titles are on the first line and
+/// empty lines are skipped
+pub async fn read<T: ImportRow>(
+    form: &mut ImportForm<'_>,
+) -> Result<Sheet<T>, accounting_api::Error> {
+    let content = content(&mut form.file).await?;
+    // xlsx files are zip archives and old xls files are compound documents
+    let lines =
+        if content.starts_with(b"PK\x03\x04") || content.starts_with(&[0xD0, 0xCF, 0x11, 0xE0]) {
+            xlsx(content)?
+        } else {
+            csv(&content)?
+        };
+    let (headers, lines) = match lines.split_first() {
+        Some(((_, headers), lines)) => (headers, lines),
+        None => return Err(accounting_api::Error::EmptySheet),
+    };
+
+    if let Some(field) = form
+        .mapping
+        .keys()
+        .find(|field| !T::FIELDS.iter().any(|(name, _)| name == field))
+    {
+        return Err(accounting_api::Error::UnknownField(field.clone()));
+    }
+
+    let mut columns = HashMap::new();
+    let mut titles = HashMap::new();
+    for &(field, title) in T::FIELDS {
+        let mapped = form.mapping.get(field).map(|title| title.trim());
+        let column = headers.iter().position(|header| {
+            let header = header.trim();
+            match mapped {
+                Some(mapped) => header == mapped,
+                None => header == title || header == field,
+            }
+        });
+        match column {
+            Some(column) => {
+                columns.insert(field, column);
+                titles.insert(field, headers[column].trim().to_owned());
+            }
+            None if mapped.is_some() || T::REQUIRED.contains(&field) => {
+                return Err(accounting_api::Error::MissingColumn(
+                    mapped.unwrap_or(title).to_owned(),
+                ));
+            }
+            None => {
+                titles.insert(field, title.to_owned());
+            }
+        }
+    }
+
+    let mut sheet = Sheet {
+        rows: vec![],
+        total: 0,
+        errors: vec![],
+        titles,
+    };
+    for (line, cells) in lines {
+        if cells.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        sheet.total += 1;
+        let mut row = Row {
+            line: *line,
+            cells,
+            columns: &columns,
+            errors: vec![],
+        };
+        let parsed = T::parse(&mut row);
+        if row.errors.is_empty() {
+            sheet.rows.push((*line, parsed));
+        } else {
+            sheet.errors.append(&mut row.errors);
+        }
+    }
+    Ok(sheet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the row of a sheet with a column for every field, in their order
+    fn parse<T: ImportRow>(cells: &[&str]) -> (T, Vec<ImportError>) {
+        let columns = T::FIELDS
+            .iter()
+            .enumerate()
+            .map(|(column, (field, _))| (*field, column))
+            .collect();
+        let cells: Vec<String> = cells.iter().map(|cell| cell.to_string()).collect();
+        let mut row = Row {
+            line: 2,
+            cells: &cells,
+            columns: &columns,
+            errors: vec![],
+        };
+        let parsed = T::parse(&mut row);
+        (parsed, row.errors)
+    }
+
+    fn columns(errors: &[ImportError]) -> Vec<&str> {
+        errors
+            .iter()
+            .filter_map(|error| error.column.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn valid_rows_are_read() {
+        let (expense, errors) = parse::<ImportExpense>(&[
+            "15/03/2024",
+            " bakery ",
+            "",
+            "bob",
+            "flour",
+            "1,140.50",
+            "standard",
+            "",
+            "mill",
+            "123456789",
+            "1%",
+        ]);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(expense.company, "bakery");
+        assert_eq!(expense.owner, None);
+        assert_eq!(expense.value, 1140.5);
+        assert_eq!(
+            expense.time.map(|time| time.date_naive()),
+            NaiveDate::from_ymd_opt(2024, 3, 15)
+        );
+        assert_eq!(expense.tax_code, TaxCode::Standard);
+        assert_eq!(expense.withholding_rate, Some(1.0));
+    }
+
+    #[test]
+    fn every_invalid_cell_is_reported() {
+        let (_, errors) = parse::<ImportExpense>(&[
+            "yesterday",
+            "",
+            "",
+            "",
+            "flour",
+            "-5",
+            "vat",
+            "-1",
+            "",
+            "12345",
+            "",
+        ]);
+        assert_eq!(
+            columns(&errors),
+            [
+                "company",
+                "value",
+                "time",
+                "taxCode",
+                "taxRate",
+                "supplierRegistration"
+            ]
+        );
+        assert!(errors.iter().all(|error| error.row == 2));
+
+        let (company, errors) = parse::<CreateCompany>(&["owner", "shop", "maybe"]);
+        assert_eq!(columns(&errors), ["isWorking"]);
+        assert!(company.is_working);
+    }
+
+    #[test]
+    fn csv_lines_are_numbered_without_the_byte_order_mark() {
+        let lines =
+            csv("\u{feff}company,value\r\nbakery,\"1,000\"\r\n".as_bytes()).expect("a csv sheet");
+        assert_eq!(
+            lines,
+            [
+                (1, vec!["company".to_string(), "value".to_string()]),
+                (2, vec!["bakery".to_string(), "1,000".to_string()]),
+            ]
+        );
+    }
+}
@@ -1,6 +1,23 @@
 pub mod accounting_api;
 pub mod local_storage;
+// the FromForm derive of rocket 0.5.0-rc.2 emits `allow(private_in_public)`
+#[allow(renamed_and_removed_lints)]
 pub mod routes;
 pub mod types;
 pub mod auth;
-pub mod file_system;
\ No newline at end of file
+pub mod file_system;
+pub mod scheduler;
+pub mod pdf;
+pub mod export;
+// the FromForm derive of rocket 0.5.0-rc.2 emits `allow(private_in_public)`
+#[allow(renamed_and_removed_lints)]
+pub mod import;
+pub mod eta;
+pub mod notifications;
+pub mod events;
+pub mod i18n;
+pub mod catchers;
+pub mod docs;
+pub mod two_factor;
+pub mod passwords;
+pub mod hashing;
@@ -1,30 +1,134 @@
-use std::{io, path::Path};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     accounting_api::{self, AcountingApi},
-    file_system::FileSystemFile,
+    events::{self, Action, Change, Entity},
+    file_system::{FileSystem, FileSystemFile, MemoryFile},
+    i18n::Message,
     local_storage::models::*,
+    notifications::templates::Template,
+    passwords::{self, PasswordPolicy},
+    two_factor,
+};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use rocket::{
+    async_trait,
+    fs::TempFile,
+    futures::{stream::BoxStream, StreamExt, TryStreamExt},
+    serde::json::{self, Value},
+    tokio::sync::RwLock,
 };
-use rocket::{async_trait, fs::TempFile};
 
-use sqlx::{postgres::PgDatabaseError, types::Uuid};
+use sqlx::{types::Uuid, Acquire, PgConnection, Transaction};
 
-use super::models;
+use super::{models, DB};
 
 impl From<sqlx::Error> for accounting_api::Error {
     fn from(error: sqlx::Error) -> Self {
         rocket::error!("[Database] {error:#?}");
         match error {
             sqlx::Error::RowNotFound => accounting_api::Error::ObjectNotFound,
-            sqlx::Error::Database(error) => error
-                .try_downcast_ref::<PgDatabaseError>()
-                .map(|error| accounting_api::Error::Other(error.message().to_owned().into()))
-                .unwrap_or(accounting_api::Error::Other("غير معروف".into())),
+            sqlx::Error::Database(error) => {
+                let known = error.constraint().and_then(constraint);
+                let field = known.map(|(field, _)| field);
+                let message = |fallback: &'static str| -> Message {
+                    known.map(|(_, id)| id).unwrap_or(fallback).into()
+                };
+                match error.code().as_deref() {
+                    Some(UNIQUE_VIOLATION) => accounting_api::Error::Duplicate {
+                        field,
+                        message: message("constraint.unique"),
+                    },
+                    Some(FOREIGN_KEY_VIOLATION) => accounting_api::Error::Constraint {
+                        field,
+                        message: message("constraint.foreignKey"),
+                    },
+                    Some(CHECK_VIOLATION | NOT_NULL_VIOLATION) => {
+                        accounting_api::Error::Constraint {
+                            field,
+                            message: message("constraint.check"),
+                        }
+                    }
+                    _ => accounting_api::Error::Other(error.message().to_owned().into()),
+                }
+            }
             _ => accounting_api::Error::Other("غير معروف".into()),
         }
     }
 }
 
+const NOT_NULL_VIOLATION: &str = "23502";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const UNIQUE_VIOLATION: &str = "23505";
+const CHECK_VIOLATION: &str = "23514";
+
+/// the API field and the message id of a named database constraint
+fn constraint(name: &str) -> Option<(&'static str, &'static str)> {
+    Some(match name {
+        "user_name_must_be_unique" => ("name", "constraint.user_name_must_be_unique"),
+        "user_email_must_be_unique" => ("email", "constraint.user_email_must_be_unique"),
+        "company_must_be_unique" => ("commercialFeature", "constraint.company_must_be_unique"),
+        "company_file_number_must_be_digits" => (
+            "fileNumber",
+            "constraint.company_file_number_must_be_digits",
+        ),
+        "company_register_number_must_be_9_digits" => (
+            "registerNumber",
+            "constraint.company_register_number_must_be_9_digits",
+        ),
+        "company_record_number_must_be_digits" => (
+            "recordNumber",
+            "constraint.company_record_number_must_be_digits",
+        ),
+        "company_username_must_be_unique" => {
+            ("username", "constraint.company_username_must_be_unique")
+        }
+        "company_email_must_be_unique" => ("email", "constraint.company_email_must_be_unique"),
+        "funder_percentage_must_be_between_0_and_100" => (
+            "percentage",
+            "constraint.funder_percentage_must_be_between_0_and_100",
+        ),
+        "profit_distribution_period_must_be_valid" => (
+            "periodEnd",
+            "constraint.profit_distribution_period_must_be_valid",
+        ),
+        "profit_distribution_period_must_be_unique" => (
+            "periodStart",
+            "constraint.profit_distribution_period_must_be_unique",
+        ),
+        "attachment_expense_name_must_be_unique" | "attachment_income_name_must_be_unique" => {
+            ("file", "constraint.attachment_must_have_unique_name")
+        }
+        "schedule_value_must_be_positive" => {
+            ("value", "constraint.schedule_value_must_be_positive")
+        }
+        "schedule_period_must_be_valid" => ("endDate", "constraint.schedule_period_must_be_valid"),
+        "invoice_line_quantity_must_be_positive" => (
+            "quantity",
+            "constraint.invoice_line_quantity_must_be_positive",
+        ),
+        "invoice_line_unit_price_must_not_be_negative" => (
+            "unitPrice",
+            "constraint.invoice_line_unit_price_must_not_be_negative",
+        ),
+        "invoice_payment_value_must_be_positive" => {
+            ("value", "constraint.invoice_payment_value_must_be_positive")
+        }
+        "income_tax_must_not_exceed_value" | "expense_tax_must_not_exceed_value" => {
+            ("taxRate", "constraint.tax_must_not_exceed_value")
+        }
+        "expense_withholding_must_have_supplier" => (
+            "supplierRegistration",
+            "constraint.expense_withholding_must_have_supplier",
+        ),
+        "tenant_name_must_be_unique" => ("name", "constraint.tenant_name_must_be_unique"),
+        _ => return None,
+    })
+}
+
 impl From<io::Error> for accounting_api::Error {
     fn from(error: io::Error) -> Self {
         rocket::error!("[FileSystem] {error:#?}");
@@ -32,6 +136,762 @@ impl From<io::Error> for accounting_api::Error {
     }
 }
 
+/// a sheet row the database rejected, pointing at the column of the constraint
+fn import_error(row: usize, error: sqlx::Error) -> ImportError {
+    let (column, message) = match accounting_api::Error::from(error) {
+        accounting_api::Error::Duplicate { field, message }
+        | accounting_api::Error::Constraint { field, message } => (field, message.to_string()),
+        error => (None, error.to_string()),
+    };
+    ImportError {
+        row,
+        column: column.map(str::to_owned),
+        message,
+    }
+}
+
+/// the rate and the tax included in an imported row value
+fn import_tax(
+    row: usize,
+    value: f64,
+    tax_code: TaxCode,
+    tax_rate: Option<f64>,
+) -> Result<(f64, f64), ImportError> {
+    tax_code.split(value, tax_rate).ok_or_else(|| ImportError {
+        row,
+        column: Some("taxRate".into()),
+        message: accounting_api::Error::InvalidTaxRate.to_string(),
+    })
+}
+
+/// the tax withheld from the `net` amount of a supplier payment, `None` when
+/// the rate is out of range or the supplier has no valid tax registration
+fn withholding(net: f64, rate: Option<f64>, supplier_registration: Option<&str>) -> Option<f64> {
+    let rate = match rate {
+        Some(rate) => rate,
+        None => return Some(0.0),
+    };
+    let registered = supplier_registration
+        .map(|number| number.len() == 9 && number.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false);
+    if rate <= 0.0 || rate >= 100.0 || !registered {
+        return None;
+    }
+    Some((net * rate / 100.0 * 100.0).round() / 100.0)
+}
+
+/// the company of an imported row by its commercial feature, the owner is
+/// only needed when several owners use the same name
+async fn import_company_id(
+    transaction: &mut Transaction<'_, DB>,
+    row: usize,
+    company: &str,
+    owner: Option<&str>,
+) -> Result<Uuid, ImportError> {
+    let companies = sqlx::query!(
+        r#"
+            SELECT
+                id
+            FROM
+                companies
+            WHERE
+                commercial_feature = $1 AND ($2::VARCHAR IS NULL OR owner = $2)
+        "#,
+        company,
+        owner,
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .map_err(|error| import_error(row, error))?;
+
+    let message = match companies.as_slice() {
+        [company] => return Ok(company.id),
+        [] => format!("لم يتم العثور علي الشركة \"{company}\""),
+        _ => format!("يوجد اكثر من شركة باسم \"{company}\"، حدد المالك"),
+    };
+    Err(ImportError {
+        row,
+        column: Some("company".into()),
+        message,
+    })
+}
+
+/// the user named in an imported row, `default` when the cell is empty
+async fn import_user_id(
+    transaction: &mut Transaction<'_, DB>,
+    row: usize,
+    column: &str,
+    name: Option<&str>,
+    default: Uuid,
+) -> Result<Uuid, ImportError> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(default),
+    };
+    sqlx::query!(
+        r#"
+            SELECT
+                id
+            FROM
+                users
+            WHERE
+                name = $1
+        "#,
+        name,
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(|error| import_error(row, error))?
+    .map(|user| user.id)
+    .ok_or_else(|| ImportError {
+        row,
+        column: Some(column.into()),
+        message: format!("لم يتم العثور علي المستخدم \"{name}\""),
+    })
+}
+
+/// publishes a change of a company document on the channel of the table
+/// change triggers, documents live on the file system
+async fn publish_document(
+    conn: &mut PgConnection,
+    company_id: Option<Uuid>,
+    path: &Path,
+    action: Action,
+) -> Result<(), accounting_api::Error> {
+    let change = Change {
+        entity: Entity::Document,
+        action,
+        id: None,
+        company_id,
+        path: Some(path.to_string_lossy().into_owned()),
+        tenant_id: None,
+    };
+    sqlx::query!(
+        r#"
+            SELECT
+                pg_notify(
+                    $1,
+                    (
+                        $2::TEXT::JSONB || jsonb_build_object('tenantId', current_setting('app.tenant_id'))
+                    )::TEXT
+                )
+        "#,
+        events::CHANNEL,
+        json::to_string(&change)
+            .map_err(|error| accounting_api::Error::Other(error.to_string().into()))?,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// replaces the recovery codes of a user, the codes are only kept hashed
+async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<String>, accounting_api::Error> {
+    sqlx::query!(
+        r#"
+            DELETE FROM
+                recovery_codes
+            WHERE
+                user_id = $1
+        "#,
+        user_id,
+    )
+    .execute(&mut *conn)
+    .await?;
+    let codes = two_factor::recovery_codes();
+    let hashes = codes
+        .iter()
+        .map(|code| two_factor::hash_recovery_code(code))
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        r#"
+            INSERT INTO
+                recovery_codes (user_id, code_hash)
+            SELECT
+                $1, code_hash
+            FROM
+                UNNEST($2::VARCHAR[]) AS code_hash
+        "#,
+        user_id,
+        &hashes,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(codes)
+}
+
+/// sets a new password of a user, their sessions are closed
+async fn set_password(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    password: &str,
+) -> Result<(), accounting_api::Error> {
+    sqlx::query!(
+        r#"
+            UPDATE
+                users
+            SET
+                password = $2,
+                must_change_password = FALSE
+            WHERE
+                id = $1
+        "#,
+        user_id,
+        passwords::hash_password(password).await,
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        r#"
+            DELETE FROM
+                sessions
+            WHERE
+                user_id = $1
+        "#,
+        user_id,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// the stored form of a new portal password, `None` when none is given
+async fn portal_password(
+    policy: &PasswordPolicy,
+    password: Option<&str>,
+) -> Result<Option<String>, accounting_api::Error> {
+    match password {
+        Some(password) => {
+            policy.check(password)?;
+            Ok(Some(passwords::hash_password(password).await))
+        }
+        None => Ok(None),
+    }
+}
+
+/// records the uploaded files of the expense or income `id` in `dir` and
+/// commits the transaction of the caller with them, the saved files are
+/// deleted again when a later one or the commit fails
+async fn commit_attachments(
+    mut transaction: Transaction<'_, DB>,
+    fs: &RwLock<FileSystem>,
+    kind: &str,
+    id: Uuid,
+    dir: &Path,
+    files: &mut [TempFile<'_>],
+) -> Result<Vec<models::Attachment>, accounting_api::Error> {
+    let mut attachments = Vec::with_capacity(files.len());
+    let result = async {
+        for file in files.iter_mut() {
+            let name = file
+                .name_with_ext()
+                .and_then(|name| Path::new(name).file_name())
+                .and_then(|name| name.to_str())
+                .map(ToOwned::to_owned)
+                .ok_or(accounting_api::Error::Other(
+                    "حدث خطأ في انشاء المرفق".into(),
+                ))?;
+            let attachment = sqlx::query_as!(
+                models::Attachment,
+                r#"
+                    INSERT INTO
+                        attachments (name, size, expense_id, income_id)
+                    VALUES
+                        ($1, $2, $3, $4)
+                    RETURNING
+                        id,
+                        name,
+                        size,
+                        time,
+                        '/api/' || $5::TEXT || '/' || COALESCE(expense_id, income_id) ||
+                            '/attachments/' || id AS "url!"
+                "#,
+                name,
+                file.len() as i64,
+                (kind == "expenses").then_some(id),
+                (kind == "incomes").then_some(id),
+                kind,
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+            fs.write()
+                .await
+                .save(dir.join(&attachment.name), file)
+                .await?;
+            attachments.push(attachment);
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => Ok(attachments),
+        Err(error) => {
+            for attachment in attachments {
+                let path = dir.join(&attachment.name);
+                if let Err(error) = fs.write().await.delete(&path).await {
+                    rocket::error!("[commit_attachments] could not delete {path:?}: {error}");
+                }
+            }
+            Err(error)
+        }
+    }
+}
+
+/// takes the new expense `id` from the custody of its user, or reserves it
+/// when it waits for a reviewer, `available` is the custody of the user
+/// before it
+async fn book_expense(
+    conn: &mut PgConnection,
+    low_custody_threshold: Option<f64>,
+    id: Uuid,
+    actor_id: Option<Uuid>,
+    available: f64,
+) -> Result<(), accounting_api::Error> {
+    let expense = sqlx::query!(
+        r#"
+            SELECT
+                expenses.user_id AS "user_id!",
+                expenses.value,
+                expenses.description,
+                expenses.status AS "status: ExpenseStatus",
+                users.name AS "user",
+                companies.commercial_feature AS "company"
+            FROM
+                expenses
+            INNER JOIN
+                users
+            ON
+                users.id = expenses.user_id
+            INNER JOIN
+                companies
+            ON
+                companies.id = expenses.company_id
+            WHERE
+                expenses.id = $1
+        "#,
+        id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if expense.status == ExpenseStatus::Pending {
+        sqlx::query!(
+            r#"
+                UPDATE
+                    users
+                SET
+                    reserved = reserved + $2
+                WHERE id = $1
+            "#,
+            expense.user_id,
+            expense.value,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        notify_user(
+            &mut *conn,
+            expense.user_id,
+            &Template::ExpenseSubmitted {
+                description: &expense.description,
+                value: expense.value,
+            },
+        )
+        .await?;
+        notify_staff(
+            &mut *conn,
+            true,
+            Some(expense.user_id),
+            &Template::ExpenseAwaitingReview {
+                user: &expense.user,
+                company: &expense.company,
+                description: &expense.description,
+                value: expense.value,
+            },
+        )
+        .await?;
+    } else {
+        sqlx::query!(
+            r#"
+                UPDATE
+                    users
+                SET
+                    value = value - $2
+                WHERE id = $1
+            "#,
+            expense.user_id,
+            expense.value,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    custody_transactions (kind, value, note, user_id, actor_id, expense_id)
+                VALUES
+                    ('expense', $1, $2, $3, $4, $5)
+            "#,
+            -expense.value,
+            expense.description,
+            expense.user_id,
+            actor_id,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    notify_low_custody(
+        &mut *conn,
+        low_custody_threshold,
+        expense.user_id,
+        available,
+        available - expense.value,
+    )
+    .await?;
+    Ok(())
+}
+
+/// stores a notification in the inbox of a user, rendered in their language
+/// and queued for email when they have an address
+async fn notify_user(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    template: &Template<'_>,
+) -> sqlx::Result<()> {
+    let user = sqlx::query!(
+        r#"
+            SELECT
+                email, language AS "language: Language"
+            FROM
+                users
+            WHERE
+                id = $1
+        "#,
+        user_id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let (title, body) = template.render(user.language);
+    sqlx::query!(
+        r#"
+            INSERT INTO
+                notifications (kind, title, body, user_id, email)
+            VALUES
+                ($1, $2, $3, $4, $5)
+        "#,
+        template.kind() as _,
+        title,
+        body,
+        user_id,
+        user.email,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// notifies the admins, and the reviewers too when `reviewers` is set,
+/// except the user who caused the notification
+async fn notify_staff(
+    conn: &mut PgConnection,
+    reviewers: bool,
+    except: Option<Uuid>,
+    template: &Template<'_>,
+) -> sqlx::Result<()> {
+    let users = sqlx::query!(
+        r#"
+            SELECT
+                id
+            FROM
+                users
+            WHERE
+                (is_admin OR ($1 AND is_reviewer)) AND id IS DISTINCT FROM $2
+        "#,
+        reviewers,
+        except,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for user in users {
+        notify_user(conn, user.id, template).await?;
+    }
+    Ok(())
+}
+
+/// emails a company in both languages, companies cannot sign in so nothing
+/// is sent when they have no address
+async fn notify_company(
+    conn: &mut PgConnection,
+    company_id: Uuid,
+    template: &Template<'_>,
+) -> sqlx::Result<()> {
+    let (title, body) = template.render_bilingual();
+    sqlx::query!(
+        r#"
+            INSERT INTO
+                notifications (kind, title, body, company_id, email)
+            SELECT
+                $1, $2, $3, id, email
+            FROM
+                companies
+            WHERE
+                id = $4 AND email IS NOT NULL
+        "#,
+        template.kind() as _,
+        title,
+        body,
+        company_id,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// warns a user and the admins when the available custody of the user falls
+/// below the threshold, only once per crossing
+async fn notify_low_custody(
+    conn: &mut PgConnection,
+    threshold: Option<f64>,
+    user_id: Uuid,
+    before: f64,
+    after: f64,
+) -> sqlx::Result<()> {
+    let threshold = match threshold {
+        Some(threshold) if before >= threshold && after < threshold => threshold,
+        _ => return Ok(()),
+    };
+    rocket::debug!("[notify_low_custody] {user_id} fell below {threshold}");
+    let user = sqlx::query!(
+        r#"
+            SELECT
+                name
+            FROM
+                users
+            WHERE
+                id = $1
+        "#,
+        user_id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let template = Template::LowCustody {
+        user: &user.name,
+        available: after,
+    };
+    notify_user(conn, user_id, &template).await?;
+    notify_staff(conn, false, Some(user_id), &template).await
+}
+
+impl super::LocalStorageAccountingApi {
+    /// generates the rows of the schedule `id` due until `today` in a
+    /// transaction of their own, returns how many were generated
+    async fn run_schedule(&self, id: Uuid, today: NaiveDate) -> Result<u64, accounting_api::Error> {
+        let mut generated = 0;
+        let mut transaction = self.db.begin().await?;
+
+        // another worker may be running the same schedule
+        let schedule = match sqlx::query!(
+            r#"
+                SELECT
+                    kind AS "kind: ScheduleKind",
+                    cadence AS "cadence: Cadence",
+                    value,
+                    description,
+                    start_date,
+                    end_date,
+                    runs,
+                    user_id,
+                    company_id
+                FROM
+                    recurring_schedules
+                WHERE
+                    id = $1 AND active
+                FOR UPDATE SKIP LOCKED
+            "#,
+            id,
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        {
+            Some(schedule) => schedule,
+            None => return Ok(0),
+        };
+
+        let mut runs = schedule.runs;
+        let mut active = true;
+        while let Some(occurrence) = schedule.cadence.occurrence(schedule.start_date, runs) {
+            if occurrence > today
+                || schedule
+                    .end_date
+                    .map(|end| occurrence > end)
+                    .unwrap_or(false)
+            {
+                break;
+            }
+            let time = occurrence
+                .and_hms_opt(0, 0, 0)
+                .map(|time| DateTime::<Utc>::from_utc(time, Utc))
+                .ok_or(accounting_api::Error::InvalidValue)?;
+
+            match schedule.kind {
+                ScheduleKind::Income => {
+                    generated += sqlx::query!(
+                        r#"
+                            INSERT INTO
+                                incomes (
+                                    company_id,
+                                    admin_id,
+                                    value,
+                                    description,
+                                    time,
+                                    schedule_id,
+                                    occurrence
+                                )
+                            VALUES
+                                ($1, $2, $3, $4, $5, $6, $7)
+                            ON CONFLICT
+                                (schedule_id, occurrence)
+                            DO NOTHING
+                        "#,
+                        schedule.company_id,
+                        schedule.user_id,
+                        schedule.value,
+                        schedule.description,
+                        time,
+                        id,
+                        occurrence,
+                    )
+                    .execute(&mut transaction)
+                    .await?
+                    .rows_affected();
+                }
+                ScheduleKind::Expense => {
+                    let user = sqlx::query!(
+                        r#"
+                            SELECT
+                                value - reserved AS "available!"
+                            FROM
+                                users
+                            WHERE
+                                id = $1
+                            FOR UPDATE
+                        "#,
+                        schedule.user_id,
+                    )
+                    .fetch_one(&mut transaction)
+                    .await?;
+
+                    if schedule.value > user.available {
+                        active = false;
+                        notify_user(
+                            &mut transaction,
+                            schedule.user_id,
+                            &Template::ScheduleStopped {
+                                description: &schedule.description,
+                                occurrence,
+                            },
+                        )
+                        .await?;
+                        break;
+                    }
+
+                    // held for a reviewer like the expenses entered by hand
+                    let needs_approval = self
+                        .expense_approval_threshold
+                        .map(|threshold| schedule.value > threshold)
+                        .unwrap_or(false);
+
+                    let expense = sqlx::query!(
+                        r#"
+                            INSERT INTO
+                                expenses (
+                                    user_id,
+                                    company_id,
+                                    value,
+                                    description,
+                                    time,
+                                    schedule_id,
+                                    occurrence,
+                                    status
+                                )
+                            VALUES
+                                ($1, $2, $3, $4, $5, $6, $7, $8)
+                            ON CONFLICT
+                                (schedule_id, occurrence)
+                            DO NOTHING
+                            RETURNING
+                                id
+                        "#,
+                        schedule.user_id,
+                        schedule.company_id,
+                        schedule.value,
+                        schedule.description,
+                        time,
+                        id,
+                        occurrence,
+                        if needs_approval {
+                            ExpenseStatus::Pending
+                        } else {
+                            ExpenseStatus::Approved
+                        } as _,
+                    )
+                    .fetch_optional(&mut transaction)
+                    .await?;
+
+                    if let Some(expense) = expense {
+                        book_expense(
+                            &mut transaction,
+                            self.low_custody_threshold,
+                            expense.id,
+                            None,
+                            user.available,
+                        )
+                        .await?;
+                        generated += 1;
+                    }
+                }
+            }
+            runs += 1;
+        }
+
+        let next_run = schedule
+            .cadence
+            .occurrence(schedule.start_date, runs)
+            .filter(|next| schedule.end_date.map(|end| *next <= end).unwrap_or(true));
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    recurring_schedules
+                SET
+                    runs = $2,
+                    next_run = $3,
+                    active = $4
+                WHERE
+                    id = $1
+            "#,
+            id,
+            runs,
+            next_run,
+            active,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(generated)
+    }
+}
+
 #[async_trait]
 impl AcountingApi for super::LocalStorageAccountingApi {
     type Company = models::Company;
@@ -40,12 +900,29 @@ impl AcountingApi for super::LocalStorageAccountingApi {
     type Income = models::Income;
     type Document = models::Document;
     type Funder = models::Funder;
+    type ProfitDistribution = models::ProfitDistribution;
+    type CustodyTransaction = models::CustodyTransaction;
+    type Notification = models::Notification;
+    type Attachment = models::Attachment;
+    type Schedule = models::Schedule;
+    type Invoice = models::Invoice;
+    type ReceivablesAging = models::ReceivablesAging;
+    type CompanyStatementEntry = models::CompanyStatementEntry;
+    type ImportError = models::ImportError;
+    type EtaDocument = models::EtaDocument;
+    type VatReturn = models::VatReturn;
+    type VatReturnEntry = models::VatReturnEntry;
+    type WithholdingStatement = models::WithholdingStatement;
+    type WithholdingEntry = models::WithholdingEntry;
+    type Deadline = models::Deadline;
+    type PendingEmail = models::PendingEmail;
     type Error = accounting_api::Error;
 
     async fn create_company(
         &self,
         c: &CreateCompany,
     ) -> Result<Self::Company, accounting_api::Error> {
+        let password = portal_password(&self.password_policy, c.password.as_deref()).await?;
         let mut transaction = self.db.begin().await?;
 
         let company = sqlx::query_as!(
@@ -73,7 +950,22 @@ impl AcountingApi for super::LocalStorageAccountingApi {
                 VALUES
                     ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
                 RETURNING
-                    *
+                    id,
+                    owner,
+                    commercial_feature,
+                    is_working,
+                    legal_entity,
+                    file_number,
+                    register_number,
+                    start_date,
+                    stop_date,
+                    general_tax_mission,
+                    value_tax_mission,
+                    activity_nature,
+                    activity_location,
+                    record_number,
+                    username,
+                    email
             "#,
             &c.owner,
             &c.commercial_feature,
@@ -89,7 +981,7 @@ impl AcountingApi for super::LocalStorageAccountingApi {
             &c.activity_location as _,
             &c.record_number as _,
             &c.username as _,
-            &c.password as _,
+            password,
             &c.email as _,
         )
         .fetch_one(&mut transaction)
@@ -105,6 +997,7 @@ impl AcountingApi for super::LocalStorageAccountingApi {
         id: Uuid,
         c: &UpdateCompany,
     ) -> Result<Self::Company, accounting_api::Error> {
+        let password = portal_password(&self.password_policy, c.password.as_deref()).await?;
         let mut transaction = self.db.begin().await?;
 
         let old_company = sqlx::query!(
@@ -141,12 +1034,32 @@ impl AcountingApi for super::LocalStorageAccountingApi {
                     activity_location = $12,
                     record_number = $13,
                     username = $14,
-                    password = $15,
-                    email = $16
+                    password = COALESCE($15, password),
+                    email = $16,
+                    portal_session = CASE
+                        WHEN $15::VARCHAR IS NOT NULL OR username IS DISTINCT FROM $14::VARCHAR
+                            THEN gen_random_uuid()
+                        ELSE portal_session
+                    END
                 WHERE
                     id = $17
                 RETURNING
-                    *
+                    id,
+                    owner,
+                    commercial_feature,
+                    is_working,
+                    legal_entity,
+                    file_number,
+                    register_number,
+                    start_date,
+                    stop_date,
+                    general_tax_mission,
+                    value_tax_mission,
+                    activity_nature,
+                    activity_location,
+                    record_number,
+                    username,
+                    email
             "#,
             &c.owner,
             &c.commercial_feature,
@@ -162,7 +1075,7 @@ impl AcountingApi for super::LocalStorageAccountingApi {
             &c.activity_location as _,
             &c.record_number as _,
             &c.username as _,
-            &c.password as _,
+            password,
             &c.email as _,
             &id as _
         )
@@ -170,7 +1083,7 @@ impl AcountingApi for super::LocalStorageAccountingApi {
         .await?;
 
         let (from, to) = (
-            Path::new("companies").join(&format!(
+            Path::new("companies").join(format!(
                 "{} - {}",
                 &old_company.owner, &old_company.commercial_feature
             )),
@@ -188,8 +1101,65 @@ impl AcountingApi for super::LocalStorageAccountingApi {
         Ok(company)
     }
 
-    async fn search_company(&self, s: &str) -> Result<Vec<Self::Company>, accounting_api::Error> {
-        let companies = sqlx::query_as!(
+    async fn get_company(&self, id: Uuid) -> Result<Self::Company, Self::Error> {
+        let company = sqlx::query_as!(
+            models::Company,
+            r#"
+                SELECT
+                    id,
+                    owner,
+                    commercial_feature,
+                    is_working,
+                    legal_entity,
+                    file_number,
+                    register_number,
+                    start_date,
+                    stop_date,
+                    general_tax_mission,
+                    value_tax_mission,
+                    activity_nature,
+                    activity_location,
+                    record_number,
+                    username,
+                    email
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(company)
+    }
+
+    async fn login_company(&self, c: &LoginCompany) -> Result<Self::Company, Self::Error> {
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    id,
+                    password AS "password!"
+                FROM
+                    companies
+                WHERE
+                    username = $1 AND password IS NOT NULL
+            "#,
+            &c.username,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        let hash = company.as_ref().map(|company| company.password.as_str());
+        if !passwords::verify_password(&c.password, hash).await {
+            return Err(Self::Error::InvalidCredentials);
+        }
+        let company = company.ok_or(Self::Error::InvalidCredentials)?;
+        self.get_company(company.id).await
+    }
+
+    fn stream_companies(&self, s: String) -> BoxStream<'_, Result<Self::Company, Self::Error>> {
+        sqlx::query_as!(
             models::Company,
             r#"
                 SELECT DISTINCT ON (companies.id)
@@ -208,7 +1178,6 @@ impl AcountingApi for super::LocalStorageAccountingApi {
                     activity_location,
                     record_number,
                     username,
-                    password,
                     email
                 FROM 
                     companies
@@ -224,8 +1193,13 @@ impl AcountingApi for super::LocalStorageAccountingApi {
             "#,
             s,
         )
-        .fetch_all(&self.db)
-        .await?;
+        .fetch(&self.db)
+        .map_err(Self::Error::from)
+        .boxed()
+    }
+
+    async fn search_company(&self, s: &str) -> Result<Vec<Self::Company>, accounting_api::Error> {
+        let companies: Vec<_> = self.stream_companies(s.to_owned()).try_collect().await?;
 
         if companies.is_empty() {
             return Err(Self::Error::ObjectNotFound);
@@ -234,64 +1208,476 @@ impl AcountingApi for super::LocalStorageAccountingApi {
         Ok(companies)
     }
 
-    async fn pay_company(&self, _c: &Self::Company, _v: f64) -> Result<Self::Company, Self::Error> {
-        unimplemented!()
-    }
-
-    async fn register_user(&self, u: &RegisterUser) -> Result<Self::User, Self::Error> {
-        let mut transaction = self.db.begin().await?;
-
-        let user = sqlx::query_as!(
-            models::User,
+    async fn get_company_statement(
+        &self,
+        company_id: Uuid,
+    ) -> Result<Vec<Self::CompanyStatementEntry>, Self::Error> {
+        sqlx::query!(
             r#"
-                INSERT INTO
-                    users (name, password, is_admin, value)
-                VALUES
-                    ($1, $2, $3, 0)
-                RETURNING
-                    *
+                SELECT
+                    id
+                FROM
+                    companies
+                WHERE
+                    id = $1
             "#,
-            &u.name,
-            &u.password,
-            &u.is_admin,
+            company_id,
         )
-        .fetch_one(&mut transaction)
+        .fetch_one(&self.db)
         .await?;
 
-        transaction.commit().await?;
-        Ok(user)
-    }
-    async fn update_user(&self, id: Uuid, c: &UpdateUser) -> Result<Self::User, Self::Error> {
+        let entries = sqlx::query_as!(
+            models::CompanyStatementEntry,
+            r#"
+                SELECT
+                    entries.id AS "id!",
+                    entries.description AS "description!",
+                    entries.debit AS "debit!",
+                    entries.credit AS "credit!",
+                    SUM(entries.credit - entries.debit) OVER (
+                        ORDER BY entries.time, entries.id
+                    ) AS "balance!",
+                    entries.time AS "time!",
+                    users.name AS "actor?"
+                FROM
+                    (
+                        SELECT
+                            id, description, 0::DOUBLE PRECISION AS debit, value AS credit, time, admin_id AS actor_id
+                        FROM
+                            incomes
+                        WHERE
+                            company_id = $1
+                        UNION ALL
+                        SELECT
+                            id, description, value AS debit, 0::DOUBLE PRECISION AS credit, time, user_id AS actor_id
+                        FROM
+                            expenses
+                        WHERE
+                            company_id = $1 AND status = 'approved'
+                    ) AS entries
+                LEFT JOIN
+                    users
+                ON
+                    entries.actor_id = users.id
+                ORDER BY
+                    entries.time, entries.id
+            "#,
+            company_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(entries)
+    }
+
+    async fn get_vat_return(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+    ) -> Result<Self::VatReturn, Self::Error> {
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    commercial_feature, register_number, value_tax_mission
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            company_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let lines = sqlx::query_as!(
+            models::VatReturnLine,
+            r#"
+                SELECT
+                    entries.tax_code AS "tax_code!: _",
+                    entries.tax_rate AS "tax_rate!",
+                    SUM(entries.sales_net) AS "sales_net!",
+                    SUM(entries.sales_tax) AS "sales_tax!",
+                    SUM(entries.purchases_net) AS "purchases_net!",
+                    SUM(entries.purchases_tax) AS "purchases_tax!"
+                FROM
+                    (
+                        SELECT
+                            tax_code,
+                            tax_rate,
+                            net AS sales_net,
+                            tax AS sales_tax,
+                            0::DOUBLE PRECISION AS purchases_net,
+                            0::DOUBLE PRECISION AS purchases_tax
+                        FROM
+                            incomes
+                        WHERE
+                            company_id = $1 AND
+                            time >= $2::DATE AND time < $2::DATE + INTERVAL '1 month'
+                        UNION ALL
+                        SELECT
+                            tax_code,
+                            tax_rate,
+                            0::DOUBLE PRECISION AS sales_net,
+                            0::DOUBLE PRECISION AS sales_tax,
+                            net AS purchases_net,
+                            tax AS purchases_tax
+                        FROM
+                            expenses
+                        WHERE
+                            company_id = $1 AND status = 'approved' AND
+                            time >= $2::DATE AND time < $2::DATE + INTERVAL '1 month'
+                    ) AS entries
+                GROUP BY
+                    entries.tax_code, entries.tax_rate
+                ORDER BY
+                    entries.tax_code, entries.tax_rate
+            "#,
+            company_id,
+            period,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let total = |code: TaxCode, tax: fn(&models::VatReturnLine) -> f64| {
+            let total = lines
+                .iter()
+                .filter(|line| line.tax_code == code)
+                .fold(0.0, |total, line| total + tax(line));
+            (total * 100.0).round() / 100.0
+        };
+        let output_vat = total(TaxCode::Standard, |line| line.sales_tax);
+        let input_vat = total(TaxCode::Standard, |line| line.purchases_tax);
+        let output_table_tax = total(TaxCode::Table, |line| line.sales_tax);
+        let input_table_tax = total(TaxCode::Table, |line| line.purchases_tax);
+        let net_payable =
+            ((output_vat + output_table_tax - input_vat - input_table_tax) * 100.0).round() / 100.0;
+
+        Ok(models::VatReturn {
+            company_id,
+            company: company.commercial_feature,
+            register_number: company.register_number,
+            value_tax_mission: company.value_tax_mission,
+            period,
+            lines,
+            output_vat,
+            input_vat,
+            output_table_tax,
+            input_table_tax,
+            net_payable,
+        })
+    }
+
+    fn stream_vat_entries(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+        side: VatSide,
+    ) -> BoxStream<'_, Result<Self::VatReturnEntry, Self::Error>> {
+        match side {
+            VatSide::Sales => sqlx::query_as!(
+                models::VatReturnEntry,
+                r#"
+                    SELECT
+                        id,
+                        tax_code AS "tax_code: _",
+                        tax_rate,
+                        description,
+                        time,
+                        net AS "net!",
+                        tax,
+                        value
+                    FROM
+                        incomes
+                    WHERE
+                        company_id = $1 AND
+                        time >= $2::DATE AND time < $2::DATE + INTERVAL '1 month'
+                    ORDER BY
+                        time, id
+                "#,
+                company_id,
+                period,
+            )
+            .fetch(&self.db)
+            .map_err(Self::Error::from)
+            .boxed(),
+            VatSide::Purchases => sqlx::query_as!(
+                models::VatReturnEntry,
+                r#"
+                    SELECT
+                        id,
+                        tax_code AS "tax_code: _",
+                        tax_rate,
+                        description,
+                        time,
+                        net AS "net!",
+                        tax,
+                        value
+                    FROM
+                        expenses
+                    WHERE
+                        company_id = $1 AND status = 'approved' AND
+                        time >= $2::DATE AND time < $2::DATE + INTERVAL '1 month'
+                    ORDER BY
+                        time, id
+                "#,
+                company_id,
+                period,
+            )
+            .fetch(&self.db)
+            .map_err(Self::Error::from)
+            .boxed(),
+        }
+    }
+
+    async fn import_companies(
+        &self,
+        rows: &[(usize, CreateCompany)],
+        commit: bool,
+    ) -> Result<Vec<Self::ImportError>, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        let mut errors = vec![];
+
+        for (row, c) in rows {
+            // a failed row only rolls back its savepoint so the rest are
+            // still checked against the database
+            let mut savepoint = (&mut transaction).begin().await?;
+            let result = sqlx::query!(
+                r#"
+                    INSERT INTO
+                        companies (
+                            owner,
+                            commercial_feature,
+                            is_working,
+                            legal_entity,
+                            file_number,
+                            register_number,
+                            start_date,
+                            stop_date,
+                            general_tax_mission,
+                            value_tax_mission,
+                            activity_nature,
+                            activity_location,
+                            record_number,
+                            email
+                        )
+                    VALUES
+                        ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                "#,
+                &c.owner,
+                &c.commercial_feature,
+                &c.is_working,
+                &c.legal_entity as _,
+                &c.file_number as _,
+                &c.register_number as _,
+                &c.start_date as _,
+                &c.stop_date as _,
+                &c.general_tax_mission as _,
+                &c.value_tax_mission as _,
+                &c.activity_nature as _,
+                &c.activity_location as _,
+                &c.record_number as _,
+                &c.email as _,
+            )
+            .execute(&mut savepoint)
+            .await;
+
+            match result {
+                Ok(_) => savepoint.commit().await?,
+                Err(error) => {
+                    savepoint.rollback().await?;
+                    errors.push(import_error(*row, error));
+                }
+            }
+        }
+
+        if commit && errors.is_empty() {
+            transaction.commit().await?;
+        } else {
+            transaction.rollback().await?;
+        }
+        Ok(errors)
+    }
+
+    async fn get_withholding_statement(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+    ) -> Result<Self::WithholdingStatement, Self::Error> {
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    commercial_feature, register_number, general_tax_mission
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            company_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let entries: Vec<_> = self
+            .stream_withholding_entries(company_id, period)
+            .try_collect()
+            .await?;
+        let total_net = entries.iter().fold(0.0, |total, entry| total + entry.net);
+        let total_withholding = entries
+            .iter()
+            .fold(0.0, |total, entry| total + entry.withholding);
+
+        Ok(models::WithholdingStatement {
+            company_id,
+            company: company.commercial_feature,
+            register_number: company.register_number,
+            general_tax_mission: company.general_tax_mission,
+            period,
+            entries,
+            total_net: (total_net * 100.0).round() / 100.0,
+            total_withholding: (total_withholding * 100.0).round() / 100.0,
+        })
+    }
+
+    fn stream_withholding_entries(
+        &self,
+        company_id: Uuid,
+        period: NaiveDate,
+    ) -> BoxStream<'_, Result<Self::WithholdingEntry, Self::Error>> {
+        sqlx::query_as!(
+            models::WithholdingEntry,
+            r#"
+                SELECT
+                    id,
+                    supplier,
+                    supplier_registration AS "supplier_registration!",
+                    description,
+                    time,
+                    net AS "net!",
+                    withholding_rate AS "withholding_rate!",
+                    withholding
+                FROM
+                    expenses
+                WHERE
+                    company_id = $1 AND status = 'approved' AND withholding_rate IS NOT NULL AND
+                    time >= $2::DATE AND time < $2::DATE + INTERVAL '3 months'
+                ORDER BY
+                    time, id
+            "#,
+            company_id,
+            period,
+        )
+        .fetch(&self.db)
+        .map_err(Self::Error::from)
+        .boxed()
+    }
+
+    #[allow(clippy::diverging_sub_expression)]
+    async fn pay_company(&self, _c: &Self::Company, _v: f64) -> Result<Self::Company, Self::Error> {
+        unimplemented!()
+    }
+
+    async fn register_user(&self, u: &RegisterUser) -> Result<Self::User, Self::Error> {
+        self.password_policy.check(&u.password)?;
         let mut transaction = self.db.begin().await?;
+
         let user = sqlx::query_as!(
             models::User,
+            r#"
+                INSERT INTO
+                    users (name, password, is_admin, is_reviewer, value, email, language)
+                VALUES
+                    ($1, $2, $3, $4, 0, $5, $6)
+                RETURNING
+                    id,
+                    name,
+                    is_admin,
+                    value,
+                    is_reviewer,
+                    reserved,
+                    email,
+                    language AS "language: _",
+                    must_change_password
+            "#,
+            &u.name,
+            passwords::hash_password(&u.password).await,
+            &u.is_admin,
+            &u.is_reviewer,
+            u.email,
+            u.language as _,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(user)
+    }
+    async fn update_user(&self, id: Uuid, c: &UpdateUser) -> Result<Self::User, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        let current = sqlx::query!(
+            r#"
+                SELECT
+                    password
+                FROM
+                    users
+                WHERE
+                    id = $1
+                FOR UPDATE
+            "#,
+            id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        // the current password may be sent again, the policy is for the new ones
+        let password = match &c.password {
+            Some(password)
+                if !passwords::verify_password(password, Some(&current.password)).await =>
+            {
+                self.password_policy.check(password)?;
+                Some(password)
+            }
+            _ => None,
+        };
+        sqlx::query!(
             r#"
                 UPDATE
                     users
                 SET
                     name = $2,
-                    password = $3
+                    is_reviewer = $3,
+                    email = $4,
+                    language = $5
                 WHERE
                     id = $1
-                RETURNING
-                    *
             "#,
             &id as _,
             &c.name,
-            &c.password,
+            &c.is_reviewer,
+            c.email,
+            c.language as _,
         )
-        .fetch_one(&mut transaction)
+        .execute(&mut transaction)
         .await?;
+        if let Some(password) = password {
+            set_password(&mut transaction, id, password).await?;
+        }
 
         transaction.commit().await?;
-        Ok(user)
+        self.get_user(id).await
     }
     async fn get_users(&self) -> Result<Vec<Self::User>, Self::Error> {
         let users = sqlx::query_as!(
             models::User,
             r#"
                 SELECT
-                    *
+                    id,
+                    name,
+                    is_admin,
+                    value,
+                    is_reviewer,
+                    reserved,
+                    email,
+                    language AS "language: _",
+                    must_change_password
                 FROM
                     users
             "#,
@@ -301,7 +1687,16 @@ impl AcountingApi for super::LocalStorageAccountingApi {
 
         Ok(users)
     }
-    async fn pay_user(&self, id: Uuid, v: f64) -> Result<Self::User, Self::Error> {
+    async fn pay_user(
+        &self,
+        actor_id: Uuid,
+        id: Uuid,
+        t: &CreateCustodyTransaction,
+    ) -> Result<Self::User, Self::Error> {
+        if t.value <= 0.0 {
+            return Err(Self::Error::InvalidValue);
+        }
+
         let mut transaction = self.db.begin().await?;
 
         let user = sqlx::query_as!(
@@ -310,481 +1705,3724 @@ impl AcountingApi for super::LocalStorageAccountingApi {
                 UPDATE
                     users
                 SET
-                    value = $2
+                    value = value + $2
                 WHERE
                     id = $1
                 RETURNING
-                    *
+                    id,
+                    name,
+                    is_admin,
+                    value,
+                    is_reviewer,
+                    reserved,
+                    email,
+                    language AS "language: _",
+                    must_change_password
             "#,
             id as _,
-            v,
+            t.value,
         )
         .fetch_one(&mut transaction)
         .await?;
 
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    custody_transactions (kind, value, note, user_id, actor_id)
+                VALUES
+                    ('top_up', $1, $2, $3, $4)
+            "#,
+            t.value,
+            t.note,
+            id,
+            actor_id,
+        )
+        .execute(&mut transaction)
+        .await?;
+
         transaction.commit().await?;
         Ok(user)
     }
 
-    async fn login_user(&self, u: &LoginUser) -> Result<Self::User, Self::Error> {
-        let user = sqlx::query_as!(
-            models::User,
+    async fn adjust_user(
+        &self,
+        actor_id: Uuid,
+        id: Uuid,
+        t: &CreateCustodyTransaction,
+    ) -> Result<Self::User, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let balance = sqlx::query!(
             r#"
                 SELECT
-                    *
+                    value, reserved
                 FROM
                     users
                 WHERE
-                    name = $1 AND password = $2
+                    id = $1
+                FOR UPDATE
             "#,
-            &u.name,
-            &u.password,
+            id,
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut transaction)
         .await?;
-        Ok(user)
-    }
 
-    async fn get_user(&self, id: Uuid) -> Result<Self::User, Self::Error> {
+        // the reserved value backs expenses awaiting approval, it can not be
+        // taken back by an adjustment
+        let available = balance.value - balance.reserved;
+        if available + t.value < 0.0 {
+            return Err(Self::Error::NotEnoughUserValue(-t.value, available));
+        }
+
         let user = sqlx::query_as!(
             models::User,
             r#"
-                SELECT
-                    *
-                FROM
+                UPDATE
                     users
+                SET
+                    value = value + $2
                 WHERE
                     id = $1
+                RETURNING
+                    id,
+                    name,
+                    is_admin,
+                    value,
+                    is_reviewer,
+                    reserved,
+                    email,
+                    language AS "language: _",
+                    must_change_password
             "#,
-            id,
+            id as _,
+            t.value,
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut transaction)
         .await?;
-        Ok(user)
-    }
 
-    async fn delete_company(&self, id: Uuid) -> Result<(), Self::Error> {
         sqlx::query!(
             r#"
-                DELETE FROM
-                    companies
-                WHERE
-                    id = $1
+                INSERT INTO
+                    custody_transactions (kind, value, note, user_id, actor_id)
+                VALUES
+                    ('adjustment', $1, $2, $3, $4)
             "#,
+            t.value,
+            t.note,
             id,
+            actor_id,
         )
-        .execute(&self.db)
+        .execute(&mut transaction)
         .await?;
-        Ok(())
+
+        let available = user.value - user.reserved;
+        notify_low_custody(
+            &mut transaction,
+            self.low_custody_threshold,
+            id,
+            available - t.value,
+            available,
+        )
+        .await?;
+
+        transaction.commit().await?;
+        Ok(user)
     }
 
-    async fn delete_user(&self, id: Uuid) -> Result<(), Self::Error> {
+    async fn get_user_statement(
+        &self,
+        id: Uuid,
+    ) -> Result<Vec<Self::CustodyTransaction>, Self::Error> {
         sqlx::query!(
             r#"
-                DELETE FROM
+                SELECT
+                    id
+                FROM
                     users
                 WHERE
                     id = $1
             "#,
             id,
         )
-        .execute(&self.db)
+        .fetch_one(&self.db)
         .await?;
-        Ok(())
-    }
 
-    async fn get_expenses(
-        &self,
-        user_id: Option<Uuid>,
-        company_id: Option<Uuid>,
-    ) -> Result<Vec<Self::Expense>, Self::Error> {
-        let expenses = sqlx::query_as!(
-            models::Expense,
+        let transactions = sqlx::query_as!(
+            models::CustodyTransaction,
             r#"
                 SELECT
-                    expenses.id,
-                    expenses.value,
-                    description,
+                    custody_transactions.id,
+                    kind AS "kind: _",
+                    custody_transactions.value,
+                    SUM(custody_transactions.value) OVER (
+                        ORDER BY time, custody_transactions.id
+                    ) AS "balance!",
+                    note,
                     time,
-                    users.name AS "user!: _",
-                    companies.commercial_feature AS "company!: _"
+                    users.name AS "actor?",
+                    expense_id
                 FROM
-                    expenses
-                LEFT JOIN
-                    companies
-                ON
-                    expenses.company_id = companies.id
+                    custody_transactions
                 LEFT JOIN
                     users
                 ON
-                    expenses.user_id = users.id
+                    custody_transactions.actor_id = users.id
                 WHERE
-                    (user_id = $1 OR $1 IS NULL) AND (company_id = $2 OR $2 IS NULL)
+                    user_id = $1
+                ORDER BY
+                    time, custody_transactions.id
             "#,
-            user_id,
-            company_id,
+            id,
         )
         .fetch_all(&self.db)
         .await?;
 
-        Ok(expenses)
+        Ok(transactions)
     }
 
-    async fn create_expense(
-        &self,
-        user_id: Uuid,
-        company_id: Uuid,
-        expense: &CreateExpense,
-    ) -> Result<Self::Expense, Self::Error> {
-        if expense.value <= 0.0 {
-            return Err(Self::Error::InvalidValue);
-        }
-
-        let user_value = sqlx::query!(
+    async fn login_user(&self, u: &LoginUser) -> Result<Self::User, Self::Error> {
+        let user = sqlx::query!(
             r#"
                 SELECT
-                    id, value, name
+                    id,
+                    password
                 FROM
                     users
                 WHERE
-                    id = $1
+                    name = $1
             "#,
-            &user_id
+            &u.name,
         )
-        .fetch_one(&self.db)
-        .await?
-        .value;
-
-        if expense.value > user_value {
-            return Err(Self::Error::NotEnoughUserValue(expense.value, user_value));
+        .fetch_optional(&self.db)
+        .await?;
+        let hash = user.as_ref().map(|user| user.password.as_str());
+        if !passwords::verify_password(&u.password, hash).await {
+            return Err(Self::Error::InvalidCredentials);
         }
+        let user = user.ok_or(Self::Error::InvalidCredentials)?;
+        self.get_user(user.id).await
+    }
 
-        let mut transaction = self.db.begin().await?;
+    async fn get_login_attempts(
+        &self,
+        outcome: Option<LoginOutcome>,
+    ) -> Result<Vec<LoginAttempt>, Self::Error> {
+        let attempts = sqlx::query_as!(
+            LoginAttempt,
+            r#"
+                SELECT
+                    id,
+                    name,
+                    ip,
+                    outcome AS "outcome: _",
+                    time
+                FROM
+                    login_attempts
+                WHERE
+                    outcome = $1 OR $1 IS NULL
+                ORDER BY
+                    time DESC
+            "#,
+            outcome as _,
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(attempts)
+    }
 
-        sqlx::query!(
+    async fn get_two_factor(&self, user_id: Uuid) -> Result<TwoFactorStatus, Self::Error> {
+        let status = sqlx::query_as!(
+            TwoFactorStatus,
             r#"
-                UPDATE
+                SELECT
+                    users.totp_enabled AS enabled,
+                    users.is_admin AND tenants.admins_require_two_factor AS "required!"
+                FROM
                     users
-                SET
-                    value = $2
-                WHERE id = $1
+                JOIN
+                    tenants
+                ON
+                    users.tenant_id = tenants.id
+                WHERE
+                    users.id = $1
             "#,
             user_id,
-            user_value - expense.value,
         )
-        .execute(&mut transaction)
+        .fetch_one(&self.db)
         .await?;
+        Ok(status)
+    }
 
-        let expense = sqlx::query_as!(
-            models::Expense,
+    async fn setup_two_factor(&self, user_id: Uuid) -> Result<TwoFactorSetup, Self::Error> {
+        let secret = two_factor::secret();
+        let user = sqlx::query!(
             r#"
-                INSERT INTO
-                    expenses (user_id, company_id, value, description)
-                VALUES
-                    ($1, $2, $3, $4)
+                UPDATE
+                    users
+                SET
+                    totp_secret = $2,
+                    totp_last_step = NULL
+                WHERE
+                    id = $1 AND NOT totp_enabled
                 RETURNING
-                    id,
-                    value,
-                    description,
-                    time,
-                    (
-                        SELECT
-                            name
-                        FROM
-                            users
-                        WHERE
-                            id = $1
-                    ) AS "user!: _",
-                    (
-                        SELECT
-                            commercial_feature
-                        FROM
-                            companies
-                        WHERE
-                            id = $2
-                    ) AS "company!: _"
+                    name
             "#,
             user_id,
-            company_id,
-            expense.value,
-            expense.description,
+            &secret,
         )
-        .fetch_one(&mut transaction)
+        .fetch_optional(&self.db)
         .await?;
-
-        transaction.commit().await?;
-        Ok(expense)
+        let user = match user {
+            Some(user) => user,
+            None => {
+                // tells a missing user from an enabled authenticator
+                self.get_two_factor(user_id).await?;
+                return Err(Self::Error::InvalidTwoFactorState);
+            }
+        };
+        Ok(TwoFactorSetup {
+            uri: two_factor::provisioning_uri(&user.name, &secret),
+            secret,
+        })
     }
-    async fn delete_expense(&self, id: Uuid) -> Result<(), Self::Error> {
+
+    async fn enable_two_factor(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<Vec<String>, Self::Error> {
         let mut transaction = self.db.begin().await?;
-        let result = sqlx::query!(
+
+        let user = sqlx::query!(
             r#"
-                DELETE FROM
-                    expenses
+                SELECT
+                    totp_secret,
+                    totp_enabled
+                FROM
+                    users
                 WHERE
                     id = $1
-                RETURNING
-                    user_id, value
+                FOR UPDATE
             "#,
-            id
+            user_id,
         )
         .fetch_one(&mut transaction)
         .await?;
+        let secret = match user.totp_secret {
+            Some(secret) if !user.totp_enabled => secret,
+            _ => return Err(Self::Error::InvalidTwoFactorState),
+        };
+        let step =
+            two_factor::verify(&secret, code, None).ok_or(Self::Error::InvalidTwoFactorCode)?;
 
         sqlx::query!(
             r#"
                 UPDATE
                     users
                 SET
-                    value = value + $2
+                    totp_enabled = TRUE,
+                    totp_last_step = $2
                 WHERE
                     id = $1
             "#,
-            result.user_id,
-            result.value,
+            user_id,
+            step,
         )
         .execute(&mut transaction)
         .await?;
+        let codes = replace_recovery_codes(&mut transaction, user_id).await?;
+
         transaction.commit().await?;
-        Ok(())
+        Ok(codes)
     }
-    async fn get_incomes(
-        &self,
-        admin_id: Option<Uuid>,
-        company_id: Option<Uuid>,
-    ) -> Result<Vec<Self::Income>, Self::Error> {
-        let incomes = sqlx::query_as!(
-            models::Income,
+
+    async fn verify_two_factor(&self, user_id: Uuid, code: &str) -> Result<(), Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        // locked so the same code cannot be used by two requests
+        let user = sqlx::query!(
             r#"
                 SELECT
-                    incomes.id,
-                    incomes.value,
-                    description,
-                    time,
-                    users.name AS "admin!: _",
-                    companies.commercial_feature AS "company!: _"
+                    totp_secret,
+                    totp_enabled,
+                    totp_last_step
                 FROM
-                    incomes
-                LEFT JOIN
-                    companies
-                ON
-                    incomes.company_id = companies.id
-                LEFT JOIN
                     users
-                ON
-                    incomes.admin_id = users.id
                 WHERE
-                    (admin_id = $1 OR $1 IS NULL) AND (company_id = $2 OR $2 IS NULL)
+                    id = $1
+                FOR UPDATE
             "#,
-            admin_id,
-            company_id,
+            user_id,
         )
-        .fetch_all(&self.db)
+        .fetch_one(&mut transaction)
         .await?;
+        let secret = match user.totp_secret {
+            Some(secret) if user.totp_enabled => secret,
+            _ => return Err(Self::Error::InvalidTwoFactorState),
+        };
+
+        if let Some(step) = two_factor::verify(&secret, code, user.totp_last_step) {
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        users
+                    SET
+                        totp_last_step = $2
+                    WHERE
+                        id = $1
+                "#,
+                user_id,
+                step,
+            )
+            .execute(&mut transaction)
+            .await?;
+        } else {
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        recovery_codes
+                    SET
+                        used_at = CURRENT_TIMESTAMP
+                    WHERE
+                        user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                    RETURNING
+                        id
+                "#,
+                user_id,
+                two_factor::hash_recovery_code(code),
+            )
+            .fetch_optional(&mut transaction)
+            .await?
+            .ok_or(Self::Error::InvalidTwoFactorCode)?;
+        }
 
-        Ok(incomes)
+        transaction.commit().await?;
+        Ok(())
     }
 
-    async fn create_income(
+    async fn regenerate_recovery_codes(
         &self,
-        admin_id: Uuid,
-        company_id: Uuid,
-        income: &CreateIncome,
-    ) -> Result<Self::Income, Self::Error> {
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<Vec<String>, Self::Error> {
         let mut transaction = self.db.begin().await?;
 
-        let income = sqlx::query_as!(
-            models::Income,
+        // locked so the same code cannot be used by two requests
+        let user = sqlx::query!(
             r#"
-                INSERT INTO
-                    incomes (company_id, admin_id, value, description)
-                VALUES
-                    ($1, $2, $3, $4)
-                RETURNING
-                    id,
-                    value,
-                    description,
-                    time,
-                    (
-                        SELECT
-                            commercial_feature
-                        FROM
-                            companies
-                        WHERE
-                            id = $1
-                    ) AS "company!: _",
-                    (
-                        SELECT
-                            name
-                        FROM
-                            users
-                        WHERE
-                            id = $2
-                    ) AS "admin!: _"
+                SELECT
+                    totp_secret,
+                    totp_enabled,
+                    totp_last_step
+                FROM
+                    users
+                WHERE
+                    id = $1
+                FOR UPDATE
             "#,
-            company_id,
-            admin_id,
-            income.value,
-            income.description,
+            user_id,
         )
         .fetch_one(&mut transaction)
         .await?;
+        let secret = match user.totp_secret {
+            Some(secret) if user.totp_enabled => secret,
+            _ => return Err(Self::Error::InvalidTwoFactorState),
+        };
+        // a recovery code cannot replace the others
+        let step = two_factor::verify(&secret, code, user.totp_last_step)
+            .ok_or(Self::Error::InvalidTwoFactorCode)?;
+        sqlx::query!(
+            r#"
+                UPDATE
+                    users
+                SET
+                    totp_last_step = $2
+                WHERE
+                    id = $1
+            "#,
+            user_id,
+            step,
+        )
+        .execute(&mut transaction)
+        .await?;
 
+        let codes = replace_recovery_codes(&mut transaction, user_id).await?;
         transaction.commit().await?;
-        Ok(income)
+        Ok(codes)
     }
-    async fn delete_income(&self, id: Uuid) -> Result<(), Self::Error> {
+
+    async fn disable_two_factor(&self, user_id: Uuid) -> Result<(), Self::Error> {
         let mut transaction = self.db.begin().await?;
+
         sqlx::query!(
             r#"
-                DELETE FROM
-                    incomes
+                UPDATE
+                    users
+                SET
+                    totp_secret = NULL,
+                    totp_enabled = FALSE,
+                    totp_last_step = NULL
                 WHERE
                     id = $1
+                RETURNING
+                    id
             "#,
-            id
+            user_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    recovery_codes
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
         )
         .execute(&mut transaction)
         .await?;
+
         transaction.commit().await?;
         Ok(())
     }
 
-    async fn create_document(
-        &self,
-        company_id: Uuid,
-        file: &mut TempFile<'_>,
-    ) -> Result<Self::Document, Self::Error> {
-        rocket::debug!("[create_document] creating {:?}", file.name_with_ext());
-        let company = sqlx::query!(
+    async fn get_two_factor_policy(&self) -> Result<TwoFactorPolicy, Self::Error> {
+        let policy = sqlx::query_as!(
+            TwoFactorPolicy,
             r#"
                 SELECT
-                    owner, commercial_feature
+                    admins_require_two_factor
                 FROM
-                    companies
-                WHERE
-                    id = $1
-
+                    tenants
             "#,
-            company_id,
         )
         .fetch_one(&self.db)
         .await?;
+        Ok(policy)
+    }
 
-        let document = models::Document::new(&company.owner, &company.commercial_feature, &file)
-            .await
-            .ok_or(Self::Error::Other("حدث خطأ في انشاء المستند".into()))?;
-
-        self.fs.write().await.save(&document.path, file).await?;
-
-        Ok(document)
+    async fn update_two_factor_policy(
+        &self,
+        p: &TwoFactorPolicy,
+    ) -> Result<TwoFactorPolicy, Self::Error> {
+        let policy = sqlx::query_as!(
+            TwoFactorPolicy,
+            r#"
+                UPDATE
+                    tenants
+                SET
+                    admins_require_two_factor = $1
+                RETURNING
+                    admins_require_two_factor
+            "#,
+            p.admins_require_two_factor,
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(policy)
     }
 
-    async fn get_documents(&self, company_id: Uuid) -> Result<Vec<Self::Document>, Self::Error> {
-        let company = sqlx::query!(
+    async fn get_user(&self, id: Uuid) -> Result<Self::User, Self::Error> {
+        let user = sqlx::query_as!(
+            models::User,
             r#"
                 SELECT
-                    owner, commercial_feature
+                    id,
+                    name,
+                    is_admin,
+                    value,
+                    is_reviewer,
+                    reserved,
+                    email,
+                    language AS "language: _",
+                    must_change_password
                 FROM
-                    companies
+                    users
                 WHERE
                     id = $1
-
             "#,
-            company_id,
+            id,
         )
         .fetch_one(&self.db)
         .await?;
-
-        let path = Path::new("companies")
-            .join(&format!(
-                "{} - {}",
-                company.owner, company.commercial_feature,
-            ))
-            .join("documents");
-
-        let mut documents = vec![];
-
-        for path in self.fs.read().await.get(path).await {
-            if let Some(document) =
-                models::Document::new(&company.owner, &company.commercial_feature, &path.as_ref())
-                    .await
-            {
-                documents.push(document);
-            }
-        }
-
-        Ok(documents)
+        Ok(user)
     }
 
-    async fn delete_document(&self, path: impl AsRef<Path> + Send) -> Result<(), Self::Error> {
-        rocket::debug!("[delete_document] deleting {:?}", path.as_ref());
-        self.fs.write().await.delete(path).await?;
+    async fn delete_company(&self, id: Uuid) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .execute(&self.db)
+        .await?;
         Ok(())
     }
 
-    async fn create_funder(
-        &self,
-        company_id: Uuid,
-        f: &CreateFunder,
-    ) -> Result<Self::Funder, Self::Error> {
+    async fn delete_user(&self, id: Uuid) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    users
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn change_password(&self, user_id: Uuid, c: &ChangePassword) -> Result<(), Self::Error> {
         let mut transaction = self.db.begin().await?;
+        let user = sqlx::query!(
+            r#"
+                SELECT
+                    password
+                FROM
+                    users
+                WHERE
+                    id = $1
+                FOR UPDATE
+            "#,
+            user_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        if !passwords::verify_password(&c.old_password, Some(&user.password)).await {
+            return Err(Self::Error::WrongPassword);
+        }
+        self.password_policy.check(&c.password)?;
+        set_password(&mut transaction, user_id, &c.password).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
 
-        let funder = sqlx::query_as!(
-            models::Funder,
+    async fn create_password_reset(&self, user_id: Uuid) -> Result<PasswordReset, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        // only the latest token works
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    password_resets
+                WHERE
+                    user_id = $1 AND used_at IS NULL
+            "#,
+            user_id,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let token = passwords::reset_token();
+        let reset = sqlx::query!(
             r#"
                 INSERT INTO
-                    funders (
-                        name, company_id
-                    )
-                VALUES (
-                    $1, $2
-                )
+                    password_resets (user_id, token_hash, expires_at)
+                VALUES
+                    ($1, $2, now() + make_interval(hours => $3))
                 RETURNING
-                    id, name
+                    expires_at
             "#,
-            f.name as _,
-            company_id as _,
+            user_id,
+            passwords::hash_reset_token(&token),
+            passwords::RESET_TOKEN_HOURS as i32,
         )
         .fetch_one(&mut transaction)
         .await?;
         transaction.commit().await?;
-        Ok(funder)
+        Ok(PasswordReset {
+            token,
+            expires_at: reset.expires_at,
+        })
     }
-    async fn get_funders(&self, company_id: Uuid) -> Result<Vec<Self::Funder>, Self::Error> {
-        let funders = sqlx::query_as!(
-            models::Funder,
+
+    async fn reset_password(&self, r: &ResetPassword) -> Result<(), Self::Error> {
+        self.password_policy.check(&r.password)?;
+        let mut transaction = self.db.begin().await?;
+        let reset = sqlx::query!(
+            r#"
+                UPDATE
+                    password_resets
+                SET
+                    used_at = now()
+                WHERE
+                    token_hash = $1 AND used_at IS NULL AND expires_at > now()
+                RETURNING
+                    user_id
+            "#,
+            passwords::hash_reset_token(&r.token),
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or(Self::Error::InvalidResetToken)?;
+        set_password(&mut transaction, reset.user_id, &r.password).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn create_session(
+        &self,
+        user_id: Uuid,
+        s: &CreateSession,
+    ) -> Result<Session, Self::Error> {
+        let session = sqlx::query_as!(
+            Session,
+            r#"
+                INSERT INTO
+                    sessions (user_id, device_name, ip, user_agent)
+                VALUES
+                    ($1, $2, $3, $4)
+                RETURNING
+                    id,
+                    device_name,
+                    ip,
+                    user_agent,
+                    TRUE AS "current!",
+                    time,
+                    last_seen
+            "#,
+            user_id,
+            s.device_name,
+            s.ip,
+            s.user_agent,
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(session)
+    }
+
+    async fn get_sessions(
+        &self,
+        user_id: Uuid,
+        current: Uuid,
+    ) -> Result<Vec<Session>, Self::Error> {
+        let sessions = sqlx::query_as!(
+            Session,
             r#"
                 SELECT
-                    id, name
+                    id,
+                    device_name,
+                    ip,
+                    user_agent,
+                    id = $2 AS "current!",
+                    time,
+                    last_seen
                 FROM
-                    funders
+                    sessions
                 WHERE
-                    company_id = $1
+                    user_id = $1
+                ORDER BY
+                    last_seen DESC
             "#,
-            company_id,
+            user_id,
+            current,
         )
         .fetch_all(&self.db)
         .await?;
-
-        Ok(funders)
+        Ok(sessions)
     }
-    async fn delete_funder(&self, id: Uuid) -> Result<(), Self::Error> {
-        let mut transaction = self.db.begin().await?;
 
+    async fn delete_session(&self, user_id: Uuid, id: Uuid) -> Result<(), Self::Error> {
         sqlx::query!(
             r#"
                 DELETE FROM
-                    funders
+                    sessions
                 WHERE
-                    id = $1
+                    id = $1 AND user_id = $2
+                RETURNING
+                    id
             "#,
             id,
+            user_id,
         )
-        .execute(&mut transaction)
+        .fetch_one(&self.db)
         .await?;
+        Ok(())
+    }
 
-        transaction.commit().await?;
+    async fn delete_sessions(&self, user_id: Uuid) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    sessions
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&self.db)
+        .await?;
         Ok(())
     }
+
+    fn stream_expenses(
+        &self,
+        user_id: Option<Uuid>,
+        company_id: Option<Uuid>,
+        status: Option<ExpenseStatus>,
+    ) -> BoxStream<'_, Result<Self::Expense, Self::Error>> {
+        sqlx::query_as!(
+            models::Expense,
+            r#"
+                SELECT
+                    expenses.id,
+                    expenses.value,
+                    expenses.net AS "net!",
+                    expenses.tax,
+                    expenses.tax_code AS "tax_code: _",
+                    expenses.tax_rate,
+                    expenses.supplier,
+                    expenses.supplier_registration,
+                    expenses.withholding_rate,
+                    expenses.withholding,
+                    description,
+                    time,
+                    users.name AS "user!: _",
+                    companies.commercial_feature AS "company!: _",
+                    status AS "status: _",
+                    reviewers.name AS "reviewer?",
+                    review_comment,
+                    reviewed_at,
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'id', attachments.id,
+                                    'name', attachments.name,
+                                    'size', attachments.size,
+                                    'time', attachments.time,
+                                    'url', '/api/expenses/' || expenses.id || '/attachments/' || attachments.id
+                                ) ORDER BY attachments.time)
+                            FROM
+                                attachments
+                            WHERE
+                                attachments.expense_id = expenses.id
+                        ),
+                        '[]'
+                    ) AS "attachments!: _"
+                FROM
+                    expenses
+                LEFT JOIN
+                    companies
+                ON
+                    expenses.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    expenses.user_id = users.id
+                LEFT JOIN
+                    users AS reviewers
+                ON
+                    expenses.reviewer_id = reviewers.id
+                WHERE
+                    (user_id = $1 OR $1 IS NULL) AND
+                    (company_id = $2 OR $2 IS NULL) AND
+                    (status = $3 OR $3 IS NULL)
+            "#,
+            user_id,
+            company_id,
+            status as _,
+        )
+        .fetch(&self.db)
+        .map_err(Self::Error::from)
+        .boxed()
+    }
+
+    async fn get_expenses(
+        &self,
+        user_id: Option<Uuid>,
+        company_id: Option<Uuid>,
+        status: Option<ExpenseStatus>,
+    ) -> Result<Vec<Self::Expense>, Self::Error> {
+        self.stream_expenses(user_id, company_id, status)
+            .try_collect()
+            .await
+    }
+
+    async fn get_expense(&self, id: Uuid) -> Result<Self::Expense, Self::Error> {
+        let expense = sqlx::query_as!(
+            models::Expense,
+            r#"
+                SELECT
+                    expenses.id,
+                    expenses.value,
+                    expenses.net AS "net!",
+                    expenses.tax,
+                    expenses.tax_code AS "tax_code: _",
+                    expenses.tax_rate,
+                    expenses.supplier,
+                    expenses.supplier_registration,
+                    expenses.withholding_rate,
+                    expenses.withholding,
+                    description,
+                    time,
+                    users.name AS "user!: _",
+                    companies.commercial_feature AS "company!: _",
+                    status AS "status: _",
+                    reviewers.name AS "reviewer?",
+                    review_comment,
+                    reviewed_at,
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'id', attachments.id,
+                                    'name', attachments.name,
+                                    'size', attachments.size,
+                                    'time', attachments.time,
+                                    'url', '/api/expenses/' || expenses.id || '/attachments/' || attachments.id
+                                ) ORDER BY attachments.time)
+                            FROM
+                                attachments
+                            WHERE
+                                attachments.expense_id = expenses.id
+                        ),
+                        '[]'
+                    ) AS "attachments!: _"
+                FROM
+                    expenses
+                LEFT JOIN
+                    companies
+                ON
+                    expenses.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    expenses.user_id = users.id
+                LEFT JOIN
+                    users AS reviewers
+                ON
+                    expenses.reviewer_id = reviewers.id
+                WHERE
+                    expenses.id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(expense)
+    }
+
+    async fn create_expense(
+        &self,
+        user_id: Uuid,
+        company_id: Uuid,
+        expense: &CreateExpense,
+        files: &mut [TempFile<'_>],
+    ) -> Result<Self::Expense, Self::Error> {
+        if expense.value <= 0.0 {
+            return Err(Self::Error::InvalidValue);
+        }
+        let (tax_rate, tax) = expense
+            .tax_code
+            .split(expense.value, expense.tax_rate)
+            .ok_or(Self::Error::InvalidTaxRate)?;
+        let withholding = withholding(
+            expense.value - tax,
+            expense.withholding_rate,
+            expense.supplier_registration.as_deref(),
+        )
+        .ok_or(Self::Error::InvalidWithholding)?;
+
+        let mut transaction = self.db.begin().await?;
+
+        let user = sqlx::query!(
+            r#"
+                SELECT
+                    value, reserved
+                FROM
+                    users
+                WHERE
+                    id = $1
+                FOR UPDATE
+            "#,
+            &user_id
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let available = user.value - user.reserved;
+        if expense.value > available {
+            return Err(Self::Error::NotEnoughUserValue(expense.value, available));
+        }
+
+        let needs_approval = self
+            .expense_approval_threshold
+            .map(|threshold| expense.value > threshold)
+            .unwrap_or(false);
+
+        let id = sqlx::query!(
+            r#"
+                INSERT INTO
+                    expenses (
+                        user_id,
+                        company_id,
+                        value,
+                        description,
+                        status,
+                        tax_code,
+                        tax_rate,
+                        tax,
+                        supplier,
+                        supplier_registration,
+                        withholding_rate,
+                        withholding
+                    )
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                RETURNING
+                    id
+            "#,
+            user_id,
+            company_id,
+            expense.value,
+            expense.description,
+            if needs_approval {
+                ExpenseStatus::Pending
+            } else {
+                ExpenseStatus::Approved
+            } as _,
+            expense.tax_code as _,
+            tax_rate,
+            tax,
+            expense.supplier,
+            expense.supplier_registration,
+            expense.withholding_rate,
+            withholding,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .id;
+
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            company_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        book_expense(
+            &mut transaction,
+            self.low_custody_threshold,
+            id,
+            Some(user_id),
+            available,
+        )
+        .await?;
+
+        let dir =
+            models::Attachment::dir(&company.owner, &company.commercial_feature, "expenses", id);
+        commit_attachments(transaction, &self.fs, "expenses", id, &dir, files).await?;
+        self.get_expense(id).await
+    }
+
+    async fn approve_expense(
+        &self,
+        reviewer_id: Uuid,
+        id: Uuid,
+        review: &ReviewExpense,
+    ) -> Result<Self::Expense, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let expense = sqlx::query!(
+            r#"
+                UPDATE
+                    expenses
+                SET
+                    status = 'approved',
+                    reviewer_id = $2,
+                    review_comment = $3,
+                    reviewed_at = CURRENT_TIMESTAMP
+                WHERE
+                    id = $1 AND status = 'pending'
+                RETURNING
+                    user_id AS "user_id!", value, description
+            "#,
+            id,
+            reviewer_id,
+            review.comment,
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or(Self::Error::ExpenseNotPending)?;
+        if expense.user_id == reviewer_id {
+            return Err(Self::Error::OwnExpenseReview);
+        }
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    users
+                SET
+                    value = value - $2,
+                    reserved = reserved - $2
+                WHERE
+                    id = $1
+            "#,
+            expense.user_id,
+            expense.value,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    custody_transactions (kind, value, note, user_id, actor_id, expense_id)
+                VALUES
+                    ('expense', $1, $2, $3, $4, $5)
+            "#,
+            -expense.value,
+            expense.description,
+            expense.user_id,
+            reviewer_id,
+            id,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        notify_user(
+            &mut transaction,
+            expense.user_id,
+            &Template::ExpenseApproved {
+                description: &expense.description,
+                value: expense.value,
+                comment: review.comment.as_deref(),
+            },
+        )
+        .await?;
+
+        transaction.commit().await?;
+        self.get_expense(id).await
+    }
+
+    async fn reject_expense(
+        &self,
+        reviewer_id: Uuid,
+        id: Uuid,
+        review: &ReviewExpense,
+    ) -> Result<Self::Expense, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let expense = sqlx::query!(
+            r#"
+                UPDATE
+                    expenses
+                SET
+                    status = 'rejected',
+                    reviewer_id = $2,
+                    review_comment = $3,
+                    reviewed_at = CURRENT_TIMESTAMP
+                WHERE
+                    id = $1 AND status = 'pending'
+                RETURNING
+                    user_id AS "user_id!", value, description
+            "#,
+            id,
+            reviewer_id,
+            review.comment,
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or(Self::Error::ExpenseNotPending)?;
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    users
+                SET
+                    reserved = reserved - $2
+                WHERE
+                    id = $1
+            "#,
+            expense.user_id,
+            expense.value,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        notify_user(
+            &mut transaction,
+            expense.user_id,
+            &Template::ExpenseRejected {
+                description: &expense.description,
+                value: expense.value,
+                comment: review.comment.as_deref(),
+            },
+        )
+        .await?;
+
+        transaction.commit().await?;
+        self.get_expense(id).await
+    }
+
+    async fn import_expenses(
+        &self,
+        admin_id: Uuid,
+        rows: &[(usize, ImportExpense)],
+        commit: bool,
+    ) -> Result<Vec<Self::ImportError>, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        let mut errors = vec![];
+
+        for (row, expense) in rows {
+            let mut savepoint = (&mut transaction).begin().await?;
+            let result = async {
+                let user_id = import_user_id(
+                    &mut savepoint,
+                    *row,
+                    "user",
+                    expense.user.as_deref(),
+                    admin_id,
+                )
+                .await?;
+                let company_id = import_company_id(
+                    &mut savepoint,
+                    *row,
+                    &expense.company,
+                    expense.owner.as_deref(),
+                )
+                .await?;
+                let (tax_rate, tax) =
+                    import_tax(*row, expense.value, expense.tax_code, expense.tax_rate)?;
+                let withholding = withholding(
+                    expense.value - tax,
+                    expense.withholding_rate,
+                    expense.supplier_registration.as_deref(),
+                )
+                .ok_or_else(|| ImportError {
+                    row: *row,
+                    column: Some("withholdingRate".into()),
+                    message: accounting_api::Error::InvalidWithholding.to_string(),
+                })?;
+                let user = sqlx::query!(
+                    r#"
+                        SELECT
+                            value, reserved
+                        FROM
+                            users
+                        WHERE
+                            id = $1
+                        FOR UPDATE
+                    "#,
+                    user_id,
+                )
+                .fetch_one(&mut savepoint)
+                .await
+                .map_err(|error| import_error(*row, error))?;
+                let available = user.value - user.reserved;
+                if expense.value > available {
+                    return Err(ImportError {
+                        row: *row,
+                        column: Some("value".into()),
+                        message: accounting_api::Error::NotEnoughUserValue(
+                            expense.value,
+                            available,
+                        )
+                        .to_string(),
+                    });
+                }
+                let needs_approval = self
+                    .expense_approval_threshold
+                    .map(|threshold| expense.value > threshold)
+                    .unwrap_or(false);
+
+                let id = sqlx::query!(
+                    r#"
+                        INSERT INTO
+                            expenses (
+                                user_id,
+                                company_id,
+                                value,
+                                description,
+                                time,
+                                status,
+                                tax_code,
+                                tax_rate,
+                                tax,
+                                supplier,
+                                supplier_registration,
+                                withholding_rate,
+                                withholding
+                            )
+                        VALUES
+                            (
+                                $1, $2, $3, $4, COALESCE($5, CURRENT_TIMESTAMP), $6,
+                                $7, $8, $9, $10, $11, $12, $13
+                            )
+                        RETURNING
+                            id
+                    "#,
+                    user_id,
+                    company_id,
+                    expense.value,
+                    expense.description,
+                    expense.time,
+                    if needs_approval {
+                        ExpenseStatus::Pending
+                    } else {
+                        ExpenseStatus::Approved
+                    } as _,
+                    expense.tax_code as _,
+                    tax_rate,
+                    tax,
+                    expense.supplier,
+                    expense.supplier_registration,
+                    expense.withholding_rate,
+                    withholding,
+                )
+                .fetch_one(&mut savepoint)
+                .await
+                .map_err(|error| import_error(*row, error))?
+                .id;
+
+                book_expense(
+                    &mut savepoint,
+                    self.low_custody_threshold,
+                    id,
+                    Some(admin_id),
+                    available,
+                )
+                .await
+                .map_err(|error| ImportError {
+                    row: *row,
+                    column: None,
+                    message: error.to_string(),
+                })
+            }
+            .await;
+
+            match result {
+                Ok(_) => savepoint.commit().await?,
+                Err(error) => {
+                    savepoint.rollback().await?;
+                    errors.push(error);
+                }
+            }
+        }
+
+        if commit && errors.is_empty() {
+            transaction.commit().await?;
+        } else {
+            transaction.rollback().await?;
+        }
+        Ok(errors)
+    }
+
+    async fn delete_expense(&self, actor_id: Uuid, id: Uuid) -> Result<(), Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM
+                    expenses
+                WHERE
+                    id = $1
+                RETURNING
+                    user_id,
+                    value,
+                    description,
+                    status AS "status: ExpenseStatus",
+                    (
+                        SELECT
+                            owner || ' - ' || commercial_feature
+                        FROM
+                            companies
+                        WHERE
+                            companies.id = company_id
+                    ) AS "company!"
+            "#,
+            id
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        match result.status {
+            ExpenseStatus::Approved => {
+                sqlx::query!(
+                    r#"
+                        INSERT INTO
+                            custody_transactions (kind, value, note, user_id, actor_id)
+                        VALUES
+                            ('refund', $1, $2, $3, $4)
+                    "#,
+                    result.value,
+                    result.description,
+                    result.user_id,
+                    actor_id,
+                )
+                .execute(&mut transaction)
+                .await?;
+
+                sqlx::query!(
+                    r#"
+                        UPDATE
+                            users
+                        SET
+                            value = value + $2
+                        WHERE
+                            id = $1
+                    "#,
+                    result.user_id,
+                    result.value,
+                )
+                .execute(&mut transaction)
+                .await?;
+            }
+            ExpenseStatus::Pending => {
+                sqlx::query!(
+                    r#"
+                        UPDATE
+                            users
+                        SET
+                            reserved = reserved - $2
+                        WHERE
+                            id = $1
+                    "#,
+                    result.user_id,
+                    result.value,
+                )
+                .execute(&mut transaction)
+                .await?;
+            }
+            ExpenseStatus::Rejected => {}
+        }
+
+        transaction.commit().await?;
+
+        self.fs
+            .write()
+            .await
+            .delete_dir(
+                Path::new("companies")
+                    .join(result.company)
+                    .join("expenses")
+                    .join(id.to_string()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_notifications(
+        &self,
+        user_id: Uuid,
+        unread_only: bool,
+    ) -> Result<Vec<Self::Notification>, Self::Error> {
+        let notifications = sqlx::query_as!(
+            models::Notification,
+            r#"
+                SELECT
+                    id, kind AS "kind: _", title, body, time, read_at
+                FROM
+                    notifications
+                WHERE
+                    user_id = $1 AND (NOT $2 OR read_at IS NULL)
+                ORDER BY
+                    time DESC
+            "#,
+            user_id,
+            unread_only,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(notifications)
+    }
+
+    async fn count_unread_notifications(&self, user_id: Uuid) -> Result<i64, Self::Error> {
+        let count = sqlx::query!(
+            r#"
+                SELECT
+                    COUNT(*) AS "count!"
+                FROM
+                    notifications
+                WHERE
+                    user_id = $1 AND read_at IS NULL
+            "#,
+            user_id,
+        )
+        .fetch_one(&self.db)
+        .await?
+        .count;
+
+        Ok(count)
+    }
+
+    async fn mark_notification_read(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        read: bool,
+    ) -> Result<Self::Notification, Self::Error> {
+        let notification = sqlx::query_as!(
+            models::Notification,
+            r#"
+                UPDATE
+                    notifications
+                SET
+                    read_at = CASE WHEN $3 THEN COALESCE(read_at, CURRENT_TIMESTAMP) END
+                WHERE
+                    id = $1 AND user_id = $2
+                RETURNING
+                    id, kind AS "kind: _", title, body, time, read_at
+            "#,
+            id,
+            user_id,
+            read,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(notification)
+    }
+
+    async fn mark_all_notifications_read(&self, user_id: Uuid) -> Result<u64, Self::Error> {
+        let marked = sqlx::query!(
+            r#"
+                UPDATE
+                    notifications
+                SET
+                    read_at = CURRENT_TIMESTAMP
+                WHERE
+                    user_id = $1 AND read_at IS NULL
+            "#,
+            user_id,
+        )
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(marked)
+    }
+
+    async fn get_pending_emails(
+        &self,
+        max_attempts: i32,
+    ) -> Result<Vec<Self::PendingEmail>, Self::Error> {
+        let emails = sqlx::query_as!(
+            models::PendingEmail,
+            r#"
+                SELECT
+                    id,
+                    email AS "email!",
+                    title,
+                    body,
+                    email_attempts AS attempts
+                FROM
+                    notifications
+                WHERE
+                    email IS NOT NULL AND emailed_at IS NULL AND email_attempts < $1
+                ORDER BY
+                    time
+                LIMIT
+                    100
+            "#,
+            max_attempts,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(emails)
+    }
+
+    async fn mark_email_sent(&self, id: Uuid, error: Option<&str>) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                UPDATE
+                    notifications
+                SET
+                    email_attempts = email_attempts + 1,
+                    emailed_at = CASE WHEN $2::VARCHAR IS NULL THEN CURRENT_TIMESTAMP END,
+                    email_error = $2
+                WHERE
+                    id = $1
+            "#,
+            id,
+            error,
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+    fn stream_incomes(
+        &self,
+        admin_id: Option<Uuid>,
+        company_id: Option<Uuid>,
+    ) -> BoxStream<'_, Result<Self::Income, Self::Error>> {
+        sqlx::query_as!(
+            models::Income,
+            r#"
+                SELECT
+                    incomes.id,
+                    incomes.value,
+                    incomes.net AS "net!",
+                    incomes.tax,
+                    incomes.tax_code AS "tax_code: _",
+                    incomes.tax_rate,
+                    description,
+                    time,
+                    users.name AS "admin!: _",
+                    incomes.company_id AS "company_id!",
+                    companies.commercial_feature AS "company!: _",
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'id', attachments.id,
+                                    'name', attachments.name,
+                                    'size', attachments.size,
+                                    'time', attachments.time,
+                                    'url', '/api/incomes/' || incomes.id || '/attachments/' || attachments.id
+                                ) ORDER BY attachments.time)
+                            FROM
+                                attachments
+                            WHERE
+                                attachments.income_id = incomes.id
+                        ),
+                        '[]'
+                    ) AS "attachments!: _"
+                FROM
+                    incomes
+                LEFT JOIN
+                    companies
+                ON
+                    incomes.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    incomes.admin_id = users.id
+                WHERE
+                    (admin_id = $1 OR $1 IS NULL) AND (company_id = $2 OR $2 IS NULL)
+            "#,
+            admin_id,
+            company_id,
+        )
+        .fetch(&self.db)
+        .map_err(Self::Error::from)
+        .boxed()
+    }
+
+    async fn get_incomes(
+        &self,
+        admin_id: Option<Uuid>,
+        company_id: Option<Uuid>,
+    ) -> Result<Vec<Self::Income>, Self::Error> {
+        self.stream_incomes(admin_id, company_id).try_collect().await
+    }
+
+    async fn get_income(&self, id: Uuid) -> Result<Self::Income, Self::Error> {
+        let income = sqlx::query_as!(
+            models::Income,
+            r#"
+                SELECT
+                    incomes.id,
+                    incomes.value,
+                    incomes.net AS "net!",
+                    incomes.tax,
+                    incomes.tax_code AS "tax_code: _",
+                    incomes.tax_rate,
+                    description,
+                    time,
+                    users.name AS "admin!: _",
+                    incomes.company_id AS "company_id!",
+                    companies.commercial_feature AS "company!: _",
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'id', attachments.id,
+                                    'name', attachments.name,
+                                    'size', attachments.size,
+                                    'time', attachments.time,
+                                    'url', '/api/incomes/' || incomes.id || '/attachments/' || attachments.id
+                                ) ORDER BY attachments.time)
+                            FROM
+                                attachments
+                            WHERE
+                                attachments.income_id = incomes.id
+                        ),
+                        '[]'
+                    ) AS "attachments!: _"
+                FROM
+                    incomes
+                LEFT JOIN
+                    companies
+                ON
+                    incomes.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    incomes.admin_id = users.id
+                WHERE
+                    incomes.id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(income)
+    }
+
+    async fn create_income(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        income: &CreateIncome,
+        files: &mut [TempFile<'_>],
+    ) -> Result<Self::Income, Self::Error> {
+        let (tax_rate, tax) = income
+            .tax_code
+            .split(income.value, income.tax_rate)
+            .ok_or(Self::Error::InvalidTaxRate)?;
+        let mut transaction = self.db.begin().await?;
+
+        let mut income = sqlx::query_as!(
+            models::Income,
+            r#"
+                INSERT INTO
+                    incomes (company_id, admin_id, value, description, tax_code, tax_rate, tax)
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING
+                    id,
+                    value,
+                    net AS "net!",
+                    tax,
+                    tax_code AS "tax_code: _",
+                    tax_rate,
+                    description,
+                    time,
+                    company_id AS "company_id!",
+                    (
+                        SELECT
+                            commercial_feature
+                        FROM
+                            companies
+                        WHERE
+                            id = $1
+                    ) AS "company!: _",
+                    (
+                        SELECT
+                            name
+                        FROM
+                            users
+                        WHERE
+                            id = $2
+                    ) AS "admin!: _",
+                    '[]'::JSON AS "attachments!: _"
+            "#,
+            company_id,
+            admin_id,
+            income.value,
+            income.description,
+            income.tax_code as _,
+            tax_rate,
+            tax,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            company_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let dir = models::Attachment::dir(
+            &company.owner,
+            &company.commercial_feature,
+            "incomes",
+            income.id,
+        );
+        income.attachments.0 =
+            commit_attachments(transaction, &self.fs, "incomes", income.id, &dir, files).await?;
+        Ok(income)
+    }
+    async fn import_incomes(
+        &self,
+        admin_id: Uuid,
+        rows: &[(usize, ImportIncome)],
+        commit: bool,
+    ) -> Result<Vec<Self::ImportError>, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        let mut errors = vec![];
+
+        for (row, income) in rows {
+            let mut savepoint = (&mut transaction).begin().await?;
+            let result = async {
+                let admin_id = import_user_id(
+                    &mut savepoint,
+                    *row,
+                    "admin",
+                    income.admin.as_deref(),
+                    admin_id,
+                )
+                .await?;
+                let company_id = import_company_id(
+                    &mut savepoint,
+                    *row,
+                    &income.company,
+                    income.owner.as_deref(),
+                )
+                .await?;
+                let (tax_rate, tax) =
+                    import_tax(*row, income.value, income.tax_code, income.tax_rate)?;
+                sqlx::query!(
+                    r#"
+                        INSERT INTO
+                            incomes (admin_id, company_id, value, description, time, tax_code, tax_rate, tax)
+                        VALUES
+                            ($1, $2, $3, $4, COALESCE($5, CURRENT_TIMESTAMP), $6, $7, $8)
+                    "#,
+                    admin_id,
+                    company_id,
+                    income.value,
+                    income.description,
+                    income.time,
+                    income.tax_code as _,
+                    tax_rate,
+                    tax,
+                )
+                .execute(&mut savepoint)
+                .await
+                .map_err(|error| import_error(*row, error))
+            }
+            .await;
+
+            match result {
+                Ok(_) => savepoint.commit().await?,
+                Err(error) => {
+                    savepoint.rollback().await?;
+                    errors.push(error);
+                }
+            }
+        }
+
+        if commit && errors.is_empty() {
+            transaction.commit().await?;
+        } else {
+            transaction.rollback().await?;
+        }
+        Ok(errors)
+    }
+
+    async fn delete_income(&self, id: Uuid) -> Result<(), Self::Error> {
+        let mut transaction = self.db.begin().await?;
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM
+                    incomes
+                WHERE
+                    id = $1
+                RETURNING
+                    (
+                        SELECT
+                            owner || ' - ' || commercial_feature
+                        FROM
+                            companies
+                        WHERE
+                            companies.id = company_id
+                    ) AS "company!"
+            "#,
+            id
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        self.fs
+            .write()
+            .await
+            .delete_dir(
+                Path::new("companies")
+                    .join(result.company)
+                    .join("incomes")
+                    .join(id.to_string()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn create_expense_attachments(
+        &self,
+        expense_id: Uuid,
+        files: &mut [TempFile<'_>],
+    ) -> Result<Vec<Self::Attachment>, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                INNER JOIN
+                    expenses
+                ON
+                    expenses.company_id = companies.id
+                WHERE
+                    expenses.id = $1
+            "#,
+            expense_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let dir = models::Attachment::dir(
+            &company.owner,
+            &company.commercial_feature,
+            "expenses",
+            expense_id,
+        );
+        commit_attachments(transaction, &self.fs, "expenses", expense_id, &dir, files).await
+    }
+
+    async fn create_income_attachments(
+        &self,
+        income_id: Uuid,
+        files: &mut [TempFile<'_>],
+    ) -> Result<Vec<Self::Attachment>, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                INNER JOIN
+                    incomes
+                ON
+                    incomes.company_id = companies.id
+                WHERE
+                    incomes.id = $1
+            "#,
+            income_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let dir = models::Attachment::dir(
+            &company.owner,
+            &company.commercial_feature,
+            "incomes",
+            income_id,
+        );
+        commit_attachments(transaction, &self.fs, "incomes", income_id, &dir, files).await
+    }
+
+    async fn get_attachment(&self, owner_id: Uuid, id: Uuid) -> Result<PathBuf, Self::Error> {
+        let attachment = sqlx::query!(
+            r#"
+                SELECT
+                    attachments.name,
+                    expense_id,
+                    income_id,
+                    owner,
+                    commercial_feature
+                FROM
+                    attachments
+                LEFT JOIN
+                    expenses
+                ON
+                    attachments.expense_id = expenses.id
+                LEFT JOIN
+                    incomes
+                ON
+                    attachments.income_id = incomes.id
+                INNER JOIN
+                    companies
+                ON
+                    companies.id = COALESCE(expenses.company_id, incomes.company_id)
+                WHERE
+                    attachments.id = $2 AND (expense_id = $1 OR income_id = $1)
+            "#,
+            owner_id,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let (kind, owner_id) = match (attachment.expense_id, attachment.income_id) {
+            (Some(expense_id), _) => ("expenses", expense_id),
+            (_, Some(income_id)) => ("incomes", income_id),
+            _ => return Err(Self::Error::ObjectNotFound),
+        };
+
+        Ok(self.fs.read().await.root.join(
+            models::Attachment::dir(
+                &attachment.owner,
+                &attachment.commercial_feature,
+                kind,
+                owner_id,
+            )
+            .join(attachment.name),
+        ))
+    }
+
+    async fn delete_attachment(&self, owner_id: Uuid, id: Uuid) -> Result<(), Self::Error> {
+        let path = self.get_attachment(owner_id, id).await?;
+
+        let mut transaction = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    attachments
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        let root = self.fs.read().await.root.clone();
+        self.fs
+            .write()
+            .await
+            .delete(path.strip_prefix(&root).unwrap_or(&path))
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn create_document(
+        &self,
+        company_id: Uuid,
+        file: &mut TempFile<'_>,
+    ) -> Result<Self::Document, Self::Error> {
+        rocket::debug!("[create_document] creating {:?}", file.name_with_ext());
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                WHERE
+                    id = $1
+
+            "#,
+            company_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let document = models::Document::new(&company.owner, &company.commercial_feature, &file)
+            .await
+            .ok_or(Self::Error::Other("حدث خطأ في انشاء المستند".into()))?;
+
+        self.fs.write().await.save(&document.path, file).await?;
+
+        let mut conn = self.db.acquire().await?;
+        publish_document(&mut conn, Some(company_id), &document.path, Action::Created).await?;
+        notify_company(
+            &mut conn,
+            company_id,
+            &Template::NewDocument {
+                company: &company.commercial_feature,
+                name: &document.name,
+            },
+        )
+        .await?;
+
+        Ok(document)
+    }
+
+    async fn save_company_document(
+        &self,
+        company_id: Uuid,
+        file: MemoryFile,
+    ) -> Result<Self::Document, Self::Error> {
+        rocket::debug!("[save_company_document] saving {:?}", file.name_with_ext());
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            company_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let document = models::Document::new(&company.owner, &company.commercial_feature, &file)
+            .await
+            .ok_or(Self::Error::Other("حدث خطأ في انشاء المستند".into()))?;
+
+        self.fs.write().await.save(&document.path, file).await?;
+
+        let mut conn = self.db.acquire().await?;
+        publish_document(&mut conn, Some(company_id), &document.path, Action::Created).await?;
+        notify_company(
+            &mut conn,
+            company_id,
+            &Template::NewDocument {
+                company: &company.commercial_feature,
+                name: &document.name,
+            },
+        )
+        .await?;
+
+        Ok(document)
+    }
+
+    async fn get_documents(&self, company_id: Uuid) -> Result<Vec<Self::Document>, Self::Error> {
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                WHERE
+                    id = $1
+
+            "#,
+            company_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let path = Path::new("companies")
+            .join(format!(
+                "{} - {}",
+                company.owner, company.commercial_feature,
+            ))
+            .join("documents");
+
+        let mut documents = vec![];
+
+        for path in self.fs.read().await.get(path).await {
+            if let Some(document) =
+                models::Document::new(&company.owner, &company.commercial_feature, &path.as_ref())
+                    .await
+            {
+                documents.push(document);
+            }
+        }
+
+        Ok(documents)
+    }
+
+    async fn delete_document(&self, company_id: Uuid, name: &str) -> Result<(), Self::Error> {
+        let document = self
+            .get_documents(company_id)
+            .await?
+            .into_iter()
+            .find(|document| document.name == name)
+            .ok_or(Self::Error::ObjectNotFound)?;
+        rocket::debug!("[delete_document] deleting {:?}", document.path);
+        self.fs.write().await.delete(&document.path).await?;
+        publish_document(
+            &mut *self.db.acquire().await?,
+            Some(company_id),
+            &document.path,
+            Action::Deleted,
+        )
+        .await
+    }
+
+    async fn create_funder(
+        &self,
+        company_id: Uuid,
+        f: &CreateFunder,
+    ) -> Result<Self::Funder, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let funder = sqlx::query_as!(
+            models::Funder,
+            r#"
+                INSERT INTO
+                    funders (
+                        name, percentage, company_id
+                    )
+                VALUES (
+                    $1, $2, $3
+                )
+                RETURNING
+                    id, name, percentage
+            "#,
+            f.name as _,
+            f.percentage,
+            company_id as _,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(funder)
+    }
+    async fn get_funders(&self, company_id: Uuid) -> Result<Vec<Self::Funder>, Self::Error> {
+        let funders = sqlx::query_as!(
+            models::Funder,
+            r#"
+                SELECT
+                    id, name, percentage
+                FROM
+                    funders
+                WHERE
+                    company_id = $1
+            "#,
+            company_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(funders)
+    }
+    async fn update_funder(&self, id: Uuid, f: &UpdateFunder) -> Result<Self::Funder, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let funder = sqlx::query_as!(
+            models::Funder,
+            r#"
+                UPDATE
+                    funders
+                SET
+                    name = $2,
+                    percentage = $3
+                WHERE
+                    id = $1
+                RETURNING
+                    id, name, percentage
+            "#,
+            id,
+            f.name,
+            f.percentage,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(funder)
+    }
+    async fn delete_funder(&self, id: Uuid) -> Result<(), Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    funders
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn create_distribution(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        d: &CreateDistribution,
+    ) -> Result<Self::ProfitDistribution, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    owner, commercial_feature
+                FROM
+                    companies
+                WHERE
+                    id = $1
+            "#,
+            company_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let funders = sqlx::query_as!(
+            models::Funder,
+            r#"
+                SELECT
+                    id, name, percentage
+                FROM
+                    funders
+                WHERE
+                    company_id = $1
+            "#,
+            company_id,
+        )
+        .fetch_all(&mut transaction)
+        .await?;
+
+        let total_percentage: f64 = funders.iter().map(|f| f.percentage).sum();
+        if funders.is_empty() || (total_percentage - 100.0).abs() > 0.01 {
+            return Err(Self::Error::InvalidFundersPercentage(total_percentage));
+        }
+
+        // the tax collected and paid belongs to the tax authority, not to the
+        // funders
+        let total_incomes = sqlx::query!(
+            r#"
+                SELECT
+                    COALESCE(SUM(net), 0) AS "total!"
+                FROM
+                    incomes
+                WHERE
+                    company_id = $1 AND time >= $2 AND time < $3
+            "#,
+            company_id,
+            d.period_start,
+            d.period_end,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .total;
+
+        let total_expenses = sqlx::query!(
+            r#"
+                SELECT
+                    COALESCE(SUM(net), 0) AS "total!"
+                FROM
+                    expenses
+                WHERE
+                    company_id = $1 AND status = 'approved' AND time >= $2 AND time < $3
+            "#,
+            company_id,
+            d.period_start,
+            d.period_end,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .total;
+
+        let net_profit = total_incomes - total_expenses;
+
+        let distribution = sqlx::query!(
+            r#"
+                INSERT INTO
+                    profit_distributions (
+                        period_start,
+                        period_end,
+                        total_incomes,
+                        total_expenses,
+                        net_profit,
+                        admin_id,
+                        company_id
+                    )
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING
+                    id, time
+            "#,
+            d.period_start,
+            d.period_end,
+            total_incomes,
+            total_expenses,
+            net_profit,
+            admin_id,
+            company_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let mut entries = Vec::with_capacity(funders.len());
+        for funder in funders {
+            let statement = models::DistributionEntry::statement_path(d, funder.id);
+            let statement_path = statement.to_string_lossy();
+            let mut entry = sqlx::query_as!(
+                models::DistributionEntry,
+                r#"
+                    INSERT INTO
+                        profit_distribution_entries (
+                            funder_name,
+                            percentage,
+                            amount,
+                            statement,
+                            funder_id,
+                            distribution_id
+                        )
+                    VALUES
+                        ($1, $2, $3, $4, $5, $6)
+                    RETURNING
+                        id, funder_id, funder_name, percentage, amount, statement
+                "#,
+                funder.name,
+                funder.percentage,
+                net_profit * funder.percentage / 100.0,
+                statement_path.as_ref(),
+                funder.id,
+                distribution.id,
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+
+            let statement = Path::new("companies")
+                .join(format!(
+                    "{} - {}",
+                    company.owner, company.commercial_feature
+                ))
+                .join(statement);
+            let file = MemoryFile::new(
+                format!("{}.csv", funder.id),
+                entry.statement(d, total_incomes, total_expenses),
+            );
+            self.fs.write().await.save(&statement, file).await?;
+            entry.statement = statement.to_string_lossy().into_owned();
+
+            entries.push(entry);
+        }
+
+        transaction.commit().await?;
+
+        Ok(models::ProfitDistribution {
+            id: distribution.id,
+            period_start: d.period_start,
+            period_end: d.period_end,
+            total_incomes,
+            total_expenses,
+            net_profit,
+            time: distribution.time,
+            entries,
+        })
+    }
+
+    async fn get_distributions(
+        &self,
+        company_id: Uuid,
+    ) -> Result<Vec<Self::ProfitDistribution>, Self::Error> {
+        let rows = sqlx::query!(
+            r#"
+                SELECT
+                    id,
+                    period_start,
+                    period_end,
+                    total_incomes,
+                    total_expenses,
+                    net_profit,
+                    time
+                FROM
+                    profit_distributions
+                WHERE
+                    company_id = $1
+                ORDER BY
+                    period_start DESC
+            "#,
+            company_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut distributions = Vec::with_capacity(rows.len());
+        for row in rows {
+            let entries = sqlx::query_as!(
+                models::DistributionEntry,
+                r#"
+                    SELECT
+                        profit_distribution_entries.id,
+                        funder_id,
+                        funder_name,
+                        percentage,
+                        amount,
+                        'companies/' || owner || ' - ' || commercial_feature || '/' || statement
+                            AS "statement!"
+                    FROM
+                        profit_distribution_entries
+                    INNER JOIN
+                        profit_distributions
+                    ON
+                        profit_distribution_entries.distribution_id = profit_distributions.id
+                    INNER JOIN
+                        companies
+                    ON
+                        profit_distributions.company_id = companies.id
+                    WHERE
+                        distribution_id = $1
+                "#,
+                row.id,
+            )
+            .fetch_all(&self.db)
+            .await?;
+
+            distributions.push(models::ProfitDistribution {
+                id: row.id,
+                period_start: row.period_start,
+                period_end: row.period_end,
+                total_incomes: row.total_incomes,
+                total_expenses: row.total_expenses,
+                net_profit: row.net_profit,
+                time: row.time,
+                entries,
+            });
+        }
+
+        Ok(distributions)
+    }
+
+    async fn get_distribution_statement(
+        &self,
+        company_id: Uuid,
+        distribution_id: Uuid,
+        funder_id: Uuid,
+    ) -> Result<PathBuf, Self::Error> {
+        let entry = sqlx::query!(
+            r#"
+                SELECT
+                    'companies/' || owner || ' - ' || commercial_feature || '/' || statement
+                        AS "statement!"
+                FROM
+                    profit_distribution_entries
+                INNER JOIN
+                    profit_distributions
+                ON
+                    profit_distribution_entries.distribution_id = profit_distributions.id
+                INNER JOIN
+                    companies
+                ON
+                    profit_distributions.company_id = companies.id
+                WHERE
+                    profit_distributions.company_id = $1 AND
+                    profit_distributions.id = $2 AND
+                    profit_distribution_entries.funder_id = $3
+            "#,
+            company_id,
+            distribution_id,
+            funder_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(self.fs.read().await.root.join(entry.statement))
+    }
+
+    async fn create_schedule(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        schedule: &CreateSchedule,
+    ) -> Result<Self::Schedule, Self::Error> {
+        if schedule.value <= 0.0 {
+            return Err(Self::Error::InvalidValue);
+        }
+
+        let mut transaction = self.db.begin().await?;
+
+        let id = sqlx::query!(
+            r#"
+                INSERT INTO
+                    recurring_schedules (
+                        kind,
+                        cadence,
+                        value,
+                        description,
+                        start_date,
+                        end_date,
+                        next_run,
+                        user_id,
+                        company_id
+                    )
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $5, $7, $8)
+                RETURNING
+                    id
+            "#,
+            schedule.kind as _,
+            schedule.cadence as _,
+            schedule.value,
+            schedule.description,
+            schedule.start_date,
+            schedule.end_date,
+            schedule.user_id.unwrap_or(admin_id),
+            company_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .id;
+
+        transaction.commit().await?;
+        self.get_schedule(id).await
+    }
+
+    async fn get_schedule(&self, id: Uuid) -> Result<Self::Schedule, Self::Error> {
+        let schedule = sqlx::query_as!(
+            models::Schedule,
+            r#"
+                SELECT
+                    recurring_schedules.id,
+                    kind AS "kind: _",
+                    cadence AS "cadence: _",
+                    recurring_schedules.value,
+                    description,
+                    recurring_schedules.start_date,
+                    end_date,
+                    runs,
+                    next_run,
+                    active,
+                    companies.commercial_feature AS "company!: _",
+                    users.name AS "user!: _"
+                FROM
+                    recurring_schedules
+                LEFT JOIN
+                    companies
+                ON
+                    recurring_schedules.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    recurring_schedules.user_id = users.id
+                WHERE
+                    recurring_schedules.id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(schedule)
+    }
+
+    async fn get_schedules(
+        &self,
+        company_id: Option<Uuid>,
+    ) -> Result<Vec<Self::Schedule>, Self::Error> {
+        let schedules = sqlx::query_as!(
+            models::Schedule,
+            r#"
+                SELECT
+                    recurring_schedules.id,
+                    kind AS "kind: _",
+                    cadence AS "cadence: _",
+                    recurring_schedules.value,
+                    description,
+                    recurring_schedules.start_date,
+                    end_date,
+                    runs,
+                    next_run,
+                    active,
+                    companies.commercial_feature AS "company!: _",
+                    users.name AS "user!: _"
+                FROM
+                    recurring_schedules
+                LEFT JOIN
+                    companies
+                ON
+                    recurring_schedules.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    recurring_schedules.user_id = users.id
+                WHERE
+                    recurring_schedules.company_id = $1 OR $1 IS NULL
+                ORDER BY
+                    next_run
+            "#,
+            company_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(schedules)
+    }
+
+    async fn update_schedule(
+        &self,
+        id: Uuid,
+        schedule: &UpdateSchedule,
+    ) -> Result<Self::Schedule, Self::Error> {
+        if schedule.value <= 0.0 {
+            return Err(Self::Error::InvalidValue);
+        }
+
+        let mut transaction = self.db.begin().await?;
+
+        let old = sqlx::query!(
+            r#"
+                SELECT
+                    cadence AS "cadence: Cadence", start_date, runs
+                FROM
+                    recurring_schedules
+                WHERE
+                    id = $1
+                FOR UPDATE
+            "#,
+            id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let next_run = old
+            .cadence
+            .occurrence(old.start_date, old.runs)
+            .filter(|next| schedule.end_date.map(|end| *next <= end).unwrap_or(true));
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    recurring_schedules
+                SET
+                    value = $2,
+                    description = $3,
+                    end_date = $4,
+                    active = $5,
+                    next_run = $6
+                WHERE
+                    id = $1
+            "#,
+            id,
+            schedule.value,
+            schedule.description,
+            schedule.end_date,
+            schedule.active,
+            next_run,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        self.get_schedule(id).await
+    }
+
+    async fn delete_schedule(&self, id: Uuid) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    recurring_schedules
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn run_schedules(&self, today: NaiveDate) -> Result<u64, Self::Error> {
+        let due = sqlx::query!(
+            r#"
+                SELECT
+                    id
+                FROM
+                    recurring_schedules
+                WHERE
+                    active AND next_run <= $1
+            "#,
+            today,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut generated = 0;
+        for due in due {
+            match self.run_schedule(due.id, today).await {
+                Ok(rows) => generated += rows,
+                // a broken schedule must not hold back the others
+                Err(error) => rocket::error!("[run_schedules] schedule {}: {error}", due.id),
+            }
+        }
+
+        Ok(generated)
+    }
+
+    async fn create_invoice(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        invoice: &CreateInvoice,
+    ) -> Result<Self::Invoice, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let id = sqlx::query!(
+            r#"
+                INSERT INTO
+                    invoices (due_date, notes, admin_id, company_id)
+                VALUES
+                    ($1, $2, $3, $4)
+                RETURNING
+                    id
+            "#,
+            invoice.due_date,
+            invoice.notes,
+            admin_id,
+            company_id,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .id;
+
+        for (position, line) in invoice.lines.iter().enumerate() {
+            sqlx::query!(
+                r#"
+                    INSERT INTO
+                        invoice_lines (position, description, quantity, unit_price, invoice_id)
+                    VALUES
+                        ($1, $2, $3, $4, $5)
+                "#,
+                position as i32,
+                line.description,
+                line.quantity,
+                line.unit_price,
+                id,
+            )
+            .execute(&mut transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        self.get_invoice(id).await
+    }
+
+    async fn get_invoice(&self, id: Uuid) -> Result<Self::Invoice, Self::Error> {
+        let invoice = sqlx::query_as!(
+            models::Invoice,
+            r#"
+                SELECT
+                    invoice_summaries.id AS "id!",
+                    invoice_summaries.year || '-' || lpad(number::TEXT, 6, '0') AS number,
+                    status AS "status!: _",
+                    issue_date,
+                    due_date AS "due_date!",
+                    notes,
+                    total AS "total!",
+                    paid AS "paid!",
+                    invoice_summaries.time AS "time!",
+                    company_id AS "company_id!",
+                    companies.commercial_feature AS "company!: _",
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'description', description,
+                                    'quantity', quantity,
+                                    'unitPrice', unit_price
+                                ) ORDER BY position)
+                            FROM
+                                invoice_lines
+                            WHERE
+                                invoice_id = invoice_summaries.id
+                        ),
+                        '[]'
+                    ) AS "lines!: _",
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'id', id,
+                                    'value', value,
+                                    'note', note,
+                                    'time', time,
+                                    'incomeId', income_id
+                                ) ORDER BY time)
+                            FROM
+                                invoice_payments
+                            WHERE
+                                invoice_id = invoice_summaries.id
+                        ),
+                        '[]'
+                    ) AS "payments!: _"
+                FROM
+                    invoice_summaries
+                LEFT JOIN
+                    companies
+                ON
+                    invoice_summaries.company_id = companies.id
+                WHERE
+                    invoice_summaries.id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(invoice)
+    }
+
+    async fn get_invoices(
+        &self,
+        company_id: Option<Uuid>,
+        status: Option<InvoiceStatus>,
+    ) -> Result<Vec<Self::Invoice>, Self::Error> {
+        let invoices = sqlx::query_as!(
+            models::Invoice,
+            r#"
+                SELECT
+                    invoice_summaries.id AS "id!",
+                    invoice_summaries.year || '-' || lpad(number::TEXT, 6, '0') AS number,
+                    status AS "status!: _",
+                    issue_date,
+                    due_date AS "due_date!",
+                    notes,
+                    total AS "total!",
+                    paid AS "paid!",
+                    invoice_summaries.time AS "time!",
+                    company_id AS "company_id!",
+                    companies.commercial_feature AS "company!: _",
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'description', description,
+                                    'quantity', quantity,
+                                    'unitPrice', unit_price
+                                ) ORDER BY position)
+                            FROM
+                                invoice_lines
+                            WHERE
+                                invoice_id = invoice_summaries.id
+                        ),
+                        '[]'
+                    ) AS "lines!: _",
+                    COALESCE(
+                        (
+                            SELECT
+                                json_agg(json_build_object(
+                                    'id', id,
+                                    'value', value,
+                                    'note', note,
+                                    'time', time,
+                                    'incomeId', income_id
+                                ) ORDER BY time)
+                            FROM
+                                invoice_payments
+                            WHERE
+                                invoice_id = invoice_summaries.id
+                        ),
+                        '[]'
+                    ) AS "payments!: _"
+                FROM
+                    invoice_summaries
+                LEFT JOIN
+                    companies
+                ON
+                    invoice_summaries.company_id = companies.id
+                WHERE
+                    (company_id = $1 OR $1 IS NULL) AND (status = $2 OR $2 IS NULL)
+                ORDER BY
+                    invoice_summaries.year, invoice_summaries.number, invoice_summaries.time
+            "#,
+            company_id,
+            status as _,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(invoices)
+    }
+
+    async fn update_invoice(
+        &self,
+        id: Uuid,
+        invoice: &UpdateInvoice,
+    ) -> Result<Self::Invoice, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    invoices
+                SET
+                    due_date = $2,
+                    notes = $3
+                WHERE
+                    id = $1 AND state = 'draft'
+                RETURNING
+                    id
+            "#,
+            id,
+            invoice.due_date,
+            invoice.notes,
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or(Self::Error::InvalidInvoiceState)?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    invoice_lines
+                WHERE
+                    invoice_id = $1
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        for (position, line) in invoice.lines.iter().enumerate() {
+            sqlx::query!(
+                r#"
+                    INSERT INTO
+                        invoice_lines (position, description, quantity, unit_price, invoice_id)
+                    VALUES
+                        ($1, $2, $3, $4, $5)
+                "#,
+                position as i32,
+                line.description,
+                line.quantity,
+                line.unit_price,
+                id,
+            )
+            .execute(&mut transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        self.get_invoice(id).await
+    }
+
+    async fn delete_invoice(&self, id: Uuid) -> Result<(), Self::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    invoices
+                WHERE
+                    id = $1 AND state = 'draft'
+                RETURNING
+                    id
+            "#,
+            id,
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(Self::Error::InvalidInvoiceState)?;
+        Ok(())
+    }
+
+    async fn issue_invoice(
+        &self,
+        id: Uuid,
+        issue_date: NaiveDate,
+    ) -> Result<Self::Invoice, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        let invoice = sqlx::query!(
+            r#"
+                SELECT
+                    state AS "state: String",
+                    (SELECT COUNT(*) FROM invoice_lines WHERE invoice_id = $1) AS "lines!"
+                FROM
+                    invoices
+                WHERE
+                    id = $1
+                FOR UPDATE
+            "#,
+            id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        if invoice.state != "draft" {
+            return Err(Self::Error::InvalidInvoiceState);
+        }
+        if invoice.lines == 0 {
+            return Err(Self::Error::EmptyInvoice);
+        }
+
+        // the counter row stays locked until commit so numbers are never skipped
+        let year = issue_date.year();
+        let number = sqlx::query!(
+            r#"
+                INSERT INTO
+                    invoice_counters (year, last_number)
+                VALUES
+                    ($1, 1)
+                ON CONFLICT
+                    (tenant_id, year)
+                DO UPDATE SET
+                    last_number = invoice_counters.last_number + 1
+                RETURNING
+                    last_number
+            "#,
+            year,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .last_number;
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    invoices
+                SET
+                    state = 'issued',
+                    year = $2,
+                    number = $3,
+                    issue_date = $4
+                WHERE
+                    id = $1
+            "#,
+            id,
+            year,
+            number,
+            issue_date,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        self.get_invoice(id).await
+    }
+
+    async fn void_invoice(&self, id: Uuid) -> Result<Self::Invoice, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE
+                    invoices
+                SET
+                    state = 'void'
+                WHERE
+                    id = $1 AND
+                    state = 'issued' AND
+                    NOT EXISTS (SELECT 1 FROM invoice_payments WHERE invoice_id = $1) AND
+                    NOT EXISTS (
+                        SELECT 1 FROM eta_documents
+                        WHERE invoice_id = $1 AND status IN ('submitted', 'valid')
+                    )
+                RETURNING
+                    id
+            "#,
+            id,
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .ok_or(Self::Error::InvalidInvoiceState)?;
+
+        transaction.commit().await?;
+        self.get_invoice(id).await
+    }
+
+    async fn create_invoice_payment(
+        &self,
+        admin_id: Uuid,
+        id: Uuid,
+        payment: &CreateInvoicePayment,
+    ) -> Result<Self::Invoice, Self::Error> {
+        if payment.value <= 0.0 {
+            return Err(Self::Error::InvalidValue);
+        }
+
+        let mut transaction = self.db.begin().await?;
+
+        sqlx::query!(
+            r#"
+                SELECT
+                    id
+                FROM
+                    invoices
+                WHERE
+                    id = $1
+                FOR UPDATE
+            "#,
+            id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        let invoice = sqlx::query!(
+            r#"
+                SELECT
+                    company_id AS "company_id!",
+                    year || '-' || lpad(number::TEXT, 6, '0') AS "number!",
+                    status AS "status!: InvoiceStatus",
+                    total AS "total!",
+                    paid AS "paid!"
+                FROM
+                    invoice_summaries
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        if !matches!(
+            invoice.status,
+            InvoiceStatus::Issued | InvoiceStatus::PartiallyPaid
+        ) {
+            return Err(Self::Error::InvalidInvoiceState);
+        }
+        let balance = invoice.total - invoice.paid;
+        // compared in cents, the sums of the lines and payments are not exact
+        if (payment.value * 100.0).round() > (balance * 100.0).round() {
+            return Err(Self::Error::PaymentExceedsBalance(payment.value, balance));
+        }
+
+        let income_id = sqlx::query!(
+            r#"
+                INSERT INTO
+                    incomes (company_id, admin_id, value, description)
+                VALUES
+                    ($1, $2, $3, $4)
+                RETURNING
+                    id
+            "#,
+            invoice.company_id,
+            admin_id,
+            payment.value,
+            format!("سداد فاتورة رقم {}", invoice.number),
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .id;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    invoice_payments (value, note, admin_id, invoice_id, income_id)
+                VALUES
+                    ($1, $2, $3, $4, $5)
+            "#,
+            payment.value,
+            payment.note,
+            admin_id,
+            id,
+            income_id,
+        )
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+        self.get_invoice(id).await
+    }
+
+    async fn get_receivables_aging(
+        &self,
+        today: NaiveDate,
+        company_id: Option<Uuid>,
+    ) -> Result<Vec<Self::ReceivablesAging>, Self::Error> {
+        let aging = sqlx::query_as!(
+            models::ReceivablesAging,
+            r#"
+                SELECT
+                    companies.id AS "company_id!",
+                    companies.commercial_feature AS "company!",
+                    COALESCE(SUM(balance) FILTER (WHERE $1 - due_date <= 0), 0) AS "current!",
+                    COALESCE(SUM(balance) FILTER (WHERE $1 - due_date BETWEEN 1 AND 30), 0)
+                        AS "days_1_to_30!",
+                    COALESCE(SUM(balance) FILTER (WHERE $1 - due_date BETWEEN 31 AND 60), 0)
+                        AS "days_31_to_60!",
+                    COALESCE(SUM(balance) FILTER (WHERE $1 - due_date BETWEEN 61 AND 90), 0)
+                        AS "days_61_to_90!",
+                    COALESCE(SUM(balance) FILTER (WHERE $1 - due_date > 90), 0) AS "over_90!",
+                    COALESCE(SUM(balance), 0) AS "total!"
+                FROM
+                    (
+                        SELECT
+                            company_id, due_date, total - paid AS balance
+                        FROM
+                            invoice_summaries
+                        WHERE
+                            status IN ('issued', 'partially_paid')
+                    ) AS outstanding
+                INNER JOIN
+                    companies
+                ON
+                    outstanding.company_id = companies.id
+                WHERE
+                    companies.id = $2 OR $2 IS NULL
+                GROUP BY
+                    companies.id
+                ORDER BY
+                    "total!" DESC
+            "#,
+            today,
+            company_id,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(aging)
+    }
+
+    async fn get_eta_document(&self, invoice_id: Uuid) -> Result<Self::EtaDocument, Self::Error> {
+        let document = sqlx::query_as!(
+            models::EtaDocument,
+            r#"
+                SELECT
+                    invoice_id,
+                    status AS "status: _",
+                    uuid,
+                    long_id,
+                    submission_id,
+                    hash,
+                    document AS "document: _",
+                    errors AS "errors: _",
+                    time,
+                    updated_at
+                FROM
+                    eta_documents
+                WHERE
+                    invoice_id = $1
+            "#,
+            invoice_id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(document)
+    }
+
+    async fn get_submitted_eta_documents(&self) -> Result<Vec<Self::EtaDocument>, Self::Error> {
+        let documents = sqlx::query_as!(
+            models::EtaDocument,
+            r#"
+                SELECT
+                    invoice_id,
+                    status AS "status: _",
+                    uuid,
+                    long_id,
+                    submission_id,
+                    hash,
+                    document AS "document: _",
+                    errors AS "errors: _",
+                    time,
+                    updated_at
+                FROM
+                    eta_documents
+                WHERE
+                    status = 'submitted'
+                ORDER BY
+                    time
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(documents)
+    }
+
+    async fn save_eta_document(
+        &self,
+        invoice_id: Uuid,
+        d: &CreateEtaDocument,
+    ) -> Result<Self::EtaDocument, Self::Error> {
+        let document = sqlx::query_as!(
+            models::EtaDocument,
+            r#"
+                INSERT INTO
+                    eta_documents (
+                        invoice_id,
+                        status,
+                        uuid,
+                        long_id,
+                        submission_id,
+                        hash,
+                        document,
+                        errors
+                    )
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT
+                    (invoice_id)
+                DO UPDATE SET
+                    status = EXCLUDED.status,
+                    uuid = EXCLUDED.uuid,
+                    long_id = EXCLUDED.long_id,
+                    submission_id = EXCLUDED.submission_id,
+                    hash = EXCLUDED.hash,
+                    document = EXCLUDED.document,
+                    errors = EXCLUDED.errors,
+                    time = CURRENT_TIMESTAMP,
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE
+                    eta_documents.status IN ('rejected', 'invalid')
+                RETURNING
+                    invoice_id,
+                    status AS "status: _",
+                    uuid,
+                    long_id,
+                    submission_id,
+                    hash,
+                    document AS "document: _",
+                    errors AS "errors: _",
+                    time,
+                    updated_at
+            "#,
+            invoice_id,
+            d.status as _,
+            d.uuid,
+            d.long_id,
+            d.submission_id,
+            d.hash,
+            d.document,
+            d.errors,
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(Self::Error::EtaAlreadySubmitted)?;
+
+        Ok(document)
+    }
+
+    async fn update_eta_status(
+        &self,
+        invoice_id: Uuid,
+        status: EtaStatus,
+        errors: Option<&Value>,
+    ) -> Result<Self::EtaDocument, Self::Error> {
+        let document = sqlx::query_as!(
+            models::EtaDocument,
+            r#"
+                UPDATE
+                    eta_documents
+                SET
+                    status = $2,
+                    errors = COALESCE($3, errors),
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE
+                    invoice_id = $1
+                RETURNING
+                    invoice_id,
+                    status AS "status: _",
+                    uuid,
+                    long_id,
+                    submission_id,
+                    hash,
+                    document AS "document: _",
+                    errors AS "errors: _",
+                    time,
+                    updated_at
+            "#,
+            invoice_id,
+            status as _,
+            errors,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(document)
+    }
+
+    async fn create_deadline(
+        &self,
+        admin_id: Uuid,
+        company_id: Uuid,
+        d: &CreateDeadline,
+    ) -> Result<Self::Deadline, Self::Error> {
+        let id = sqlx::query!(
+            r#"
+                INSERT INTO
+                    deadlines (title, due_date, note, admin_id, company_id)
+                VALUES
+                    ($1, $2, $3, $4, $5)
+                RETURNING
+                    id
+            "#,
+            d.title,
+            d.due_date,
+            d.note,
+            admin_id,
+            company_id,
+        )
+        .fetch_one(&self.db)
+        .await?
+        .id;
+
+        self.get_deadline(id).await
+    }
+
+    async fn get_deadline(&self, id: Uuid) -> Result<Self::Deadline, Self::Error> {
+        let deadline = sqlx::query_as!(
+            models::Deadline,
+            r#"
+                SELECT
+                    deadline_summaries.id AS "id!",
+                    kind AS "kind!: _",
+                    status AS "status!: _",
+                    title AS "title!",
+                    period,
+                    due_date AS "due_date!",
+                    note,
+                    fulfilled_at,
+                    users.name AS "fulfilled_by?",
+                    deadline_summaries.time AS "time!",
+                    company_id AS "company_id!",
+                    companies.commercial_feature AS "company!"
+                FROM
+                    deadline_summaries
+                JOIN
+                    companies
+                ON
+                    deadline_summaries.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    deadline_summaries.fulfilled_by = users.id
+                WHERE
+                    deadline_summaries.id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(deadline)
+    }
+
+    async fn get_deadlines(
+        &self,
+        company_id: Option<Uuid>,
+        status: Option<DeadlineStatus>,
+    ) -> Result<Vec<Self::Deadline>, Self::Error> {
+        let deadlines = sqlx::query_as!(
+            models::Deadline,
+            r#"
+                SELECT
+                    deadline_summaries.id AS "id!",
+                    kind AS "kind!: _",
+                    status AS "status!: _",
+                    title AS "title!",
+                    period,
+                    due_date AS "due_date!",
+                    note,
+                    fulfilled_at,
+                    users.name AS "fulfilled_by?",
+                    deadline_summaries.time AS "time!",
+                    company_id AS "company_id!",
+                    companies.commercial_feature AS "company!"
+                FROM
+                    deadline_summaries
+                JOIN
+                    companies
+                ON
+                    deadline_summaries.company_id = companies.id
+                LEFT JOIN
+                    users
+                ON
+                    deadline_summaries.fulfilled_by = users.id
+                WHERE
+                    (company_id = $1 OR $1 IS NULL) AND
+                    (status = $2 OR ($2 IS NULL AND status <> 'fulfilled'))
+                ORDER BY
+                    due_date, companies.commercial_feature, title
+            "#,
+            company_id,
+            status as _,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(deadlines)
+    }
+
+    async fn fulfill_deadline(
+        &self,
+        actor_id: Uuid,
+        id: Uuid,
+        d: &FulfillDeadline,
+    ) -> Result<Self::Deadline, Self::Error> {
+        let fulfilled = sqlx::query!(
+            r#"
+                UPDATE
+                    deadlines
+                SET
+                    fulfilled_at = CURRENT_TIMESTAMP,
+                    fulfilled_by = $2,
+                    note = COALESCE($3, note)
+                WHERE
+                    id = $1 AND fulfilled_at IS NULL
+            "#,
+            id,
+            actor_id,
+            d.note,
+        )
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        let deadline = self.get_deadline(id).await?;
+        if fulfilled == 0 {
+            return Err(Self::Error::DeadlineAlreadyFulfilled);
+        }
+        Ok(deadline)
+    }
+
+    async fn delete_deadline(&self, id: Uuid) -> Result<(), Self::Error> {
+        let deleted = sqlx::query!(
+            r#"
+                DELETE FROM
+                    deadlines
+                WHERE
+                    id = $1 AND kind = 'custom'
+            "#,
+            id,
+        )
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        if deleted == 0 {
+            self.get_deadline(id).await?;
+            return Err(Self::Error::DerivedDeadline);
+        }
+        Ok(())
+    }
+
+    async fn sync_deadlines(&self, from: NaiveDate, until: NaiveDate) -> Result<u64, Self::Error> {
+        let companies = sqlx::query_as!(
+            models::Company,
+            r#"
+                SELECT
+                    id,
+                    owner,
+                    commercial_feature,
+                    is_working,
+                    legal_entity,
+                    file_number,
+                    register_number,
+                    start_date,
+                    stop_date,
+                    general_tax_mission,
+                    value_tax_mission,
+                    activity_nature,
+                    activity_location,
+                    record_number,
+                    username,
+                    email
+                FROM
+                    companies
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut transaction = self.db.begin().await?;
+        let mut added = 0;
+        for company in companies {
+            for obligation in company.obligations(from, until) {
+                added += sqlx::query!(
+                    r#"
+                        INSERT INTO
+                            deadlines (kind, title, period, due_date, company_id)
+                        VALUES
+                            ($1, $2, $3, $4, $5)
+                        ON CONFLICT
+                            (company_id, kind, period)
+                        DO NOTHING
+                    "#,
+                    obligation.kind as _,
+                    obligation.title,
+                    obligation.period,
+                    obligation.due_date,
+                    company.id,
+                )
+                .execute(&mut transaction)
+                .await?
+                .rows_affected();
+            }
+        }
+
+        transaction.commit().await?;
+        Ok(added)
+    }
+
+    async fn remind_deadlines(&self, until: NaiveDate) -> Result<u64, Self::Error> {
+        let mut transaction = self.db.begin().await?;
+
+        // overdue deadlines are left out, they were reminded before falling due
+        let deadlines = sqlx::query!(
+            r#"
+                WITH reminded AS (
+                    UPDATE
+                        deadlines
+                    SET
+                        reminded_at = CURRENT_TIMESTAMP
+                    WHERE
+                        fulfilled_at IS NULL
+                        AND reminded_at IS NULL
+                        AND due_date BETWEEN CURRENT_DATE AND $1
+                    RETURNING
+                        title, due_date, company_id
+                )
+                SELECT
+                    reminded.title,
+                    reminded.due_date,
+                    reminded.company_id,
+                    companies.commercial_feature AS company
+                FROM
+                    reminded
+                INNER JOIN
+                    companies ON companies.id = reminded.company_id
+                ORDER BY
+                    reminded.due_date
+            "#,
+            until,
+        )
+        .fetch_all(&mut transaction)
+        .await?;
+
+        for deadline in &deadlines {
+            let template = Template::DeadlineApproaching {
+                company: &deadline.company,
+                title: &deadline.title,
+                due_date: deadline.due_date,
+            };
+            notify_staff(&mut transaction, false, None, &template).await?;
+            notify_company(&mut transaction, deadline.company_id, &template).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(deadlines.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn withholding_is_taken_from_registered_suppliers() {
+        assert_eq!(withholding(1000.0, None, None), Some(0.0));
+        assert_eq!(
+            withholding(1000.0, Some(1.0), Some("123456789")),
+            Some(10.0)
+        );
+        assert_eq!(
+            withholding(333.33, Some(3.0), Some("123456789")),
+            Some(10.0)
+        );
+        for registration in [
+            None,
+            Some("12345678"),
+            Some("12345678X"),
+            Some("1234567890"),
+        ] {
+            assert_eq!(
+                withholding(1000.0, Some(1.0), registration),
+                None,
+                "{registration:?}"
+            );
+        }
+        for rate in [0.0, -1.0, 100.0] {
+            assert_eq!(
+                withholding(1000.0, Some(rate), Some("123456789")),
+                None,
+                "{rate}"
+            );
+        }
+    }
 }
@@ -1,47 +1,134 @@
 pub mod accounting_api_impl;
+// the FromForm derive of rocket 0.5.0-rc.2 emits `allow(private_in_public)`
+#[allow(renamed_and_removed_lints)]
 pub mod models;
-pub use models::*;
+pub mod sessions;
+pub mod tenants;
+pub mod throttle;
 pub use models::*;
 use sqlx::pool::PoolOptions;
 
+use chrono::Duration;
 use rocket::{fairing::AdHoc, tokio::sync::RwLock};
-use sqlx::{Pool, Postgres};
-use std::env;
+use sqlx::{types::Uuid, Pool, Postgres};
+use std::{env, path::Path};
+
+use crate::{file_system::FileSystem, passwords::PasswordPolicy};
 
-use crate::file_system::FileSystem;
+pub use tenants::{Portal, Tenants};
+pub use throttle::Throttle;
 
 pub type DB = Postgres;
 
+/// the data of one tenant, its connections only see the rows of the tenant
+/// and its files live in a directory of their own
 #[derive(Debug)]
 pub struct LocalStorageAccountingApi {
     pub db: Pool<DB>,
     pub fs: RwLock<FileSystem>,
+    pub tenant_id: Uuid,
+    /// expenses above this value wait for a reviewer approval
+    pub expense_approval_threshold: Option<f64>,
+    /// users and admins are notified when a custody falls below this value
+    pub low_custody_threshold: Option<f64>,
+    pub password_policy: PasswordPolicy,
 }
 
 impl LocalStorageAccountingApi {
-    async fn new(db_url: &str, fs_path: &str) -> sqlx::Result<Self> {
+    async fn new(
+        db_url: &str,
+        root: &Path,
+        tenant_id: Uuid,
+        expense_approval_threshold: Option<f64>,
+        low_custody_threshold: Option<f64>,
+        password_policy: PasswordPolicy,
+    ) -> sqlx::Result<Self> {
         Ok(LocalStorageAccountingApi {
+            // connects on the first query, the tenants without requests hold
+            // no connection
             db: PoolOptions::new()
-                .max_connections(100)
-                .connect(db_url)
-                .await?,
-            fs: RwLock::new(FileSystem::new(fs_path).await),
+                .max_connections(TENANT_MAX_CONNECTIONS)
+                .idle_timeout(TENANT_IDLE_TIMEOUT)
+                .after_connect(move |conn, _| {
+                    Box::pin(async move {
+                        sqlx::query("SET ROLE accounting_tenant")
+                            .execute(&mut *conn)
+                            .await?;
+                        sqlx::query("SELECT set_config('app.tenant_id', $1, FALSE)")
+                            .bind(tenant_id.to_string())
+                            .execute(&mut *conn)
+                            .await?;
+                        Ok(())
+                    })
+                })
+                .connect_lazy(db_url)?,
+            fs: RwLock::new(FileSystem::new(root.join(tenant_id.to_string())).await),
+            tenant_id,
+            expense_approval_threshold,
+            low_custody_threshold,
+            password_policy,
         })
     }
 }
 
+/// the connections of a tenant, the offices share the database server
+const TENANT_MAX_CONNECTIONS: u32 = 5;
+/// how long the connection of a tenant is kept unused before it is closed
+const TENANT_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("database stage", |rocket| async {
-        let storage = LocalStorageAccountingApi::new(
+        let tenants = Tenants::new(
             &env::var("DATABASE_URL").expect("`DATABASE_URL` must be set"),
             &env::var("DATA_PATH").expect("`DATA_PATH` must be set"),
+            env::var("EXPENSE_APPROVAL_THRESHOLD").ok().map(|v| {
+                v.parse()
+                    .expect("`EXPENSE_APPROVAL_THRESHOLD` must be a number")
+            }),
+            env::var("LOW_CUSTODY_THRESHOLD")
+                .ok()
+                .map(|v| v.parse().expect("`LOW_CUSTODY_THRESHOLD` must be a number")),
+            Throttle {
+                max_failures: env::var("LOGIN_MAX_FAILURES")
+                    .map(|v| v.parse().expect("`LOGIN_MAX_FAILURES` must be a number"))
+                    .unwrap_or(Throttle::default().max_failures),
+                lockout: env::var("LOGIN_LOCKOUT_SECS")
+                    .map(|v| {
+                        Duration::seconds(
+                            v.parse().expect("`LOGIN_LOCKOUT_SECS` must be a number"),
+                        )
+                    })
+                    .unwrap_or(Throttle::default().lockout),
+            },
+            PasswordPolicy {
+                min_length: env::var("PASSWORD_MIN_LENGTH")
+                    .map(|v| v.parse().expect("`PASSWORD_MIN_LENGTH` must be a number"))
+                    .unwrap_or(PasswordPolicy::default().min_length),
+                check_breached: env::var("PASSWORD_CHECK_BREACHED")
+                    .map(|v| v.parse().expect("`PASSWORD_CHECK_BREACHED` must be a boolean"))
+                    .unwrap_or(PasswordPolicy::default().check_breached),
+            },
         )
         .await
         .expect("database connection");
         sqlx::migrate!()
-            .run(&storage.db)
+            .run(&tenants.db)
             .await
             .expect("migrations run");
-        rocket.manage(storage)
+        if let (Ok(name), Ok(password)) = (
+            env::var("SUPER_ADMIN_NAME"),
+            env::var("SUPER_ADMIN_PASSWORD"),
+        ) {
+            tenants
+                .create_first_super_admin(&name, &password)
+                .await
+                .expect("`SUPER_ADMIN_PASSWORD` must satisfy the password policy");
+        }
+        tenants
+            .hash_passwords()
+            .await
+            .expect("the stored passwords are hashed");
+        tenants.adopt_documents().await;
+        rocket.manage(tenants)
     })
 }
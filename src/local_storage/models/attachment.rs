@@ -0,0 +1,27 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: Uuid,
+    pub name: String,
+    pub size: i64,
+    pub time: DateTime<Utc>,
+    /// download link of the attachment
+    pub url: String,
+}
+
+impl Attachment {
+    /// directory of the attachments of an expense or an income relative to the file system root
+    pub fn dir(owner: &str, commercial_feature: &str, kind: &str, id: Uuid) -> PathBuf {
+        Path::new("companies")
+            .join(format!("{} - {}", owner, commercial_feature))
+            .join(kind)
+            .join(id.to_string())
+    }
+}
@@ -1,8 +1,9 @@
 use chrono::Utc;
 use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use sqlx::types::{chrono::DateTime, Uuid};
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Default, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct Company {
     pub id: Uuid,
@@ -20,11 +21,10 @@ pub struct Company {
     pub activity_location: Option<String>,
     pub record_number: Option<String>,
     pub username: Option<String>,
-    pub password: Option<String>,
     pub email: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct CreateCompany {
     pub owner: String,
@@ -45,7 +45,7 @@ pub struct CreateCompany {
     pub email: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct UpdateCompany {
     pub owner: String,
@@ -62,6 +62,7 @@ pub struct UpdateCompany {
     pub activity_location: Option<String>,
     pub record_number: Option<String>,
     pub username: Option<String>,
+    /// a new portal password, the current one is kept when missing
     pub password: Option<String>,
     pub email: Option<String>,
 }
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "custody_kind", rename_all = "snake_case")]
+pub enum CustodyKind {
+    TopUp,
+    Expense,
+    Refund,
+    Adjustment,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CustodyTransaction {
+    pub id: Uuid,
+    pub kind: CustodyKind,
+    pub value: f64,
+    pub balance: f64,
+    pub note: Option<String>,
+    pub time: DateTime<Utc>,
+    pub actor: Option<String>,
+    pub expense_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateCustodyTransaction {
+    pub value: f64,
+    pub note: Option<String>,
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use rocket::{
+    serde::{Deserialize, Serialize},
+    FromFormField,
+};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use super::Company;
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "deadline_kind", rename_all = "snake_case")]
+pub enum DeadlineKind {
+    /// monthly VAT return
+    Vat,
+    /// quarterly withholding tax statement (form 41)
+    Withholding,
+    /// annual income tax return
+    IncomeTax,
+    /// commercial register renewal, every five years
+    RegisterRenewal,
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "deadline_status", rename_all = "snake_case")]
+pub enum DeadlineStatus {
+    Upcoming,
+    Overdue,
+    Fulfilled,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Deadline {
+    pub id: Uuid,
+    pub kind: DeadlineKind,
+    pub status: DeadlineStatus,
+    pub title: String,
+    /// first day of the period the obligation covers
+    pub period: Option<NaiveDate>,
+    pub due_date: NaiveDate,
+    pub note: Option<String>,
+    pub fulfilled_at: Option<DateTime<Utc>>,
+    pub fulfilled_by: Option<String>,
+    pub time: DateTime<Utc>,
+    pub company_id: Uuid,
+    pub company: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateDeadline {
+    pub title: String,
+    pub due_date: NaiveDate,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct FulfillDeadline {
+    pub note: Option<String>,
+}
+
+/// a recurring obligation derived from the company registrations
+#[derive(Debug)]
+pub struct Obligation {
+    pub kind: DeadlineKind,
+    pub title: String,
+    pub period: NaiveDate,
+    pub due_date: NaiveDate,
+}
+
+/// years between commercial register renewals
+const REGISTER_RENEWAL_YEARS: u32 = 5;
+
+fn month_end(month: NaiveDate, months: u32) -> Option<NaiveDate> {
+    month
+        .checked_add_months(Months::new(months + 1))?
+        .pred_opt()
+}
+
+fn registered(field: &Option<String>) -> bool {
+    field
+        .as_deref()
+        .map(|field| !field.trim().is_empty())
+        .unwrap_or(false)
+}
+
+impl Company {
+    /// the obligations falling due between `from` and `until`, a period is owed
+    /// when the company was working during any part of it
+    pub fn obligations(&self, from: NaiveDate, until: NaiveDate) -> Vec<Obligation> {
+        let start = self.start_date.map(|date| date.date_naive());
+        let stop = self.stop_date.map(|date| date.date_naive());
+        // a company that stopped without a stop date owes nothing new
+        if !self.is_working && stop.is_none() {
+            return vec![];
+        }
+        let active = |first: NaiveDate, last: NaiveDate| {
+            start.map(|start| start <= last).unwrap_or(true)
+                && stop.map(|stop| stop >= first).unwrap_or(true)
+        };
+        let mut obligations = vec![];
+        let mut push = |kind, title, period, due_date: Option<NaiveDate>| {
+            if let Some(due_date) = due_date.filter(|due| *due >= from && *due <= until) {
+                obligations.push(Obligation {
+                    kind,
+                    title,
+                    period,
+                    due_date,
+                });
+            }
+        };
+
+        let vat = registered(&self.value_tax_mission);
+        let income_tax = registered(&self.general_tax_mission);
+        // whether the company worked during the `months` starting at `first`
+        let owed = |first: NaiveDate, months: u32| {
+            month_end(first, months - 1)
+                .map(|last| active(first, last))
+                .unwrap_or(false)
+        };
+        // the annual return is due up to four months after its year ends
+        let mut month = NaiveDate::from_ymd_opt(from.year() - 1, 1, 1);
+        while let Some(first) = month.filter(|month| *month <= until) {
+            if vat && owed(first, 1) {
+                push(
+                    DeadlineKind::Vat,
+                    format!("اقرار القيمة المضافة عن شهر {}", first.format("%Y-%m")),
+                    first,
+                    month_end(first, 1),
+                );
+            }
+            if income_tax && first.month() % 3 == 1 && owed(first, 3) {
+                push(
+                    DeadlineKind::Withholding,
+                    format!(
+                        "نموذج 41 خصم واضافة عن الربع {} لسنة {}",
+                        first.month() / 3 + 1,
+                        first.year()
+                    ),
+                    first,
+                    month_end(first, 3),
+                );
+            }
+            if income_tax && first.month() == 1 && owed(first, 12) {
+                // sole proprietors file by the end of march, companies by the end of april
+                let individual = self
+                    .legal_entity
+                    .as_deref()
+                    .map(|entity| entity.contains("فرد"))
+                    .unwrap_or(false);
+                push(
+                    DeadlineKind::IncomeTax,
+                    format!("اقرار ضريبة الدخل عن سنة {}", first.year()),
+                    first,
+                    month_end(first, if individual { 14 } else { 15 }),
+                );
+            }
+            month = first.checked_add_months(Months::new(1));
+        }
+
+        if let (true, Some(start)) = (registered(&self.record_number), start) {
+            let record_number = self.record_number.as_deref().unwrap_or_default();
+            let mut renewal = 1;
+            while let Some(due_date) = start
+                .checked_add_months(Months::new(12 * REGISTER_RENEWAL_YEARS * renewal))
+                .filter(|due| *due <= until)
+            {
+                let period = start
+                    .checked_add_months(Months::new(12 * REGISTER_RENEWAL_YEARS * (renewal - 1)));
+                if let Some(period) = period.filter(|_| active(due_date, due_date)) {
+                    push(
+                        DeadlineKind::RegisterRenewal,
+                        format!("تجديد السجل التجاري رقم {record_number}"),
+                        period,
+                        Some(due_date),
+                    );
+                }
+                renewal += 1;
+            }
+        }
+
+        obligations
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ProfitDistribution {
+    pub id: Uuid,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub total_incomes: f64,
+    pub total_expenses: f64,
+    pub net_profit: f64,
+    pub time: DateTime<Utc>,
+    pub entries: Vec<DistributionEntry>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct DistributionEntry {
+    pub id: Uuid,
+    pub funder_id: Option<Uuid>,
+    pub funder_name: String,
+    pub percentage: f64,
+    pub amount: f64,
+    pub statement: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateDistribution {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+}
+
+impl DistributionEntry {
+    /// path of the funder statement relative to the company directory,
+    /// so renaming the company keeps the statement reachable, named by the
+    /// funder id since the funder name is free text
+    pub fn statement_path(d: &CreateDistribution, funder_id: Uuid) -> PathBuf {
+        Path::new("distributions")
+            .join(format!(
+                "{} - {}",
+                d.period_start.format("%Y-%m-%d"),
+                d.period_end.format("%Y-%m-%d")
+            ))
+            .join(format!("{}.csv", funder_id))
+    }
+
+    /// renders the funder statement as a UTF-8 CSV
+    pub fn statement(&self, d: &CreateDistribution, incomes: f64, expenses: f64) -> String {
+        format!(
+            "\u{feff}البيان,القيمة\n\
+             الممول,{}\n\
+             بداية الفترة,{}\n\
+             نهاية الفترة,{}\n\
+             اجمالي الواردات,{:.2}\n\
+             اجمالي المصروفات,{:.2}\n\
+             صافي الربح,{:.2}\n\
+             نسبة الملكية,{:.2}%\n\
+             نصيب الممول,{:.2}\n",
+            self.funder_name,
+            d.period_start.format("%Y-%m-%d"),
+            d.period_end.format("%Y-%m-%d"),
+            incomes,
+            expenses,
+            incomes - expenses,
+            self.percentage,
+            self.amount,
+        )
+    }
+}
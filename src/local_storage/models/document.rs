@@ -2,10 +2,11 @@ use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
 use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 use crate::file_system::FileSystemFile;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 #[serde(crate = "rocket::serde")]
 pub struct Document {
     pub path: PathBuf,
@@ -27,7 +28,7 @@ impl Document {
         let time = file
             .create_time()
             .await
-            .unwrap_or(DateTime::from(Utc::now()));
+            .unwrap_or(Utc::now());
         Some(Self { path, name, time })
     }
 }
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{json::Value, Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::{Json, Uuid};
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "eta_status", rename_all = "snake_case")]
+pub enum EtaStatus {
+    Submitted,
+    Valid,
+    Invalid,
+    Rejected,
+    Cancelled,
+}
+
+/// the last e-invoice submission of an invoice, `uuid` and `long_id` are
+/// assigned by the tax authority once the document is accepted
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct EtaDocument {
+    pub invoice_id: Uuid,
+    pub status: EtaStatus,
+    pub uuid: Option<String>,
+    pub long_id: Option<String>,
+    pub submission_id: Option<String>,
+    /// sha256 of the canonical serialization of the document
+    pub hash: String,
+    #[schemars(with = "Value")]
+    pub document: Json<Value>,
+    #[schemars(with = "Option<Value>")]
+    pub errors: Option<Json<Value>>,
+    pub time: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateEtaDocument {
+    pub status: EtaStatus,
+    pub uuid: Option<String>,
+    pub long_id: Option<String>,
+    pub submission_id: Option<String>,
+    pub hash: String,
+    pub document: Value,
+    pub errors: Option<Value>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CancelEtaDocument {
+    pub reason: String,
+}
@@ -1,21 +1,67 @@
 use chrono::{DateTime, Utc};
-use rocket::serde::{Deserialize, Serialize};
-use sqlx::types::Uuid;
+use rocket::{
+    serde::{Deserialize, Serialize},
+    FromFormField,
+};
+use schemars::JsonSchema;
+use sqlx::types::{Json, Uuid};
 
-#[derive(Serialize, Debug)]
-#[serde(crate = "rocket::serde")]
+use super::{Attachment, TaxCode};
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "expense_status", rename_all = "snake_case")]
+pub enum ExpenseStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct Expense {
     pub id: Uuid,
+    /// gross amount, tax included
     pub value: f64,
+    pub net: f64,
+    pub tax: f64,
+    pub tax_code: TaxCode,
+    pub tax_rate: f64,
+    pub supplier: Option<String>,
+    pub supplier_registration: Option<String>,
+    pub withholding_rate: Option<f64>,
+    /// withholding tax deducted from the net amount
+    pub withholding: f64,
     pub description: String,
     pub time: DateTime<Utc>,
     pub company: String,
     pub user: String,
+    pub status: ExpenseStatus,
+    pub reviewer: Option<String>,
+    pub review_comment: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    #[schemars(with = "Vec<Attachment>")]
+    pub attachments: Json<Vec<Attachment>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct CreateExpense {
+    /// gross amount, tax included
     pub value: f64,
     pub description: String,
+    #[serde(default)]
+    pub tax_code: TaxCode,
+    /// required for table tax
+    pub tax_rate: Option<f64>,
+    pub supplier: Option<String>,
+    /// the 9 digits tax registration number, required for withholding
+    pub supplier_registration: Option<String>,
+    pub withholding_rate: Option<f64>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ReviewExpense {
+    pub comment: Option<String>,
 }
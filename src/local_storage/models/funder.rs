@@ -1,15 +1,26 @@
 use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use sqlx::types::Uuid;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct Funder {
     pub id: Uuid,
     pub name: String,
+    pub percentage: f64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct CreateFunder {
     pub name: String,
+    #[serde(default)]
+    pub percentage: f64,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct UpdateFunder {
+    pub name: String,
+    pub percentage: f64,
 }
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::Serialize;
+use schemars::JsonSchema;
+
+use super::TaxCode;
+
+/// an expense row of an imported sheet, the employee and company are looked up
+/// by name and the employee defaults to the importing admin
+#[derive(Debug)]
+pub struct ImportExpense {
+    pub user: Option<String>,
+    pub company: String,
+    pub owner: Option<String>,
+    pub value: f64,
+    pub description: String,
+    pub time: Option<DateTime<Utc>>,
+    pub tax_code: TaxCode,
+    pub tax_rate: Option<f64>,
+    pub supplier: Option<String>,
+    pub supplier_registration: Option<String>,
+    pub withholding_rate: Option<f64>,
+}
+
+/// an income row of an imported sheet, the admin defaults to the importing one
+#[derive(Debug)]
+pub struct ImportIncome {
+    pub admin: Option<String>,
+    pub company: String,
+    pub owner: Option<String>,
+    pub value: f64,
+    pub description: String,
+    pub time: Option<DateTime<Utc>>,
+    pub tax_code: TaxCode,
+    pub tax_rate: Option<f64>,
+}
+
+/// a rejected row, `row` is the line number in the sheet with the titles on
+/// the first line
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ImportError {
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ImportReport {
+    pub rows: usize,
+    pub dry_run: bool,
+    pub committed: bool,
+    pub errors: Vec<ImportError>,
+}
@@ -1,22 +1,44 @@
 use chrono::{DateTime, Utc};
 use rocket::serde::{Deserialize, Serialize};
-use sqlx::types::Uuid;
+use schemars::JsonSchema;
+use sqlx::types::{Json, Uuid};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use super::{Attachment, TaxCode};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(crate = "rocket::serde")]
 pub struct Income {
     #[serde(default)]
     pub id: Uuid,
+    /// gross amount, tax included
     pub value: f64,
+    #[serde(default)]
+    pub net: f64,
+    #[serde(default)]
+    pub tax: f64,
+    #[serde(default)]
+    pub tax_code: TaxCode,
+    #[serde(default)]
+    pub tax_rate: f64,
     pub description: String,
     pub time: DateTime<Utc>,
+    #[serde(default)]
+    pub company_id: Uuid,
     pub company: String,
     pub admin: String,
+    #[serde(default)]
+    #[schemars(with = "Vec<Attachment>")]
+    pub attachments: Json<Vec<Attachment>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct CreateIncome {
+    /// gross amount, tax included
     pub value: f64,
     pub description: String,
+    #[serde(default)]
+    pub tax_code: TaxCode,
+    /// required for table tax
+    pub tax_rate: Option<f64>,
 }
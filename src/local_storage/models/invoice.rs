@@ -0,0 +1,95 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rocket::{
+    serde::{Deserialize, Serialize},
+    FromFormField,
+};
+use schemars::JsonSchema;
+use sqlx::types::{Json, Uuid};
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "invoice_status", rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Draft,
+    Issued,
+    PartiallyPaid,
+    Paid,
+    Void,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Invoice {
+    pub id: Uuid,
+    /// `YEAR-NUMBER`, assigned once the invoice is issued
+    pub number: Option<String>,
+    pub status: InvoiceStatus,
+    pub issue_date: Option<NaiveDate>,
+    pub due_date: NaiveDate,
+    pub notes: Option<String>,
+    pub total: f64,
+    pub paid: f64,
+    pub time: DateTime<Utc>,
+    pub company_id: Uuid,
+    pub company: String,
+    #[schemars(with = "Vec<InvoiceLine>")]
+    pub lines: Json<Vec<InvoiceLine>>,
+    #[schemars(with = "Vec<InvoicePayment>")]
+    pub payments: Json<Vec<InvoicePayment>>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct InvoicePayment {
+    pub id: Uuid,
+    pub value: f64,
+    pub note: Option<String>,
+    pub time: DateTime<Utc>,
+    pub income_id: Uuid,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateInvoice {
+    pub due_date: NaiveDate,
+    pub notes: Option<String>,
+    pub lines: Vec<InvoiceLine>,
+}
+
+pub type UpdateInvoice = CreateInvoice;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateInvoicePayment {
+    pub value: f64,
+    pub note: Option<String>,
+}
+
+/// outstanding receivables of a company bucketed by days past due
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ReceivablesAging {
+    pub company_id: Uuid,
+    pub company: String,
+    pub current: f64,
+    pub days_1_to_30: f64,
+    pub days_31_to_60: f64,
+    pub days_61_to_90: f64,
+    pub over_90: f64,
+    pub total: f64,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct IssueInvoice {
+    /// defaults to today
+    pub issue_date: Option<NaiveDate>,
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use rocket::{
+    serde::{Deserialize, Serialize},
+    FromFormField,
+};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "login_outcome", rename_all = "snake_case")]
+pub enum LoginOutcome {
+    Succeeded,
+    /// wrong credentials or authenticator code
+    Failed,
+    /// refused without checking the credentials, the user or the address
+    /// failed too often
+    Locked,
+}
+
+/// a sign in to the staff login, the audit trail of the guessed passwords
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct LoginAttempt {
+    pub id: Uuid,
+    /// the user name as it was typed
+    pub name: String,
+    /// the client address, when the server knows it
+    pub ip: Option<String>,
+    pub outcome: LoginOutcome,
+    pub time: DateTime<Utc>,
+}
@@ -4,6 +4,23 @@ pub mod expense;
 pub mod income;
 pub mod document;
 pub mod funder;
+pub mod distribution;
+pub mod custody;
+pub mod notification;
+pub mod attachment;
+pub mod schedule;
+pub mod invoice;
+pub mod statement;
+pub mod import;
+pub mod eta;
+pub mod tax;
+pub mod deadline;
+pub mod tenant;
+pub mod portal;
+pub mod two_factor;
+pub mod login;
+pub mod session;
+pub mod password;
 
 pub use company::*;
 pub use user::*;
@@ -11,3 +28,20 @@ pub use expense::*;
 pub use income::*;
 pub use document::*;
 pub use funder::*;
+pub use distribution::*;
+pub use custody::*;
+pub use notification::*;
+pub use attachment::*;
+pub use schedule::*;
+pub use invoice::*;
+pub use statement::*;
+pub use import::*;
+pub use eta::*;
+pub use tax::*;
+pub use deadline::*;
+pub use tenant::*;
+pub use portal::*;
+pub use two_factor::*;
+pub use login::*;
+pub use session::*;
+pub use password::*;
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "language", rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    Ar,
+    En,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "notification_kind", rename_all = "snake_case")]
+pub enum NotificationKind {
+    General,
+    Deadline,
+    PendingApproval,
+    ExpenseReview,
+    LowCustody,
+    NewDocument,
+    ScheduleStopped,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Notification {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub time: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// a notification waiting to be emailed
+#[derive(Debug)]
+pub struct PendingEmail {
+    pub id: Uuid,
+    pub email: String,
+    pub title: String,
+    pub body: String,
+    pub attempts: i32,
+}
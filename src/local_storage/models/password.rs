@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ChangePassword {
+    pub old_password: String,
+    pub password: String,
+}
+
+/// a token an admin hands to a user who forgot their password
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PasswordReset {
+    /// shown once, it works a single time
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+}
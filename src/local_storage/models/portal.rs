@@ -0,0 +1,153 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use super::{Company, CompanyStatementEntry, Income, Invoice, InvoiceLine, InvoiceStatus, TaxCode};
+
+/// the portal credentials stored on the company
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct LoginCompany {
+    pub username: String,
+    pub password: String,
+    /// the name of the office, the default one when missing
+    pub tenant: Option<String>,
+}
+
+/// the company as its owner sees it, without the portal credentials
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PortalCompany {
+    pub id: Uuid,
+    pub owner: String,
+    pub commercial_feature: String,
+    pub is_working: bool,
+    pub legal_entity: Option<String>,
+    pub file_number: Option<String>,
+    pub register_number: Option<String>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub stop_date: Option<DateTime<Utc>>,
+    pub general_tax_mission: Option<String>,
+    pub value_tax_mission: Option<String>,
+    pub activity_nature: Option<String>,
+    pub activity_location: Option<String>,
+    pub record_number: Option<String>,
+    pub email: Option<String>,
+}
+
+impl From<Company> for PortalCompany {
+    fn from(c: Company) -> Self {
+        PortalCompany {
+            id: c.id,
+            owner: c.owner,
+            commercial_feature: c.commercial_feature,
+            is_working: c.is_working,
+            legal_entity: c.legal_entity,
+            file_number: c.file_number,
+            register_number: c.register_number,
+            start_date: c.start_date,
+            stop_date: c.stop_date,
+            general_tax_mission: c.general_tax_mission,
+            value_tax_mission: c.value_tax_mission,
+            activity_nature: c.activity_nature,
+            activity_location: c.activity_location,
+            record_number: c.record_number,
+            email: c.email,
+        }
+    }
+}
+
+/// an income of the company, without who recorded it nor its attachments
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PortalIncome {
+    pub id: Uuid,
+    pub value: f64,
+    pub net: f64,
+    pub tax: f64,
+    pub tax_code: TaxCode,
+    pub tax_rate: f64,
+    pub description: String,
+    pub time: DateTime<Utc>,
+}
+
+impl From<Income> for PortalIncome {
+    fn from(i: Income) -> Self {
+        PortalIncome {
+            id: i.id,
+            value: i.value,
+            net: i.net,
+            tax: i.tax,
+            tax_code: i.tax_code,
+            tax_rate: i.tax_rate,
+            description: i.description,
+            time: i.time,
+        }
+    }
+}
+
+/// an issued invoice of the company, the notes of its payments stay internal
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PortalInvoice {
+    pub id: Uuid,
+    pub number: Option<String>,
+    pub status: InvoiceStatus,
+    pub issue_date: Option<NaiveDate>,
+    pub due_date: NaiveDate,
+    /// printed on the invoice
+    pub notes: Option<String>,
+    pub total: f64,
+    pub paid: f64,
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl From<Invoice> for PortalInvoice {
+    fn from(i: Invoice) -> Self {
+        PortalInvoice {
+            id: i.id,
+            number: i.number,
+            status: i.status,
+            issue_date: i.issue_date,
+            due_date: i.due_date,
+            notes: i.notes,
+            total: i.total,
+            paid: i.paid,
+            lines: i.lines.0,
+        }
+    }
+}
+
+/// the account of the company with the office, its incomes against its
+/// approved expenses
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PortalBalance {
+    pub balance: f64,
+    /// what is left to pay of the issued invoices
+    pub outstanding: f64,
+    pub entries: Vec<PortalStatementEntry>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PortalStatementEntry {
+    pub description: String,
+    pub debit: f64,
+    pub credit: f64,
+    pub balance: f64,
+    pub time: DateTime<Utc>,
+}
+
+impl From<CompanyStatementEntry> for PortalStatementEntry {
+    fn from(e: CompanyStatementEntry) -> Self {
+        PortalStatementEntry {
+            description: e.description,
+            debit: e.debit,
+            credit: e.credit,
+            balance: e.balance,
+            time: e.time,
+        }
+    }
+}
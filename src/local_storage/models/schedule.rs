@@ -0,0 +1,123 @@
+use chrono::{Months, NaiveDate};
+use rocket::{
+    serde::{Deserialize, Serialize},
+    FromFormField,
+};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "schedule_kind", rename_all = "snake_case")]
+pub enum ScheduleKind {
+    Income,
+    Expense,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "schedule_cadence", rename_all = "snake_case")]
+pub enum Cadence {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Cadence {
+    pub fn months(self) -> u32 {
+        match self {
+            Cadence::Monthly => 1,
+            Cadence::Quarterly => 3,
+            Cadence::Yearly => 12,
+        }
+    }
+
+    /// date of the `n`th occurrence, always counted from `start` so that
+    /// month-end clamping does not drift the schedule
+    pub fn occurrence(self, start: NaiveDate, n: i32) -> Option<NaiveDate> {
+        start.checked_add_months(Months::new(self.months() * u32::try_from(n).ok()?))
+    }
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: Uuid,
+    pub kind: ScheduleKind,
+    pub cadence: Cadence,
+    pub value: f64,
+    pub description: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub runs: i32,
+    pub next_run: Option<NaiveDate>,
+    pub active: bool,
+    pub company: String,
+    pub user: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateSchedule {
+    pub kind: ScheduleKind,
+    pub cadence: Cadence,
+    pub value: f64,
+    pub description: String,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    /// custody the expenses are paid from, defaults to the creating admin
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct UpdateSchedule {
+    pub value: f64,
+    pub description: String,
+    pub end_date: Option<NaiveDate>,
+    pub active: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).expect("a valid date")
+    }
+
+    #[test]
+    fn month_ends_do_not_drift() {
+        let start = date(2024, 1, 31);
+        let dates: Vec<NaiveDate> = (0..4)
+            .filter_map(|n| Cadence::Monthly.occurrence(start, n))
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                date(2024, 1, 31),
+                date(2024, 2, 29),
+                date(2024, 3, 31),
+                date(2024, 4, 30)
+            ]
+        );
+    }
+
+    #[test]
+    fn cadences_step_by_their_months() {
+        let start = date(2023, 11, 15);
+        assert_eq!(
+            Cadence::Quarterly.occurrence(start, 1),
+            Some(date(2024, 2, 15))
+        );
+        assert_eq!(
+            Cadence::Yearly.occurrence(date(2024, 2, 29), 1),
+            Some(date(2025, 2, 28))
+        );
+        assert_eq!(
+            Cadence::Yearly.occurrence(date(2024, 2, 29), 4),
+            Some(date(2028, 2, 29))
+        );
+        assert_eq!(Cadence::Monthly.occurrence(start, -1), None);
+    }
+}
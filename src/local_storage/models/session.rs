@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::Serialize;
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+/// a signed in device of a user, removing it signs the device out
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Session {
+    pub id: Uuid,
+    /// the `X-Device-Name` sent with the login
+    pub device_name: Option<String>,
+    /// the address of the latest request
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// the session of the token listing the sessions
+    pub current: bool,
+    pub time: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// the device a user signs in from
+#[derive(Debug)]
+pub struct CreateSession {
+    pub device_name: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
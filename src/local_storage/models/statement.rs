@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::Serialize;
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+/// a line of a company account statement, incomes are credited and approved
+/// expenses are debited
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CompanyStatementEntry {
+    pub id: Uuid,
+    pub description: String,
+    pub debit: f64,
+    pub credit: f64,
+    pub balance: f64,
+    pub time: DateTime<Utc>,
+    pub actor: Option<String>,
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rocket::{
+    serde::{Deserialize, Serialize},
+    FromFormField,
+};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+#[derive(Serialize, Deserialize, JsonSchema, sqlx::Type, FromFormField, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[sqlx(type_name = "tax_code", rename_all = "snake_case")]
+pub enum TaxCode {
+    /// value added tax at the standard rate
+    Standard,
+    #[default]
+    Exempt,
+    /// taxable at 0%, still declared in the VAT return unlike exempt rows
+    ZeroRated,
+    /// schedule (table) tax, the rate depends on the item
+    Table,
+}
+
+impl TaxCode {
+    pub const STANDARD_RATE: f64 = 14.0;
+
+    /// the percentage of the code, table tax rates are given by the caller
+    pub fn rate(self, table_rate: Option<f64>) -> Option<f64> {
+        match self {
+            Self::Standard => Some(Self::STANDARD_RATE),
+            Self::Exempt | Self::ZeroRated => Some(0.0),
+            Self::Table => table_rate.filter(|rate| *rate > 0.0 && *rate < 100.0),
+        }
+    }
+
+    /// the rate and the tax included in a gross `value`, rounded to piasters
+    pub fn split(self, value: f64, table_rate: Option<f64>) -> Option<(f64, f64)> {
+        let rate = self.rate(table_rate)?;
+        let tax = (value * rate / (100.0 + rate) * 100.0).round() / 100.0;
+        Some((rate, tax))
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Standard => "ضريبة القيمة المضافة",
+            Self::Exempt => "معفي",
+            Self::ZeroRated => "خاضع بسعر صفر",
+            Self::Table => "ضريبة الجدول",
+        }
+    }
+
+    /// parses a label or the API name of a code
+    pub fn parse(text: &str) -> Option<Self> {
+        [Self::Standard, Self::Exempt, Self::ZeroRated, Self::Table]
+            .into_iter()
+            .find(|code| {
+                code.label() == text
+                    || rocket::serde::json::to_value(code)
+                        .map(|name| name == text)
+                        .unwrap_or(false)
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, FromFormField, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum VatSide {
+    /// incomes, output tax
+    Sales,
+    /// approved expenses, input tax
+    Purchases,
+}
+
+/// totals of one tax code and rate in a VAT return period
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct VatReturnLine {
+    pub tax_code: TaxCode,
+    pub tax_rate: f64,
+    pub sales_net: f64,
+    pub sales_tax: f64,
+    pub purchases_net: f64,
+    pub purchases_tax: f64,
+}
+
+/// the monthly VAT return of a company
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct VatReturn {
+    pub company_id: Uuid,
+    pub company: String,
+    pub register_number: Option<String>,
+    pub value_tax_mission: Option<String>,
+    /// first day of the month
+    pub period: NaiveDate,
+    pub lines: Vec<VatReturnLine>,
+    pub output_vat: f64,
+    pub input_vat: f64,
+    pub output_table_tax: f64,
+    pub input_table_tax: f64,
+    /// negative when the input tax is carried forward
+    pub net_payable: f64,
+}
+
+/// an income or expense row of the VAT return, as uploaded to the tax portal
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct VatReturnEntry {
+    pub id: Uuid,
+    pub tax_code: TaxCode,
+    pub tax_rate: f64,
+    pub description: String,
+    pub time: DateTime<Utc>,
+    pub net: f64,
+    pub tax: f64,
+    pub value: f64,
+}
+
+/// a supplier payment in the quarterly withholding statement (form 41)
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct WithholdingEntry {
+    pub id: Uuid,
+    pub supplier: Option<String>,
+    pub supplier_registration: String,
+    pub description: String,
+    pub time: DateTime<Utc>,
+    pub net: f64,
+    pub withholding_rate: f64,
+    pub withholding: f64,
+}
+
+/// the withholding tax deducted by a company in a quarter
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct WithholdingStatement {
+    pub company_id: Uuid,
+    pub company: String,
+    pub register_number: Option<String>,
+    pub general_tax_mission: Option<String>,
+    /// first day of the quarter
+    pub period: NaiveDate,
+    pub entries: Vec<WithholdingEntry>,
+    pub total_net: f64,
+    pub total_withholding: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gross_values_are_split_to_piasters() {
+        assert_eq!(TaxCode::Standard.split(1140.0, None), Some((14.0, 140.0)));
+        assert_eq!(TaxCode::Standard.split(100.0, None), Some((14.0, 12.28)));
+        // the table rate of the standard code is ignored
+        assert_eq!(
+            TaxCode::Standard.split(114.0, Some(5.0)),
+            Some((14.0, 14.0))
+        );
+        assert_eq!(TaxCode::Exempt.split(100.0, None), Some((0.0, 0.0)));
+        assert_eq!(TaxCode::ZeroRated.split(100.0, Some(5.0)), Some((0.0, 0.0)));
+        assert_eq!(TaxCode::Table.split(105.0, Some(5.0)), Some((5.0, 5.0)));
+    }
+
+    #[test]
+    fn table_rates_must_be_percentages() {
+        for rate in [None, Some(0.0), Some(-5.0), Some(100.0)] {
+            assert_eq!(TaxCode::Table.split(100.0, rate), None, "{rate:?}");
+        }
+    }
+
+    #[test]
+    fn codes_are_parsed_from_labels_and_names() {
+        assert_eq!(TaxCode::parse("zeroRated"), Some(TaxCode::ZeroRated));
+        assert_eq!(TaxCode::parse("معفي"), Some(TaxCode::Exempt));
+        assert_eq!(TaxCode::parse("vat"), None);
+    }
+}
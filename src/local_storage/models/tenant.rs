@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use super::{Language, RegisterUser, User};
+
+/// the office migrated data belongs to, signed in to when no tenant is given
+pub const DEFAULT_TENANT: &str = "default";
+
+/// an accounting office, its users only ever see its own data
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct Tenant {
+    pub id: Uuid,
+    pub name: String,
+    pub time: DateTime<Utc>,
+}
+
+/// a new office with the admin who manages it
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreateTenant {
+    pub name: String,
+    pub admin: TenantAdmin,
+}
+
+/// the first user of an office, the other users are registered by them
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct TenantAdmin {
+    pub name: String,
+    pub password: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub language: Language,
+}
+
+impl From<&TenantAdmin> for RegisterUser {
+    fn from(admin: &TenantAdmin) -> Self {
+        RegisterUser {
+            name: admin.name.clone(),
+            password: admin.password.clone(),
+            is_admin: true,
+            is_reviewer: false,
+            email: admin.email.clone(),
+            language: admin.language,
+        }
+    }
+}
+
+/// a created office and its admin
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct CreatedTenant {
+    pub tenant: Tenant,
+    pub admin: User,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct SuperAdmin {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct LoginSuperAdmin {
+    pub name: String,
+    pub password: String,
+}
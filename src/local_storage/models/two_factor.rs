@@ -0,0 +1,34 @@
+use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+/// whether the user signs in with an authenticator code
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// set for the admins of an office enforcing two factor authentication
+    pub required: bool,
+}
+
+/// a pending authenticator, enabled once one of its codes is confirmed
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct TwoFactorSetup {
+    /// base32, for typing the secret in by hand
+    pub secret: String,
+    /// the `otpauth://` uri to show as a QR code
+    pub uri: String,
+}
+
+/// an authenticator code or a recovery code
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct TwoFactorCode {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct TwoFactorPolicy {
+    pub admins_require_two_factor: bool,
+}
@@ -1,35 +1,59 @@
 use rocket::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use sqlx::types::Uuid;
 
-#[derive(Serialize, Debug)]
+use super::Language;
+
+#[derive(Serialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct User {
     pub id: Uuid,
     pub name: String,
-    pub password: String,
     pub is_admin: bool,
     pub value: f64,
+    pub is_reviewer: bool,
+    pub reserved: f64,
+    pub email: Option<String>,
+    pub language: Language,
+    /// set for the seeded admin, they change the password before signing in
+    pub must_change_password: bool,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct RegisterUser {
     pub name: String,
     pub password: String,
     pub is_admin: bool,
+    #[serde(default)]
+    pub is_reviewer: bool,
+    /// notifications are emailed too when set
+    pub email: Option<String>,
+    #[serde(default)]
+    pub language: Language,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct LoginUser {
     pub name: String,
     pub password: String,
+    /// the name of the office, the default one when missing
+    pub tenant: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
 pub struct UpdateUser {
     pub name: String,
-    pub password: String,
+    /// a new password, the current one is kept when missing
+    #[serde(default)]
+    pub password: Option<String>,
     pub is_admin: bool,
+    #[serde(default)]
+    pub is_reviewer: bool,
+    /// notifications are emailed too when set
+    pub email: Option<String>,
+    #[serde(default)]
+    pub language: Language,
 }
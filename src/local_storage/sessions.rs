@@ -0,0 +1,137 @@
+use std::{convert::Infallible, net::IpAddr};
+
+use chrono::{Duration, Utc};
+use rocket::{
+    request::{FromRequest, Outcome},
+    Request,
+};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    okapi::openapi3::{Object, Parameter, ParameterValue},
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use sqlx::types::Uuid;
+
+use crate::accounting_api;
+
+use super::{models::*, Tenants};
+
+/// the header naming the device at the login
+const DEVICE_NAME: &str = "X-Device-Name";
+/// how often the last seen time of a session is saved, not on every request
+const TOUCH_MINUTES: i64 = 1;
+
+impl Tenants {
+    /// whether the session of the user is still open, its last seen time
+    /// and address are kept up to date
+    pub async fn touch_session(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        id: Uuid,
+        ip: Option<IpAddr>,
+    ) -> Result<bool, accounting_api::Error> {
+        let session = sqlx::query!(
+            r#"
+                SELECT
+                    last_seen
+                FROM
+                    sessions
+                WHERE
+                    id = $1 AND tenant_id = $2 AND user_id = $3
+            "#,
+            id,
+            tenant_id,
+            user_id,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        let session = match session {
+            Some(session) => session,
+            None => return Ok(false),
+        };
+        if session.last_seen < Utc::now() - Duration::minutes(TOUCH_MINUTES) {
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        sessions
+                    SET
+                        last_seen = now(),
+                        ip = COALESCE($2, ip)
+                    WHERE
+                        id = $1
+                "#,
+                id,
+                ip.map(|ip| ip.to_string()),
+            )
+            .execute(&self.db)
+            .await?;
+        }
+        Ok(true)
+    }
+
+    /// whether the user still has to change their password, their change
+    /// password token is spent once they did
+    pub async fn must_change_password(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, accounting_api::Error> {
+        let user = sqlx::query!(
+            r#"
+                SELECT
+                    must_change_password
+                FROM
+                    users
+                WHERE
+                    id = $1 AND tenant_id = $2
+            "#,
+            user_id,
+            tenant_id,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(user.map(|user| user.must_change_password).unwrap_or(false))
+    }
+}
+
+/// the device of the login request, every part of it is optional
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CreateSession {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = |name| request.headers().get_one(name).map(String::from);
+        Outcome::Success(CreateSession {
+            device_name: header(DEVICE_NAME),
+            ip: request.client_ip().map(|ip| ip.to_string()),
+            user_agent: header("User-Agent"),
+        })
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for CreateSession {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::Parameter(Parameter {
+            name: DEVICE_NAME.to_owned(),
+            location: "header".to_owned(),
+            description: Some("the name of the device shown in the sessions".to_owned()),
+            required: false,
+            deprecated: false,
+            allow_empty_value: false,
+            value: ParameterValue::Schema {
+                style: None,
+                explode: None,
+                allow_reserved: false,
+                schema: gen.json_schema::<String>(),
+                example: None,
+                examples: None,
+            },
+            extensions: Object::default(),
+        }))
+    }
+}
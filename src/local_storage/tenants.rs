@@ -0,0 +1,688 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    tokio::{self, fs, sync::RwLock},
+    Request,
+};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use sqlx::{pool::PoolOptions, types::Uuid, Pool};
+
+use crate::{
+    accounting_api::{self, AcountingApi},
+    auth::{self, ApiTokenError},
+    passwords::{self, PasswordPolicy},
+};
+
+use super::{
+    models::*,
+    throttle::{self, Throttle},
+    LocalStorageAccountingApi, DB,
+};
+
+/// the connections of the owner, shared by the logins of every tenant
+const OWNER_MAX_CONNECTIONS: u32 = 10;
+/// the tenants whose connections are kept, with the owner ones they stay
+/// below the 100 connections of a default database server
+const MAX_TENANT_POOLS: usize = 16;
+
+/// the offices hosted by the deployment, the storage of each one is created
+/// on its first use and kept for the next requests
+#[derive(Debug, Clone)]
+pub struct Tenants {
+    /// owns the tables and sees the rows of every tenant, only used for the
+    /// tenants themselves, the migrations and the change listener
+    pub db: Pool<DB>,
+    db_url: Arc<str>,
+    root: Arc<Path>,
+    expense_approval_threshold: Option<f64>,
+    low_custody_threshold: Option<f64>,
+    pub(super) throttle: Throttle,
+    password_policy: PasswordPolicy,
+    storages: Arc<RwLock<HashMap<Uuid, Arc<LocalStorageAccountingApi>>>>,
+}
+
+impl Tenants {
+    pub async fn new(
+        db_url: &str,
+        fs_path: &str,
+        expense_approval_threshold: Option<f64>,
+        low_custody_threshold: Option<f64>,
+        throttle: Throttle,
+        password_policy: PasswordPolicy,
+    ) -> sqlx::Result<Self> {
+        Ok(Tenants {
+            db: PoolOptions::new()
+                .max_connections(OWNER_MAX_CONNECTIONS)
+                .connect(db_url)
+                .await?,
+            db_url: db_url.into(),
+            root: PathBuf::from(fs_path).into(),
+            expense_approval_threshold,
+            low_custody_threshold,
+            throttle,
+            password_policy,
+            storages: Default::default(),
+        })
+    }
+
+    /// the storage of `tenant_id`, `ObjectNotFound` when there is no such
+    /// tenant, the storages no request uses are dropped once more than
+    /// `MAX_TENANT_POOLS` are kept
+    pub async fn storage(
+        &self,
+        tenant_id: Uuid,
+    ) -> Result<Arc<LocalStorageAccountingApi>, accounting_api::Error> {
+        if let Some(storage) = self.storages.read().await.get(&tenant_id) {
+            return Ok(storage.clone());
+        }
+        self.get_tenant(tenant_id).await?;
+        let storage = Arc::new(
+            LocalStorageAccountingApi::new(
+                &self.db_url,
+                &self.root,
+                tenant_id,
+                self.expense_approval_threshold,
+                self.low_custody_threshold,
+                self.password_policy,
+            )
+            .await?,
+        );
+        let mut storages = self.storages.write().await;
+        if let Some(storage) = storages.get(&tenant_id) {
+            return Ok(storage.clone());
+        }
+        if storages.len() >= MAX_TENANT_POOLS {
+            // only the map holds them, no request can take them meanwhile
+            let unused: Vec<Uuid> = storages
+                .iter()
+                .filter(|(_, storage)| Arc::strong_count(storage) == 1)
+                .map(|(id, _)| *id)
+                .take(storages.len() + 1 - MAX_TENANT_POOLS)
+                .collect();
+            for id in unused {
+                if let Some(storage) = storages.remove(&id) {
+                    tokio::spawn(async move { storage.db.close().await });
+                }
+            }
+        }
+        storages.insert(tenant_id, storage.clone());
+        Ok(storage)
+    }
+
+    pub async fn get_tenants(&self) -> Result<Vec<Tenant>, accounting_api::Error> {
+        let tenants = sqlx::query_as!(
+            Tenant,
+            r#"
+                SELECT
+                    id,
+                    name,
+                    time
+                FROM
+                    tenants
+                ORDER BY
+                    time
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(tenants)
+    }
+
+    pub async fn get_tenant(&self, id: Uuid) -> Result<Tenant, accounting_api::Error> {
+        let tenant = sqlx::query_as!(
+            Tenant,
+            r#"
+                SELECT
+                    id,
+                    name,
+                    time
+                FROM
+                    tenants
+                WHERE
+                    id = $1
+            "#,
+            id,
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok(tenant)
+    }
+
+    /// creates the tenant with its admin, the tenant is removed again when
+    /// the admin cannot be registered
+    pub async fn create_tenant(
+        &self,
+        t: &CreateTenant,
+    ) -> Result<CreatedTenant, accounting_api::Error> {
+        let tenant = sqlx::query_as!(
+            Tenant,
+            r#"
+                INSERT INTO
+                    tenants (name)
+                VALUES
+                    ($1)
+                RETURNING
+                    id,
+                    name,
+                    time
+            "#,
+            &t.name,
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        let admin = match self.storage(tenant.id).await {
+            Ok(storage) => storage.register_user(&(&t.admin).into()).await,
+            Err(error) => Err(error),
+        };
+        match admin {
+            Ok(admin) => Ok(CreatedTenant { tenant, admin }),
+            Err(error) => {
+                self.storages.write().await.remove(&tenant.id);
+                sqlx::query!(
+                    r#"
+                        DELETE FROM
+                            tenants
+                        WHERE
+                            id = $1
+                    "#,
+                    tenant.id,
+                )
+                .execute(&self.db)
+                .await?;
+                Err(error)
+            }
+        }
+    }
+
+    /// the tenant named `name`, the default one when missing, an unknown
+    /// tenant is reported like wrong credentials
+    async fn login_tenant(&self, name: Option<&str>) -> Result<Uuid, accounting_api::Error> {
+        let tenant = sqlx::query!(
+            r#"
+                SELECT
+                    id
+                FROM
+                    tenants
+                WHERE
+                    name = $1
+            "#,
+            name.unwrap_or(DEFAULT_TENANT),
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(accounting_api::Error::InvalidCredentials)?;
+        Ok(tenant.id)
+    }
+
+    /// the user, their tenant and whether they still have to give an
+    /// authenticator code, the attempts from `ip` are throttled
+    pub async fn login_user(
+        &self,
+        u: &LoginUser,
+        ip: Option<IpAddr>,
+    ) -> Result<(Uuid, User, TwoFactorStatus), accounting_api::Error> {
+        let tenant_id = match self.login_tenant(u.tenant.as_deref()).await {
+            Ok(tenant_id) => Some(tenant_id),
+            Err(accounting_api::Error::InvalidCredentials) => None,
+            Err(error) => return Err(error),
+        };
+        let login = async {
+            let tenant_id = tenant_id.ok_or(accounting_api::Error::InvalidCredentials)?;
+            let storage = self.storage(tenant_id).await?;
+            let user = storage.login_user(u).await?;
+            let two_factor = storage.get_two_factor(user.id).await?;
+            Ok((tenant_id, user, two_factor))
+        };
+        let account = self.user_account(tenant_id, &u.name).await?;
+        self.throttled(
+            tenant_id,
+            &u.name,
+            account,
+            ip,
+            login,
+            |(_, _, two_factor)| !two_factor.enabled && !two_factor.required,
+        )
+        .await
+    }
+
+    /// the user giving the authenticator `code` after their password, the
+    /// failures count with the wrong passwords
+    pub async fn login_two_factor(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        code: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<User, accounting_api::Error> {
+        let storage = self.storage(tenant_id).await?;
+        let user = storage.get_user(user_id).await?;
+        let login = storage.verify_two_factor(user_id, code);
+        let account = Some(throttle::user_key(user_id));
+        self.throttled(Some(tenant_id), &user.name, account, ip, login, |_| true)
+            .await?;
+        Ok(user)
+    }
+
+    /// the company of the portal credentials, its tenant and its portal
+    /// session, the attempts from `ip` are throttled
+    pub async fn login_company(
+        &self,
+        c: &LoginCompany,
+        ip: Option<IpAddr>,
+    ) -> Result<(Uuid, Company, Uuid), accounting_api::Error> {
+        let tenant_id = match self.login_tenant(c.tenant.as_deref()).await {
+            Ok(tenant_id) => Some(tenant_id),
+            Err(accounting_api::Error::InvalidCredentials) => None,
+            Err(error) => return Err(error),
+        };
+        let login = async {
+            let tenant_id = tenant_id.ok_or(accounting_api::Error::InvalidCredentials)?;
+            let storage = self.storage(tenant_id).await?;
+            let company = storage.login_company(c).await?;
+            let session = portal_session(&storage, company.id).await?;
+            Ok((tenant_id, company, session))
+        };
+        let account = match tenant_id {
+            Some(tenant_id) => self.company_account(tenant_id, &c.username).await?,
+            None => None,
+        };
+        self.throttled(tenant_id, &c.username, account, ip, login, |_| true)
+            .await
+    }
+
+    /// sets the password of the user the admin issued `r.token` for, their
+    /// failed logins are forgotten
+    pub async fn reset_password(&self, r: &ResetPassword) -> Result<(), accounting_api::Error> {
+        let reset = sqlx::query!(
+            r#"
+                SELECT
+                    tenant_id,
+                    user_id
+                FROM
+                    password_resets
+                WHERE
+                    token_hash = $1
+            "#,
+            passwords::hash_reset_token(&r.token),
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(accounting_api::Error::InvalidResetToken)?;
+        self.storage(reset.tenant_id)
+            .await?
+            .reset_password(r)
+            .await?;
+        self.unlock_user(reset.user_id).await
+    }
+
+    pub async fn login_super_admin(
+        &self,
+        l: &LoginSuperAdmin,
+    ) -> Result<SuperAdmin, accounting_api::Error> {
+        let super_admin = sqlx::query!(
+            r#"
+                SELECT
+                    id,
+                    name,
+                    password
+                FROM
+                    super_admins
+                WHERE
+                    name = $1
+            "#,
+            &l.name,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        let hash = super_admin.as_ref().map(|s| s.password.as_str());
+        if !passwords::verify_password(&l.password, hash).await {
+            return Err(accounting_api::Error::InvalidCredentials);
+        }
+        let super_admin = super_admin.ok_or(accounting_api::Error::InvalidCredentials)?;
+        Ok(SuperAdmin {
+            id: super_admin.id,
+            name: super_admin.name,
+        })
+    }
+
+    /// creates the first super admin of the deployment, nothing is done once
+    /// there is one
+    pub async fn create_first_super_admin(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<(), accounting_api::Error> {
+        self.password_policy.check(password)?;
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    super_admins (name, password)
+                SELECT
+                    $1, $2
+                WHERE
+                    NOT EXISTS (SELECT FROM super_admins)
+            "#,
+            name,
+            passwords::hash_password(password).await,
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// hashes the passwords stored in plain text before they were hashed
+    pub async fn hash_passwords(&self) -> Result<(), accounting_api::Error> {
+        let super_admins = sqlx::query!(
+            r#"
+                SELECT
+                    id, password
+                FROM
+                    super_admins
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+        for super_admin in super_admins {
+            if passwords::is_hashed(&super_admin.password) {
+                continue;
+            }
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        super_admins
+                    SET
+                        password = $2
+                    WHERE
+                        id = $1
+                "#,
+                super_admin.id,
+                passwords::hash_password(&super_admin.password).await,
+            )
+            .execute(&self.db)
+            .await?;
+        }
+
+        let users = sqlx::query!(
+            r#"
+                SELECT
+                    id, password
+                FROM
+                    users
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+        for user in users {
+            if passwords::is_hashed(&user.password) {
+                continue;
+            }
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        users
+                    SET
+                        password = $2
+                    WHERE
+                        id = $1
+                "#,
+                user.id,
+                passwords::hash_password(&user.password).await,
+            )
+            .execute(&self.db)
+            .await?;
+        }
+
+        let companies = sqlx::query!(
+            r#"
+                SELECT
+                    id, password AS "password!"
+                FROM
+                    companies
+                WHERE
+                    password IS NOT NULL
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+        for company in companies {
+            if passwords::is_hashed(&company.password) {
+                continue;
+            }
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        companies
+                    SET
+                        password = $2
+                    WHERE
+                        id = $1
+                "#,
+                company.id,
+                passwords::hash_password(&company.password).await,
+            )
+            .execute(&self.db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// moves the documents stored before tenants, anything at the root that
+    /// is not the directory of a tenant, to the default tenant
+    pub async fn adopt_documents(&self) {
+        let tenant = sqlx::query!(
+            r#"
+                SELECT
+                    id
+                FROM
+                    tenants
+                WHERE
+                    name = $1
+            "#,
+            DEFAULT_TENANT,
+        )
+        .fetch_optional(&self.db)
+        .await;
+        let target = match tenant {
+            Ok(Some(tenant)) => self.root.join(tenant.id.to_string()),
+            Ok(None) => return,
+            Err(error) => return rocket::error!("[tenants] {error}"),
+        };
+        let mut entries = match fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name();
+            if name
+                .to_str()
+                .and_then(|name| name.parse::<Uuid>().ok())
+                .is_some()
+            {
+                continue;
+            }
+            rocket::info!("[tenants] moving {name:?} to the default tenant");
+            let moved = match fs::create_dir_all(&target).await {
+                Ok(()) => fs::rename(entry.path(), target.join(&name)).await,
+                Err(error) => Err(error),
+            };
+            if let Err(error) = moved {
+                rocket::error!("[tenants] {name:?}: {error}");
+            }
+        }
+    }
+}
+
+/// the storage of the tenant of the request token, looked up once per request
+struct RequestStorage(Result<Arc<LocalStorageAccountingApi>, (Status, ApiTokenError)>);
+
+async fn request_storage(
+    request: &Request<'_>,
+    tenant_id: Result<Uuid, ApiTokenError>,
+) -> Result<Arc<LocalStorageAccountingApi>, (Status, ApiTokenError)> {
+    let tenant_id = tenant_id.map_err(|e| (e.status(), e))?;
+    let tenants = request
+        .rocket()
+        .state::<Tenants>()
+        .expect("database stage attached");
+    match tenants.storage(tenant_id).await {
+        Ok(storage) => Ok(storage),
+        // the tenant was removed after the token was generated
+        Err(accounting_api::Error::ObjectNotFound) => {
+            let e = auth::fail(request, ApiTokenError::Invalid);
+            Err((e.status(), e))
+        }
+        Err(error) => {
+            rocket::error!("[tenants] {error}");
+            Err((Status::InternalServerError, ApiTokenError::Invalid))
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r LocalStorageAccountingApi {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let storage = &request
+            .local_cache_async(async {
+                RequestStorage(request_storage(request, auth::tenant(request).await).await)
+            })
+            .await
+            .0;
+        match storage {
+            Ok(storage) => Outcome::Success(storage),
+            Err(failure) => Outcome::Failure(*failure),
+        }
+    }
+}
+
+/// documented by the role guards next to it
+impl<'r> OpenApiFromRequest<'r> for &'r LocalStorageAccountingApi {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// the session of the company in the portal, replaced when its credentials
+/// change
+async fn portal_session(
+    storage: &LocalStorageAccountingApi,
+    company_id: Uuid,
+) -> Result<Uuid, accounting_api::Error> {
+    let session = sqlx::query!(
+        r#"
+            SELECT
+                portal_session
+            FROM
+                companies
+            WHERE
+                id = $1
+        "#,
+        company_id,
+    )
+    .fetch_one(&storage.db)
+    .await?
+    .portal_session;
+    Ok(session)
+}
+
+/// whether the company can still sign in to the portal with the `session` of
+/// its token, the admins take the access back by clearing its user name or
+/// removing it, a new password or user name signs the portal out
+async fn portal_access(
+    storage: &LocalStorageAccountingApi,
+    company_id: Uuid,
+    session: Uuid,
+) -> Result<bool, accounting_api::Error> {
+    let access = sqlx::query!(
+        r#"
+            SELECT
+                EXISTS (
+                    SELECT FROM
+                        companies
+                    WHERE
+                        id = $1
+                        AND portal_session = $2
+                        AND username IS NOT NULL
+                        AND password IS NOT NULL
+                ) AS "access!"
+        "#,
+        company_id,
+        session,
+    )
+    .fetch_one(&storage.db)
+    .await?
+    .access;
+    Ok(access)
+}
+
+/// the storage of the tenant of a portal token and the company signed in,
+/// the portal routes only read and write the rows of that company
+pub struct Portal<'r> {
+    pub storage: &'r LocalStorageAccountingApi,
+    pub company_id: Uuid,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Portal<'r> {
+    type Error = ApiTokenError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let (tenant_id, company_id, session) = match auth::company(request) {
+            Ok(company) => company,
+            Err(e) => return Outcome::Failure((e.status(), e)),
+        };
+        let storage = &request
+            .local_cache_async(async {
+                RequestStorage(request_storage(request, Ok(tenant_id)).await)
+            })
+            .await
+            .0;
+        let storage = match storage {
+            Ok(storage) => storage,
+            Err(failure) => return Outcome::Failure(*failure),
+        };
+        match portal_access(storage, company_id, session).await {
+            Ok(true) => Outcome::Success(Portal {
+                storage,
+                company_id,
+            }),
+            Ok(false) => {
+                let e = auth::fail(request, ApiTokenError::Revoked);
+                Outcome::Failure((e.status(), e))
+            }
+            Err(error) => {
+                rocket::error!("[portal] {error}");
+                Outcome::Failure((Status::InternalServerError, ApiTokenError::Invalid))
+            }
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for Portal<'r> {
+    fn from_request_input(
+        _: &mut OpenApiGenerator,
+        _: String,
+        _: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(auth::security(
+            "company",
+            "the token of a company signed in to the portal",
+        ))
+    }
+}
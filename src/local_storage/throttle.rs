@@ -0,0 +1,376 @@
+use std::{future::Future, net::IpAddr};
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::types::Uuid;
+
+use crate::accounting_api;
+
+use super::{models::*, Tenants};
+
+/// failures of a user or an address before their next attempts wait
+const FREE_FAILURES: i32 = 3;
+/// the wait after the first delayed failure, doubled by each next one
+const BASE_DELAY_SECS: i64 = 2;
+/// an address tries many user names, it is locked after this many times
+/// more failures than a user
+const IP_FAILURES_FACTOR: i32 = 5;
+
+/// how the failed logins of the staff slow the next attempts down
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    /// the failures locking a user out
+    pub max_failures: i32,
+    /// the length of a lockout, failures older than that are forgotten
+    pub lockout: Duration,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Throttle {
+            max_failures: 10,
+            lockout: Duration::minutes(15),
+        }
+    }
+}
+
+impl Throttle {
+    /// the wait before the next attempt after `failures` consecutive ones,
+    /// exponential once the free failures are used up
+    fn delay(&self, failures: i32, max_failures: i32) -> Duration {
+        if failures >= max_failures {
+            return self.lockout;
+        }
+        if failures <= FREE_FAILURES {
+            return Duration::zero();
+        }
+        let doublings = (failures - FREE_FAILURES - 1).min(30) as u32;
+        Duration::seconds(BASE_DELAY_SECS.saturating_mul(2_i64.pow(doublings))).min(self.lockout)
+    }
+}
+
+pub(super) fn user_key(user_id: Uuid) -> String {
+    format!("user:{user_id}")
+}
+
+fn company_key(company_id: Uuid) -> String {
+    format!("company:{company_id}")
+}
+
+fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{ip}")
+}
+
+/// an attempt counted as a failure before the credentials are checked, the
+/// concurrent attempts wait as if it failed until it is released
+struct Reservation {
+    key: String,
+    time: DateTime<Utc>,
+    /// the lock of the key before the attempt
+    locked_until: DateTime<Utc>,
+}
+
+impl Tenants {
+    /// runs `login` unless `account`, the key of the user or the company
+    /// signing in, or the address is locked out, a failure delays their next
+    /// attempts, a success clears the failures of `account` once `done` tells
+    /// it signed them in, every attempt is logged under `name`
+    pub(super) async fn throttled<T, F>(
+        &self,
+        tenant_id: Option<Uuid>,
+        name: &str,
+        account: Option<String>,
+        ip: Option<IpAddr>,
+        login: F,
+        done: impl FnOnce(&T) -> bool,
+    ) -> Result<T, accounting_api::Error>
+    where
+        F: Future<Output = Result<T, accounting_api::Error>>,
+    {
+        let keys: Vec<(String, i32)> = account
+            .iter()
+            .map(|account| (account.clone(), self.throttle.max_failures))
+            .chain(ip.map(|ip| (ip_key(ip), self.throttle.max_failures * IP_FAILURES_FACTOR)))
+            .collect();
+
+        let reservations = match self.reserve(&keys).await {
+            Ok(reservations) => reservations,
+            Err(error @ accounting_api::Error::LoginLocked(_)) => {
+                self.log_attempt(tenant_id, name, ip, LoginOutcome::Locked)
+                    .await?;
+                return Err(error);
+            }
+            Err(error) => return Err(error),
+        };
+
+        match login.await {
+            Ok(value) => {
+                self.log_attempt(tenant_id, name, ip, LoginOutcome::Succeeded)
+                    .await?;
+                let signed_in = done(&value);
+                for reservation in reservations {
+                    if signed_in && account.as_ref() == Some(&reservation.key) {
+                        self.unlock(&reservation.key).await?
+                    } else {
+                        self.release(reservation).await?
+                    }
+                }
+                Ok(value)
+            }
+            Err(
+                error @ (accounting_api::Error::InvalidCredentials
+                | accounting_api::Error::InvalidTwoFactorCode),
+            ) => {
+                self.log_attempt(tenant_id, name, ip, LoginOutcome::Failed)
+                    .await?;
+                Err(error)
+            }
+            Err(error) => {
+                for reservation in reservations {
+                    self.release(reservation).await?;
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// counts the attempt as a failure of every key with its `max_failures`
+    /// unless one of them is locked out, the keys are locked while they are
+    /// checked so the concurrent attempts are counted one after the other
+    async fn reserve(
+        &self,
+        keys: &[(String, i32)],
+    ) -> Result<Vec<Reservation>, accounting_api::Error> {
+        // the database keeps microseconds, a reservation is found by its time
+        let now = Utc::now();
+        let now = now - Duration::nanoseconds(now.timestamp_subsec_nanos() as i64 % 1000);
+        let names: Vec<&str> = keys.iter().map(|(key, _)| key.as_str()).collect();
+        let mut transaction = self.db.begin().await?;
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    login_throttles (key, failures, last_failure, locked_until)
+                SELECT
+                    key, 0, $2, $2
+                FROM
+                    UNNEST($1::VARCHAR[]) AS key
+                ON CONFLICT (key) DO NOTHING
+            "#,
+            &names as _,
+            now,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let throttles = sqlx::query!(
+            r#"
+                SELECT
+                    key, failures, last_failure, locked_until
+                FROM
+                    login_throttles
+                WHERE
+                    key = ANY($1)
+                ORDER BY
+                    key
+                FOR UPDATE
+            "#,
+            &names as _,
+        )
+        .fetch_all(&mut transaction)
+        .await?;
+
+        if let Some(locked_until) = throttles
+            .iter()
+            .map(|throttle| throttle.locked_until)
+            .max()
+            .filter(|until| *until > now)
+        {
+            transaction.commit().await?;
+            let wait = (locked_until - now).num_seconds() + 1;
+            return Err(accounting_api::Error::LoginLocked(wait));
+        }
+
+        let mut reservations = vec![];
+        for throttle in throttles {
+            let max_failures = keys
+                .iter()
+                .find(|(key, _)| *key == throttle.key)
+                .map(|(_, max_failures)| *max_failures)
+                .unwrap_or(self.throttle.max_failures);
+            let failures = if throttle.last_failure < now - self.throttle.lockout {
+                1
+            } else {
+                throttle.failures + 1
+            };
+            sqlx::query!(
+                r#"
+                    UPDATE
+                        login_throttles
+                    SET
+                        failures = $2,
+                        last_failure = $3,
+                        locked_until = $4
+                    WHERE
+                        key = $1
+                "#,
+                &throttle.key,
+                failures,
+                now,
+                now + self.throttle.delay(failures, max_failures),
+            )
+            .execute(&mut transaction)
+            .await?;
+            reservations.push(Reservation {
+                key: throttle.key,
+                time: now,
+                locked_until: throttle.locked_until,
+            });
+        }
+        transaction.commit().await?;
+        Ok(reservations)
+    }
+
+    /// takes back the failure counted by `reservation`, the lock it set is
+    /// lifted unless a later attempt was counted after it
+    async fn release(&self, reservation: Reservation) -> Result<(), accounting_api::Error> {
+        sqlx::query!(
+            r#"
+                UPDATE
+                    login_throttles
+                SET
+                    failures = GREATEST(failures - 1, 0),
+                    locked_until = CASE
+                        WHEN last_failure = $2 THEN $3
+                        ELSE locked_until
+                    END
+                WHERE
+                    key = $1
+            "#,
+            reservation.key,
+            reservation.time,
+            reservation.locked_until,
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// clears the failures of the user, their next login is not delayed
+    pub async fn unlock_user(&self, user_id: Uuid) -> Result<(), accounting_api::Error> {
+        self.unlock(&user_key(user_id)).await
+    }
+
+    async fn unlock(&self, key: &str) -> Result<(), accounting_api::Error> {
+        sqlx::query!(
+            r#"
+                DELETE FROM
+                    login_throttles
+                WHERE
+                    key = $1
+            "#,
+            key,
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// the key of the user named `name` in the tenant, `None` for an unknown
+    /// name or tenant
+    pub(super) async fn user_account(
+        &self,
+        tenant_id: Option<Uuid>,
+        name: &str,
+    ) -> Result<Option<String>, accounting_api::Error> {
+        let tenant_id = match tenant_id {
+            Some(tenant_id) => tenant_id,
+            None => return Ok(None),
+        };
+        let user = sqlx::query!(
+            r#"
+                SELECT
+                    id
+                FROM
+                    users
+                WHERE
+                    tenant_id = $1 AND name = $2
+            "#,
+            tenant_id,
+            name,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(user.map(|user| user_key(user.id)))
+    }
+
+    /// the key of the company signing in to the portal as `username`, `None`
+    /// for an unknown one
+    pub(super) async fn company_account(
+        &self,
+        tenant_id: Uuid,
+        username: &str,
+    ) -> Result<Option<String>, accounting_api::Error> {
+        let company = sqlx::query!(
+            r#"
+                SELECT
+                    id
+                FROM
+                    companies
+                WHERE
+                    tenant_id = $1 AND username = $2
+            "#,
+            tenant_id,
+            username,
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(company.map(|company| company_key(company.id)))
+    }
+
+    async fn log_attempt(
+        &self,
+        tenant_id: Option<Uuid>,
+        name: &str,
+        ip: Option<IpAddr>,
+        outcome: LoginOutcome,
+    ) -> Result<(), accounting_api::Error> {
+        sqlx::query!(
+            r#"
+                INSERT INTO
+                    login_attempts (tenant_id, name, ip, outcome)
+                VALUES
+                    ($1, $2, $3, $4)
+            "#,
+            tenant_id,
+            name,
+            ip.map(|ip| ip.to_string()),
+            outcome as _,
+        )
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_double_after_the_free_failures() {
+        let throttle = Throttle::default();
+        let delays: Vec<i64> = (1..=10)
+            .map(|failures| throttle.delay(failures, 10).num_seconds())
+            .collect();
+        assert_eq!(delays, [0, 0, 0, 2, 4, 8, 16, 32, 64, 900]);
+    }
+
+    #[test]
+    fn delays_never_exceed_the_lockout() {
+        let throttle = Throttle {
+            max_failures: 100,
+            lockout: Duration::minutes(1),
+        };
+        assert_eq!(throttle.delay(20, 100), Duration::minutes(1));
+        assert_eq!(throttle.delay(99, 100), Duration::minutes(1));
+        assert_eq!(throttle.delay(i32::MAX - 1, i32::MAX), Duration::minutes(1));
+    }
+}
@@ -1,7 +1,9 @@
 #[macro_use]
 extern crate rocket;
 
-use accounting_backend::{local_storage, routes};
+use accounting_backend::{
+    auth, catchers, docs, eta, events, i18n, local_storage, notifications, routes, scheduler,
+};
 
 #[launch]
 fn rocket() -> _ {
@@ -10,6 +12,14 @@ fn rocket() -> _ {
     }
 
     rocket::build()
+        .attach(auth::stage())
         .attach(local_storage::stage())
+        .attach(events::stage())
+        .attach(i18n::stage())
+        .attach(catchers::stage())
+        .attach(docs::stage())
         .attach(routes::stage())
+        .attach(scheduler::stage())
+        .attach(eta::stage())
+        .attach(notifications::stage())
 }
@@ -0,0 +1,148 @@
+pub mod templates;
+
+use std::{env, time::Duration};
+
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use rocket::{
+    fairing::AdHoc,
+    tokio::{self, select, time},
+};
+
+use crate::{
+    accounting_api::AcountingApi,
+    local_storage::{models::PendingEmail, LocalStorageAccountingApi, Tenants},
+};
+
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+/// emails failing this many times are given up, the error stays on the notification
+const MAX_EMAIL_ATTEMPTS: i32 = 5;
+
+/// sends the queued notification emails over SMTP, disabled until
+/// `SMTP_HOST` is set
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    fn from_env() -> Option<Self> {
+        let host = env::var("SMTP_HOST").ok()?;
+        let builder = match env::var("SMTP_TLS").as_deref() {
+            Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            Ok("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+                .expect("`SMTP_HOST` must be a valid host name"),
+            Ok("starttls") | Err(_) => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+                .expect("`SMTP_HOST` must be a valid host name"),
+            Ok(_) => panic!("`SMTP_TLS` must be one of none, starttls or tls"),
+        };
+        let builder = match env::var("SMTP_PORT") {
+            Ok(port) => builder.port(port.parse().expect("`SMTP_PORT` must be a number")),
+            Err(_) => builder,
+        };
+        let builder = match (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => {
+                builder.credentials(Credentials::new(username, password))
+            }
+            _ => builder,
+        };
+        let from = env::var("SMTP_FROM")
+            .expect("`SMTP_FROM` must be set")
+            .parse()
+            .expect("`SMTP_FROM` must be an email address");
+
+        Some(Mailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+
+    pub async fn send(&self, email: &PendingEmail) -> Result<(), String> {
+        let to = email
+            .email
+            .parse::<Mailbox>()
+            .map_err(|error| error.to_string())?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.title)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())
+            .map_err(|error| error.to_string())?;
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|error| error.to_string())
+    }
+}
+
+async fn deliver(mailer: &Mailer, storage: &LocalStorageAccountingApi) {
+    let emails = match storage.get_pending_emails(MAX_EMAIL_ATTEMPTS).await {
+        Ok(emails) => emails,
+        Err(error) => return rocket::error!("[mailer] {error}"),
+    };
+    for email in emails {
+        let result = mailer.send(&email).await;
+        if let Err(error) = &result {
+            rocket::warn!(
+                "[mailer] attempt {} to {} failed: {error}",
+                email.attempts + 1,
+                email.email
+            );
+        }
+        if let Err(error) = storage
+            .mark_email_sent(email.id, result.err().as_deref())
+            .await
+        {
+            rocket::error!("[mailer] {error}");
+        }
+    }
+}
+
+/// delivers the notification emails in the background, the in-app inbox
+/// works without it
+pub fn stage() -> AdHoc {
+    AdHoc::on_liftoff("mailer stage", |rocket| {
+        Box::pin(async move {
+            let mailer = match Mailer::from_env() {
+                Some(mailer) => mailer,
+                None => {
+                    return rocket::warn!("`SMTP_HOST` is not set, notifications are not emailed")
+                }
+            };
+            let tenants = rocket
+                .state::<Tenants>()
+                .expect("database stage attached")
+                .clone();
+            let interval = env::var("MAIL_INTERVAL_SECS")
+                .ok()
+                .map(|v| v.parse().expect("`MAIL_INTERVAL_SECS` must be a number"))
+                .unwrap_or(DEFAULT_INTERVAL_SECS);
+            let mut shutdown = rocket.shutdown();
+
+            tokio::spawn(async move {
+                let mut interval = time::interval(Duration::from_secs(interval));
+                loop {
+                    select! {
+                        _ = interval.tick() => match tenants.get_tenants().await {
+                            Ok(offices) => {
+                                for tenant in offices {
+                                    match tenants.storage(tenant.id).await {
+                                        Ok(storage) => deliver(&mailer, &storage).await,
+                                        Err(error) => rocket::error!("[mailer] {error}"),
+                                    }
+                                }
+                            }
+                            Err(error) => rocket::error!("[mailer] {error}"),
+                        },
+                        _ = &mut shutdown => break,
+                    }
+                }
+            });
+        })
+    })
+}
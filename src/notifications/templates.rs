@@ -0,0 +1,236 @@
+use chrono::NaiveDate;
+
+use crate::local_storage::models::{Language, NotificationKind};
+
+/// the messages sent by the backend, rendered in the recipient language
+#[derive(Debug)]
+pub enum Template<'a> {
+    DeadlineApproaching {
+        company: &'a str,
+        title: &'a str,
+        due_date: NaiveDate,
+    },
+    /// to the employee who submitted the expense
+    ExpenseSubmitted {
+        description: &'a str,
+        value: f64,
+    },
+    /// to the reviewers
+    ExpenseAwaitingReview {
+        user: &'a str,
+        company: &'a str,
+        description: &'a str,
+        value: f64,
+    },
+    ExpenseApproved {
+        description: &'a str,
+        value: f64,
+        comment: Option<&'a str>,
+    },
+    ExpenseRejected {
+        description: &'a str,
+        value: f64,
+        comment: Option<&'a str>,
+    },
+    LowCustody {
+        user: &'a str,
+        available: f64,
+    },
+    NewDocument {
+        company: &'a str,
+        name: &'a str,
+    },
+    ScheduleStopped {
+        description: &'a str,
+        occurrence: NaiveDate,
+    },
+}
+
+fn comment(comment: Option<&str>) -> String {
+    comment.map(|c| format!(": {c}")).unwrap_or_default()
+}
+
+impl Template<'_> {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            Self::DeadlineApproaching { .. } => NotificationKind::Deadline,
+            Self::ExpenseSubmitted { .. } | Self::ExpenseAwaitingReview { .. } => {
+                NotificationKind::PendingApproval
+            }
+            Self::ExpenseApproved { .. } | Self::ExpenseRejected { .. } => {
+                NotificationKind::ExpenseReview
+            }
+            Self::LowCustody { .. } => NotificationKind::LowCustody,
+            Self::NewDocument { .. } => NotificationKind::NewDocument,
+            Self::ScheduleStopped { .. } => NotificationKind::ScheduleStopped,
+        }
+    }
+
+    /// the title and body of the message
+    pub fn render(&self, language: Language) -> (String, String) {
+        match (self, language) {
+            (
+                Self::DeadlineApproaching {
+                    company,
+                    title,
+                    due_date,
+                },
+                Language::Ar,
+            ) => (
+                format!("موعد قادم: {title}"),
+                format!("يحل موعد \"{title}\" للشركة \"{company}\" في {due_date}"),
+            ),
+            (
+                Self::DeadlineApproaching {
+                    company,
+                    title,
+                    due_date,
+                },
+                Language::En,
+            ) => (
+                format!("Upcoming deadline: {title}"),
+                format!("\"{title}\" of \"{company}\" is due on {due_date}"),
+            ),
+            (Self::ExpenseSubmitted { description, value }, Language::Ar) => (
+                "مصروف في انتظار المراجعة".to_owned(),
+                format!("المصروف \"{description}\" بقيمة {value} في انتظار موافقة المراجع"),
+            ),
+            (Self::ExpenseSubmitted { description, value }, Language::En) => (
+                "Expense pending review".to_owned(),
+                format!("The expense \"{description}\" of {value} is waiting for a reviewer"),
+            ),
+            (
+                Self::ExpenseAwaitingReview {
+                    user,
+                    company,
+                    description,
+                    value,
+                },
+                Language::Ar,
+            ) => (
+                "مصروف يحتاج الي موافقتك".to_owned(),
+                format!(
+                    "قام {user} بتسجيل المصروف \"{description}\" بقيمة {value} علي الشركة \"{company}\""
+                ),
+            ),
+            (
+                Self::ExpenseAwaitingReview {
+                    user,
+                    company,
+                    description,
+                    value,
+                },
+                Language::En,
+            ) => (
+                "Expense awaiting your approval".to_owned(),
+                format!("{user} recorded the expense \"{description}\" of {value} for \"{company}\""),
+            ),
+            (
+                Self::ExpenseApproved {
+                    description,
+                    value,
+                    comment: c,
+                },
+                Language::Ar,
+            ) => (
+                "تمت الموافقة علي المصروف".to_owned(),
+                format!(
+                    "تمت الموافقة علي المصروف \"{description}\" بقيمة {value}{}",
+                    comment(*c)
+                ),
+            ),
+            (
+                Self::ExpenseApproved {
+                    description,
+                    value,
+                    comment: c,
+                },
+                Language::En,
+            ) => (
+                "Expense approved".to_owned(),
+                format!(
+                    "The expense \"{description}\" of {value} was approved{}",
+                    comment(*c)
+                ),
+            ),
+            (
+                Self::ExpenseRejected {
+                    description,
+                    value,
+                    comment: c,
+                },
+                Language::Ar,
+            ) => (
+                "تم رفض المصروف".to_owned(),
+                format!(
+                    "تم رفض المصروف \"{description}\" بقيمة {value}{}",
+                    comment(*c)
+                ),
+            ),
+            (
+                Self::ExpenseRejected {
+                    description,
+                    value,
+                    comment: c,
+                },
+                Language::En,
+            ) => (
+                "Expense rejected".to_owned(),
+                format!(
+                    "The expense \"{description}\" of {value} was rejected{}",
+                    comment(*c)
+                ),
+            ),
+            (Self::LowCustody { user, available }, Language::Ar) => (
+                "رصيد العهدة منخفض".to_owned(),
+                format!("الرصيد المتاح في عهدة {user} اصبح {available:.2}"),
+            ),
+            (Self::LowCustody { user, available }, Language::En) => (
+                "Low custody balance".to_owned(),
+                format!("The available custody of {user} is down to {available:.2}"),
+            ),
+            (Self::NewDocument { company, name }, Language::Ar) => (
+                "مستند جديد".to_owned(),
+                format!("تم اضافة المستند \"{name}\" الي ملف الشركة \"{company}\""),
+            ),
+            (Self::NewDocument { company, name }, Language::En) => (
+                "New document".to_owned(),
+                format!("The document \"{name}\" was added to the file of \"{company}\""),
+            ),
+            (
+                Self::ScheduleStopped {
+                    description,
+                    occurrence,
+                },
+                Language::Ar,
+            ) => (
+                "تم ايقاف مصروف متكرر".to_owned(),
+                format!(
+                    "لا يوجد قيمة كافية في العهدة للمصروف المتكرر \"{description}\" بتاريخ {occurrence}"
+                ),
+            ),
+            (
+                Self::ScheduleStopped {
+                    description,
+                    occurrence,
+                },
+                Language::En,
+            ) => (
+                "Recurring expense stopped".to_owned(),
+                format!(
+                    "The custody is not enough for the recurring expense \"{description}\" of {occurrence}"
+                ),
+            ),
+        }
+    }
+
+    /// both languages, for recipients without a preference
+    pub fn render_bilingual(&self) -> (String, String) {
+        let (ar_title, ar_body) = self.render(Language::Ar);
+        let (en_title, en_body) = self.render(Language::En);
+        (
+            format!("{ar_title} | {en_title}"),
+            format!("{ar_body}\n\n{en_body}"),
+        )
+    }
+}
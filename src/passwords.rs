@@ -0,0 +1,144 @@
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use rocket::tokio::task;
+
+use crate::{accounting_api, hashing};
+
+/// the passwords of the public breach dumps, one per line
+const BREACHED: &str = include_str!("../assets/passwords/breached.txt");
+const RESET_TOKEN_LEN: usize = 32;
+/// how long an admin issued reset token can be used
+pub const RESET_TOKEN_HOURS: i64 = 24;
+
+/// what the new passwords of the users must satisfy
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordPolicy {
+    /// in characters
+    pub min_length: usize,
+    /// refuses the passwords of the bundled breach list
+    pub check_breached: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 8,
+            check_breached: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn check(&self, password: &str) -> Result<(), accounting_api::Error> {
+        if password.chars().count() < self.min_length {
+            return Err(accounting_api::Error::PasswordTooShort(self.min_length));
+        }
+        if self.check_breached && breached(password) {
+            return Err(accounting_api::Error::BreachedPassword);
+        }
+        Ok(())
+    }
+}
+
+/// whether `password` is in the breach list, ignoring case
+fn breached(password: &str) -> bool {
+    let password = password.to_lowercase();
+    BREACHED
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .any(|line| line == password)
+}
+
+/// the stored form of a password, an argon2id hash with its own salt
+pub async fn hash_password(password: &str) -> String {
+    let password = password.to_owned();
+    task::spawn_blocking(move || hash(&password))
+        .await
+        .expect("the password hash task does not panic")
+}
+
+fn hash(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("the default argon2 parameters are valid")
+        .to_string()
+}
+
+/// whether `password` is the one of the stored `hash`, an unknown user has no
+/// hash and is checked against a made up one so it takes as long
+pub async fn verify_password(password: &str, hash: Option<&str>) -> bool {
+    static UNKNOWN: OnceLock<String> = OnceLock::new();
+    let (password, hash) = (password.to_owned(), hash.map(str::to_owned));
+    task::spawn_blocking(move || {
+        let known = hash.is_some();
+        let hash = hash.unwrap_or_else(|| UNKNOWN.get_or_init(|| self::hash("unknown")).clone());
+        let matches = PasswordHash::new(&hash)
+            .map(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+            .unwrap_or(false);
+        known && matches
+    })
+    .await
+    .expect("the password hash task does not panic")
+}
+
+/// whether `stored` is a hash, the passwords stored before hashing are
+/// plain text
+pub fn is_hashed(stored: &str) -> bool {
+    stored.starts_with("$argon2")
+}
+
+/// a new single use reset token, shown to the admin once
+pub fn reset_token() -> String {
+    rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(RESET_TOKEN_LEN)
+        .map(char::from)
+        .collect()
+}
+
+/// the stored form of a reset token
+pub fn hash_reset_token(token: &str) -> String {
+    hashing::sha256_hex(token.trim().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn hashes_are_salted_and_verified() {
+        let first = hash_password("correct horse battery").await;
+        let second = hash_password("correct horse battery").await;
+        assert!(is_hashed(&first));
+        assert_ne!(first, second);
+        assert!(verify_password("correct horse battery", Some(&first)).await);
+        assert!(!verify_password("correct horse", Some(&first)).await);
+        // a plain text password is never compared as it is
+        assert!(!verify_password("correct horse battery", Some("correct horse battery")).await);
+        assert!(!verify_password("unknown", None).await);
+    }
+
+    #[test]
+    fn policy_refuses_short_and_breached_passwords() {
+        let policy = PasswordPolicy::default();
+        assert!(matches!(
+            policy.check("short"),
+            Err(accounting_api::Error::PasswordTooShort(8))
+        ));
+        assert!(matches!(
+            policy.check("Password123"),
+            Err(accounting_api::Error::BreachedPassword)
+        ));
+        assert!(policy.check("correct horse battery").is_ok());
+    }
+}
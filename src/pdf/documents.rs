@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+
+use crate::local_storage::models::{
+    Company, CompanyStatementEntry, CustodyKind, CustodyTransaction, Income, Invoice,
+    InvoiceStatus, User,
+};
+
+use super::{Column, DocumentKind, PdfDocument};
+
+/// `1234567.5` as `1,234,567.50`
+fn amount(value: f64) -> String {
+    let formatted = format!("{:.2}", value.abs());
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, "00"));
+    let mut grouped = String::new();
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+    format!("{sign}{grouped}.{fraction}")
+}
+
+fn date(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d").to_string()
+}
+
+fn invoice_status(status: InvoiceStatus) -> &'static str {
+    match status {
+        InvoiceStatus::Draft => "مسودة",
+        InvoiceStatus::Issued => "مصدرة",
+        InvoiceStatus::PartiallyPaid => "مسددة جزئيا",
+        InvoiceStatus::Paid => "مسددة",
+        InvoiceStatus::Void => "ملغاة",
+    }
+}
+
+fn custody_kind(kind: CustodyKind) -> &'static str {
+    match kind {
+        CustodyKind::TopUp => "صرف عهدة",
+        CustodyKind::Expense => "مصروف",
+        CustodyKind::Refund => "استرداد",
+        CustodyKind::Adjustment => "تسوية",
+    }
+}
+
+impl PdfDocument {
+    pub fn invoice(invoice: &Invoice) -> Self {
+        let mut fields = vec![
+            (
+                "رقم الفاتورة".into(),
+                invoice.number.clone().unwrap_or_else(|| "-".into()),
+            ),
+            ("الشركة".into(), invoice.company.clone()),
+            (
+                "تاريخ الاصدار".into(),
+                invoice
+                    .issue_date
+                    .map(|date| date.to_string())
+                    .unwrap_or_else(|| "-".into()),
+            ),
+            ("تاريخ الاستحقاق".into(), invoice.due_date.to_string()),
+            ("الحالة".into(), invoice_status(invoice.status).into()),
+        ];
+        if let Some(notes) = &invoice.notes {
+            fields.push(("ملاحظات".into(), notes.clone()));
+        }
+
+        Self {
+            kind: DocumentKind::Invoice,
+            fields,
+            columns: vec![
+                Column::text("البيان", 0.49),
+                Column::numeric("الكمية", 0.13),
+                Column::numeric("سعر الوحدة", 0.19),
+                Column::numeric("الاجمالي", 0.19),
+            ],
+            rows: invoice
+                .lines
+                .iter()
+                .map(|line| {
+                    vec![
+                        line.description.clone(),
+                        line.quantity.to_string(),
+                        amount(line.unit_price),
+                        amount(line.quantity * line.unit_price),
+                    ]
+                })
+                .collect(),
+            totals: vec![
+                ("الاجمالي".into(), amount(invoice.total)),
+                ("المدفوع".into(), amount(invoice.paid)),
+                ("المتبقي".into(), amount(invoice.total - invoice.paid)),
+            ],
+        }
+    }
+
+    pub fn receipt(income: &Income) -> Self {
+        Self {
+            kind: DocumentKind::Receipt,
+            fields: vec![
+                ("رقم الايصال".into(), income.id.to_string()),
+                ("التاريخ".into(), date(&income.time)),
+                ("استلمنا من".into(), income.company.clone()),
+                ("وذلك عن".into(), income.description.clone()),
+                ("المستلم".into(), income.admin.clone()),
+            ],
+            columns: vec![],
+            rows: vec![],
+            totals: vec![("المبلغ".into(), amount(income.value))],
+        }
+    }
+
+    pub fn custody_statement(user: &User, transactions: &[CustodyTransaction]) -> Self {
+        Self {
+            kind: DocumentKind::CustodyStatement,
+            fields: vec![
+                ("الموظف".into(), user.name.clone()),
+                ("تاريخ الكشف".into(), date(&Utc::now())),
+            ],
+            columns: vec![
+                Column::numeric("التاريخ", 0.14),
+                Column::text("النوع", 0.13),
+                Column::text("البيان", 0.31),
+                Column::text("بواسطة", 0.14),
+                Column::numeric("القيمة", 0.14),
+                Column::numeric("الرصيد", 0.14),
+            ],
+            rows: transactions
+                .iter()
+                .map(|transaction| {
+                    vec![
+                        date(&transaction.time),
+                        custody_kind(transaction.kind).into(),
+                        transaction.note.clone().unwrap_or_default(),
+                        transaction.actor.clone().unwrap_or_default(),
+                        amount(transaction.value),
+                        amount(transaction.balance),
+                    ]
+                })
+                .collect(),
+            totals: vec![
+                ("رصيد العهدة".into(), amount(user.value)),
+                ("مبالغ محجوزة".into(), amount(user.reserved)),
+            ],
+        }
+    }
+
+    pub fn company_statement(company: &Company, entries: &[CompanyStatementEntry]) -> Self {
+        let mut fields = vec![
+            ("الاسم التجاري".into(), company.commercial_feature.clone()),
+            ("المالك".into(), company.owner.clone()),
+        ];
+        if let Some(file_number) = &company.file_number {
+            fields.push(("رقم الملف".into(), file_number.clone()));
+        }
+        fields.push(("تاريخ الكشف".into(), date(&Utc::now())));
+
+        let debit = entries.iter().map(|entry| entry.debit).sum::<f64>();
+        let credit = entries.iter().map(|entry| entry.credit).sum::<f64>();
+
+        Self {
+            kind: DocumentKind::CompanyStatement,
+            fields,
+            columns: vec![
+                Column::numeric("التاريخ", 0.14),
+                Column::text("البيان", 0.3),
+                Column::text("بواسطة", 0.14),
+                Column::numeric("مدين", 0.14),
+                Column::numeric("دائن", 0.14),
+                Column::numeric("الرصيد", 0.14),
+            ],
+            rows: entries
+                .iter()
+                .map(|entry| {
+                    vec![
+                        date(&entry.time),
+                        entry.description.clone(),
+                        entry.actor.clone().unwrap_or_default(),
+                        amount(entry.debit),
+                        amount(entry.credit),
+                        amount(entry.balance),
+                    ]
+                })
+                .collect(),
+            totals: vec![
+                ("اجمالي المدين".into(), amount(debit)),
+                ("اجمالي الدائن".into(), amount(credit)),
+                ("الرصيد".into(), amount(credit - debit)),
+            ],
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+
+use pdf_writer::{
+    types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap},
+    Finish, Name, Pdf, Rect, Ref, Str,
+};
+use rustybuzz::{ttf_parser::GlyphId, Direction, Face, UnicodeBuffer};
+use subsetter::GlyphRemapper;
+use unicode_bidi::{BidiInfo, Level};
+
+const SYSTEM_INFO: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+/// a glyph placed on a line, `id` is the glyph id inside the embedded subset
+/// and the offsets are in points from the start of the line
+#[derive(Debug)]
+pub struct Glyph {
+    pub id: u16,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug)]
+pub struct Line {
+    pub glyphs: Vec<Glyph>,
+    pub width: f32,
+}
+
+/// a glyph in font units before it is added to the subset
+struct RawGlyph<'t> {
+    id: u16,
+    x: f32,
+    y: f32,
+    text: &'t str,
+}
+
+/// a bundled font shaped with harfbuzz and embedded as a subsetted CID font
+pub struct Font {
+    name: &'static str,
+    /// six upper case letters prefixed to the name of a subsetted font
+    tag: &'static str,
+    data: &'static [u8],
+    face: Face<'static>,
+    remapper: GlyphRemapper,
+    /// the text every embedded glyph stands for, used to make the text
+    /// searchable and copyable
+    texts: BTreeMap<u16, String>,
+}
+
+impl Font {
+    pub fn regular() -> Self {
+        Self::new(
+            "DejaVuSans",
+            "ACCREG",
+            include_bytes!("../../assets/fonts/DejaVuSans.ttf"),
+        )
+    }
+
+    pub fn bold() -> Self {
+        Self::new(
+            "DejaVuSans-Bold",
+            "ACCBLD",
+            include_bytes!("../../assets/fonts/DejaVuSans-Bold.ttf"),
+        )
+    }
+
+    fn new(name: &'static str, tag: &'static str, data: &'static [u8]) -> Self {
+        Self {
+            name,
+            tag,
+            data,
+            face: Face::from_slice(data, 0).expect("valid bundled font"),
+            remapper: GlyphRemapper::new(),
+            texts: BTreeMap::new(),
+        }
+    }
+
+    fn scale(&self, size: f32) -> f32 {
+        size / self.face.units_per_em() as f32
+    }
+
+    /// reorders `text` into visual runs of a right to left paragraph and
+    /// shapes every run in its own direction, so arabic letters are joined and
+    /// mixed latin words and numbers keep their order
+    fn layout<'t>(&self, text: &'t str) -> (Vec<RawGlyph<'t>>, f32) {
+        let mut glyphs = vec![];
+        let mut x = 0.0;
+        let bidi = BidiInfo::new(text, Some(Level::rtl()));
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for run in runs {
+                let part = &text[run.clone()];
+                let mut buffer = UnicodeBuffer::new();
+                buffer.push_str(part);
+                buffer.set_direction(if levels[run.start].is_rtl() {
+                    Direction::RightToLeft
+                } else {
+                    Direction::LeftToRight
+                });
+                let output = rustybuzz::shape(&self.face, &[], buffer);
+
+                let mut clusters = output
+                    .glyph_infos()
+                    .iter()
+                    .map(|info| info.cluster as usize)
+                    .collect::<Vec<_>>();
+                clusters.push(part.len());
+                clusters.sort_unstable();
+                clusters.dedup();
+
+                for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+                    let start = info.cluster as usize;
+                    let end = clusters
+                        .iter()
+                        .find(|&&cluster| cluster > start)
+                        .copied()
+                        .unwrap_or(part.len());
+                    glyphs.push(RawGlyph {
+                        id: info.glyph_id as u16,
+                        x: x + position.x_offset as f32,
+                        y: position.y_offset as f32,
+                        text: &part[start..end],
+                    });
+                    x += position.x_advance as f32;
+                }
+            }
+        }
+        (glyphs, x)
+    }
+
+    pub fn width(&self, text: &str, size: f32) -> f32 {
+        self.layout(text).1 * self.scale(size)
+    }
+
+    /// shapes `text` and adds its glyphs to the embedded subset
+    pub fn shape(&mut self, text: &str, size: f32) -> Line {
+        let scale = self.scale(size);
+        let (raw, width) = self.layout(text);
+        let glyphs = raw
+            .into_iter()
+            .map(|glyph| {
+                let id = self.remapper.remap(glyph.id);
+                self.texts
+                    .entry(id)
+                    .or_insert_with(|| glyph.text.to_owned());
+                Glyph {
+                    id,
+                    x: glyph.x * scale,
+                    y: glyph.y * scale,
+                }
+            })
+            .collect();
+        Line {
+            glyphs,
+            width: width * scale,
+        }
+    }
+
+    /// writes the font as a `Type0` font referenced by `id`, the other objects
+    /// it needs are allocated from `next`
+    pub fn embed(&self, pdf: &mut Pdf, id: Ref, next: &mut Ref) {
+        let cid_id = next.bump();
+        let descriptor_id = next.bump();
+        let file_id = next.bump();
+        let cmap_id = next.bump();
+
+        let base_font = format!("{}+{}", self.tag, self.name);
+        let base_font = Name(base_font.as_bytes());
+        let units = 1000.0 / self.face.units_per_em() as f32;
+
+        pdf.type0_font(id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_id)
+            .to_unicode(cmap_id);
+
+        let widths = self
+            .remapper
+            .remapped_gids()
+            .map(|gid| self.face.glyph_hor_advance(GlyphId(gid)).unwrap_or(0) as f32 * units)
+            .collect::<Vec<_>>();
+        let mut cid = pdf.cid_font(cid_id);
+        cid.subtype(CidFontType::Type2)
+            .base_font(base_font)
+            .system_info(SYSTEM_INFO)
+            .font_descriptor(descriptor_id)
+            .default_width(0.0)
+            .cid_to_gid_map_predefined(Name(b"Identity"));
+        cid.widths().consecutive(0, widths);
+        cid.finish();
+
+        let bbox = self.face.global_bounding_box();
+        pdf.font_descriptor(descriptor_id)
+            .name(base_font)
+            .flags(FontFlags::SYMBOLIC)
+            .bbox(Rect::new(
+                bbox.x_min as f32 * units,
+                bbox.y_min as f32 * units,
+                bbox.x_max as f32 * units,
+                bbox.y_max as f32 * units,
+            ))
+            .italic_angle(0.0)
+            .ascent(self.face.ascender() as f32 * units)
+            .descent(self.face.descender() as f32 * units)
+            .cap_height(
+                self.face
+                    .capital_height()
+                    .unwrap_or_else(|| self.face.ascender()) as f32
+                    * units,
+            )
+            .stem_v(80.0)
+            .font_file2(file_id);
+
+        let subset = subsetter::subset(self.data, 0, &self.remapper).expect("valid bundled font");
+        pdf.stream(file_id, &subset)
+            .pair(Name(b"Length1"), subset.len() as i32);
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), SYSTEM_INFO);
+        for (id, text) in &self.texts {
+            cmap.pair_with_multiple(*id, text.chars());
+        }
+        pdf.cmap(cmap_id, &cmap.finish());
+    }
+}
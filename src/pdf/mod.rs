@@ -0,0 +1,506 @@
+pub mod documents;
+pub mod font;
+
+use std::path::Path;
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use rocket::{
+    serde::{json, Deserialize},
+    tokio::fs,
+};
+
+use crate::{
+    accounting_api::{self, AcountingApi},
+    file_system::{FileSystem, MemoryFile},
+    local_storage::{models::Company, LocalStorageAccountingApi},
+    types::response::PdfFile,
+};
+
+use self::font::{Font, Line};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const FOOTER_HEIGHT: f32 = 50.0;
+const LABEL_WIDTH: f32 = 120.0;
+const CELL_PADDING: f32 = 4.0;
+
+/// the look of the generated documents, read from `templates/pdf.json` in
+/// the data directory and overridden by the same file inside a company folder
+#[derive(Deserialize, Debug, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase", default)]
+pub struct PdfTemplate {
+    pub firm_name: String,
+    pub firm_details: Vec<String>,
+    pub footer: Vec<String>,
+    pub font_size: f32,
+    pub titles: PdfTitles,
+}
+
+impl Default for PdfTemplate {
+    fn default() -> Self {
+        Self {
+            firm_name: String::new(),
+            firm_details: vec![],
+            footer: vec![],
+            font_size: 10.0,
+            titles: PdfTitles::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase", default)]
+pub struct PdfTitles {
+    pub invoice: String,
+    pub receipt: String,
+    pub custody_statement: String,
+    pub company_statement: String,
+}
+
+impl Default for PdfTitles {
+    fn default() -> Self {
+        Self {
+            invoice: "فاتورة".into(),
+            receipt: "ايصال استلام نقدية".into(),
+            custody_statement: "كشف حساب عهدة".into(),
+            company_statement: "كشف حساب شركة".into(),
+        }
+    }
+}
+
+impl PdfTemplate {
+    const FILE: &'static str = "pdf.json";
+
+    /// `company_dir` is the company folder relative to the data directory
+    pub async fn load(fs: &FileSystem, company_dir: Option<&Path>) -> Self {
+        let global = fs.root.join("templates").join(Self::FILE);
+        let paths = company_dir
+            .map(|dir| fs.root.join(dir).join("templates").join(Self::FILE))
+            .into_iter()
+            .chain([global]);
+        for path in paths {
+            let content = match fs::read(&path).await {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            match json::from_slice(&content) {
+                Ok(template) => return template,
+                Err(error) => rocket::warn!("[pdf] invalid template {path:?}: {error}"),
+            }
+        }
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Invoice,
+    Receipt,
+    CustodyStatement,
+    CompanyStatement,
+}
+
+#[derive(Debug)]
+pub struct Column {
+    pub title: String,
+    /// fraction of the page content width
+    pub width: f32,
+    pub numeric: bool,
+}
+
+impl Column {
+    pub fn text(title: &str, width: f32) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            numeric: false,
+        }
+    }
+
+    pub fn numeric(title: &str, width: f32) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            numeric: true,
+        }
+    }
+}
+
+/// the content of a document, laid out right to left as a header, a list of
+/// fields, an optional table and the totals
+#[derive(Debug)]
+pub struct PdfDocument {
+    pub kind: DocumentKind,
+    pub fields: Vec<(String, String)>,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<String>>,
+    pub totals: Vec<(String, String)>,
+}
+
+impl PdfDocument {
+    pub fn render(&self, template: &PdfTemplate) -> Vec<u8> {
+        let title = match self.kind {
+            DocumentKind::Invoice => &template.titles.invoice,
+            DocumentKind::Receipt => &template.titles.receipt,
+            DocumentKind::CustodyStatement => &template.titles.custody_statement,
+            DocumentKind::CompanyStatement => &template.titles.company_statement,
+        };
+        let mut writer = Writer::new(template, title);
+        writer.fields(&self.fields);
+        if !self.columns.is_empty() {
+            writer.table(&self.columns, &self.rows);
+        }
+        writer.fields_bold(&self.totals);
+        writer.finish()
+    }
+
+    /// renders the document with the template of `company` and saves a copy
+    /// into its documents folder when `save` is set
+    pub async fn generate(
+        &self,
+        storage: &LocalStorageAccountingApi,
+        company: Option<&Company>,
+        name: String,
+        save: bool,
+    ) -> Result<PdfFile, accounting_api::Error> {
+        let company_dir = company.map(|company| {
+            Path::new("companies").join(format!(
+                "{} - {}",
+                company.owner, company.commercial_feature
+            ))
+        });
+        let template = PdfTemplate::load(&*storage.fs.read().await, company_dir.as_deref()).await;
+        let content = self.render(&template);
+
+        if let (Some(company), true) = (company, save) {
+            storage
+                .save_company_document(company.id, MemoryFile::new(name.clone(), content.clone()))
+                .await?;
+        }
+
+        Ok(PdfFile { name, content })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Right,
+    Center,
+    Left,
+}
+
+struct Writer<'a> {
+    template: &'a PdfTemplate,
+    title: &'a str,
+    regular: Font,
+    bold: Font,
+    pages: Vec<Content>,
+    /// the page being drawn on
+    page: usize,
+    y: f32,
+}
+
+impl<'a> Writer<'a> {
+    fn new(template: &'a PdfTemplate, title: &'a str) -> Self {
+        let mut writer = Self {
+            template,
+            title,
+            regular: Font::regular(),
+            bold: Font::bold(),
+            pages: vec![],
+            page: 0,
+            y: 0.0,
+        };
+        writer.new_page();
+        writer
+    }
+
+    fn size(&self) -> f32 {
+        self.template.font_size
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        size * 1.5
+    }
+
+    fn content_width(&self) -> f32 {
+        PAGE_WIDTH - 2.0 * MARGIN
+    }
+
+    fn right(&self) -> f32 {
+        PAGE_WIDTH - MARGIN
+    }
+
+    fn font(&mut self, bold: bool) -> &mut Font {
+        if bold {
+            &mut self.bold
+        } else {
+            &mut self.regular
+        }
+    }
+
+    /// draws a single line with its baseline at `y`, `x` is the right edge,
+    /// the center or the left edge depending on `align`
+    fn text(&mut self, text: &str, bold: bool, size: f32, x: f32, y: f32, align: Align) {
+        let Line { glyphs, width } = self.font(bold).shape(text, size);
+        let x = match align {
+            Align::Right => x - width,
+            Align::Center => x - width / 2.0,
+            Align::Left => x,
+        };
+        let content = &mut self.pages[self.page];
+        content.begin_text();
+        content.set_font(Name(if bold { b"F2" } else { b"F1" }), size);
+        for glyph in glyphs {
+            content.set_text_matrix([1.0, 0.0, 0.0, 1.0, x + glyph.x, y + glyph.y]);
+            content.show(Str(&glyph.id.to_be_bytes()));
+        }
+        content.end_text();
+    }
+
+    fn rule(&mut self, y: f32, from: f32, to: f32, gray: f32) {
+        let content = &mut self.pages[self.page];
+        content
+            .set_stroke_gray(gray)
+            .set_line_width(0.5)
+            .move_to(from, y)
+            .line_to(to, y)
+            .stroke();
+    }
+
+    /// breaks `text` on spaces into lines not wider than `width`
+    fn wrap(&self, text: &str, bold: bool, size: f32, width: f32) -> Vec<String> {
+        let font = if bold { &self.bold } else { &self.regular };
+        let mut lines = vec![];
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_owned()
+                } else {
+                    format!("{line} {word}")
+                };
+                if !line.is_empty() && font.width(&candidate, size) > width {
+                    lines.push(std::mem::replace(&mut line, word.to_owned()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.page = self.pages.len() - 1;
+        self.y = PAGE_HEIGHT - MARGIN;
+
+        let size = self.size();
+        let right = self.right();
+        if !self.template.firm_name.is_empty() {
+            self.y -= size * 1.4;
+            let name = self.template.firm_name.clone();
+            self.text(&name, true, size * 1.4, right, self.y, Align::Right);
+        }
+        for detail in self.template.firm_details.clone() {
+            self.y -= self.line_height(size * 0.9);
+            self.text(&detail, false, size * 0.9, right, self.y, Align::Right);
+        }
+        self.y -= size * 2.6;
+        self.text(
+            self.title,
+            true,
+            size * 1.6,
+            PAGE_WIDTH / 2.0,
+            self.y,
+            Align::Center,
+        );
+        self.y -= size;
+        self.rule(self.y, MARGIN, right, 0.0);
+        self.y -= size;
+    }
+
+    /// starts a new page unless `height` still fits above the footer
+    fn reserve(&mut self, height: f32) -> bool {
+        if self.y - height < MARGIN + FOOTER_HEIGHT {
+            self.new_page();
+            return true;
+        }
+        false
+    }
+
+    fn field_rows(&mut self, fields: &[(String, String)], bold: bool) {
+        let size = self.size();
+        let right = self.right();
+        let width = self.content_width() - LABEL_WIDTH;
+        for (label, value) in fields {
+            let lines = self.wrap(value, bold, size, width);
+            self.reserve(lines.len() as f32 * self.line_height(size));
+            self.y -= self.line_height(size);
+            self.text(label, true, size, right, self.y, Align::Right);
+            for (index, line) in lines.iter().enumerate() {
+                if index > 0 {
+                    self.y -= self.line_height(size);
+                }
+                self.text(line, bold, size, right - LABEL_WIDTH, self.y, Align::Right);
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[(String, String)]) {
+        self.field_rows(fields, false);
+    }
+
+    fn fields_bold(&mut self, fields: &[(String, String)]) {
+        if fields.is_empty() {
+            return;
+        }
+        self.y -= self.size() / 2.0;
+        let right = self.right();
+        self.rule(self.y, MARGIN, right, 0.0);
+        self.field_rows(fields, true);
+    }
+
+    fn row(&mut self, columns: &[Column], cells: &[String], header: bool) {
+        let size = self.size();
+        let line_height = self.line_height(size);
+        let widths = columns
+            .iter()
+            .map(|column| column.width * self.content_width())
+            .collect::<Vec<_>>();
+        let cells = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| self.wrap(cell, header, size, width - 2.0 * CELL_PADDING))
+            .collect::<Vec<_>>();
+        let height =
+            cells.iter().map(Vec::len).max().unwrap_or(1) as f32 * line_height + CELL_PADDING;
+
+        if !header && self.reserve(height) {
+            self.table_header(columns);
+        }
+
+        let top = self.y;
+        if header {
+            let width = self.content_width();
+            self.pages
+                .last_mut()
+                .expect("a page")
+                .set_fill_gray(0.9)
+                .rect(MARGIN, top - height, width, height)
+                .fill_nonzero()
+                .set_fill_gray(0.0);
+        }
+
+        // the first column is the right most one
+        let mut x = self.right();
+        for ((column, lines), width) in columns.iter().zip(&cells).zip(&widths) {
+            let (anchor, align) = if column.numeric {
+                (x - width / 2.0, Align::Center)
+            } else {
+                (x - CELL_PADDING, Align::Right)
+            };
+            for (index, line) in lines.iter().enumerate() {
+                let baseline = top - (index + 1) as f32 * line_height + size * 0.3;
+                self.text(line, header, size, anchor, baseline, align);
+            }
+            x -= width;
+        }
+
+        self.y = top - height;
+        let right = self.right();
+        self.rule(self.y, MARGIN, right, 0.6);
+    }
+
+    fn table_header(&mut self, columns: &[Column]) {
+        let titles = columns
+            .iter()
+            .map(|column| column.title.clone())
+            .collect::<Vec<_>>();
+        self.row(columns, &titles, true);
+    }
+
+    fn table(&mut self, columns: &[Column], rows: &[Vec<String>]) {
+        self.y -= self.size();
+        self.reserve(3.0 * self.line_height(self.size()));
+        self.table_header(columns);
+        if rows.is_empty() {
+            let size = self.size();
+            self.y -= self.line_height(size);
+            self.text(
+                "لا توجد بيانات",
+                false,
+                size,
+                PAGE_WIDTH / 2.0,
+                self.y,
+                Align::Center,
+            );
+            self.y -= size / 2.0;
+        }
+        for row in rows {
+            self.row(columns, row, false);
+        }
+    }
+
+    /// adds the footer and page numbers then writes the pages with the
+    /// subsetted fonts
+    fn finish(mut self) -> Vec<u8> {
+        let size = self.size() * 0.8;
+        let count = self.pages.len();
+        for index in 0..count {
+            self.page = index;
+            let mut y = MARGIN + FOOTER_HEIGHT - size;
+            let right = self.right();
+            self.rule(y + size, MARGIN, right, 0.6);
+            for line in self.template.footer.clone() {
+                self.text(&line, false, size, PAGE_WIDTH / 2.0, y, Align::Center);
+                y -= self.line_height(size);
+            }
+            self.text(
+                &format!("صفحة {} من {}", index + 1, count),
+                false,
+                size,
+                MARGIN,
+                MARGIN,
+                Align::Left,
+            );
+        }
+        let pages = std::mem::take(&mut self.pages);
+
+        let mut next = Ref::new(1);
+        let catalog_id = next.bump();
+        let tree_id = next.bump();
+        let regular_id = next.bump();
+        let bold_id = next.bump();
+        let page_ids = pages.iter().map(|_| next.bump()).collect::<Vec<_>>();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(tree_id);
+        pdf.pages(tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        for (page, page_id) in pages.into_iter().zip(page_ids) {
+            let content_id = next.bump();
+            let mut writer = pdf.page(page_id);
+            writer
+                .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(tree_id)
+                .contents(content_id);
+            writer
+                .resources()
+                .fonts()
+                .pair(Name(b"F1"), regular_id)
+                .pair(Name(b"F2"), bold_id);
+            writer.finish();
+            pdf.stream(content_id, &page.finish());
+        }
+        self.regular.embed(&mut pdf, regular_id, &mut next);
+        self.bold.embed(&mut pdf, bold_id, &mut next);
+        pdf.finish()
+    }
+}
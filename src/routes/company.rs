@@ -1,61 +1,115 @@
 use rocket::{
-    delete, fairing::AdHoc, form::Form, fs::TempFile, get, post, put, routes, serde::json::Json,
-    FromForm, State,
+    delete,
+    fairing::AdHoc,
+    form::Form,
+    fs::{NamedFile, TempFile},
+    get, post, put,
+    serde::json::Json,
+    tokio::fs::File,
+    FromForm,
 };
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
 use sqlx::types::Uuid;
 
 use crate::{
-    accounting_api::AcountingApi,
+    accounting_api::{self, AcountingApi},
     auth::{AGuard, UGuard},
+    docs::{self, Binary},
+    export::{self, CsvFile},
+    import::{self, ImportForm},
     local_storage::{models::*, LocalStorageAccountingApi},
-    types::response::{ResponseEnum, ResponseResult},
+    pdf::PdfDocument,
+    types::response::{ExportFile, PdfResult, ResponseEnum, ResponseResult},
 };
 
+#[openapi(tag = "Companies")]
 #[post("/", format = "application/json", data = "<company>")]
 pub async fn create_company(
     company: Json<CreateCompany>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Company> {
     rocket::trace!("{company:#?}");
     let company = storage.create_company(&company).await?;
-    Ok(ResponseEnum::created(company, "تم انشاء شركة جديدة".into()))
+    Ok(ResponseEnum::created(company, "company.created".into()))
 }
 
+// documented by the other route of the same path
+#[openapi(skip)]
 #[get("/?<search>")]
 pub async fn search_company_admin(
     search: &str,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Vec<Company>> {
     rocket::trace!("{search:#?}");
     let companies = storage.search_company(search).await?;
-    Ok(ResponseEnum::ok(companies, "تم العثور علي شركات".into()))
+    Ok(ResponseEnum::ok(companies, "company.found".into()))
 }
 
+#[openapi(tag = "Companies")]
 #[get("/?<search>", rank = 2)]
 pub async fn search_company_user(
     search: &str,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ug: UGuard,
 ) -> ResponseResult<Vec<Company>> {
     rocket::trace!("{search:#?}");
     let companies = storage.search_company(search).await?;
-    Ok(ResponseEnum::ok(companies, "تم العثور علي شركات".into()))
+    Ok(ResponseEnum::ok(companies, "company.found".into()))
 }
 
+#[openapi(tag = "Companies")]
+#[get("/export/csv?<search>")]
+pub async fn export_companies_csv(
+    search: String,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> CsvFile<'_> {
+    export::csv(storage.stream_companies(search))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/export/xlsx?<search>")]
+pub async fn export_companies_xlsx(
+    search: &str,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> Result<ExportFile<File>, ResponseEnum<()>> {
+    let file = export::xlsx(storage.stream_companies(search.into())).await?;
+    Ok(file)
+}
+
+#[openapi(tag = "Companies")]
+#[post("/import?<dry_run>", data = "<form>")]
+pub async fn import_companies(
+    dry_run: bool,
+    mut form: Form<ImportForm<'_>>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<ImportReport> {
+    let sheet = import::read::<CreateCompany>(&mut form).await?;
+    let errors = storage
+        .import_companies(&sheet.rows, sheet.commit(dry_run))
+        .await?;
+    sheet.report(dry_run, errors)
+}
+
+#[openapi(tag = "Companies")]
 #[put("/<id>", format = "application/json", data = "<company>")]
 pub async fn update_company(
     id: Uuid,
     company: Json<UpdateCompany>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Company> {
     rocket::trace!("{company:#?}");
     let compannies = storage.update_company(id, &company).await?;
-    Ok(ResponseEnum::ok(compannies, "تم خفظ الشركة بنجاح".into()))
+    Ok(ResponseEnum::ok(compannies, "company.saved".into()))
 }
 
+#[openapi(tag = "Companies")]
 #[post(
     "/<company_id>/expenses",
     format = "application/json",
@@ -64,14 +118,58 @@ pub async fn update_company(
 pub async fn create_expense(
     company_id: Uuid,
     expense: Json<CreateExpense>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<Expense> {
+    let expense = storage
+        .create_expense(ug.0, company_id, &expense, &mut [])
+        .await?;
+
+    Ok(ResponseEnum::created(expense, "expense.created".into()))
+}
+
+#[derive(FromForm, Debug, JsonSchema)]
+struct ExpenseForm<'r> {
+    value: f64,
+    description: String,
+    tax_code: Option<TaxCode>,
+    tax_rate: Option<f64>,
+    supplier: Option<String>,
+    supplier_registration: Option<String>,
+    withholding_rate: Option<f64>,
+    #[schemars(with = "Vec<Binary>")]
+    files: Vec<TempFile<'r>>,
+}
+
+#[openapi(tag = "Companies")]
+#[post(
+    "/<company_id>/expenses",
+    format = "multipart/form-data",
+    data = "<form>"
+)]
+async fn create_expense_with_attachments(
+    company_id: Uuid,
+    mut form: Form<ExpenseForm<'_>>,
+    storage: &LocalStorageAccountingApi,
     ug: UGuard,
 ) -> ResponseResult<Expense> {
-    let expense = storage.create_expense(ug.0, company_id, &expense).await?;
+    let expense = CreateExpense {
+        value: form.value,
+        description: form.description.clone(),
+        tax_code: form.tax_code.unwrap_or_default(),
+        tax_rate: form.tax_rate,
+        supplier: form.supplier.clone(),
+        supplier_registration: form.supplier_registration.clone(),
+        withholding_rate: form.withholding_rate,
+    };
+    let expense = storage
+        .create_expense(ug.0, company_id, &expense, &mut form.files)
+        .await?;
 
-    Ok(ResponseEnum::created(expense, "تم اضافة مصروفات".into()))
+    Ok(ResponseEnum::created(expense, "expense.created".into()))
 }
 
+#[openapi(tag = "Companies")]
 #[post(
     "/<company_id>/incomes",
     format = "application/json",
@@ -80,65 +178,279 @@ pub async fn create_expense(
 pub async fn create_income(
     company_id: Uuid,
     income: Json<CreateIncome>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<Income> {
+    let income = storage
+        .create_income(ag.0, company_id, &income, &mut [])
+        .await?;
+
+    Ok(ResponseEnum::created(income, "income.created".into()))
+}
+
+#[derive(FromForm, Debug, JsonSchema)]
+struct IncomeForm<'r> {
+    value: f64,
+    description: String,
+    tax_code: Option<TaxCode>,
+    tax_rate: Option<f64>,
+    #[schemars(with = "Vec<Binary>")]
+    files: Vec<TempFile<'r>>,
+}
+
+#[openapi(tag = "Companies")]
+#[post(
+    "/<company_id>/incomes",
+    format = "multipart/form-data",
+    data = "<form>"
+)]
+async fn create_income_with_attachments(
+    company_id: Uuid,
+    mut form: Form<IncomeForm<'_>>,
+    storage: &LocalStorageAccountingApi,
     ag: AGuard,
 ) -> ResponseResult<Income> {
-    let income = storage.create_income(ag.0, company_id, &income).await?;
+    let income = CreateIncome {
+        value: form.value,
+        description: form.description.clone(),
+        tax_code: form.tax_code.unwrap_or_default(),
+        tax_rate: form.tax_rate,
+    };
+    let income = storage
+        .create_income(ag.0, company_id, &income, &mut form.files)
+        .await?;
 
-    Ok(ResponseEnum::created(income, "تم اضافة واردات".into()))
+    Ok(ResponseEnum::created(income, "income.created".into()))
 }
 
+#[openapi(tag = "Companies")]
 #[delete("/<id>")]
 pub async fn delete_company(
     id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<()> {
     storage.delete_company(id).await?;
-    Ok(ResponseEnum::ok((), "تم حذف الشركة".into()))
+    Ok(ResponseEnum::ok((), "company.deleted".into()))
 }
 
-#[derive(FromForm, Debug)]
+#[derive(FromForm, Debug, JsonSchema)]
 struct Upload<'r> {
+    #[schemars(with = "Binary")]
     file: TempFile<'r>,
 }
 
+#[openapi(tag = "Companies")]
 #[post("/<company_id>/documents", data = "<upload>")]
 async fn upload_document(
     company_id: Uuid,
     mut upload: Form<Upload<'_>>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
 ) -> ResponseResult<Document> {
     let document = storage
         .create_document(company_id, &mut upload.file)
         .await?;
 
-    Ok(ResponseEnum::created(
-        document,
-        "تم انشاء مستند بنجاح".into(),
-    ))
+    Ok(ResponseEnum::created(document, "document.created".into()))
 }
 
+// documented by the other route of the same path
+#[openapi(skip)]
 #[get("/<company_id>/documents")]
 async fn get_documents_admin(
     company_id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Vec<Document>> {
     let documents = storage.get_documents(company_id).await?;
-    Ok(ResponseEnum::ok(documents, "تم ايجاد مستندات بنجاح".into()))
+    Ok(ResponseEnum::ok(documents, "document.found".into()))
 }
 
+/// `name` is one of the documents of the company, never a path
+#[openapi(tag = "Companies")]
+#[delete("/<company_id>/documents/<name>")]
+async fn delete_document(
+    company_id: Uuid,
+    name: &str,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    storage.delete_document(company_id, name).await?;
+    Ok(ResponseEnum::ok((), "document.deleted".into()))
+}
+
+#[openapi(tag = "Companies")]
 #[get("/<company_id>/documents", rank = 2)]
 async fn get_documents_user(
     company_id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ug: UGuard,
 ) -> ResponseResult<Vec<Document>> {
     let documents = storage.get_documents(company_id).await?;
-    Ok(ResponseEnum::ok(documents, "تم ايجاد مستندات بنجاح".into()))
+    Ok(ResponseEnum::ok(documents, "document.found".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/statement")]
+async fn get_company_statement(
+    company_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<CompanyStatementEntry>> {
+    let statement = storage.get_company_statement(company_id).await?;
+    Ok(ResponseEnum::ok(statement, "company.statement".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/statement/pdf?<save>")]
+async fn get_company_statement_pdf(
+    company_id: Uuid,
+    save: Option<bool>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> PdfResult {
+    let company = storage.get_company(company_id).await?;
+    let statement = storage.get_company_statement(company_id).await?;
+    let name = format!("statement-{}.pdf", chrono::Utc::now().format("%Y-%m-%d"));
+    let pdf = PdfDocument::company_statement(&company, &statement)
+        .generate(storage, Some(&company), name, save.unwrap_or(false))
+        .await?;
+    Ok(pdf)
 }
 
+#[derive(FromForm, Debug, JsonSchema)]
+struct VatPeriod {
+    year: i32,
+    month: u32,
+}
+
+impl VatPeriod {
+    fn start(&self) -> Result<chrono::NaiveDate, accounting_api::Error> {
+        chrono::NaiveDate::from_ymd_opt(self.year, self.month, 1)
+            .ok_or(accounting_api::Error::InvalidTaxPeriod)
+    }
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/vat-return?<period..>")]
+async fn get_vat_return(
+    company_id: Uuid,
+    period: VatPeriod,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<VatReturn> {
+    let vat_return = storage.get_vat_return(company_id, period.start()?).await?;
+    Ok(ResponseEnum::ok(vat_return, "company.vatReturn".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/vat-return/csv?<side>&<period..>")]
+async fn export_vat_return_csv(
+    company_id: Uuid,
+    side: VatSide,
+    period: VatPeriod,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> Result<CsvFile<'_>, ResponseEnum<()>> {
+    let start = period.start()?;
+    let file = export::csv(storage.stream_vat_entries(company_id, start, side));
+    Ok(ExportFile {
+        name: vat_return_name(side, start, "csv"),
+        ..file
+    })
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/vat-return/xlsx?<side>&<period..>")]
+async fn export_vat_return_xlsx(
+    company_id: Uuid,
+    side: VatSide,
+    period: VatPeriod,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> Result<ExportFile<File>, ResponseEnum<()>> {
+    let start = period.start()?;
+    let file = export::xlsx(storage.stream_vat_entries(company_id, start, side)).await?;
+    Ok(ExportFile {
+        name: vat_return_name(side, start, "xlsx"),
+        ..file
+    })
+}
+
+fn vat_return_name(side: VatSide, start: chrono::NaiveDate, extension: &str) -> String {
+    let side = match side {
+        VatSide::Sales => "sales",
+        VatSide::Purchases => "purchases",
+    };
+    format!("vat-{side}-{}.{extension}", start.format("%Y-%m"))
+}
+
+#[derive(FromForm, Debug, JsonSchema)]
+struct TaxQuarter {
+    year: i32,
+    quarter: u32,
+}
+
+impl TaxQuarter {
+    fn start(&self) -> Result<chrono::NaiveDate, accounting_api::Error> {
+        match self.quarter {
+            1..=4 => chrono::NaiveDate::from_ymd_opt(self.year, self.quarter * 3 - 2, 1),
+            _ => None,
+        }
+        .ok_or(accounting_api::Error::InvalidTaxPeriod)
+    }
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/withholding?<quarter..>")]
+async fn get_withholding_statement(
+    company_id: Uuid,
+    quarter: TaxQuarter,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<WithholdingStatement> {
+    let statement = storage
+        .get_withholding_statement(company_id, quarter.start()?)
+        .await?;
+    Ok(ResponseEnum::ok(
+        statement,
+        "company.withholdingStatement".into(),
+    ))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/withholding/csv?<quarter..>")]
+async fn export_withholding_csv(
+    company_id: Uuid,
+    quarter: TaxQuarter,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> Result<CsvFile<'_>, ResponseEnum<()>> {
+    let start = quarter.start()?;
+    let file = export::csv(storage.stream_withholding_entries(company_id, start));
+    Ok(ExportFile {
+        name: format!("form-41-{}-q{}.csv", quarter.year, quarter.quarter),
+        ..file
+    })
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/withholding/xlsx?<quarter..>")]
+async fn export_withholding_xlsx(
+    company_id: Uuid,
+    quarter: TaxQuarter,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> Result<ExportFile<File>, ResponseEnum<()>> {
+    let start = quarter.start()?;
+    let file = export::xlsx(storage.stream_withholding_entries(company_id, start)).await?;
+    Ok(ExportFile {
+        name: format!("form-41-{}-q{}.xlsx", quarter.year, quarter.quarter),
+        ..file
+    })
+}
+
+#[openapi(tag = "Companies")]
 #[post(
     "/<company_id>/funders",
     format = "application/json",
@@ -147,51 +459,180 @@ async fn get_documents_user(
 async fn create_funder(
     company_id: Uuid,
     funder: Json<CreateFunder>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Funder> {
     let funder = storage.create_funder(company_id, &funder).await?;
-    Ok(ResponseEnum::created(funder, "تم اضافة ممول ببنجاح".into()))
+    Ok(ResponseEnum::created(funder, "funder.created".into()))
 }
 
+// documented by the other route of the same path
+#[openapi(skip)]
 #[get("/<company_id>/funders")]
 async fn get_funders_admin(
     company_id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Vec<Funder>> {
     let funder = storage.get_funders(company_id).await?;
-    Ok(ResponseEnum::created(funder, "تم اضافة ممول ببنجاح".into()))
+    Ok(ResponseEnum::created(funder, "funder.created".into()))
 }
 
+#[openapi(tag = "Companies")]
 #[get("/<company_id>/funders", rank = 2)]
 async fn get_funders_user(
     company_id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ug: UGuard,
 ) -> ResponseResult<Vec<Funder>> {
     let funder = storage.get_funders(company_id).await?;
-    Ok(ResponseEnum::created(funder, "تم اضافة ممول ببنجاح".into()))
+    Ok(ResponseEnum::created(funder, "funder.created".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[post(
+    "/<company_id>/funders/distribution",
+    format = "application/json",
+    data = "<distribution>"
+)]
+async fn create_distribution(
+    company_id: Uuid,
+    distribution: Json<CreateDistribution>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<ProfitDistribution> {
+    let distribution = storage
+        .create_distribution(ag.0, company_id, &distribution)
+        .await?;
+    Ok(ResponseEnum::created(
+        distribution,
+        "distribution.created".into(),
+    ))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/funders/distribution")]
+async fn get_distributions(
+    company_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<ProfitDistribution>> {
+    let distributions = storage.get_distributions(company_id).await?;
+    Ok(ResponseEnum::ok(distributions, "distribution.found".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[get("/<company_id>/funders/distribution/<distribution_id>/<funder_id>")]
+async fn download_distribution_statement(
+    company_id: Uuid,
+    distribution_id: Uuid,
+    funder_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> Result<NamedFile, ResponseEnum<()>> {
+    let path = storage
+        .get_distribution_statement(company_id, distribution_id, funder_id)
+        .await?;
+    rocket::info!("[admin|distribution] requesting: {path:?}");
+    NamedFile::open(path)
+        .await
+        .map_err(|e| ResponseEnum::from(accounting_api::Error::from(e)))
+}
+
+#[openapi(tag = "Companies")]
+#[post(
+    "/<company_id>/schedules",
+    format = "application/json",
+    data = "<schedule>"
+)]
+async fn create_schedule(
+    company_id: Uuid,
+    schedule: Json<CreateSchedule>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<Schedule> {
+    let schedule = storage.create_schedule(ag.0, company_id, &schedule).await?;
+    Ok(ResponseEnum::created(schedule, "schedule.created".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[post(
+    "/<company_id>/invoices",
+    format = "application/json",
+    data = "<invoice>"
+)]
+async fn create_invoice(
+    company_id: Uuid,
+    invoice: Json<CreateInvoice>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<Invoice> {
+    let invoice = storage.create_invoice(ag.0, company_id, &invoice).await?;
+    Ok(ResponseEnum::created(invoice, "invoice.created".into()))
+}
+
+#[openapi(tag = "Companies")]
+#[post(
+    "/<company_id>/deadlines",
+    format = "application/json",
+    data = "<deadline>"
+)]
+async fn create_deadline(
+    company_id: Uuid,
+    deadline: Json<CreateDeadline>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<Deadline> {
+    let deadline = storage.create_deadline(ag.0, company_id, &deadline).await?;
+    Ok(ResponseEnum::created(deadline, "deadline.created".into()))
 }
 
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("companies stage", |rocket| async {
-        rocket.mount(
+        let rocket = docs::mount(
+            rocket,
             "/api/company",
-            routes![
+            openapi_get_routes_spec![
                 create_company,
                 update_company,
                 search_company_admin,
                 search_company_user,
+                export_companies_csv,
+                export_companies_xlsx,
+                import_companies,
                 create_expense,
                 create_income,
                 delete_company,
                 upload_document,
                 get_documents_admin,
                 get_documents_user,
+                delete_document,
+                get_company_statement,
+                get_company_statement_pdf,
+                get_vat_return,
+                export_vat_return_csv,
+                export_vat_return_xlsx,
+                get_withholding_statement,
+                export_withholding_csv,
+                export_withholding_xlsx,
                 create_funder,
                 get_funders_admin,
                 get_funders_user,
+                create_distribution,
+                get_distributions,
+                download_distribution_statement,
+                create_schedule,
+                create_invoice,
+                create_deadline,
+            ],
+        );
+        // mounted apart so their bodies are added to the JSON ones
+        docs::mount(
+            rocket,
+            "/api/company",
+            openapi_get_routes_spec![
+                create_expense_with_attachments,
+                create_income_with_attachments
             ],
         )
     })
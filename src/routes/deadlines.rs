@@ -0,0 +1,93 @@
+use chrono::Utc;
+use rocket::{delete, fairing::AdHoc, get, post, serde::json::Json, FromForm};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use crate::{
+    accounting_api::AcountingApi,
+    auth::AGuard,
+    docs,
+    local_storage::{models, LocalStorageAccountingApi},
+    scheduler,
+    types::response::{ResponseEnum, ResponseResult},
+};
+
+#[derive(Debug, FromForm, PartialEq, JsonSchema)]
+#[allow(dead_code)]
+pub struct GetParam {
+    #[schemars(rename = "company.id", with = "Option<Uuid>")]
+    company: Option<Company>,
+    status: Option<models::DeadlineStatus>,
+}
+
+#[derive(Debug, FromForm, PartialEq)]
+#[allow(dead_code)]
+struct Company {
+    id: Uuid,
+}
+
+#[openapi(tag = "Deadlines")]
+#[get("/?<param..>")]
+pub async fn get_deadlines(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<models::Deadline>> {
+    rocket::debug!("{param:?}");
+    let deadlines = storage
+        .get_deadlines(param.company.map(|c| c.id), param.status)
+        .await?;
+    Ok(ResponseEnum::ok(deadlines, "deadline.found".into()))
+}
+
+/// the note is optional, so is the body
+#[openapi(tag = "Deadlines")]
+#[post("/<id>/fulfill", data = "<fulfillment>")]
+pub async fn fulfill_deadline(
+    id: Uuid,
+    fulfillment: Option<Json<models::FulfillDeadline>>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<models::Deadline> {
+    let fulfillment = fulfillment.map(Json::into_inner).unwrap_or_default();
+    let deadline = storage.fulfill_deadline(ag.0, id, &fulfillment).await?;
+    Ok(ResponseEnum::ok(deadline, "deadline.fulfilled".into()))
+}
+
+#[openapi(tag = "Deadlines")]
+#[delete("/<id>")]
+pub async fn delete_deadline(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    storage.delete_deadline(id).await?;
+    Ok(ResponseEnum::ok((), "deadline.deleted".into()))
+}
+
+#[openapi(tag = "Deadlines")]
+#[post("/sync")]
+pub async fn sync_deadlines(
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<u64> {
+    let (from, until) = scheduler::deadlines_window(Utc::now().date_naive());
+    let added = storage.sync_deadlines(from, until).await?;
+    Ok(ResponseEnum::ok(added, "deadline.synced".into()))
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("deadlines stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/deadlines",
+            openapi_get_routes_spec![
+                get_deadlines,
+                fulfill_deadline,
+                delete_deadline,
+                sync_deadlines
+            ],
+        )
+    })
+}
@@ -3,26 +3,21 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use rocket::{
-    delete,
-    fairing::AdHoc,
-    fs::NamedFile,
-    get, routes,
-    serde::{json::Json, Deserialize, Serialize},
-    State,
-};
+use rocket::{fairing::AdHoc, fs::NamedFile, get};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
 
 use crate::{
-    accounting_api::AcountingApi,
     auth::{AGuard, UGuard},
+    docs,
     local_storage::LocalStorageAccountingApi,
-    types::response::{ResponseEnum, ResponseResult},
 };
 
+// documented by the other route of the same path
+#[openapi(skip)]
 #[get("/<path..>")]
 pub async fn download_document_admin(
     path: PathBuf,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> io::Result<NamedFile> {
     let path = Path::new(&storage.fs.read().await.root).join(path);
@@ -30,10 +25,11 @@ pub async fn download_document_admin(
     NamedFile::open(path).await
 }
 
+#[openapi(tag = "Documents")]
 #[get("/<path..>", rank = 2)]
 pub async fn download_document_user(
     path: PathBuf,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ug: UGuard,
 ) -> io::Result<NamedFile> {
     let path = Path::new(&storage.fs.read().await.root).join(path);
@@ -41,31 +37,12 @@ pub async fn download_document_user(
     NamedFile::open(path).await
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(crate = "rocket::serde")]
-pub struct DeleteData {
-    pub path: PathBuf,
-}
-
-#[delete("/", format = "application/json", data = "<data>")]
-pub async fn delete_document(
-    data: Json<DeleteData>,
-    storage: &State<LocalStorageAccountingApi>,
-    _ag: AGuard,
-) -> ResponseResult<()> {
-    storage.delete_document(data.into_inner().path).await?;
-    Ok(ResponseEnum::ok((), "تم مسح المستند".into()))
-}
-
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("documents stage", |rocket| async {
-        rocket.mount(
+        docs::mount(
+            rocket,
             "/api/documents",
-            routes![
-                download_document_admin,
-                download_document_user,
-                delete_document,
-            ],
+            openapi_get_routes_spec![download_document_admin, download_document_user],
         )
     })
 }
@@ -0,0 +1,115 @@
+use rocket::{
+    fairing::AdHoc,
+    futures::Stream,
+    get,
+    response::stream::{Event, EventStream},
+    tokio::{select, sync::broadcast::error::RecvError},
+    FromForm, Shutdown, State,
+};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use crate::{
+    auth::{AGuard, UGuard},
+    docs,
+    events::{Change, Entity, Events},
+    local_storage::LocalStorageAccountingApi,
+};
+
+#[derive(Debug, FromForm, PartialEq, JsonSchema)]
+#[allow(dead_code)]
+pub struct GetParam {
+    #[schemars(rename = "company.id", with = "Option<Uuid>")]
+    company: Option<Company>,
+}
+
+#[derive(Debug, FromForm, PartialEq)]
+#[allow(dead_code)]
+struct Company {
+    id: Uuid,
+}
+
+/// the changes of `company` or of all companies of `tenant`, funders are only
+/// shown to admins like their routes,
+/// a `lagged` event tells the client it missed changes and should reload
+fn stream(
+    events: &Events,
+    tenant: Uuid,
+    admin: bool,
+    company: Option<Uuid>,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let mut changes = events.subscribe();
+    let visible = move |change: &Change| {
+        change.tenant_id == Some(tenant)
+            && (admin || change.entity != Entity::Funder)
+            && company
+                .map(|company| change.company_id == Some(company))
+                .unwrap_or(true)
+    };
+    EventStream! {
+        loop {
+            let change = select! {
+                change = changes.recv() => change,
+                _ = &mut shutdown => break,
+            };
+            match change {
+                Ok(change) if visible(&change) => {
+                    yield Event::json(&change).event(change.entity.name())
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => yield Event::data(missed.to_string()).event("lagged"),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+// documented by the other route of the same path
+#[openapi(skip)]
+#[get("/?<param..>")]
+pub fn get_events_admin(
+    param: GetParam,
+    events: &State<Events>,
+    storage: &LocalStorageAccountingApi,
+    shutdown: Shutdown,
+    _ag: AGuard,
+) -> EventStream<impl Stream<Item = Event>> {
+    stream(
+        events,
+        storage.tenant_id,
+        true,
+        param.company.map(|c| c.id),
+        shutdown,
+    )
+}
+
+/// server sent events of the changes, funders are only streamed to admins
+#[openapi(tag = "Events")]
+#[get("/?<param..>", rank = 2)]
+pub fn get_events_user(
+    param: GetParam,
+    events: &State<Events>,
+    storage: &LocalStorageAccountingApi,
+    shutdown: Shutdown,
+    _ug: UGuard,
+) -> EventStream<impl Stream<Item = Event>> {
+    stream(
+        events,
+        storage.tenant_id,
+        false,
+        param.company.map(|c| c.id),
+        shutdown,
+    )
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("events routes stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/events",
+            openapi_get_routes_spec![get_events_admin, get_events_user],
+        )
+    })
+}
@@ -1,18 +1,35 @@
-use rocket::{delete, fairing::AdHoc, get, routes, FromForm, State};
+use rocket::{
+    delete,
+    fairing::AdHoc,
+    form::Form,
+    fs::{NamedFile, TempFile},
+    get, post,
+    serde::json::Json,
+    tokio::fs::File,
+    FromForm,
+};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
 use sqlx::types::Uuid;
 
 use crate::{
-    accounting_api::AcountingApi,
-    auth::UGuard,
+    accounting_api::{self, AcountingApi},
+    auth::{AGuard, RGuard, UGuard},
+    docs::{self, Binary},
+    export::{self, CsvFile},
+    import::{self, ImportForm},
     local_storage::{models, LocalStorageAccountingApi},
-    types::response::{ResponseEnum, ResponseResult},
+    types::response::{ExportFile, ResponseEnum, ResponseResult},
 };
 
-#[derive(Debug, FromForm, PartialEq)]
+#[derive(Debug, FromForm, PartialEq, JsonSchema)]
 #[allow(dead_code)]
 pub struct GetParam {
+    #[schemars(rename = "company.id", with = "Option<Uuid>")]
     company: Option<Company>,
+    #[schemars(rename = "user.id", with = "Option<Uuid>")]
     user: Option<User>,
+    status: Option<models::ExpenseStatus>,
 }
 
 #[derive(Debug, FromForm, PartialEq)]
@@ -27,33 +44,170 @@ struct User {
     id: Uuid,
 }
 
+#[openapi(tag = "Expenses")]
 #[get("/?<param..>")]
 pub async fn get_expenses(
     param: GetParam,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
 ) -> ResponseResult<Vec<models::Expense>> {
     rocket::debug!("{param:?}");
     let money_capitals = storage
-        .get_expenses(param.user.map(|u| u.id), param.company.map(|c| c.id))
+        .get_expenses(
+            param.user.map(|u| u.id),
+            param.company.map(|c| c.id),
+            param.status,
+        )
+        .await?;
+    Ok(ResponseEnum::ok(money_capitals, "expense.found".into()))
+}
+
+#[openapi(tag = "Expenses")]
+#[get("/export/csv?<param..>")]
+pub async fn export_expenses_csv(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> CsvFile<'_> {
+    export::csv(storage.stream_expenses(
+        param.user.map(|u| u.id),
+        param.company.map(|c| c.id),
+        param.status,
+    ))
+}
+
+#[openapi(tag = "Expenses")]
+#[get("/export/xlsx?<param..>")]
+pub async fn export_expenses_xlsx(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> Result<ExportFile<File>, ResponseEnum<()>> {
+    let file = export::xlsx(storage.stream_expenses(
+        param.user.map(|u| u.id),
+        param.company.map(|c| c.id),
+        param.status,
+    ))
+    .await?;
+    Ok(file)
+}
+
+#[openapi(tag = "Expenses")]
+#[post("/<id>/approve", format = "application/json", data = "<review>")]
+pub async fn approve_expense(
+    id: Uuid,
+    review: Json<models::ReviewExpense>,
+    storage: &LocalStorageAccountingApi,
+    rg: RGuard,
+) -> ResponseResult<models::Expense> {
+    let expense = storage.approve_expense(rg.0, id, &review).await?;
+    Ok(ResponseEnum::ok(expense, "expense.approved".into()))
+}
+
+#[openapi(tag = "Expenses")]
+#[post("/<id>/reject", format = "application/json", data = "<review>")]
+pub async fn reject_expense(
+    id: Uuid,
+    review: Json<models::ReviewExpense>,
+    storage: &LocalStorageAccountingApi,
+    rg: RGuard,
+) -> ResponseResult<models::Expense> {
+    let expense = storage.reject_expense(rg.0, id, &review).await?;
+    Ok(ResponseEnum::ok(expense, "expense.rejected".into()))
+}
+
+#[openapi(tag = "Expenses")]
+#[post("/import?<dry_run>", data = "<form>")]
+pub async fn import_expenses(
+    dry_run: bool,
+    mut form: Form<ImportForm<'_>>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<models::ImportReport> {
+    let sheet = import::read::<models::ImportExpense>(&mut form).await?;
+    let errors = storage
+        .import_expenses(ag.0, &sheet.rows, sheet.commit(dry_run))
+        .await?;
+    sheet.report(dry_run, errors)
+}
+
+#[derive(FromForm, Debug, JsonSchema)]
+struct Attachments<'r> {
+    #[schemars(with = "Vec<Binary>")]
+    files: Vec<TempFile<'r>>,
+}
+
+#[openapi(tag = "Expenses")]
+#[post("/<id>/attachments", data = "<upload>")]
+async fn create_expense_attachments(
+    id: Uuid,
+    mut upload: Form<Attachments<'_>>,
+    storage: &LocalStorageAccountingApi,
+    _g: UGuard,
+) -> ResponseResult<Vec<models::Attachment>> {
+    let attachments = storage
+        .create_expense_attachments(id, &mut upload.files)
         .await?;
-    Ok(ResponseEnum::ok(
-        money_capitals,
-        "تم ايجاد رؤؤوس اموال".into(),
+    Ok(ResponseEnum::created(
+        attachments,
+        "attachment.created".into(),
     ))
 }
 
+#[openapi(tag = "Expenses")]
+#[get("/<id>/attachments/<attachment_id>")]
+pub async fn download_expense_attachment(
+    id: Uuid,
+    attachment_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> Result<NamedFile, ResponseEnum<()>> {
+    let path = storage.get_attachment(id, attachment_id).await?;
+    rocket::info!("[expenses|attachments] requesting: {path:?}");
+    NamedFile::open(path)
+        .await
+        .map_err(|e| ResponseEnum::from(accounting_api::Error::from(e)))
+}
+
+#[openapi(tag = "Expenses")]
+#[delete("/<id>/attachments/<attachment_id>")]
+pub async fn delete_expense_attachment(
+    id: Uuid,
+    attachment_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _g: UGuard,
+) -> ResponseResult<()> {
+    storage.delete_attachment(id, attachment_id).await?;
+    Ok(ResponseEnum::ok((), "attachment.deleted".into()))
+}
+
+#[openapi(tag = "Expenses")]
 #[delete("/<id>")]
 pub async fn delete_expense(
     id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
-    _ug: UGuard,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
 ) -> ResponseResult<()> {
-    storage.delete_expense(id).await?;
-    Ok(ResponseEnum::ok((), "تم مسح مصروفات".into()))
+    storage.delete_expense(ug.0, id).await?;
+    Ok(ResponseEnum::ok((), "expense.deleted".into()))
 }
 
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("expenses stage", |rocket| async {
-        rocket.mount("/api/expenses", routes![get_expenses, delete_expense])
+        docs::mount(
+            rocket,
+            "/api/expenses",
+            openapi_get_routes_spec![
+                get_expenses,
+                export_expenses_csv,
+                export_expenses_xlsx,
+                import_expenses,
+                approve_expense,
+                reject_expense,
+                create_expense_attachments,
+                download_expense_attachment,
+                delete_expense_attachment,
+                delete_expense
+            ],
+        )
     })
 }
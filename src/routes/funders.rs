@@ -1,25 +1,44 @@
-use rocket::{delete, fairing::AdHoc, routes, State};
+use rocket::{delete, fairing::AdHoc, put, serde::json::Json};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
 use sqlx::types::Uuid;
 
 use crate::{
     accounting_api::AcountingApi,
     auth::AGuard,
-    local_storage::LocalStorageAccountingApi,
+    docs,
+    local_storage::{models::*, LocalStorageAccountingApi},
     types::response::{ResponseEnum, ResponseResult},
 };
 
+#[openapi(tag = "Funders")]
+#[put("/<id>", format = "application/json", data = "<funder>")]
+pub async fn update_funder(
+    id: Uuid,
+    funder: Json<UpdateFunder>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Funder> {
+    let funder = storage.update_funder(id, &funder).await?;
+    Ok(ResponseEnum::ok(funder, "funder.updated".into()))
+}
+
+#[openapi(tag = "Funders")]
 #[delete("/<id>")]
 pub async fn delete_funder(
     id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<()> {
     storage.delete_funder(id).await?;
-    Ok(ResponseEnum::ok((), "تم مسح الممول بنجاح".into()))
+    Ok(ResponseEnum::ok((), "funder.deleted".into()))
 }
 
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("funders stage", |rocket| async {
-        rocket.mount("/api/funders", routes![delete_funder,])
+        docs::mount(
+            rocket,
+            "/api/funders",
+            openapi_get_routes_spec![update_funder, delete_funder,],
+        )
     })
 }
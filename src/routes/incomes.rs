@@ -1,17 +1,33 @@
-use rocket::{delete, fairing::AdHoc, get, routes, FromForm, State};
+use rocket::{
+    delete,
+    fairing::AdHoc,
+    form::Form,
+    fs::{NamedFile, TempFile},
+    get, post,
+    tokio::fs::File,
+    FromForm,
+};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
 use sqlx::types::Uuid;
 
 use crate::{
-    accounting_api::AcountingApi,
-    auth::AGuard,
+    accounting_api::{self, AcountingApi},
+    auth::{AGuard, UGuard},
+    docs::{self, Binary},
+    export::{self, CsvFile},
+    import::{self, ImportForm},
     local_storage::{models, LocalStorageAccountingApi},
-    types::response::{ResponseEnum, ResponseResult},
+    pdf::PdfDocument,
+    types::response::{ExportFile, PdfResult, ResponseEnum, ResponseResult},
 };
 
-#[derive(Debug, FromForm, PartialEq)]
+#[derive(Debug, FromForm, PartialEq, JsonSchema)]
 #[allow(dead_code)]
 pub struct GetParam {
+    #[schemars(rename = "company.id", with = "Option<Uuid>")]
     company: Option<Company>,
+    #[schemars(rename = "admin.id", with = "Option<Uuid>")]
     admin: Option<User>,
 }
 
@@ -27,30 +43,156 @@ struct User {
     id: Uuid,
 }
 
+#[openapi(tag = "Incomes")]
 #[get("/?<param..>")]
 pub async fn get_incomes(
     param: GetParam,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
 ) -> ResponseResult<Vec<models::Income>> {
     rocket::debug!("{param:?}");
     let incomes = storage
         .get_incomes(param.admin.map(|u| u.id), param.company.map(|c| c.id))
         .await?;
-    Ok(ResponseEnum::ok(incomes, "تم ايجاد رؤؤوس اموال".into()))
+    Ok(ResponseEnum::ok(incomes, "income.found".into()))
+}
+
+#[openapi(tag = "Incomes")]
+#[get("/export/csv?<param..>")]
+pub async fn export_incomes_csv(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> CsvFile<'_> {
+    export::csv(storage.stream_incomes(param.admin.map(|u| u.id), param.company.map(|c| c.id)))
+}
+
+#[openapi(tag = "Incomes")]
+#[get("/export/xlsx?<param..>")]
+pub async fn export_incomes_xlsx(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> Result<ExportFile<File>, ResponseEnum<()>> {
+    let file = export::xlsx(
+        storage.stream_incomes(param.admin.map(|u| u.id), param.company.map(|c| c.id)),
+    )
+    .await?;
+    Ok(file)
+}
+
+#[openapi(tag = "Incomes")]
+#[post("/import?<dry_run>", data = "<form>")]
+pub async fn import_incomes(
+    dry_run: bool,
+    mut form: Form<ImportForm<'_>>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<models::ImportReport> {
+    let sheet = import::read::<models::ImportIncome>(&mut form).await?;
+    let errors = storage
+        .import_incomes(ag.0, &sheet.rows, sheet.commit(dry_run))
+        .await?;
+    sheet.report(dry_run, errors)
+}
+
+#[derive(FromForm, Debug, JsonSchema)]
+struct Attachments<'r> {
+    #[schemars(with = "Vec<Binary>")]
+    files: Vec<TempFile<'r>>,
+}
+
+#[openapi(tag = "Incomes")]
+#[post("/<id>/attachments", data = "<upload>")]
+async fn create_income_attachments(
+    id: Uuid,
+    mut upload: Form<Attachments<'_>>,
+    storage: &LocalStorageAccountingApi,
+    _g: AGuard,
+) -> ResponseResult<Vec<models::Attachment>> {
+    let attachments = storage
+        .create_income_attachments(id, &mut upload.files)
+        .await?;
+    Ok(ResponseEnum::created(
+        attachments,
+        "attachment.created".into(),
+    ))
+}
+
+#[openapi(tag = "Incomes")]
+#[get("/<id>/attachments/<attachment_id>")]
+pub async fn download_income_attachment(
+    id: Uuid,
+    attachment_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ug: UGuard,
+) -> Result<NamedFile, ResponseEnum<()>> {
+    let path = storage.get_attachment(id, attachment_id).await?;
+    rocket::info!("[incomes|attachments] requesting: {path:?}");
+    NamedFile::open(path)
+        .await
+        .map_err(|e| ResponseEnum::from(accounting_api::Error::from(e)))
+}
+
+#[openapi(tag = "Incomes")]
+#[delete("/<id>/attachments/<attachment_id>")]
+pub async fn delete_income_attachment(
+    id: Uuid,
+    attachment_id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _g: AGuard,
+) -> ResponseResult<()> {
+    storage.delete_attachment(id, attachment_id).await?;
+    Ok(ResponseEnum::ok((), "attachment.deleted".into()))
+}
+
+#[openapi(tag = "Incomes")]
+#[get("/<id>/receipt?<save>")]
+pub async fn get_income_receipt(
+    id: Uuid,
+    save: Option<bool>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> PdfResult {
+    let income = storage.get_income(id).await?;
+    let company = storage.get_company(income.company_id).await?;
+    let pdf = PdfDocument::receipt(&income)
+        .generate(
+            storage,
+            Some(&company),
+            format!("receipt-{}.pdf", income.id),
+            save.unwrap_or(false),
+        )
+        .await?;
+    Ok(pdf)
 }
 
+#[openapi(tag = "Incomes")]
 #[delete("/<id>")]
 pub async fn delete_income(
     id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<()> {
     storage.delete_income(id).await?;
-    Ok(ResponseEnum::ok((), "تم مسح واردات".into()))
+    Ok(ResponseEnum::ok((), "income.deleted".into()))
 }
 
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("incomes stage", |rocket| async {
-        rocket.mount("/api/incomes", routes![get_incomes, delete_income])
+        docs::mount(
+            rocket,
+            "/api/incomes",
+            openapi_get_routes_spec![
+                get_incomes,
+                export_incomes_csv,
+                export_incomes_xlsx,
+                import_incomes,
+                create_income_attachments,
+                download_income_attachment,
+                delete_income_attachment,
+                get_income_receipt,
+                delete_income
+            ],
+        )
     })
 }
@@ -0,0 +1,249 @@
+use chrono::Utc;
+use rocket::{
+    delete,
+    fairing::AdHoc,
+    get,
+    http::ContentType,
+    post, put,
+    serde::json::{Json, Value},
+    FromForm, State,
+};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use crate::{
+    accounting_api::AcountingApi,
+    auth::AGuard,
+    docs,
+    eta::Eta,
+    local_storage::{models, LocalStorageAccountingApi},
+    pdf::PdfDocument,
+    types::response::{ExportFile, PdfResult, ResponseEnum, ResponseResult},
+};
+
+#[derive(Debug, FromForm, PartialEq, JsonSchema)]
+#[allow(dead_code)]
+pub struct GetParam {
+    #[schemars(rename = "company.id", with = "Option<Uuid>")]
+    company: Option<Company>,
+    status: Option<models::InvoiceStatus>,
+}
+
+#[derive(Debug, FromForm, PartialEq)]
+#[allow(dead_code)]
+struct Company {
+    id: Uuid,
+}
+
+#[openapi(tag = "Invoices")]
+#[get("/?<param..>")]
+pub async fn get_invoices(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<models::Invoice>> {
+    rocket::debug!("{param:?}");
+    let invoices = storage
+        .get_invoices(param.company.map(|c| c.id), param.status)
+        .await?;
+    Ok(ResponseEnum::ok(invoices, "invoice.found".into()))
+}
+
+/// `id` limits the aging to one company
+#[openapi(tag = "Invoices")]
+#[get("/aging?<id>")]
+pub async fn get_receivables_aging(
+    id: Option<Uuid>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<models::ReceivablesAging>> {
+    let aging = storage
+        .get_receivables_aging(Utc::now().date_naive(), id)
+        .await?;
+    Ok(ResponseEnum::ok(aging, "invoice.aging".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[get("/<id>")]
+pub async fn get_invoice(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<models::Invoice> {
+    let invoice = storage.get_invoice(id).await?;
+    Ok(ResponseEnum::ok(invoice, "invoice.foundOne".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[put("/<id>", format = "application/json", data = "<invoice>")]
+pub async fn update_invoice(
+    id: Uuid,
+    invoice: Json<models::UpdateInvoice>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<models::Invoice> {
+    let invoice = storage.update_invoice(id, &invoice).await?;
+    Ok(ResponseEnum::ok(invoice, "invoice.updated".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[post("/<id>/issue", data = "<issue>")]
+pub async fn issue_invoice(
+    id: Uuid,
+    issue: Option<Json<models::IssueInvoice>>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<models::Invoice> {
+    let issue_date = issue
+        .and_then(|issue| issue.issue_date)
+        .unwrap_or_else(|| Utc::now().date_naive());
+    let invoice = storage.issue_invoice(id, issue_date).await?;
+    Ok(ResponseEnum::ok(invoice, "invoice.issued".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[post("/<id>/void")]
+pub async fn void_invoice(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<models::Invoice> {
+    let invoice = storage.void_invoice(id).await?;
+    Ok(ResponseEnum::ok(invoice, "invoice.voided".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[post("/<id>/payments", format = "application/json", data = "<payment>")]
+pub async fn create_invoice_payment(
+    id: Uuid,
+    payment: Json<models::CreateInvoicePayment>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<models::Invoice> {
+    let invoice = storage.create_invoice_payment(ag.0, id, &payment).await?;
+    Ok(ResponseEnum::created(invoice, "invoice.paid".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[get("/<id>/pdf?<save>")]
+pub async fn get_invoice_pdf(
+    id: Uuid,
+    save: Option<bool>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> PdfResult {
+    let invoice = storage.get_invoice(id).await?;
+    let company = storage.get_company(invoice.company_id).await?;
+    let name = format!(
+        "invoice-{}.pdf",
+        invoice
+            .number
+            .clone()
+            .unwrap_or_else(|| invoice.id.to_string())
+    );
+    let pdf = PdfDocument::invoice(&invoice)
+        .generate(storage, Some(&company), name, save.unwrap_or(false))
+        .await?;
+    Ok(pdf)
+}
+
+#[openapi(tag = "Invoices")]
+#[get("/<id>/eta")]
+pub async fn get_eta_document(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<models::EtaDocument> {
+    let document = storage.get_eta_document(id).await?;
+    Ok(ResponseEnum::ok(document, "eta.found".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[get("/<id>/eta/document")]
+pub async fn export_eta_document(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    eta: &State<Eta>,
+    _ag: AGuard,
+) -> Result<ExportFile<Json<Value>>, ResponseEnum<()>> {
+    let document = eta.document(storage, id).await?;
+    Ok(ExportFile {
+        name: format!("eta-{id}.json"),
+        content_type: ContentType::JSON,
+        body: Json(document),
+    })
+}
+
+#[openapi(tag = "Invoices")]
+#[post("/<id>/eta")]
+pub async fn submit_eta_document(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    eta: &State<Eta>,
+    _ag: AGuard,
+) -> ResponseResult<models::EtaDocument> {
+    let document = eta.submit(storage, id).await?;
+    Ok(ResponseEnum::created(document, "eta.submitted".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[post("/<id>/eta/refresh")]
+pub async fn refresh_eta_document(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    eta: &State<Eta>,
+    _ag: AGuard,
+) -> ResponseResult<models::EtaDocument> {
+    let document = eta.refresh(storage, id).await?;
+    Ok(ResponseEnum::ok(document, "eta.refreshed".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[post("/<id>/eta/cancel", format = "application/json", data = "<cancel>")]
+pub async fn cancel_eta_document(
+    id: Uuid,
+    cancel: Json<models::CancelEtaDocument>,
+    storage: &LocalStorageAccountingApi,
+    eta: &State<Eta>,
+    _ag: AGuard,
+) -> ResponseResult<models::EtaDocument> {
+    let document = eta.cancel(storage, id, &cancel.reason).await?;
+    Ok(ResponseEnum::ok(document, "eta.cancelled".into()))
+}
+
+#[openapi(tag = "Invoices")]
+#[delete("/<id>")]
+pub async fn delete_invoice(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    storage.delete_invoice(id).await?;
+    Ok(ResponseEnum::ok((), "invoice.deleted".into()))
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("invoices stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/invoices",
+            openapi_get_routes_spec![
+                get_invoices,
+                get_receivables_aging,
+                get_invoice,
+                update_invoice,
+                issue_invoice,
+                void_invoice,
+                create_invoice_payment,
+                get_invoice_pdf,
+                get_eta_document,
+                export_eta_document,
+                submit_eta_document,
+                refresh_eta_document,
+                cancel_eta_document,
+                delete_invoice,
+            ],
+        )
+    })
+}
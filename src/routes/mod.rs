@@ -1,10 +1,17 @@
 use rocket::fairing::AdHoc;
 
 pub mod company;
+pub mod deadlines;
 pub mod funders;
 pub mod documents;
+pub mod events;
 pub mod expenses;
 pub mod incomes;
+pub mod invoices;
+pub mod notifications;
+pub mod portal;
+pub mod schedules;
+pub mod tenants;
 pub mod user;
 
 pub fn stage() -> AdHoc {
@@ -16,5 +23,12 @@ pub fn stage() -> AdHoc {
             .attach(expenses::stage())
             .attach(incomes::stage())
             .attach(documents::stage())
+            .attach(notifications::stage())
+            .attach(schedules::stage())
+            .attach(invoices::stage())
+            .attach(deadlines::stage())
+            .attach(events::stage())
+            .attach(tenants::stage())
+            .attach(portal::stage())
     })
 }
@@ -0,0 +1,82 @@
+use rocket::{fairing::AdHoc, get, post};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use sqlx::types::Uuid;
+
+use crate::{
+    accounting_api::AcountingApi,
+    auth::UGuard,
+    docs,
+    local_storage::{models, LocalStorageAccountingApi},
+    types::response::{ResponseEnum, ResponseResult},
+};
+
+#[openapi(tag = "Notifications")]
+#[get("/?<unread>")]
+pub async fn get_notifications(
+    unread: Option<bool>,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<Vec<models::Notification>> {
+    let notifications = storage
+        .get_notifications(ug.0, unread.unwrap_or(false))
+        .await?;
+    Ok(ResponseEnum::ok(notifications, "notification.found".into()))
+}
+
+#[openapi(tag = "Notifications")]
+#[get("/unread-count")]
+pub async fn count_unread_notifications(
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<i64> {
+    let count = storage.count_unread_notifications(ug.0).await?;
+    Ok(ResponseEnum::ok(count, "notification.counted".into()))
+}
+
+#[openapi(tag = "Notifications")]
+#[post("/<id>/read")]
+pub async fn read_notification(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<models::Notification> {
+    let notification = storage.mark_notification_read(ug.0, id, true).await?;
+    Ok(ResponseEnum::ok(notification, "notification.read".into()))
+}
+
+#[openapi(tag = "Notifications")]
+#[post("/<id>/unread")]
+pub async fn unread_notification(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<models::Notification> {
+    let notification = storage.mark_notification_read(ug.0, id, false).await?;
+    Ok(ResponseEnum::ok(notification, "notification.unread".into()))
+}
+
+#[openapi(tag = "Notifications")]
+#[post("/read")]
+pub async fn read_all_notifications(
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<u64> {
+    let marked = storage.mark_all_notifications_read(ug.0).await?;
+    Ok(ResponseEnum::ok(marked, "notification.readAll".into()))
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("notifications stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/notifications",
+            openapi_get_routes_spec![
+                get_notifications,
+                count_unread_notifications,
+                read_notification,
+                unread_notification,
+                read_all_notifications,
+            ],
+        )
+    })
+}
@@ -0,0 +1,189 @@
+use std::net::IpAddr;
+
+use rocket::{
+    fairing::AdHoc,
+    form::Form,
+    fs::{NamedFile, TempFile},
+    get, post,
+    serde::json::Json,
+    FromForm, State,
+};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use crate::{
+    accounting_api::{self, AcountingApi},
+    auth::ApiToken,
+    docs::{self, Binary},
+    local_storage::{models::*, Portal, Tenants},
+    pdf::PdfDocument,
+    types::response::{PdfResult, ResponseEnum, ResponseResult},
+};
+
+#[openapi(tag = "Portal")]
+#[post("/login", format = "application/json", data = "<company>")]
+pub async fn login_company(
+    company: Json<LoginCompany>,
+    ip: Option<IpAddr>,
+    tenants: &State<Tenants>,
+) -> ResponseResult<ApiToken<'static>> {
+    let (tenant_id, company, session) = tenants.login_company(&company, ip).await?;
+    let token = ApiToken::company(company.id, tenant_id, session);
+    Ok(ResponseEnum::ok(token, "portal.loggedIn".into()))
+}
+
+#[openapi(tag = "Portal")]
+#[get("/company")]
+pub async fn get_company(portal: Portal<'_>) -> ResponseResult<PortalCompany> {
+    let company = portal.storage.get_company(portal.company_id).await?;
+    Ok(ResponseEnum::ok(company.into(), "company.found".into()))
+}
+
+#[openapi(tag = "Portal")]
+#[get("/incomes")]
+pub async fn get_incomes(portal: Portal<'_>) -> ResponseResult<Vec<PortalIncome>> {
+    let incomes = portal
+        .storage
+        .get_incomes(None, Some(portal.company_id))
+        .await?;
+    Ok(ResponseEnum::ok(
+        incomes.into_iter().map(Into::into).collect(),
+        "income.found".into(),
+    ))
+}
+
+/// the issued invoices, drafts are still being prepared by the office
+async fn issued_invoices(portal: &Portal<'_>) -> Result<Vec<Invoice>, accounting_api::Error> {
+    let invoices = portal
+        .storage
+        .get_invoices(Some(portal.company_id), None)
+        .await?;
+    Ok(invoices
+        .into_iter()
+        .filter(|invoice| invoice.status != InvoiceStatus::Draft)
+        .collect())
+}
+
+#[openapi(tag = "Portal")]
+#[get("/invoices")]
+pub async fn get_invoices(portal: Portal<'_>) -> ResponseResult<Vec<PortalInvoice>> {
+    let invoices = issued_invoices(&portal).await?;
+    Ok(ResponseEnum::ok(
+        invoices.into_iter().map(Into::into).collect(),
+        "invoice.found".into(),
+    ))
+}
+
+#[openapi(tag = "Portal")]
+#[get("/invoices/<id>/pdf")]
+pub async fn get_invoice_pdf(id: Uuid, portal: Portal<'_>) -> PdfResult {
+    let invoice = portal.storage.get_invoice(id).await?;
+    if invoice.company_id != portal.company_id || invoice.status == InvoiceStatus::Draft {
+        return Err(accounting_api::Error::ObjectNotFound.into());
+    }
+    let company = portal.storage.get_company(portal.company_id).await?;
+    let name = format!(
+        "invoice-{}.pdf",
+        invoice
+            .number
+            .clone()
+            .unwrap_or_else(|| invoice.id.to_string())
+    );
+    let pdf = PdfDocument::invoice(&invoice)
+        .generate(portal.storage, Some(&company), name, false)
+        .await?;
+    Ok(pdf)
+}
+
+#[openapi(tag = "Portal")]
+#[get("/balance")]
+pub async fn get_balance(portal: Portal<'_>) -> ResponseResult<PortalBalance> {
+    let entries = portal
+        .storage
+        .get_company_statement(portal.company_id)
+        .await?;
+    let outstanding = issued_invoices(&portal)
+        .await?
+        .iter()
+        .filter(|invoice| invoice.status != InvoiceStatus::Void)
+        .map(|invoice| invoice.total - invoice.paid)
+        .sum();
+    let balance = PortalBalance {
+        balance: entries
+            .last()
+            .map(|entry| entry.balance)
+            .unwrap_or_default(),
+        outstanding,
+        entries: entries.into_iter().map(Into::into).collect(),
+    };
+    Ok(ResponseEnum::ok(balance, "company.statement".into()))
+}
+
+#[openapi(tag = "Portal")]
+#[get("/documents")]
+pub async fn get_documents(portal: Portal<'_>) -> ResponseResult<Vec<Document>> {
+    let documents = portal.storage.get_documents(portal.company_id).await?;
+    Ok(ResponseEnum::ok(documents, "document.found".into()))
+}
+
+/// `name` is one of the documents of the company, never a path
+#[openapi(tag = "Portal")]
+#[get("/documents/<name>")]
+pub async fn download_document(
+    name: &str,
+    portal: Portal<'_>,
+) -> Result<NamedFile, ResponseEnum<()>> {
+    let document = portal
+        .storage
+        .get_documents(portal.company_id)
+        .await?
+        .into_iter()
+        .find(|document| document.name == name)
+        .ok_or(accounting_api::Error::ObjectNotFound)?;
+    let path = portal.storage.fs.read().await.root.join(&document.path);
+    let file = NamedFile::open(path)
+        .await
+        .map_err(accounting_api::Error::from)?;
+    Ok(file)
+}
+
+#[derive(FromForm, Debug, JsonSchema)]
+struct Upload<'r> {
+    #[schemars(with = "Binary")]
+    file: TempFile<'r>,
+}
+
+/// sends a document to the office, they are notified of it
+#[openapi(tag = "Portal")]
+#[post("/documents", data = "<upload>")]
+async fn upload_document(
+    mut upload: Form<Upload<'_>>,
+    portal: Portal<'_>,
+) -> ResponseResult<Document> {
+    let document = portal
+        .storage
+        .create_document(portal.company_id, &mut upload.file)
+        .await?;
+    Ok(ResponseEnum::created(document, "document.created".into()))
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("portal stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/portal",
+            openapi_get_routes_spec![
+                login_company,
+                get_company,
+                get_incomes,
+                get_invoices,
+                get_invoice_pdf,
+                get_balance,
+                get_documents,
+                download_document,
+                upload_document,
+            ],
+        )
+    })
+}
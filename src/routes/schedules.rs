@@ -0,0 +1,86 @@
+use chrono::Utc;
+use rocket::{delete, fairing::AdHoc, get, post, put, serde::json::Json, FromForm};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
+use sqlx::types::Uuid;
+
+use crate::{
+    accounting_api::AcountingApi,
+    auth::AGuard,
+    docs,
+    local_storage::{models, LocalStorageAccountingApi},
+    types::response::{ResponseEnum, ResponseResult},
+};
+
+#[derive(Debug, FromForm, PartialEq, JsonSchema)]
+#[allow(dead_code)]
+pub struct GetParam {
+    #[schemars(rename = "company.id", with = "Option<Uuid>")]
+    company: Option<Company>,
+}
+
+#[derive(Debug, FromForm, PartialEq)]
+#[allow(dead_code)]
+struct Company {
+    id: Uuid,
+}
+
+#[openapi(tag = "Schedules")]
+#[get("/?<param..>")]
+pub async fn get_schedules(
+    param: GetParam,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<models::Schedule>> {
+    rocket::debug!("{param:?}");
+    let schedules = storage.get_schedules(param.company.map(|c| c.id)).await?;
+    Ok(ResponseEnum::ok(schedules, "schedule.found".into()))
+}
+
+#[openapi(tag = "Schedules")]
+#[put("/<id>", format = "application/json", data = "<schedule>")]
+pub async fn update_schedule(
+    id: Uuid,
+    schedule: Json<models::UpdateSchedule>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<models::Schedule> {
+    let schedule = storage.update_schedule(id, &schedule).await?;
+    Ok(ResponseEnum::ok(schedule, "schedule.updated".into()))
+}
+
+#[openapi(tag = "Schedules")]
+#[delete("/<id>")]
+pub async fn delete_schedule(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    storage.delete_schedule(id).await?;
+    Ok(ResponseEnum::ok((), "schedule.deleted".into()))
+}
+
+#[openapi(tag = "Schedules")]
+#[post("/run")]
+pub async fn run_schedules(
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<u64> {
+    let generated = storage.run_schedules(Utc::now().date_naive()).await?;
+    Ok(ResponseEnum::ok(generated, "schedule.ran".into()))
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("schedules stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/schedules",
+            openapi_get_routes_spec![
+                get_schedules,
+                update_schedule,
+                delete_schedule,
+                run_schedules
+            ],
+        )
+    })
+}
@@ -0,0 +1,49 @@
+use rocket::{fairing::AdHoc, get, post, serde::json::Json, State};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+
+use crate::{
+    auth::{ApiToken, SGuard},
+    docs,
+    local_storage::{models::*, Tenants},
+    types::response::{ResponseEnum, ResponseResult},
+};
+
+#[openapi(tag = "Tenants")]
+#[post("/login", format = "application/json", data = "<super_admin>")]
+pub async fn login_super_admin(
+    super_admin: Json<LoginSuperAdmin>,
+    tenants: &State<Tenants>,
+) -> ResponseResult<ApiToken<'static>> {
+    let super_admin = tenants.login_super_admin(&super_admin).await?;
+    let token = ApiToken::super_admin(super_admin.id);
+    Ok(ResponseEnum::ok(token, "tenant.loggedIn".into()))
+}
+
+/// creates the office with its first admin
+#[openapi(tag = "Tenants")]
+#[post("/", format = "application/json", data = "<tenant>")]
+pub async fn create_tenant(
+    tenant: Json<CreateTenant>,
+    tenants: &State<Tenants>,
+    _sg: SGuard,
+) -> ResponseResult<CreatedTenant> {
+    let tenant = tenants.create_tenant(&tenant).await?;
+    Ok(ResponseEnum::created(tenant, "tenant.created".into()))
+}
+
+#[openapi(tag = "Tenants")]
+#[get("/")]
+pub async fn get_tenants(tenants: &State<Tenants>, _sg: SGuard) -> ResponseResult<Vec<Tenant>> {
+    let tenants = tenants.get_tenants().await?;
+    Ok(ResponseEnum::ok(tenants, "tenant.found".into()))
+}
+
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("tenants stage", |rocket| async {
+        docs::mount(
+            rocket,
+            "/api/tenants",
+            openapi_get_routes_spec![login_super_admin, create_tenant, get_tenants],
+        )
+    })
+}
@@ -1,114 +1,509 @@
+use std::net::IpAddr;
+
 use rocket::fairing::AdHoc;
-use rocket::serde::json::Json;
-use rocket::serde::{Deserialize, Serialize};
+use rocket::serde::{json::Json, Serialize};
 
-use rocket::{delete, get, patch, post, routes, State};
+use rocket::{delete, get, patch, post, put, State};
+use rocket_okapi::{openapi, openapi_get_routes_spec};
+use schemars::JsonSchema;
 use sqlx::types::Uuid;
 
-use crate::accounting_api::AcountingApi;
-use crate::auth::{AGuard, ApiToken, UGuard};
+use crate::accounting_api::{self, AcountingApi};
+use crate::auth::{AGuard, ApiToken, CGuard, PGuard, TGuard, UGuard};
+use crate::docs;
 use crate::local_storage::{LocalStorageAccountingApi, *};
 
-use crate::types::response::{ResponseEnum, ResponseResult};
+use crate::pdf::PdfDocument;
+use crate::types::response::{PdfResult, ResponseEnum, ResponseResult};
+
+/// opens a session on the device and signs the user in with it, a user who
+/// must change their password only gets a token for that
+async fn sign_in(
+    storage: &LocalStorageAccountingApi,
+    user: &User,
+    device: &CreateSession,
+) -> Result<ApiToken<'static>, accounting_api::Error> {
+    if user.must_change_password {
+        return Ok(ApiToken::change_password(user.id, storage.tenant_id));
+    }
+    let session = storage.create_session(user.id, device).await?;
+    Ok(ApiToken::generate(
+        user.id,
+        storage.tenant_id,
+        session.id,
+        user.is_admin,
+        user.is_reviewer,
+    ))
+}
+
+/// the answer of the last login step with the token of `sign_in`
+fn logged_in(token: ApiToken<'static>, user: &User) -> ResponseEnum<ApiToken<'static>> {
+    if user.must_change_password {
+        ResponseEnum::accepted(token, "user.passwordChangeRequired".into())
+    } else {
+        ResponseEnum::ok(token, "user.loggedIn".into())
+    }
+}
 
+#[openapi(tag = "Users")]
 #[post("/login", format = "application/json", data = "<user>")]
 pub async fn login_user(
     user: Json<LoginUser>,
-    storage: &State<LocalStorageAccountingApi>,
-) -> ResponseResult<ApiToken> {
-    let user = storage.login_user(&user).await?;
-    let token = ApiToken::generate(user.id, user.is_admin);
-    Ok(ResponseEnum::ok(token, "تم تسجيل الدخول بنجاح".into()))
+    ip: Option<IpAddr>,
+    device: CreateSession,
+    tenants: &State<Tenants>,
+) -> ResponseResult<ApiToken<'_>> {
+    let (tenant_id, user, two_factor) = tenants.login_user(&user, ip).await?;
+    if two_factor.enabled || two_factor.required {
+        let token = ApiToken::pre_auth(user.id, tenant_id);
+        let message = if two_factor.enabled {
+            "user.twoFactorRequired"
+        } else {
+            "user.twoFactorSetupRequired"
+        };
+        return Ok(ResponseEnum::accepted(token, message.into()));
+    }
+    let token = sign_in(&*tenants.storage(tenant_id).await?, &user, &device).await?;
+    Ok(logged_in(token, &user))
+}
+
+/// the second login step, `code` is an authenticator code or a recovery code
+#[openapi(tag = "Users")]
+#[post("/login/two-factor", format = "application/json", data = "<code>")]
+pub async fn login_two_factor(
+    code: Json<TwoFactorCode>,
+    ip: Option<IpAddr>,
+    device: CreateSession,
+    tenants: &State<Tenants>,
+    pg: PGuard,
+) -> ResponseResult<ApiToken<'static>> {
+    let user = tenants.login_two_factor(pg.1, pg.0, &code.code, ip).await?;
+    let token = sign_in(&*tenants.storage(pg.1).await?, &user, &device).await?;
+    Ok(logged_in(token, &user))
+}
+
+/// sets a new password, every device is signed out and this one signed in
+/// again with the returned token
+#[openapi(tag = "Users")]
+#[post("/current/password", format = "application/json", data = "<password>")]
+pub async fn change_password(
+    password: Json<ChangePassword>,
+    device: CreateSession,
+    tenants: &State<Tenants>,
+    cg: CGuard,
+) -> ResponseResult<ApiToken<'static>> {
+    let storage = tenants.storage(cg.1).await?;
+    storage.change_password(cg.0, &password).await?;
+    let user = storage.get_user(cg.0).await?;
+    let token = sign_in(&storage, &user, &device).await?;
+    Ok(ResponseEnum::ok(token, "user.passwordChanged".into()))
+}
+
+/// a single use token for a user who forgot their password, the admin hands
+/// it to them
+#[openapi(tag = "Users")]
+#[post("/<id>/password-reset")]
+pub async fn create_password_reset(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<PasswordReset> {
+    storage.get_user(id).await?;
+    let reset = storage.create_password_reset(id).await?;
+    Ok(ResponseEnum::created(
+        reset,
+        "user.passwordResetCreated".into(),
+    ))
+}
+
+/// sets the password with a reset token, every device of the user is signed
+/// out
+#[openapi(tag = "Users")]
+#[post("/password-reset", format = "application/json", data = "<reset>")]
+pub async fn reset_password(
+    reset: Json<ResetPassword>,
+    tenants: &State<Tenants>,
+) -> ResponseResult<()> {
+    tenants.reset_password(&reset).await?;
+    Ok(ResponseEnum::ok((), "user.passwordReset".into()))
+}
+
+/// the staff logins of the office, the failed ones are the guessed passwords
+#[openapi(tag = "Users")]
+#[get("/login-attempts?<outcome>")]
+pub async fn get_login_attempts(
+    outcome: Option<LoginOutcome>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<LoginAttempt>> {
+    let attempts = storage.get_login_attempts(outcome).await?;
+    Ok(ResponseEnum::ok(attempts, "user.loginAttempts".into()))
+}
+
+/// lets a user locked out by failed logins try again at once
+#[openapi(tag = "Users")]
+#[delete("/<id>/lockout")]
+pub async fn unlock_user(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    tenants: &State<Tenants>,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    // only the users of the office of the admin
+    storage.get_user(id).await?;
+    tenants.unlock_user(id).await?;
+    Ok(ResponseEnum::ok((), "user.unlocked".into()))
+}
+
+#[openapi(tag = "Users")]
+#[get("/two-factor")]
+pub async fn get_two_factor(
+    tenants: &State<Tenants>,
+    tg: TGuard,
+) -> ResponseResult<TwoFactorStatus> {
+    let status = tenants.storage(tg.1).await?.get_two_factor(tg.0).await?;
+    Ok(ResponseEnum::ok(status, "user.twoFactor".into()))
+}
+
+/// a new authenticator secret, confirmed by `enable`
+#[openapi(tag = "Users")]
+#[post("/two-factor/setup")]
+pub async fn setup_two_factor(
+    tenants: &State<Tenants>,
+    tg: TGuard,
+) -> ResponseResult<TwoFactorSetup> {
+    let setup = tenants.storage(tg.1).await?.setup_two_factor(tg.0).await?;
+    Ok(ResponseEnum::ok(setup, "user.twoFactorSetUp".into()))
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct TwoFactorEnabled {
+    /// shown once, each one signs in a single time without the authenticator
+    pub recovery_codes: Vec<String>,
+    /// signs in the users who set up their authenticator during the login
+    pub token: ApiToken<'static>,
+}
+
+#[openapi(tag = "Users")]
+#[post("/two-factor/enable", format = "application/json", data = "<code>")]
+pub async fn enable_two_factor(
+    code: Json<TwoFactorCode>,
+    device: CreateSession,
+    tenants: &State<Tenants>,
+    tg: TGuard,
+) -> ResponseResult<TwoFactorEnabled> {
+    let storage = tenants.storage(tg.1).await?;
+    let recovery_codes = storage.enable_two_factor(tg.0, &code.code).await?;
+    let user = storage.get_user(tg.0).await?;
+    let enabled = TwoFactorEnabled {
+        recovery_codes,
+        token: sign_in(&storage, &user, &device).await?,
+    };
+    Ok(ResponseEnum::ok(enabled, "user.twoFactorEnabled".into()))
+}
+
+/// replaces the recovery codes, `code` is an authenticator code
+#[openapi(tag = "Users")]
+#[post(
+    "/two-factor/recovery-codes",
+    format = "application/json",
+    data = "<code>"
+)]
+pub async fn regenerate_recovery_codes(
+    code: Json<TwoFactorCode>,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<Vec<String>> {
+    let codes = storage.regenerate_recovery_codes(ug.0, &code.code).await?;
+    Ok(ResponseEnum::ok(codes, "user.recoveryCodes".into()))
+}
+
+/// turns the authenticator of the current user off, refused to the admins
+/// of an office enforcing it
+#[openapi(tag = "Users")]
+#[delete("/two-factor", format = "application/json", data = "<code>")]
+pub async fn disable_two_factor(
+    code: Json<TwoFactorCode>,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<()> {
+    if storage.get_two_factor(ug.0).await?.required {
+        return Err(accounting_api::Error::TwoFactorEnforced.into());
+    }
+    storage.verify_two_factor(ug.0, &code.code).await?;
+    storage.disable_two_factor(ug.0).await?;
+    Ok(ResponseEnum::ok((), "user.twoFactorDisabled".into()))
+}
+
+/// removes the authenticator of a user who lost it, they set up a new one
+/// at their next login when it is required
+#[openapi(tag = "Users")]
+#[delete("/<id>/two-factor")]
+pub async fn reset_two_factor(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    storage.disable_two_factor(id).await?;
+    Ok(ResponseEnum::ok((), "user.twoFactorReset".into()))
 }
 
+#[openapi(tag = "Users")]
+#[get("/two-factor/policy")]
+pub async fn get_two_factor_policy(
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<TwoFactorPolicy> {
+    let policy = storage.get_two_factor_policy().await?;
+    Ok(ResponseEnum::ok(policy, "user.twoFactorPolicy".into()))
+}
+
+#[openapi(tag = "Users")]
+#[put("/two-factor/policy", format = "application/json", data = "<policy>")]
+pub async fn update_two_factor_policy(
+    policy: Json<TwoFactorPolicy>,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<TwoFactorPolicy> {
+    let policy = storage.update_two_factor_policy(&policy).await?;
+    Ok(ResponseEnum::ok(
+        policy,
+        "user.twoFactorPolicyUpdated".into(),
+    ))
+}
+
+#[openapi(tag = "Users")]
 #[post("/", format = "application/json", data = "<user>")]
 pub async fn register_user(
     user: Json<RegisterUser>,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<User> {
     let user = storage.register_user(&user).await?;
-    Ok(ResponseEnum::created(
-        user,
-        "تم تسجيل مستخدم جديد بنجاح".into(),
-    ))
+    Ok(ResponseEnum::created(user, "user.registered".into()))
 }
 
+// documented by the other route of the same path
+#[openapi(skip)]
 #[get("/")]
 pub async fn get_users_admin(
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<Vec<User>> {
     let users = storage.get_users().await?;
-    Ok(ResponseEnum::ok(users, "تم ايجاد مستخدمين".into()))
+    Ok(ResponseEnum::ok(users, "user.found".into()))
 }
 
+#[openapi(tag = "Users")]
 #[get("/", rank = 2)]
 pub async fn get_users_user(
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ug: UGuard,
 ) -> ResponseResult<Vec<User>> {
     let users = storage.get_users().await?;
-    Ok(ResponseEnum::ok(users, "تم ايجاد مستخدمين".into()))
+    Ok(ResponseEnum::ok(users, "user.found".into()))
 }
 
+#[openapi(tag = "Users")]
 #[get("/current")]
 pub async fn get_current_user(
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     ug: UGuard,
 ) -> ResponseResult<User> {
     let user = storage.get_user(ug.0).await?;
-    Ok(ResponseEnum::ok(user, "تم ايجاد مستخدمين".into()))
+    Ok(ResponseEnum::ok(user, "user.found".into()))
 }
 
+// documented by the other route of the same path
+#[openapi(skip)]
 #[get("/current", rank = 2)]
 pub async fn get_current_admin(
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     ag: AGuard,
 ) -> ResponseResult<User> {
     let user = storage.get_user(ag.0).await?;
-    Ok(ResponseEnum::ok(user, "تم ايجاد مستخدمين".into()))
+    Ok(ResponseEnum::ok(user, "user.found".into()))
+}
+
+/// the devices the user is signed in on
+#[openapi(tag = "Users")]
+#[get("/current/sessions")]
+pub async fn get_sessions(
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<Vec<Session>> {
+    let sessions = storage.get_sessions(ug.0, ug.1).await?;
+    Ok(ResponseEnum::ok(sessions, "user.sessions".into()))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(crate = "rocket::serde")]
-pub struct Value {
-    value: f64,
+/// signs one device out, the current one too
+#[openapi(tag = "Users")]
+#[delete("/current/sessions/<id>")]
+pub async fn delete_session(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<()> {
+    storage.delete_session(ug.0, id).await?;
+    Ok(ResponseEnum::ok((), "user.sessionDeleted".into()))
+}
+
+/// signs every device out, the current one too
+#[openapi(tag = "Users")]
+#[delete("/current/sessions")]
+pub async fn delete_sessions(
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<()> {
+    storage.delete_sessions(ug.0).await?;
+    Ok(ResponseEnum::ok((), "user.sessionsDeleted".into()))
+}
+
+/// signs every device of the user out, for a lost or stolen device
+#[openapi(tag = "Users")]
+#[delete("/<id>/sessions")]
+pub async fn log_out_user(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<()> {
+    storage.get_user(id).await?;
+    storage.delete_sessions(id).await?;
+    Ok(ResponseEnum::ok((), "user.loggedOut".into()))
 }
 
+#[openapi(tag = "Users")]
 #[patch("/<id>", format = "application/json", data = "<value>")]
 pub async fn pay_user(
     id: Uuid,
-    value: Json<Value>,
-    storage: &State<LocalStorageAccountingApi>,
-    _ag: AGuard,
+    value: Json<CreateCustodyTransaction>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
+) -> ResponseResult<User> {
+    let user = storage.pay_user(ag.0, id, &value).await?;
+    Ok(ResponseEnum::ok(user, "user.paid".into()))
+}
+
+#[openapi(tag = "Users")]
+#[post("/<id>/adjustments", format = "application/json", data = "<value>")]
+pub async fn adjust_user(
+    id: Uuid,
+    value: Json<CreateCustodyTransaction>,
+    storage: &LocalStorageAccountingApi,
+    ag: AGuard,
 ) -> ResponseResult<User> {
-    let user = storage.pay_user(id, value.value).await?;
-    Ok(ResponseEnum::ok(user, "تم تعديل القيمة".into()))
+    let user = storage.adjust_user(ag.0, id, &value).await?;
+    Ok(ResponseEnum::ok(user, "user.adjusted".into()))
+}
+
+// documented by the other route of the same path
+#[openapi(skip)]
+#[get("/<id>/statement")]
+pub async fn get_user_statement_admin(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> ResponseResult<Vec<CustodyTransaction>> {
+    let statement = storage.get_user_statement(id).await?;
+    Ok(ResponseEnum::ok(statement, "user.statement".into()))
+}
+
+#[openapi(tag = "Users")]
+#[get("/<id>/statement", rank = 2)]
+pub async fn get_user_statement_user(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> ResponseResult<Vec<CustodyTransaction>> {
+    if id != ug.0 {
+        return Err(ResponseEnum::forbidden("user.statementForbidden".into()));
+    }
+    let statement = storage.get_user_statement(id).await?;
+    Ok(ResponseEnum::ok(statement, "user.statement".into()))
+}
+
+// documented by the other route of the same path
+#[openapi(skip)]
+#[get("/<id>/statement/pdf")]
+pub async fn get_user_statement_pdf_admin(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    _ag: AGuard,
+) -> PdfResult {
+    let user = storage.get_user(id).await?;
+    let statement = storage.get_user_statement(id).await?;
+    let pdf = PdfDocument::custody_statement(&user, &statement)
+        .generate(storage, None, format!("custody-{}.pdf", user.id), false)
+        .await?;
+    Ok(pdf)
+}
+
+#[openapi(tag = "Users")]
+#[get("/<id>/statement/pdf", rank = 2)]
+pub async fn get_user_statement_pdf_user(
+    id: Uuid,
+    storage: &LocalStorageAccountingApi,
+    ug: UGuard,
+) -> PdfResult {
+    if id != ug.0 {
+        return Err(ResponseEnum::forbidden("user.statementForbidden".into()));
+    }
+    let user = storage.get_user(id).await?;
+    let statement = storage.get_user_statement(id).await?;
+    let pdf = PdfDocument::custody_statement(&user, &statement)
+        .generate(storage, None, format!("custody-{}.pdf", user.id), false)
+        .await?;
+    Ok(pdf)
 }
 
+#[openapi(tag = "Users")]
 #[delete("/<id>")]
 pub async fn delete_user(
     id: Uuid,
-    storage: &State<LocalStorageAccountingApi>,
+    storage: &LocalStorageAccountingApi,
     _ag: AGuard,
 ) -> ResponseResult<()> {
     storage.delete_user(id).await?;
-    Ok(ResponseEnum::ok((), "تم حذف المستخدم".into()))
+    Ok(ResponseEnum::ok((), "user.deleted".into()))
 }
 
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("users stage", |rocket| async {
-        rocket.mount(
+        docs::mount(
+            rocket,
             "/api/users",
-            routes![
+            openapi_get_routes_spec![
                 register_user,
                 login_user,
+                login_two_factor,
+                change_password,
+                create_password_reset,
+                reset_password,
+                get_login_attempts,
+                unlock_user,
+                get_two_factor,
+                setup_two_factor,
+                enable_two_factor,
+                regenerate_recovery_codes,
+                disable_two_factor,
+                reset_two_factor,
+                get_two_factor_policy,
+                update_two_factor_policy,
                 get_users_user,
                 get_users_admin,
                 get_current_user,
                 get_current_admin,
+                get_sessions,
+                delete_session,
+                delete_sessions,
+                log_out_user,
                 pay_user,
+                adjust_user,
+                get_user_statement_admin,
+                get_user_statement_user,
+                get_user_statement_pdf_admin,
+                get_user_statement_pdf_user,
                 delete_user,
             ],
         )
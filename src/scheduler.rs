@@ -0,0 +1,91 @@
+use std::{env, time::Duration};
+
+use chrono::{NaiveDate, Utc};
+use rocket::{
+    fairing::AdHoc,
+    tokio::{self, select, time},
+};
+
+use crate::{
+    accounting_api::AcountingApi,
+    local_storage::{LocalStorageAccountingApi, Tenants},
+};
+
+const DEFAULT_INTERVAL_SECS: u64 = 60 * 60;
+/// how far back deadlines are recorded, covers the server being down for a month
+const DEADLINES_CATCH_UP_DAYS: i64 = 31;
+/// how far ahead deadlines are recorded
+const DEADLINES_HORIZON_DAYS: i64 = 90;
+/// how long before their due date deadlines are reminded
+const DEADLINES_REMINDER_DAYS: i64 = 7;
+
+/// the due dates of the deadlines recorded on `today`
+pub fn deadlines_window(today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    (
+        today - chrono::Duration::days(DEADLINES_CATCH_UP_DAYS),
+        today + chrono::Duration::days(DEADLINES_HORIZON_DAYS),
+    )
+}
+
+/// the tasks of one tenant
+async fn run(storage: &LocalStorageAccountingApi, today: NaiveDate) {
+    match storage.run_schedules(today).await {
+        Ok(generated) => rocket::info!("[scheduler] generated {generated} rows"),
+        Err(error) => rocket::error!("[scheduler] {error}"),
+    }
+    let (from, until) = deadlines_window(today);
+    match storage.sync_deadlines(from, until).await {
+        Ok(added) => rocket::info!("[scheduler] added {added} deadlines"),
+        Err(error) => rocket::error!("[scheduler] {error}"),
+    }
+    let until = today + chrono::Duration::days(DEADLINES_REMINDER_DAYS);
+    match storage.remind_deadlines(until).await {
+        Ok(reminded) => rocket::info!("[scheduler] reminded {reminded} deadlines"),
+        Err(error) => rocket::error!("[scheduler] {error}"),
+    }
+}
+
+/// generates due recurring incomes and expenses, records the upcoming
+/// company deadlines of every tenant and reminds the ones falling due in the background,
+/// the first run on liftoff catches up on anything missed while the server was down
+pub fn stage() -> AdHoc {
+    AdHoc::on_liftoff("scheduler stage", |rocket| {
+        Box::pin(async move {
+            let tenants = rocket
+                .state::<Tenants>()
+                .expect("database stage attached")
+                .clone();
+            let interval = env::var("SCHEDULER_INTERVAL_SECS")
+                .ok()
+                .map(|v| {
+                    v.parse()
+                        .expect("`SCHEDULER_INTERVAL_SECS` must be a number")
+                })
+                .unwrap_or(DEFAULT_INTERVAL_SECS);
+            let mut shutdown = rocket.shutdown();
+
+            tokio::spawn(async move {
+                let mut interval = time::interval(Duration::from_secs(interval));
+                loop {
+                    select! {
+                        _ = interval.tick() => {
+                            let today = Utc::now().date_naive();
+                            match tenants.get_tenants().await {
+                                Ok(offices) => {
+                                    for tenant in offices {
+                                        match tenants.storage(tenant.id).await {
+                                            Ok(storage) => run(&storage, today).await,
+                                            Err(error) => rocket::error!("[scheduler] {error}"),
+                                        }
+                                    }
+                                }
+                                Err(error) => rocket::error!("[scheduler] {error}"),
+                            }
+                        }
+                        _ = &mut shutdown => break,
+                    }
+                }
+            });
+        })
+    })
+}
@@ -0,0 +1,140 @@
+use std::env;
+
+use base32::Alphabet;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{distributions::Uniform, Rng, RngCore};
+use sha1::Sha1;
+
+use crate::hashing;
+
+/// the seconds a code is valid for, the authenticator apps default
+const PERIOD: i64 = 30;
+const DIGITS: u32 = 6;
+/// the steps accepted before and after the current one, for clock drift
+const DRIFT: i64 = 1;
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODES: usize = 10;
+const RECOVERY_CODE_LEN: usize = 10;
+/// easy to read back, no `0`/`O` nor `1`/`I`
+const RECOVERY_CODE_CHARS: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+const ALPHABET: Alphabet = Alphabet::RFC4648 { padding: false };
+
+/// a new base32 secret for an authenticator
+pub fn secret() -> String {
+    let mut bytes = [0; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32::encode(ALPHABET, &bytes)
+}
+
+/// the `otpauth://` uri the authenticator apps scan from a QR code, the issuer
+/// is `TOTP_ISSUER`
+pub fn provisioning_uri(account: &str, secret: &str) -> String {
+    let issuer = env::var("TOTP_ISSUER").unwrap_or_else(|_| "Accounting".into());
+    let issuer = utf8_percent_encode(&issuer, NON_ALPHANUMERIC);
+    let account = utf8_percent_encode(account, NON_ALPHANUMERIC);
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}\
+         &algorithm=SHA1&digits={DIGITS}&period={PERIOD}"
+    )
+}
+
+/// the code of `secret` at the time step `step`, RFC 6238
+fn code(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("any key length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    value % 10u32.pow(DIGITS)
+}
+
+/// the time step `code` belongs to when it is valid now, a step up to
+/// `last_step` was already used and is rejected so a code works once
+pub fn verify(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    verify_at(secret, code, last_step, Utc::now().timestamp())
+}
+
+/// `verify` at the unix time `time`
+fn verify_at(secret: &str, code: &str, last_step: Option<i64>, time: i64) -> Option<i64> {
+    let secret = base32::decode(ALPHABET, secret)?;
+    let code = code.trim();
+    if code.len() != DIGITS as usize {
+        return None;
+    }
+    let code = code.parse::<u32>().ok()?;
+    let now = time / PERIOD;
+    (now - DRIFT..=now + DRIFT)
+        .filter(|step| last_step.map(|last| *step > last).unwrap_or(true))
+        .find(|step| self::code(&secret, *step) == code)
+}
+
+/// new single use recovery codes, shown to the user once
+pub fn recovery_codes() -> Vec<String> {
+    let chars = Uniform::new(0, RECOVERY_CODE_CHARS.len());
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            (0..RECOVERY_CODE_LEN)
+                .map(|_| RECOVERY_CODE_CHARS[rng.sample(chars)] as char)
+                .collect()
+        })
+        .collect()
+}
+
+/// the stored form of a recovery code, ignoring case, spaces and dashes
+pub fn hash_recovery_code(code: &str) -> String {
+    let code: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    hashing::sha256_hex(code.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the SHA-1 seed of RFC 6238 appendix B, "12345678901234567890"
+    const SEED: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn codes_match_the_rfc_vectors() {
+        let secret = base32::decode(ALPHABET, SEED).expect("a base32 seed");
+        // the last six digits of the eight digit codes of the RFC
+        for (time, expected) in [
+            (59, 287082),
+            (1111111109, 81804),
+            (1111111111, 50471),
+            (1234567890, 5924),
+            (2000000000, 279037),
+            (20000000000, 353130),
+        ] {
+            assert_eq!(code(&secret, time / PERIOD), expected, "{time}");
+        }
+    }
+
+    #[test]
+    fn codes_are_accepted_once_within_the_drift() {
+        let time = 1111111111;
+        let step = time / PERIOD;
+        assert_eq!(verify_at(SEED, "050471", None, time), Some(step));
+        // the previous step and the next one are accepted for clock drift
+        assert_eq!(verify_at(SEED, "050471", None, time + PERIOD), Some(step));
+        assert_eq!(verify_at(SEED, "050471", None, time - PERIOD), Some(step));
+        assert_eq!(verify_at(SEED, "050471", None, time + 2 * PERIOD), None);
+        // the step of a used code is refused
+        assert_eq!(verify_at(SEED, "050471", Some(step), time), None);
+        assert_eq!(verify_at(SEED, "050471", Some(step - 1), time), Some(step));
+        assert_eq!(verify_at(SEED, "50471", None, time), None);
+        assert_eq!(verify_at(SEED, "050472", None, time), None);
+    }
+}
@@ -1,5 +1,14 @@
+use std::borrow::Cow;
+
+use rocket::{
+    http::Status,
+    serde::{Deserialize, Serialize},
+};
+use schemars::JsonSchema;
 use thiserror::Error;
 
+use crate::{accounting_api, auth::ApiTokenError, i18n::Message};
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("{:#?} خطأ في قاعدة البيانات", source)]
@@ -14,3 +23,118 @@ pub enum Error {
     },
 }
 
+/// stable machine readable codes of the failed responses
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum ErrorCode {
+    BadRequest,
+    /// the body does not match the fields of the route
+    MalformedBody,
+    PayloadTooLarge,
+    Unauthorized,
+    MissingToken,
+    ExpiredToken,
+    InvalidToken,
+    /// the session of the token was signed out
+    RevokedToken,
+    InvalidCredentials,
+    InvalidTwoFactorCode,
+    /// too many failed logins, the message tells how long to wait
+    TooManyAttempts,
+    Forbidden,
+    NotFound,
+    /// a value that must be unique is taken
+    Duplicate,
+    /// the object is not in a state allowing the operation
+    InvalidState,
+    Validation,
+    NotEnoughValue,
+    Internal,
+    EtaNotConfigured,
+    EtaError,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> Status {
+        match self {
+            Self::BadRequest => Status::BadRequest,
+            Self::PayloadTooLarge => Status::PayloadTooLarge,
+            Self::Unauthorized
+            | Self::MissingToken
+            | Self::ExpiredToken
+            | Self::InvalidToken
+            | Self::RevokedToken
+            | Self::InvalidCredentials
+            | Self::InvalidTwoFactorCode => Status::Unauthorized,
+            Self::TooManyAttempts => Status::TooManyRequests,
+            Self::Forbidden => Status::Forbidden,
+            Self::NotFound => Status::NotFound,
+            Self::Duplicate | Self::InvalidState => Status::Conflict,
+            Self::Validation | Self::NotEnoughValue | Self::MalformedBody => {
+                Status::UnprocessableEntity
+            }
+            Self::Internal | Self::EtaNotConfigured | Self::EtaError => Status::InternalServerError,
+        }
+    }
+}
+
+/// the field of the request body a validation error is about
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: Cow<'static, str>,
+    pub message: Message,
+}
+
+impl From<&accounting_api::Error> for ErrorCode {
+    fn from(error: &accounting_api::Error) -> Self {
+        use accounting_api::Error;
+        match error {
+            Error::ObjectNotFound => Self::NotFound,
+            Error::InvalidCredentials => Self::InvalidCredentials,
+            Error::InvalidTwoFactorCode => Self::InvalidTwoFactorCode,
+            Error::LoginLocked(_) => Self::TooManyAttempts,
+            Error::TwoFactorEnforced | Error::OwnExpenseReview => Self::Forbidden,
+            Error::NotEnoughUserValue(..) => Self::NotEnoughValue,
+            Error::InvalidValue
+            | Error::PasswordTooShort(_)
+            | Error::BreachedPassword
+            | Error::WrongPassword
+            | Error::InvalidResetToken
+            | Error::InvalidTaxRate
+            | Error::InvalidWithholding
+            | Error::InvalidTaxPeriod
+            | Error::EmptyInvoice
+            | Error::PaymentExceedsBalance(..)
+            | Error::InvalidFundersPercentage(_)
+            | Error::MissingTaxRegistration
+            | Error::Constraint { .. } => Self::Validation,
+            Error::DeadlineAlreadyFulfilled
+            | Error::DerivedDeadline
+            | Error::ExpenseNotPending
+            | Error::InvalidInvoiceState
+            | Error::InvalidTwoFactorState
+            | Error::EtaAlreadySubmitted => Self::InvalidState,
+            Error::Duplicate { .. } => Self::Duplicate,
+            Error::UnreadableSheet(_)
+            | Error::EmptySheet
+            | Error::UnknownField(_)
+            | Error::MissingColumn(_) => Self::BadRequest,
+            Error::EtaNotConfigured => Self::EtaNotConfigured,
+            Error::Eta(_) => Self::EtaError,
+            Error::Other(_) => Self::Internal,
+        }
+    }
+}
+
+impl From<ApiTokenError> for ErrorCode {
+    fn from(error: ApiTokenError) -> Self {
+        match error {
+            ApiTokenError::Missing => Self::MissingToken,
+            ApiTokenError::Expired => Self::ExpiredToken,
+            ApiTokenError::Invalid => Self::InvalidToken,
+            ApiTokenError::Revoked => Self::RevokedToken,
+            ApiTokenError::Forbidden => Self::Forbidden,
+        }
+    }
+}
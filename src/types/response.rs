@@ -1,11 +1,23 @@
-use std::borrow::Cow;
-
 use rocket::{
+    http::{ContentType, Header, Status},
+    response::{self, Responder},
     serde::{json::Json, Deserialize, Serialize},
-    Responder,
+    Request,
+};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    okapi::openapi3::{self, MediaType, RefOr, Responses},
+    response::OpenApiResponderInner,
 };
+use schemars::{gen::SchemaGenerator, JsonSchema};
 
-use crate::accounting_api;
+use crate::{
+    accounting_api,
+    i18n::{self, Message},
+    docs::Binary,
+};
+
+use super::error::{ErrorCode, FieldError};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(crate = "rocket::serde")]
@@ -17,80 +29,248 @@ pub struct Response<T> {
 
 pub type ResponseResult<T> = Result<ResponseEnum<T>, ResponseEnum<T>>;
 
-#[derive(Responder)]
+pub type PdfResult = Result<PdfFile, ResponseEnum<()>>;
+
+/// a generated pdf shown inline by the browser
+#[derive(Debug)]
+pub struct PdfFile {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+impl<'r> Responder<'r, 'static> for PdfFile {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let disposition = format!("inline; filename=\"{}\"", self.name);
+        let mut response = (ContentType::PDF, self.content).respond_to(request)?;
+        response.set_header(Header::new("Content-Disposition", disposition));
+        Ok(response)
+    }
+}
+
+impl OpenApiResponderInner for PdfFile {
+    fn responses(_: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        Ok(file_response(&["application/pdf"]))
+    }
+}
+
+/// a `200` downloading a file of one of `media_types`
+fn file_response(media_types: &[&str]) -> Responses {
+    let content = media_types
+        .iter()
+        .map(|media_type| {
+            let schema = SchemaGenerator::default().subschema_for::<Binary>();
+            let file = MediaType {
+                schema: Some(schema.into_object()),
+                ..MediaType::default()
+            };
+            (media_type.to_string(), file)
+        })
+        .collect();
+    Responses {
+        responses: [(
+            "200".to_owned(),
+            RefOr::Object(openapi3::Response {
+                description: "the file".to_owned(),
+                content,
+                ..openapi3::Response::default()
+            }),
+        )]
+        .into_iter()
+        .collect(),
+        ..Responses::default()
+    }
+}
+
+/// a spreadsheet export downloaded as an attachment
+#[derive(Debug)]
+pub struct ExportFile<R> {
+    pub name: String,
+    pub content_type: ContentType,
+    pub body: R,
+}
+
+impl<R> OpenApiResponderInner for ExportFile<R> {
+    fn responses(_: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        Ok(file_response(&[
+            "text/csv",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/json",
+        ]))
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for ExportFile<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'o> {
+        let disposition = format!("attachment; filename=\"{}\"", self.name);
+        let mut response = self.body.respond_to(request)?;
+        response.set_header(self.content_type);
+        response.set_header(Header::new("Content-Disposition", disposition));
+        Ok(response)
+    }
+}
+
 pub enum ResponseEnum<T> {
-    #[response(status = 200)]
     Ok(Json<Content<T>>),
-    #[response(status = 201)]
     Created(Json<Content<T>>),
-    #[response(status = 404)]
-    NotFound(Json<Content<T>>),
-    #[response(status = 204)]
+    Accepted(Json<Content<T>>),
     NoContent(Json<Content<T>>),
-    #[response(status = 400)]
+    BadRequest(Json<Content<T>>),
     Unauthorized(Json<Content<T>>),
-    #[response(status = 501)]
+    Forbidden(Json<Content<T>>),
+    NotFound(Json<Content<T>>),
+    Conflict(Json<Content<T>>),
+    PayloadTooLarge(Json<Content<T>>),
+    Unprocessable(Json<Content<T>>),
+    TooManyRequests(Json<Content<T>>),
     Internal(Json<Content<T>>),
 }
 
+/// the messages are written in the language of the request
+impl<'r, T: Serialize> Responder<'r, 'static> for ResponseEnum<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let (status, Json(mut content)) = match self {
+            Self::Ok(content) => (Status::Ok, content),
+            Self::Created(content) => (Status::Created, content),
+            Self::Accepted(content) => (Status::Accepted, content),
+            Self::NoContent(content) => (Status::NoContent, content),
+            Self::BadRequest(content) => (Status::BadRequest, content),
+            Self::Unauthorized(content) => (Status::Unauthorized, content),
+            Self::Forbidden(content) => (Status::Forbidden, content),
+            Self::NotFound(content) => (Status::NotFound, content),
+            Self::Conflict(content) => (Status::Conflict, content),
+            Self::PayloadTooLarge(content) => (Status::PayloadTooLarge, content),
+            Self::Unprocessable(content) => (Status::UnprocessableEntity, content),
+            Self::TooManyRequests(content) => (Status::TooManyRequests, content),
+            Self::Internal(content) => (Status::InternalServerError, content),
+        };
+        let language = i18n::language(request);
+        content.message = Message::Text(content.message.localize(language));
+        for detail in &mut content.details {
+            detail.message = Message::Text(detail.message.localize(language));
+        }
+        (status, Json(content)).respond_to(request)
+    }
+}
+
+/// the envelope of the successful responses, `default` for the failed ones
+impl<T: Serialize + JsonSchema + Send> OpenApiResponderInner for ResponseEnum<T> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Json::<Content<T>>::responses(gen)?;
+        let failure = Json::<Content<()>>::responses(gen)?.responses.remove("200");
+        if let Some(RefOr::Object(mut failure)) = failure {
+            failure.description = "a failed request, `code` tells why".to_owned();
+            responses
+                .responses
+                .insert("default".to_owned(), RefOr::Object(failure));
+        }
+        Ok(responses)
+    }
+}
+
 impl<T> From<accounting_api::Error> for ResponseEnum<T> {
     fn from(error: accounting_api::Error) -> Self {
-        match error {
-            accounting_api::Error::ObjectNotFound => Self::not_found(format!("{error}").into()),
-            _ => Self::internal(format!("{error}").into()),
-        }
+        let details = error
+            .field()
+            .map(|field| FieldError {
+                field: field.into(),
+                message: error.message(),
+            })
+            .into_iter()
+            .collect();
+        Self::error(ErrorCode::from(&error), error.message(), details)
     }
 }
 
 impl<T> ResponseEnum<T> {
-    pub fn ok(data: T, message: Cow<'static, str>) -> Self {
-        ResponseEnum::Ok(Json(Content {
-            status: true,
-            message,
-            data: Some(data),
-        }))
+    pub fn ok(data: T, message: Message) -> Self {
+        ResponseEnum::Ok(Json(Content::success(data, message)))
     }
-    pub fn created(data: T, message: Cow<'static, str>) -> Self {
-        ResponseEnum::Created(Json(Content {
-            status: true,
-            message,
+    pub fn created(data: T, message: Message) -> Self {
+        ResponseEnum::Created(Json(Content::success(data, message)))
+    }
+    /// a step of a process that is not done yet, like the first login step
+    pub fn accepted(data: T, message: Message) -> Self {
+        ResponseEnum::Accepted(Json(Content::success(data, message)))
+    }
+    /// a failed response carrying data, like the rejected rows of an import
+    pub fn unprocessable(data: T, message: Message) -> Self {
+        ResponseEnum::Unprocessable(Json(Content {
             data: Some(data),
+            ..Content::failure(ErrorCode::Validation, message, vec![])
         }))
     }
-    pub fn not_found(message: Cow<'static, str>) -> Self {
-        ResponseEnum::Created(Json(Content {
-            status: false,
-            message,
-            data: None,
-        }))
+    pub fn not_found(message: Message) -> Self {
+        Self::error(ErrorCode::NotFound, message, vec![])
     }
-    pub fn no_content(message: Cow<'static, str>) -> Self {
-        ResponseEnum::Created(Json(Content {
-            status: false,
+    pub fn no_content(message: Message) -> Self {
+        ResponseEnum::NoContent(Json(Content {
+            status: true,
             message,
             data: None,
+            code: None,
+            details: vec![],
         }))
     }
-    pub fn unauthorized(message: Cow<'static, str>) -> Self {
-        ResponseEnum::Created(Json(Content {
-            status: false,
-            message,
-            data: None,
-        }))
+    pub fn bad_request(message: Message) -> Self {
+        Self::error(ErrorCode::BadRequest, message, vec![])
     }
-    pub fn internal(message: Cow<'static, str>) -> Self {
-        ResponseEnum::Created(Json(Content {
-            status: false,
-            message,
-            data: None,
-        }))
+    pub fn unauthorized(message: Message) -> Self {
+        Self::error(ErrorCode::Unauthorized, message, vec![])
+    }
+    pub fn forbidden(message: Message) -> Self {
+        Self::error(ErrorCode::Forbidden, message, vec![])
+    }
+    pub fn internal(message: Message) -> Self {
+        Self::error(ErrorCode::Internal, message, vec![])
+    }
+    /// a failed response with the status of its code
+    pub fn error(code: ErrorCode, message: Message, details: Vec<FieldError>) -> Self {
+        let content = Json(Content::failure(code, message, details));
+        match code.status().code {
+            400 => ResponseEnum::BadRequest(content),
+            401 => ResponseEnum::Unauthorized(content),
+            403 => ResponseEnum::Forbidden(content),
+            404 => ResponseEnum::NotFound(content),
+            409 => ResponseEnum::Conflict(content),
+            413 => ResponseEnum::PayloadTooLarge(content),
+            422 => ResponseEnum::Unprocessable(content),
+            429 => ResponseEnum::TooManyRequests(content),
+            _ => ResponseEnum::Internal(content),
+        }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
 #[serde(crate = "rocket::serde")]
 pub struct Content<T> {
     pub status: bool,
-    pub message: Cow<'static, str>,
+    pub message: Message,
     pub data: Option<T>,
+    /// set on failed responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+impl<T> Content<T> {
+    fn success(data: T, message: Message) -> Self {
+        Content {
+            status: true,
+            message,
+            data: Some(data),
+            code: None,
+            details: vec![],
+        }
+    }
+
+    fn failure(code: ErrorCode, message: Message, details: Vec<FieldError>) -> Self {
+        Content {
+            status: false,
+            message,
+            data: None,
+            code: Some(code),
+            details,
+        }
+    }
 }
@@ -0,0 +1,206 @@
+//! the helpers of the integration tests, they run against a Postgres server,
+//! `DATABASE_URL` names the server the test database `accounting_test` is
+//! created on
+#![allow(dead_code)]
+
+use std::{env, sync::OnceLock};
+
+use accounting_backend::{auth, catchers, docs, eta, events, i18n, local_storage, routes};
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::asynchronous::Client,
+    serde::json::{json, Value},
+    tokio::sync::OnceCell,
+};
+use sqlx::{Connection, Executor, PgConnection};
+
+pub const SUPER_ADMIN_NAME: &str = "root";
+pub const SUPER_ADMIN_PASSWORD: &str = "root long pass";
+
+static DATABASE: OnceLock<OnceCell<()>> = OnceLock::new();
+
+/// creates the test database once, the first ignition runs the migrations
+/// and seeds the super admin
+pub async fn database() {
+    DATABASE
+        .get_or_init(OnceCell::new)
+        .get_or_init(|| async {
+            let url = env::var("DATABASE_URL").expect("`DATABASE_URL` must be set");
+            let (server, _) = url
+                .rsplit_once('/')
+                .expect("`DATABASE_URL` names a database");
+            let mut conn = PgConnection::connect(&url)
+                .await
+                .expect("database connection");
+            conn.execute("DROP DATABASE IF EXISTS accounting_test WITH (FORCE)")
+                .await
+                .expect("test database dropped");
+            conn.execute("CREATE DATABASE accounting_test")
+                .await
+                .expect("test database created");
+
+            let data = env::temp_dir().join("accounting_test");
+            std::fs::remove_dir_all(&data).ok();
+            std::fs::create_dir_all(&data).expect("data directory created");
+
+            env::set_var("DATABASE_URL", format!("{server}/accounting_test"));
+            env::set_var("DATA_PATH", data);
+            env::set_var("JWT_SECRET", "integration tests secret");
+            env::set_var("PASSWORD_CHECK_BREACHED", "false");
+            env::set_var("SUPER_ADMIN_NAME", SUPER_ADMIN_NAME);
+            env::set_var("SUPER_ADMIN_PASSWORD", SUPER_ADMIN_PASSWORD);
+            let _ = rocket::build()
+                .attach(auth::stage())
+                .attach(local_storage::stage())
+                .ignite()
+                .await
+                .expect("rocket ignites");
+            env::remove_var("SUPER_ADMIN_NAME");
+            env::remove_var("SUPER_ADMIN_PASSWORD");
+        })
+        .await;
+}
+
+/// a connection to the test database, it sees the rows of every office
+pub async fn connection() -> PgConnection {
+    database().await;
+    let url = env::var("DATABASE_URL").expect("`DATABASE_URL` must be set");
+    PgConnection::connect(&url)
+        .await
+        .expect("database connection")
+}
+
+pub async fn client() -> Client {
+    database().await;
+    let rocket = rocket::build()
+        .attach(auth::stage())
+        .attach(local_storage::stage())
+        .attach(events::stage())
+        .attach(i18n::stage())
+        .attach(catchers::stage())
+        .attach(docs::stage())
+        .attach(routes::stage())
+        .attach(eta::stage());
+    Client::tracked(rocket).await.expect("valid rocket")
+}
+
+pub async fn send(
+    client: &Client,
+    method: &str,
+    uri: String,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (Status, Value) {
+    let mut request = match method {
+        "GET" => client.get(uri),
+        "POST" => client.post(uri),
+        "PUT" => client.put(uri),
+        "PATCH" => client.patch(uri),
+        "DELETE" => client.delete(uri),
+        _ => unreachable!(),
+    };
+    if let Some(token) = token {
+        request = request.header(Header::new("Authorization", token.to_string()));
+    }
+    if let Some(body) = body {
+        request = request.header(ContentType::JSON).body(body.to_string());
+    }
+    let response = request.dispatch().await;
+    let status = response.status();
+    (status, response.into_json().await.unwrap_or(Value::Null))
+}
+
+pub fn token(body: &Value) -> String {
+    body["data"].as_str().expect("a token").to_string()
+}
+
+pub async fn login(client: &Client, office: &str, name: &str, password: &str) -> (Status, Value) {
+    let user = json!({ "name": name, "password": password, "tenant": office });
+    send(client, "POST", "/api/users/login".into(), None, Some(user)).await
+}
+
+/// creates an office and returns its name with a token of its admin
+pub async fn office(client: &Client) -> (String, String) {
+    let super_admin = json!({ "name": SUPER_ADMIN_NAME, "password": SUPER_ADMIN_PASSWORD });
+    let (status, body) = send(
+        client,
+        "POST",
+        "/api/tenants/login".into(),
+        None,
+        Some(super_admin),
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let super_token = token(&body);
+
+    let name = format!("office {}", rand::random::<u32>());
+    let tenant = json!({
+        "name": name,
+        "admin": { "name": "admin", "password": "admin long pass" },
+    });
+    let (status, body) = send(
+        client,
+        "POST",
+        "/api/tenants".into(),
+        Some(&super_token),
+        Some(tenant),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+
+    let (status, body) = login(client, &name, "admin", "admin long pass").await;
+    assert_eq!(status, Status::Ok, "{body}");
+    (name, token(&body))
+}
+
+pub async fn register(client: &Client, admin: &str, name: &str, password: &str) -> String {
+    let user = json!({ "name": name, "password": password, "isAdmin": false });
+    let (status, body) = send(client, "POST", "/api/users".into(), Some(admin), Some(user)).await;
+    assert_eq!(status, Status::Created, "{body}");
+    body["data"]["id"].as_str().expect("a user id").to_string()
+}
+
+/// posts `content` as the `file` field of a form
+pub async fn upload(
+    client: &Client,
+    uri: String,
+    token: &str,
+    name: &str,
+    content: &str,
+) -> (Status, Value) {
+    let boundary = "X-BOUNDARY";
+    let body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"{name}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n\
+         {content}\r\n\
+         --{boundary}--\r\n"
+    );
+    let response = client
+        .post(uri)
+        .header(Header::new("Authorization", token.to_string()))
+        .header(ContentType::new("multipart", "form-data").with_params(("boundary", boundary)))
+        .body(body)
+        .dispatch()
+        .await;
+    let status = response.status();
+    (status, response.into_json().await.unwrap_or(Value::Null))
+}
+
+/// creates a company and returns its id
+pub async fn company(client: &Client, admin: &str, name: &str) -> String {
+    let company = json!({ "owner": "owner", "commercialFeature": name, "isWorking": true });
+    let (status, body) = send(
+        client,
+        "POST",
+        "/api/company".into(),
+        Some(admin),
+        Some(company),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    body["data"]["id"]
+        .as_str()
+        .expect("a company id")
+        .to_string()
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{client, company, office, send};
+use rocket::{http::Status, serde::json::json};
+
+#[rocket::async_test]
+async fn funders_share_the_profit_without_the_tax() {
+    let client = client().await;
+    let (_, admin) = office(&client).await;
+    let company_id = company(&client, &admin, "bakery").await;
+
+    for funder in [
+        json!({ "name": "first", "percentage": 60.0 }),
+        json!({ "name": "second", "percentage": 40.0 }),
+    ] {
+        let (status, body) = send(
+            &client,
+            "POST",
+            format!("/api/company/{company_id}/funders"),
+            Some(&admin),
+            Some(funder),
+        )
+        .await;
+        assert_eq!(status, Status::Created, "{body}");
+    }
+    // 1140 collected of which 140 is value added tax
+    let income = json!({ "value": 1140.0, "description": "sales", "taxCode": "standard" });
+    let (status, body) = send(
+        &client,
+        "POST",
+        format!("/api/company/{company_id}/incomes"),
+        Some(&admin),
+        Some(income),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    assert_eq!(body["data"]["net"], json!(1000.0));
+
+    let period = json!({
+        "periodStart": "2000-01-01T00:00:00Z",
+        "periodEnd": "2100-01-01T00:00:00Z",
+    });
+    let (status, body) = send(
+        &client,
+        "POST",
+        format!("/api/company/{company_id}/funders/distribution"),
+        Some(&admin),
+        Some(period),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    let distribution = &body["data"];
+    assert_eq!(distribution["totalIncomes"], json!(1000.0));
+    assert_eq!(distribution["netProfit"], json!(1000.0));
+    let mut amounts: Vec<f64> = distribution["entries"]
+        .as_array()
+        .expect("the entries")
+        .iter()
+        .map(|entry| entry["amount"].as_f64().expect("an amount"))
+        .collect();
+    amounts.sort_by(f64::total_cmp);
+    assert_eq!(amounts, [400.0, 600.0]);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{client, company, login, office, register, send, token, upload};
+use rocket::{http::Status, serde::json::json};
+
+#[rocket::async_test]
+async fn imported_expenses_are_taken_from_the_custody() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    let bob = register(&client, &admin, "bob", "bobs long pass").await;
+    let (_, body) = login(&client, &office, "bob", "bobs long pass").await;
+    let bob_token = token(&body);
+    company(&client, &admin, "feature").await;
+
+    let custody = json!({ "value": 1000.0 });
+    let (status, body) = send(
+        &client,
+        "PATCH",
+        format!("/api/users/{bob}"),
+        Some(&admin),
+        Some(custody),
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+
+    let sheet = "company,user,description,value\nfeature,bob,paper,100\nfeature,bob,ink,2000";
+    let (status, body) = upload(
+        &client,
+        "/api/expenses/import?dry_run=false".into(),
+        &admin,
+        "expenses.csv",
+        sheet,
+    )
+    .await;
+    assert_eq!(status, Status::UnprocessableEntity, "{body}");
+    assert_eq!(body["data"]["errors"][0]["row"], 3);
+    assert_eq!(body["data"]["errors"][0]["column"], "value");
+
+    let sheet = "company,user,description,value\nfeature,bob,paper,100";
+    let (status, body) = upload(
+        &client,
+        "/api/expenses/import?dry_run=false".into(),
+        &admin,
+        "expenses.csv",
+        sheet,
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    let (_, body) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&bob_token),
+        None,
+    )
+    .await;
+    assert_eq!(body["data"]["value"], 900.0);
+    let (_, body) = send(
+        &client,
+        "GET",
+        format!("/api/users/{bob}/statement"),
+        Some(&admin),
+        None,
+    )
+    .await;
+    let statement = body["data"].as_array().expect("the statement");
+    assert!(statement
+        .iter()
+        .any(|entry| entry["kind"] == "expense" && entry["value"] == -100.0));
+
+    let (_, body) = send(&client, "GET", "/api/expenses".into(), Some(&admin), None).await;
+    let expense = body["data"][0]["id"].as_str().expect("an expense id");
+    let (status, body) = send(
+        &client,
+        "DELETE",
+        format!("/api/expenses/{expense}"),
+        Some(&admin),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (_, body) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&bob_token),
+        None,
+    )
+    .await;
+    assert_eq!(body["data"]["value"], 1000.0);
+}
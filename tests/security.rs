@@ -0,0 +1,500 @@
+mod common;
+
+use common::{client, connection, login, office, register, send, token, upload};
+use rocket::{
+    futures::future::join_all,
+    http::Status,
+    local::asynchronous::Client,
+    serde::json::{json, Value},
+};
+
+#[rocket::async_test]
+async fn offices_do_not_see_each_other() {
+    let client = client().await;
+    let (_, first) = office(&client).await;
+    let (_, second) = office(&client).await;
+
+    let company = json!({ "owner": "owner", "commercialFeature": "feature", "isWorking": true });
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/company".into(),
+        Some(&first),
+        Some(company.clone()),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    let company_id = body["data"]["id"]
+        .as_str()
+        .expect("a company id")
+        .to_string();
+    let bob = register(&client, &first, "bob", "bobs long pass").await;
+
+    for uri in [
+        "/api/company?search=owner".to_string(),
+        format!("/api/company/{company_id}/statement"),
+        format!("/api/users/{bob}/statement"),
+    ] {
+        let (status, body) = send(&client, "GET", uri.clone(), Some(&first), None).await;
+        assert_eq!(status, Status::Ok, "{uri} {body}");
+        let (status, _) = send(&client, "GET", uri.clone(), Some(&second), None).await;
+        assert_eq!(status, Status::NotFound, "{uri}");
+    }
+
+    // the unique names are per office
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/company".into(),
+        Some(&second),
+        Some(company),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    let (status, body) = send(
+        &client,
+        "GET",
+        "/api/company?search=owner".into(),
+        Some(&first),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(1));
+}
+
+#[rocket::async_test]
+async fn revoked_sessions_are_refused() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    let bob = register(&client, &admin, "bob", "bobs long pass").await;
+    let (_, body) = login(&client, &office, "bob", "bobs long pass").await;
+    let phone = token(&body);
+    let (_, body) = login(&client, &office, "bob", "bobs long pass").await;
+    let laptop = token(&body);
+
+    let (status, body) = send(
+        &client,
+        "GET",
+        "/api/users/current/sessions".into(),
+        Some(&laptop),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let sessions = body["data"].as_array().expect("the sessions");
+    assert_eq!(sessions.len(), 2);
+    let current = sessions
+        .iter()
+        .find(|s| s["current"] == json!(true))
+        .expect("the current session");
+    let id = current["id"].as_str().expect("a session id");
+
+    let (status, body) = send(
+        &client,
+        "DELETE",
+        format!("/api/users/current/sessions/{id}"),
+        Some(&phone),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&laptop),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Unauthorized);
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&phone),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+
+    // an admin signs every device of the user out
+    let (status, body) = send(
+        &client,
+        "DELETE",
+        format!("/api/users/{bob}/sessions"),
+        Some(&admin),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&phone),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Unauthorized);
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&admin),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+}
+
+#[rocket::async_test]
+async fn reset_tokens_are_used_once() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    let bob = register(&client, &admin, "bob", "bobs long pass").await;
+    let (_, body) = login(&client, &office, "bob", "bobs long pass").await;
+    let session = token(&body);
+
+    let (status, body) = send(
+        &client,
+        "POST",
+        format!("/api/users/{bob}/password-reset"),
+        Some(&admin),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    let reset = body["data"]["token"]
+        .as_str()
+        .expect("a reset token")
+        .to_string();
+
+    let password = json!({ "token": reset, "password": "bobs reset pass" });
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/users/password-reset".into(),
+        None,
+        Some(password),
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let password = json!({ "token": reset, "password": "bobs other pass" });
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/users/password-reset".into(),
+        None,
+        Some(password),
+    )
+    .await;
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(body["details"][0]["field"], "token");
+
+    let (status, _) = login(&client, &office, "bob", "bobs reset pass").await;
+    assert_eq!(status, Status::Ok);
+    let (status, _) = login(&client, &office, "bob", "bobs other pass").await;
+    assert_eq!(status, Status::Unauthorized);
+    // the reset signs the old sessions out
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/users/current".into(),
+        Some(&session),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn documents_are_deleted_by_name() {
+    let client = client().await;
+    let (_, first) = office(&client).await;
+    let (_, second) = office(&client).await;
+
+    let company = json!({ "owner": "owner", "commercialFeature": "feature", "isWorking": true });
+    let (_, body) = send(
+        &client,
+        "POST",
+        "/api/company".into(),
+        Some(&first),
+        Some(company),
+    )
+    .await;
+    let company_id = body["data"]["id"]
+        .as_str()
+        .expect("a company id")
+        .to_string();
+    let (status, body) = upload(
+        &client,
+        format!("/api/company/{company_id}/documents"),
+        &first,
+        "contract.pdf",
+        "%PDF-1.4",
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+
+    for (token, name) in [
+        (&second, "contract.pdf"),
+        (&first, "..%2Fdocuments%2Fcontract.pdf"),
+        (&first, "missing.pdf"),
+    ] {
+        let (status, _) = send(
+            &client,
+            "DELETE",
+            format!("/api/company/{company_id}/documents/{name}"),
+            Some(token),
+            None,
+        )
+        .await;
+        assert_eq!(status, Status::NotFound, "{name}");
+    }
+    let documents = format!("/api/company/{company_id}/documents");
+    let (_, body) = send(&client, "GET", documents.clone(), Some(&first), None).await;
+    assert_eq!(body["data"].as_array().map(Vec::len), Some(1));
+
+    let (status, body) = send(
+        &client,
+        "DELETE",
+        format!("/api/company/{company_id}/documents/contract.pdf"),
+        Some(&first),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (_, body) = send(&client, "GET", documents, Some(&first), None).await;
+    assert_eq!(body["data"], json!([]));
+}
+
+#[rocket::async_test]
+async fn concurrent_guesses_are_throttled() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    register(&client, &admin, "bob", "bobs long pass").await;
+
+    let guesses = (0..20).map(|guess| {
+        let (client, office) = (&client, &office);
+        async move { login(client, office, "bob", &format!("guess {guess}")).await }
+    });
+    let statuses: Vec<Status> = join_all(guesses)
+        .await
+        .into_iter()
+        .map(|(status, _)| status)
+        .collect();
+    // three free failures and the one that delays the next attempts
+    let failed = statuses
+        .iter()
+        .filter(|status| **status == Status::Unauthorized)
+        .count();
+    assert_eq!(failed, 4, "{statuses:?}");
+    assert!(
+        statuses
+            .iter()
+            .all(|status| [Status::Unauthorized, Status::TooManyRequests].contains(status)),
+        "{statuses:?}"
+    );
+
+    let (status, _) = login(&client, &office, "bob", "bobs long pass").await;
+    assert_eq!(status, Status::TooManyRequests);
+}
+
+#[rocket::async_test]
+async fn passwords_are_stored_hashed() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    register(&client, &admin, "bob", "bobs long pass").await;
+    let mut connection = connection().await;
+
+    let super_admins: Vec<(String,)> = sqlx::query_as("SELECT password FROM super_admins")
+        .fetch_all(&mut connection)
+        .await
+        .expect("the super admins");
+    assert!(super_admins
+        .iter()
+        .all(|(password,)| password.starts_with("$argon2id$")));
+
+    let users: Vec<(String,)> = sqlx::query_as("SELECT password FROM users")
+        .fetch_all(&mut connection)
+        .await
+        .expect("the users");
+    assert!(users
+        .iter()
+        .all(|(password,)| password.starts_with("$argon2id$")));
+
+    let (status, body) = send(&client, "GET", "/api/users".into(), Some(&admin), None).await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let users = body["data"].as_array().expect("the users");
+    assert_eq!(users.len(), 2);
+    assert!(users.iter().all(|user| user.get("password").is_none()));
+
+    let (_, body) = login(&client, &office, "bob", "bobs long pass").await;
+    let bob = token(&body);
+    let wrong = json!({ "oldPassword": "bobs wrong pass", "password": "bobs newer pass" });
+    let (status, _) = send(
+        &client,
+        "POST",
+        "/api/users/current/password".into(),
+        Some(&bob),
+        Some(wrong),
+    )
+    .await;
+    assert_ne!(status, Status::Ok);
+    let change = json!({ "oldPassword": "bobs long pass", "password": "bobs newer pass" });
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/users/current/password".into(),
+        Some(&bob),
+        Some(change),
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (status, _) = login(&client, &office, "bob", "bobs long pass").await;
+    assert_eq!(status, Status::Unauthorized);
+    let (status, _) = login(&client, &office, "bob", "bobs newer pass").await;
+    assert_eq!(status, Status::Ok);
+}
+
+/// a company of the office signing in to the portal as `username`
+async fn portal_company(client: &Client, admin: &str, username: &str) -> (String, Value) {
+    let company = json!({
+        "owner": "owner",
+        "commercialFeature": username,
+        "isWorking": true,
+        "username": username,
+        "password": "portal long pass",
+    });
+    let (status, body) = send(
+        client,
+        "POST",
+        "/api/company".into(),
+        Some(admin),
+        Some(company.clone()),
+    )
+    .await;
+    assert_eq!(status, Status::Created, "{body}");
+    assert!(body["data"].get("password").is_none(), "{body}");
+    let id = body["data"]["id"].as_str().expect("a company id");
+    (id.to_string(), company)
+}
+
+async fn portal_login(
+    client: &Client,
+    office: &str,
+    username: &str,
+    password: &str,
+) -> (Status, Value) {
+    let login = json!({ "username": username, "password": password, "tenant": office });
+    send(
+        client,
+        "POST",
+        "/api/portal/login".into(),
+        None,
+        Some(login),
+    )
+    .await
+}
+
+#[rocket::async_test]
+async fn portal_passwords_are_hashed_and_changes_sign_out() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    let (id, mut company) = portal_company(&client, &admin, "shop").await;
+
+    let (password,): (String,) =
+        sqlx::query_as("SELECT password FROM companies WHERE id::TEXT = $1")
+            .bind(&id)
+            .fetch_one(&mut connection().await)
+            .await
+            .expect("the company");
+    assert!(password.starts_with("$argon2id$"));
+
+    let (status, body) = portal_login(&client, &office, "shop", "portal long pass").await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let portal = token(&body);
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/portal/company".into(),
+        Some(&portal),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+
+    // an update keeping the password keeps the portal signed in
+    company["password"] = Value::Null;
+    company["email"] = json!("shop@example.com");
+    let (status, body) = send(
+        &client,
+        "PUT",
+        format!("/api/company/{id}"),
+        Some(&admin),
+        Some(company.clone()),
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/portal/company".into(),
+        Some(&portal),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    let (status, _) = portal_login(&client, &office, "shop", "portal long pass").await;
+    assert_eq!(status, Status::Ok);
+
+    company["password"] = json!("portal newer pass");
+    let (status, body) = send(
+        &client,
+        "PUT",
+        format!("/api/company/{id}"),
+        Some(&admin),
+        Some(company),
+    )
+    .await;
+    assert_eq!(status, Status::Ok, "{body}");
+    let (status, _) = send(
+        &client,
+        "GET",
+        "/api/portal/company".into(),
+        Some(&portal),
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Unauthorized);
+    let (status, _) = portal_login(&client, &office, "shop", "portal long pass").await;
+    assert_eq!(status, Status::Unauthorized);
+    let (status, _) = portal_login(&client, &office, "shop", "portal newer pass").await;
+    assert_eq!(status, Status::Ok);
+}
+
+#[rocket::async_test]
+async fn portal_guesses_are_throttled() {
+    let client = client().await;
+    let (office, admin) = office(&client).await;
+    portal_company(&client, &admin, "kiosk").await;
+
+    let guesses = (0..10).map(|guess| {
+        let (client, office) = (&client, &office);
+        async move { portal_login(client, office, "kiosk", &format!("guess {guess}")).await }
+    });
+    let statuses: Vec<Status> = join_all(guesses)
+        .await
+        .into_iter()
+        .map(|(status, _)| status)
+        .collect();
+    let failed = statuses
+        .iter()
+        .filter(|status| **status == Status::Unauthorized)
+        .count();
+    assert_eq!(failed, 4, "{statuses:?}");
+
+    let (status, _) = portal_login(&client, &office, "kiosk", "portal long pass").await;
+    assert_eq!(status, Status::TooManyRequests);
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::{client, connection, office, send};
+use rocket::http::Status;
+
+#[rocket::async_test]
+async fn offices_share_a_bounded_number_of_connections() {
+    let client = client().await;
+    let offices = 40;
+    for _ in 0..offices {
+        let (_, admin) = office(&client).await;
+        let (status, body) = send(&client, "GET", "/api/users".into(), Some(&admin), None).await;
+        assert_eq!(status, Status::Ok, "{body}");
+    }
+
+    // every office used a connection, only the kept ones still hold it
+    let (connections,): (i64,) =
+        sqlx::query_as("SELECT count(*) FROM pg_stat_activity WHERE datname = current_database()")
+            .fetch_one(&mut connection().await)
+            .await
+            .expect("the connections");
+    assert!(connections < offices, "{connections} connections");
+}